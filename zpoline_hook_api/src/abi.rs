@@ -0,0 +1,422 @@
+//! ローダーとフックライブラリのABIハンドシェイク
+//!
+//! ローダーは`zpoline_hook_init`を呼ぶ前に、エクスポート関数
+//! `zpoline_hook_handshake`へ自身のABIバージョン・`SyscallRegs`のサイズ・
+//! 提供する機能（`Capabilities`）・コールバックを渡します。ライブラリは同じ形式で
+//! 自身の情報と必要な機能を返し、ローダーは互換性のないライブラリを拒否します。
+//!
+//! `zpoline_hook_handshake`はこのクレートが定義するため、フックライブラリは
+//! `zpoline_hook_api`をリンクするだけで対応します。必要な機能は`zpoline_hook_init`
+//! より前（`#[ctor]`など）に`require_capabilities`で宣言します。
+
+use crate::context::SyscallFrame;
+use crate::nesting::ThreadHookState;
+use crate::{SyscallRegs, SysnoSet};
+use std::ffi::c_char;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// ABIバージョン（`SyscallRegs`やハンドシェイク構造体を変更したら上げる）
+///
+/// `LoaderCallbacks`の末尾へのコールバックの追加だけは例外で、古いローダーの
+/// 短い`LoaderInfo`では追加したコールバックが`None`として扱われます。
+pub const ZPOLINE_ABI_VERSION: u32 = 3;
+
+/// ローダーが提供する機能のフラグ
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+    /// 関心集合（`zpoline_get_hook_interests`）をトランポリンに反映する
+    pub const INTEREST_FILTER: Self = Self(1 << 0);
+    /// スレッド状態とネストポリシーを共有する（`LoaderCallbacks`）
+    pub const SHARED_THREAD_STATE: Self = Self(1 << 1);
+    /// fork後の子プロセスで`zpoline_fork_child`を呼ぶ
+    pub const FORK_NOTIFY: Self = Self(1 << 2);
+    /// 設定引数を`zpoline_hook_init_with_args`に渡す
+    pub const HOOK_ARGS: Self = Self(1 << 3);
+    /// ライブラリのホットリロード（`LoaderCallbacks::request_reload`）
+    pub const HOT_RELOAD: Self = Self(1 << 4);
+    /// 呼び出し元のコンテキスト（`LoaderCallbacks::current_frame`）
+    pub const CALL_CONTEXT: Self = Self(1 << 5);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// `self`に含まれない`other`のフラグ
+    pub const fn missing(self, other: Self) -> Self {
+        Self(other.0 & !self.0)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NAMES: [(Capabilities, &str); 6] = [
+            (Capabilities::INTEREST_FILTER, "INTEREST_FILTER"),
+            (Capabilities::SHARED_THREAD_STATE, "SHARED_THREAD_STATE"),
+            (Capabilities::FORK_NOTIFY, "FORK_NOTIFY"),
+            (Capabilities::HOOK_ARGS, "HOOK_ARGS"),
+            (Capabilities::HOT_RELOAD, "HOT_RELOAD"),
+            (Capabilities::CALL_CONTEXT, "CALL_CONTEXT"),
+        ];
+
+        let mut rest = self.0;
+        let mut first = true;
+        for (cap, name) in NAMES {
+            if self.contains(cap) {
+                write!(f, "{}{}", if first { "" } else { "|" }, name)?;
+                rest &= !cap.0;
+                first = false;
+            }
+        }
+        if rest != 0 || first {
+            write!(f, "{}{:#x}", if first { "" } else { "|" }, rest)?;
+        }
+        Ok(())
+    }
+}
+
+/// ローダーがライブラリに提供するコールバック
+///
+/// 新しいコールバックは末尾に追加する（`zpoline_hook_handshake`を参照）。
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LoaderCallbacks {
+    /// 現在のスレッドの状態（`SHARED_THREAD_STATE`）
+    pub thread_hook_state: Option<extern "C" fn() -> *const ThreadHookState>,
+    /// ネスト深さの上限（`SHARED_THREAD_STATE`）
+    pub max_depth: *const AtomicU32,
+    /// リロードの要求（`HOT_RELOAD`）
+    pub request_reload: Option<extern "C" fn()>,
+    /// 実行中のフックのトランポリンフレーム（`CALL_CONTEXT`）
+    pub current_frame: Option<extern "C" fn() -> *const SyscallFrame>,
+    /// 初期化の後に関心集合を広げる（`INTEREST_FILTER`）
+    pub extend_interests: Option<extern "C" fn(*const SysnoSet)>,
+}
+
+/// ローダーがライブラリに渡す情報
+///
+/// 先頭3つのフィールドはABIバージョンが変わっても同じ位置に置く。
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LoaderInfo {
+    /// この構造体のサイズ
+    pub size: u32,
+    pub abi_version: u32,
+    /// ライブラリが書き込める`HookLibraryDescriptor`のサイズ
+    pub descriptor_size: u32,
+    pub regs_size: u32,
+    pub capabilities: Capabilities,
+    pub callbacks: LoaderCallbacks,
+}
+
+impl LoaderInfo {
+    /// このクレートの定義でローダー側の情報を作る
+    pub fn new(capabilities: Capabilities, callbacks: LoaderCallbacks) -> Self {
+        Self {
+            size: std::mem::size_of::<Self>() as u32,
+            abi_version: ZPOLINE_ABI_VERSION,
+            descriptor_size: std::mem::size_of::<HookLibraryDescriptor>() as u32,
+            regs_size: std::mem::size_of::<SyscallRegs>() as u32,
+            capabilities,
+            callbacks,
+        }
+    }
+}
+
+/// ライブラリがローダーに返す情報
+///
+/// 先頭2つのフィールドはABIバージョンが変わっても同じ位置に置く。
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HookLibraryDescriptor {
+    /// この構造体のサイズ
+    pub size: u32,
+    pub abi_version: u32,
+    pub regs_size: u32,
+    /// ライブラリが必要とする機能
+    pub required_capabilities: Capabilities,
+    /// リンクされた`zpoline_hook_api`のバージョン（NUL終端）
+    pub api_version: *const c_char,
+}
+
+impl HookLibraryDescriptor {
+    /// このクレートの定義でライブラリ側の情報を作る
+    pub fn new(required_capabilities: Capabilities) -> Self {
+        Self {
+            size: std::mem::size_of::<Self>() as u32,
+            abi_version: ZPOLINE_ABI_VERSION,
+            regs_size: std::mem::size_of::<SyscallRegs>() as u32,
+            required_capabilities,
+            api_version: API_VERSION.as_ptr() as *const c_char,
+        }
+    }
+
+    /// ローダーがこのライブラリを受け入れられるか
+    pub fn check_compatible(&self, loader: &LoaderInfo) -> Result<(), AbiError> {
+        if self.size < std::mem::size_of::<Self>() as u32 {
+            return Err(AbiError::DescriptorTooSmall(self.size));
+        }
+        if self.abi_version != loader.abi_version {
+            return Err(AbiError::VersionMismatch {
+                loader: loader.abi_version,
+                library: self.abi_version,
+            });
+        }
+        if self.regs_size != loader.regs_size {
+            return Err(AbiError::RegsSizeMismatch {
+                loader: loader.regs_size,
+                library: self.regs_size,
+            });
+        }
+        let missing = loader.capabilities.missing(self.required_capabilities);
+        if missing != Capabilities::empty() {
+            return Err(AbiError::MissingCapabilities(missing));
+        }
+        Ok(())
+    }
+}
+
+/// 互換性のない理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiError {
+    DescriptorTooSmall(u32),
+    VersionMismatch { loader: u32, library: u32 },
+    RegsSizeMismatch { loader: u32, library: u32 },
+    MissingCapabilities(Capabilities),
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiError::DescriptorTooSmall(size) => {
+                write!(f, "descriptor too small ({} bytes)", size)
+            }
+            AbiError::VersionMismatch { loader, library } => {
+                write!(f, "ABI version {} (loader supports {})", library, loader)
+            }
+            AbiError::RegsSizeMismatch { loader, library } => write!(
+                f,
+                "SyscallRegs is {} bytes (loader expects {})",
+                library, loader
+            ),
+            AbiError::MissingCapabilities(caps) => {
+                write!(f, "loader does not provide {}", caps)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+const API_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// ライブラリが必要とする機能
+static REQUIRED_CAPABILITIES: AtomicU64 = AtomicU64::new(0);
+
+/// フックライブラリが必要とするローダーの機能を宣言する
+///
+/// ローダーが提供しない場合、ライブラリはロードされません。
+/// `zpoline_hook_init`より前（`#[ctor]`など）に呼んでください。
+pub fn require_capabilities(caps: Capabilities) {
+    REQUIRED_CAPABILITIES.fetch_or(caps.0, Ordering::Relaxed);
+}
+
+/// ローダーが呼ぶハンドシェイク関数
+///
+/// ローダーの情報を受け取り、`out`にライブラリの情報を書き込みます。
+/// ローダーと互換性がある場合、提供されたコールバックを取り込みます。
+///
+/// # Safety
+///
+/// `loader`は有効な`LoaderInfo`を、`out`は書き込み可能な`HookLibraryDescriptor`を
+/// 指している必要がある
+#[no_mangle]
+pub unsafe extern "C" fn zpoline_hook_handshake(
+    loader: *const LoaderInfo,
+    out: *mut HookLibraryDescriptor,
+) {
+    if loader.is_null() {
+        return;
+    }
+    let Some(loader) = read_loader_info(loader) else {
+        return;
+    };
+
+    // ローダーが用意した領域を超えて書き込まない
+    let required = Capabilities(REQUIRED_CAPABILITIES.load(Ordering::Relaxed));
+    let descriptor = HookLibraryDescriptor::new(required);
+    if !out.is_null() {
+        let len =
+            (loader.descriptor_size as usize).min(std::mem::size_of::<HookLibraryDescriptor>());
+        std::ptr::copy_nonoverlapping(
+            &descriptor as *const HookLibraryDescriptor as *const u8,
+            out as *mut u8,
+            len,
+        );
+    }
+
+    // 互換性を確認してからコールバックを使う
+    if descriptor.check_compatible(&loader).is_err() {
+        return;
+    }
+
+    if loader
+        .capabilities
+        .contains(Capabilities::SHARED_THREAD_STATE)
+    {
+        if let Some(thread_state) = loader.callbacks.thread_hook_state {
+            crate::nesting::attach_guard_host(thread_state, loader.callbacks.max_depth);
+        }
+    }
+    if loader.capabilities.contains(Capabilities::HOT_RELOAD) {
+        if let Some(request_reload) = loader.callbacks.request_reload {
+            crate::reload::attach_host(request_reload);
+        }
+    }
+    if loader.capabilities.contains(Capabilities::CALL_CONTEXT) {
+        if let Some(current_frame) = loader.callbacks.current_frame {
+            crate::context::attach_host(current_frame);
+        }
+    }
+    if loader.capabilities.contains(Capabilities::INTEREST_FILTER) {
+        if let Some(extend_interests) = loader.callbacks.extend_interests {
+            crate::interests::attach_host(extend_interests);
+        }
+    }
+}
+
+/// ローダーの`LoaderInfo`を読む
+///
+/// 末尾のコールバックを持たない古いローダーの構造体は短いため、`size`の範囲の
+/// フィールドだけをコピーし、残りのコールバックは`None`のままにします。
+/// コールバックより前のフィールドが揃っていなければ`None`を返します。
+///
+/// # Safety
+///
+/// `loader`は少なくとも`size`バイトの`LoaderInfo`を指している必要がある
+unsafe fn read_loader_info(loader: *const LoaderInfo) -> Option<LoaderInfo> {
+    let size = std::ptr::addr_of!((*loader).size).read() as usize;
+    if size < std::mem::offset_of!(LoaderInfo, callbacks) {
+        return None;
+    }
+    // 途中で切れたフィールドは使わない
+    let len =
+        size.min(std::mem::size_of::<LoaderInfo>()) & !(std::mem::align_of::<LoaderInfo>() - 1);
+    let mut info: LoaderInfo = std::mem::zeroed();
+    std::ptr::copy_nonoverlapping(
+        loader as *const u8,
+        &mut info as *mut LoaderInfo as *mut u8,
+        len,
+    );
+    Some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader_info(capabilities: Capabilities) -> LoaderInfo {
+        LoaderInfo::new(
+            capabilities,
+            LoaderCallbacks {
+                thread_hook_state: None,
+                max_depth: std::ptr::null(),
+                request_reload: None,
+                current_frame: None,
+                extend_interests: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let loader = loader_info(Capabilities::INTEREST_FILTER.union(Capabilities::FORK_NOTIFY));
+        let library = HookLibraryDescriptor::new(Capabilities::FORK_NOTIFY);
+        assert_eq!(library.check_compatible(&loader), Ok(()));
+
+        let library = HookLibraryDescriptor::new(Capabilities::SHARED_THREAD_STATE);
+        assert_eq!(
+            library.check_compatible(&loader),
+            Err(AbiError::MissingCapabilities(
+                Capabilities::SHARED_THREAD_STATE
+            ))
+        );
+
+        let mut library = HookLibraryDescriptor::new(Capabilities::empty());
+        library.regs_size = 48;
+        let err = library.check_compatible(&loader).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SyscallRegs is 48 bytes (loader expects 56)"
+        );
+
+        library.abi_version = ZPOLINE_ABI_VERSION + 1;
+        assert!(matches!(
+            library.check_compatible(&loader),
+            Err(AbiError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_short_loader_info() {
+        extern "C" fn frame() -> *const SyscallFrame {
+            std::ptr::null()
+        }
+        extern "C" fn reload() {}
+
+        let mut loader = loader_info(Capabilities::CALL_CONTEXT);
+        loader.callbacks.request_reload = Some(reload);
+        loader.callbacks.current_frame = Some(frame);
+
+        // current_frameを持たないローダーの構造体
+        loader.size = std::mem::offset_of!(LoaderInfo, callbacks.current_frame) as u32;
+        let info = unsafe { read_loader_info(&loader) }.unwrap();
+        assert_eq!(info.abi_version, ZPOLINE_ABI_VERSION);
+        assert!(info.callbacks.request_reload.is_some());
+        assert!(info.callbacks.current_frame.is_none());
+
+        loader.size += 4;
+        let info = unsafe { read_loader_info(&loader) }.unwrap();
+        assert!(info.callbacks.current_frame.is_none());
+
+        loader.size = std::mem::size_of::<LoaderInfo>() as u32;
+        let info = unsafe { read_loader_info(&loader) }.unwrap();
+        assert!(info.callbacks.current_frame.is_some());
+
+        loader.size = 16;
+        assert!(unsafe { read_loader_info(&loader) }.is_none());
+    }
+
+    #[test]
+    fn test_handshake() {
+        let saved = REQUIRED_CAPABILITIES.load(Ordering::Relaxed);
+        require_capabilities(Capabilities::INTEREST_FILTER);
+        let loader = loader_info(Capabilities::INTEREST_FILTER);
+        let mut out = HookLibraryDescriptor::new(Capabilities::empty());
+        unsafe { zpoline_hook_handshake(&loader, &mut out) };
+        // 並行して走る他のテストに宣言を残さない
+        REQUIRED_CAPABILITIES.store(saved, Ordering::Relaxed);
+
+        assert_eq!(out.abi_version, ZPOLINE_ABI_VERSION);
+        assert_eq!(out.regs_size as usize, std::mem::size_of::<SyscallRegs>());
+        assert!(out
+            .required_capabilities
+            .contains(Capabilities::INTEREST_FILTER));
+        let api_version = unsafe { std::ffi::CStr::from_ptr(out.api_version) };
+        assert_eq!(api_version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+
+        let caps = Capabilities::INTEREST_FILTER.union(Capabilities(1 << 10));
+        assert_eq!(caps.to_string(), "INTEREST_FILTER|0x400");
+    }
+}
@@ -0,0 +1,227 @@
+//! フックライブラリに渡す設定引数
+//!
+//! ローダーは`ZPOLINE_HOOK`の`lib.so?key=value&...`と`ZPOLINE_HOOK_ARGS`を
+//! 解析し、ライブラリが`zpoline_hook_init_with_args`をエクスポートしていれば
+//! キーと値の組を渡します。同じ`.so`をプロセスごとに異なる設定で使えます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::args::{HookArgs, RawHookArgs};
+//!
+//! #[no_mangle]
+//! pub unsafe extern "C" fn zpoline_hook_init_with_args(args: *const RawHookArgs) -> *const () {
+//!     let args = HookArgs::from_raw(args);
+//!     let verbose = args.flag("verbose");
+//!     // ...
+//! #   let _ = verbose;
+//!     zpoline_hook_api::get_trait_dispatch_hook()
+//! }
+//! ```
+
+use std::ffi::{c_char, CStr, CString};
+use std::str::FromStr;
+
+/// 引数の区切り文字
+const SEPARATORS: [char; 2] = ['&', ','];
+
+/// `lib.so?args`形式の指定をパスと引数に分ける
+///
+/// `?`を含まない場合、引数は`None`になります。
+pub fn split_hook_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.split_once('?') {
+        Some((path, args)) => (path, Some(args)),
+        None => (spec, None),
+    }
+}
+
+/// 解析済みの引数（指定順を保持する）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookArgs {
+    pairs: Vec<(String, String)>,
+}
+
+impl HookArgs {
+    /// `key=value`を`&`または`,`で区切った文字列を解析する
+    ///
+    /// `=`のない項目は値が空文字列のフラグとして扱い、空の項目は無視します。
+    pub fn parse(s: &str) -> Self {
+        let mut args = Self::default();
+        args.extend_from_str(s);
+        args
+    }
+
+    /// 文字列を解析して末尾に追加する
+    pub fn extend_from_str(&mut self, s: &str) {
+        for item in s.split(SEPARATORS) {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            let (key, value) = item.split_once('=').unwrap_or((item, ""));
+            self.push(key.trim(), value.trim());
+        }
+    }
+
+    /// 引数を追加する
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+
+    /// キーに対応する値（同じキーが複数ある場合は後の指定を優先する）
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// キーに対応する値を解析する（ないか解析できない場合は`None`）
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// フラグが有効か（`verbose`、`verbose=1`、`verbose=true`など）
+    pub fn flag(&self, key: &str) -> bool {
+        matches!(self.get(key), Some("" | "1" | "true" | "yes" | "on"))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// C ABIで渡せる形式に変換する（NULを含む項目は除く）
+    pub fn to_raw(&self) -> RawHookArgsBuf {
+        let strings: Vec<(CString, CString)> = self
+            .pairs
+            .iter()
+            .filter_map(|(k, v)| {
+                Some((
+                    CString::new(k.as_str()).ok()?,
+                    CString::new(v.as_str()).ok()?,
+                ))
+            })
+            .collect();
+        let entries = strings
+            .iter()
+            .map(|(k, v)| RawHookArg {
+                key: k.as_ptr(),
+                value: v.as_ptr(),
+            })
+            .collect();
+        RawHookArgsBuf {
+            _strings: strings,
+            entries,
+        }
+    }
+
+    /// ローダーから渡された引数をコピーする
+    ///
+    /// # Safety
+    ///
+    /// `raw`はnullか、有効な`RawHookArgs`を指している必要がある
+    pub unsafe fn from_raw(raw: *const RawHookArgs) -> Self {
+        let mut args = Self::default();
+        let Some(raw) = raw.as_ref() else {
+            return args;
+        };
+        if raw.args.is_null() {
+            return args;
+        }
+        for entry in std::slice::from_raw_parts(raw.args, raw.len) {
+            if entry.key.is_null() {
+                continue;
+            }
+            let key = CStr::from_ptr(entry.key).to_string_lossy();
+            let value = if entry.value.is_null() {
+                "".into()
+            } else {
+                CStr::from_ptr(entry.value).to_string_lossy()
+            };
+            args.push(key, value);
+        }
+        args
+    }
+}
+
+/// C ABIの引数1つ（NUL終端文字列）
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawHookArg {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+/// `zpoline_hook_init_with_args`に渡される引数の配列
+///
+/// ポインタは呼び出しの間だけ有効です。
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RawHookArgs {
+    pub len: usize,
+    pub args: *const RawHookArg,
+}
+
+/// `RawHookArgs`が指す文字列を保持するバッファ
+pub struct RawHookArgsBuf {
+    _strings: Vec<(CString, CString)>,
+    entries: Vec<RawHookArg>,
+}
+
+impl RawHookArgsBuf {
+    /// このバッファが生きている間有効な`RawHookArgs`
+    pub fn as_raw(&self) -> RawHookArgs {
+        RawHookArgs {
+            len: self.entries.len(),
+            args: self.entries.as_ptr(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let args = HookArgs::parse("level=2&verbose, path=/tmp/a=b,,level=3");
+        assert_eq!(args.len(), 4);
+        assert_eq!(args.get("level"), Some("3"));
+        assert_eq!(args.get("path"), Some("/tmp/a=b"));
+        assert_eq!(args.get_parsed::<u32>("level"), Some(3));
+        assert!(args.flag("verbose"));
+        assert!(!args.flag("level"));
+        assert_eq!(args.get_parsed::<u32>("path"), None);
+        assert!(!args.contains("missing"));
+
+        assert_eq!(
+            split_hook_spec("/usr/lib/libhooks.so?level=2"),
+            ("/usr/lib/libhooks.so", Some("level=2"))
+        );
+        assert_eq!(split_hook_spec("libhooks.so"), ("libhooks.so", None));
+    }
+
+    #[test]
+    fn test_raw_roundtrip() {
+        let mut args = HookArgs::parse("a=1,b");
+        args.push("bad\0key", "x");
+        let buf = args.to_raw();
+        let raw = buf.as_raw();
+        assert_eq!(raw.len, 2);
+
+        let copied = unsafe { HookArgs::from_raw(&raw) };
+        assert_eq!(copied, HookArgs::parse("a=1,b"));
+        assert!(unsafe { HookArgs::from_raw(std::ptr::null()) }.is_empty());
+    }
+}
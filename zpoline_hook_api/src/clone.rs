@@ -0,0 +1,697 @@
+//! スレッドと子プロセスの作成（clone / clone3 / vfork）
+//!
+//! 新しいスタックを指定した`clone`/`clone3`では、子はカーネルから戻った時点で
+//! フックのフレームを持たないスタックにいます。`raw_syscall`はこれらを
+//! `raw_clone`で実行し、子のスタックに呼び出し元のレジスタと戻り先を積んでおいて、
+//! 子ではそこから直接アプリケーションに戻ります。
+//!
+//! `vfork`（スタックを指定しない`CLONE_VM|CLONE_VFORK`を含む）の子は親のスタックを
+//! 使い続けるため、フックのフレームを壊さないよう`CLONE_VM`を外して実行します。
+//! 親は子の`execve`/終了まで止まりますが、子のメモリへの書き込みは親に見えません。
+//!
+//! 新しいスタックを指定した`clone`/`clone3`を呼び出し元のフレームなしで
+//! （トランポリンを経由せずに`raw_syscall`から）実行すると、子の戻り先がないため
+//! `-ENOSYS`を返します。
+//!
+//! 新しいスタックを指定した`CLONE_VM|CLONE_VFORK`（`posix_spawn`など）で
+//! `CLONE_SETTLS`がない子は、親のスレッドとTLSを共有したまま`execve`します。
+//! 子で始めたフックは`execve`が成功すると終わらず、ネスト深さやディスパッチャの
+//! ロックが親に残ってしまうため、親が待っている間の子のシステムコールは
+//! フックに渡さずに実行します（`__set_vfork_child_hook`で登録した関数を除く）。
+//!
+//! `SyscallHooks::clone_child_fn`が返した関数は、子がアプリケーションに戻る前に
+//! 子で呼ばれます。子スレッドは親とフック実装を共有するため、`&mut self`ではなく
+//! 関数ポインタで渡します。
+
+use crate::context::SyscallContext;
+use crate::user_mem::{try_read_bytes, try_write_bytes, try_write_val, UserMemError};
+use crate::{raw_syscall, raw_syscall_bypass, raw_syscall_impl, HookFn, SyscallRegs, Sysno};
+use libc::{c_int, c_void, size_t};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// `clone_args`の最初の版（`CLONE_ARGS_SIZE_VER0`）のサイズ
+pub const CLONE_ARGS_SIZE_VER0: size_t = 64;
+
+/// `exit_signal`を表す下位8ビット
+pub const CSIGNAL: u64 = 0xff;
+
+/// フラグ名の表
+const FLAG_NAMES: &[(u64, &str)] = &[
+    (0x0000_0100, "CLONE_VM"),
+    (0x0000_0200, "CLONE_FS"),
+    (0x0000_0400, "CLONE_FILES"),
+    (0x0000_0800, "CLONE_SIGHAND"),
+    (0x0000_1000, "CLONE_PIDFD"),
+    (0x0000_2000, "CLONE_PTRACE"),
+    (0x0000_4000, "CLONE_VFORK"),
+    (0x0000_8000, "CLONE_PARENT"),
+    (0x0001_0000, "CLONE_THREAD"),
+    (0x0002_0000, "CLONE_NEWNS"),
+    (0x0004_0000, "CLONE_SYSVSEM"),
+    (0x0008_0000, "CLONE_SETTLS"),
+    (0x0010_0000, "CLONE_PARENT_SETTID"),
+    (0x0020_0000, "CLONE_CHILD_CLEARTID"),
+    (0x0040_0000, "CLONE_DETACHED"),
+    (0x0080_0000, "CLONE_UNTRACED"),
+    (0x0100_0000, "CLONE_CHILD_SETTID"),
+    (0x0200_0000, "CLONE_NEWCGROUP"),
+    (0x0400_0000, "CLONE_NEWUTS"),
+    (0x0800_0000, "CLONE_NEWIPC"),
+    (0x1000_0000, "CLONE_NEWUSER"),
+    (0x2000_0000, "CLONE_NEWPID"),
+    (0x4000_0000, "CLONE_NEWNET"),
+    (0x8000_0000, "CLONE_IO"),
+    (0x1_0000_0000, "CLONE_CLEAR_SIGHAND"),
+    (0x2_0000_0000, "CLONE_INTO_CGROUP"),
+];
+
+/// `clone`のフラグ（下位8ビットは子の終了時に親へ送るシグナル）
+///
+/// `clone3`では`clone_args`の`flags`と`exit_signal`をまとめたものです。
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CloneFlags(u64);
+
+impl CloneFlags {
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// `flags`（`libc::CLONE_*`）をすべて含むか
+    pub fn contains(&self, flags: c_int) -> bool {
+        let flags = flags as u32 as u64;
+        self.0 & flags == flags
+    }
+
+    /// 子の終了時に親へ送るシグナル（0なら送らない）
+    pub fn exit_signal(&self) -> c_int {
+        (self.0 & CSIGNAL) as c_int
+    }
+
+    /// 同じスレッドグループのスレッドを作る
+    pub fn is_thread(&self) -> bool {
+        self.contains(libc::CLONE_THREAD)
+    }
+
+    /// アドレス空間を共有する
+    pub fn shares_vm(&self) -> bool {
+        self.contains(libc::CLONE_VM)
+    }
+
+    /// 親が子の`execve`/終了まで止まる
+    ///
+    /// スタックを指定しない`CLONE_VM|CLONE_VFORK`（`vfork`を含む）は`CLONE_VM`を
+    /// 外して実行されるため、`clone_flags`が返すフラグでは`shares_vm`が偽になります。
+    pub fn is_vfork(&self) -> bool {
+        self.contains(libc::CLONE_VFORK)
+    }
+
+    /// 立っているフラグの名前（未知のビットは含まない）
+    pub fn names(&self) -> Vec<&'static str> {
+        FLAG_NAMES
+            .iter()
+            .filter(|(bit, _)| self.0 & bit != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+}
+
+impl fmt::Debug for CloneFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.names().into_iter().map(String::from).collect();
+        let known = FLAG_NAMES.iter().fold(CSIGNAL, |mask, (bit, _)| mask | bit);
+        if self.0 & !known != 0 {
+            parts.push(format!("{:#x}", self.0 & !known));
+        }
+        if self.exit_signal() != 0 {
+            parts.push(format!("sig={}", self.exit_signal()));
+        }
+        if parts.is_empty() {
+            f.write_str("0")
+        } else {
+            f.write_str(&parts.join("|"))
+        }
+    }
+}
+
+/// `clone3`の`struct clone_args`（`CLONE_ARGS_SIZE_VER2`）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneArgs {
+    pub flags: u64,
+    pub pidfd: u64,
+    pub child_tid: u64,
+    pub parent_tid: u64,
+    pub exit_signal: u64,
+    /// スタックの最下位アドレス（子のrspは`stack + stack_size`）
+    pub stack: u64,
+    pub stack_size: u64,
+    pub tls: u64,
+    pub set_tid: u64,
+    pub set_tid_size: u64,
+    pub cgroup: u64,
+}
+
+impl CloneArgs {
+    /// `flags`と`exit_signal`をまとめたフラグ
+    pub fn clone_flags(&self) -> CloneFlags {
+        CloneFlags(self.flags | (self.exit_signal & CSIGNAL))
+    }
+
+    /// 子のスタックの先頭（指定されていなければ`None`）
+    pub fn stack_top(&self) -> Option<u64> {
+        (self.stack != 0).then(|| self.stack.wrapping_add(self.stack_size))
+    }
+}
+
+/// `clone3`に渡された`clone_args`と`size`
+///
+/// アプリケーションがビルドされた時点の版によってサイズが異なるため、
+/// `size`分だけを読み書きします。
+#[derive(Debug, Clone, Copy)]
+pub struct CloneArgsPtr {
+    ptr: *mut CloneArgs,
+    size: size_t,
+}
+
+impl CloneArgsPtr {
+    pub fn new(ptr: *mut CloneArgs, size: size_t) -> Self {
+        Self { ptr, size }
+    }
+
+    pub fn as_ptr(&self) -> *mut CloneArgs {
+        self.ptr
+    }
+
+    /// アプリケーションが渡したサイズ
+    pub fn size(&self) -> size_t {
+        self.size
+    }
+
+    fn copy_len(&self) -> usize {
+        self.size.min(std::mem::size_of::<CloneArgs>())
+    }
+
+    /// 内容を読み取る（知らないフィールドは0、最初の版より小さければ`EINVAL`）
+    pub fn read(&self) -> Result<CloneArgs, UserMemError> {
+        if self.size < CLONE_ARGS_SIZE_VER0 {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        let mut args = CloneArgs::default();
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(&mut args as *mut CloneArgs as *mut u8, self.copy_len())
+        };
+        try_read_bytes(self.ptr as *const c_void, bytes)?;
+        Ok(args)
+    }
+
+    /// 内容を書き換える（`size`を超えるフィールドは書き込まない）
+    pub fn write(&self, args: &CloneArgs) -> Result<(), UserMemError> {
+        if self.size < CLONE_ARGS_SIZE_VER0 {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        let bytes = unsafe {
+            std::slice::from_raw_parts(args as *const CloneArgs as *const u8, self.copy_len())
+        };
+        try_write_bytes(self.ptr as *mut c_void, bytes)
+    }
+}
+
+/// 子がアプリケーションに戻る前に子で呼ぶ関数
+///
+/// 新しいスタックで作られた子スレッドでは、そのスタック上でアプリケーションの
+/// スレッドの初期化より前に呼ばれます。async-signal-safeな処理に留めてください。
+pub type CloneChildFn = fn(CloneFlags);
+
+thread_local! {
+    /// ディスパッチ中の`clone`で子に渡す関数
+    static CHILD_FN: Cell<Option<CloneChildFn>> = const { Cell::new(None) };
+}
+
+/// 子に渡す関数を設定し、以前の設定を返す
+pub(crate) fn set_child_fn(child_fn: Option<CloneChildFn>) -> Option<CloneChildFn> {
+    CHILD_FN.with(|current| current.replace(child_fn))
+}
+
+thread_local! {
+    /// TLSを共有する子の`execve`/終了を待っているスレッドのtid（待っていなければ0）
+    static VFORK_WAITER: Cell<i32> = const { Cell::new(0) };
+}
+
+/// 親が待っている間の子で実行する関数（zpoline_loaderで設定）
+static VFORK_CHILD_HOOK: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// 親のTLSを共有したまま、親を止めて実行される子を作るか
+pub(crate) fn shares_caller_tls(flags: CloneFlags) -> bool {
+    flags.shares_vm() && flags.is_vfork() && !flags.contains(libc::CLONE_SETTLS)
+}
+
+fn gettid() -> i32 {
+    unsafe { raw_syscall_bypass(Sysno::gettid.nr(), 0, 0, 0, 0, 0, 0) as i32 }
+}
+
+/// 子を待つ間のTLSに印を付けて`f`を実行する
+pub(crate) fn while_sharing_tls<R>(f: impl FnOnce() -> R) -> R {
+    let prev = VFORK_WAITER.with(|waiter| waiter.replace(gettid()));
+    let result = f();
+    VFORK_WAITER.with(|waiter| waiter.set(prev));
+    result
+}
+
+/// TLSを共有したまま親が待っている子で実行しているか
+pub(crate) fn in_vfork_child() -> bool {
+    let waiter = VFORK_WAITER.with(|waiter| waiter.get());
+    waiter != 0 && waiter != gettid()
+}
+
+/// 親が待っている間の子のシステムコールを実行する
+pub(crate) fn vfork_child_syscall(regs: &mut SyscallRegs) -> i64 {
+    let hook = VFORK_CHILD_HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        unsafe { raw_syscall(regs) }
+    } else {
+        let hook: HookFn = unsafe { std::mem::transmute(hook) };
+        hook(regs)
+    }
+}
+
+/// 親が待っている間の子で実行する関数を設定（zpoline_loader用）
+///
+/// 関数は`execve`で戻らなくても状態を残さないもの（mallocやロックを使わない）に
+/// 限られます。
+#[doc(hidden)]
+pub fn __set_vfork_child_hook(hook: Option<HookFn>) {
+    let ptr = hook.map_or(std::ptr::null_mut(), |hook| hook as *mut ());
+    VFORK_CHILD_HOOK.store(ptr, Ordering::Release);
+}
+
+/// 子を作るシステムコールか
+pub fn is_clone(nr: u64) -> bool {
+    matches!(
+        Sysno::from_raw(nr),
+        Some(Sysno::fork | Sysno::vfork | Sysno::clone | Sysno::clone3)
+    )
+}
+
+/// 作る子のフラグ（子を作らないシステムコールや読めない`clone_args`は`None`）
+///
+/// `vfork`として実行されるものは`CLONE_VM`を除いたフラグになります。
+pub fn clone_flags(regs: &SyscallRegs) -> Option<CloneFlags> {
+    let (flags, stack) = match Sysno::from_raw(regs.rax)? {
+        Sysno::fork => return Some(CloneFlags(libc::SIGCHLD as u64)),
+        Sysno::vfork => return Some(vfork_flags()),
+        Sysno::clone => (CloneFlags(regs.rdi), regs.rsi),
+        Sysno::clone3 => {
+            let args = CloneArgsPtr::new(regs.rdi as *mut CloneArgs, regs.rsi as size_t)
+                .read()
+                .ok()?;
+            (args.clone_flags(), args.stack_top().unwrap_or(0))
+        }
+        _ => return None,
+    };
+    Some(without_shared_stack(flags, stack))
+}
+
+/// `vfork`を実行するフラグ
+fn vfork_flags() -> CloneFlags {
+    CloneFlags((libc::CLONE_VFORK | libc::SIGCHLD) as u64)
+}
+
+/// スタックを指定しない`CLONE_VM|CLONE_VFORK`は`CLONE_VM`を外して実行する
+///
+/// 子が親のスタックでフックから戻るとフックのフレームを壊すため、メモリを
+/// コピーさせます。親が子の`execve`/終了まで止まるのは変わりません。
+fn without_shared_stack(flags: CloneFlags, stack: u64) -> CloneFlags {
+    let vfork = (libc::CLONE_VM | libc::CLONE_VFORK) as u64;
+    if stack == 0 && flags.0 & vfork == vfork {
+        CloneFlags(flags.0 & !(libc::CLONE_VM as u64))
+    } else {
+        flags
+    }
+}
+
+/// 子のスタックに積むレコード
+///
+/// 子はカーネルから戻るとrspがこのレコードを指しているので、`child_fn`を呼んでから
+/// 呼び出し元のレジスタを復元し、`rip`に戻る（このときrspは指定されたスタックの先頭）。
+///
+/// 引数レジスタも、フックが`default_clone3`などで組み立て直した値ではなく
+/// 呼び出し元の値を復元する（glibcの`__clone3`は子でrdxの関数をr8の引数で呼ぶ）。
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ChildRecord {
+    entry: u64,
+    child_fn: u64,
+    flags: u64,
+    rbx: u64,
+    rbp: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rdi: u64,
+    rsi: u64,
+    rdx: u64,
+    r10: u64,
+    r8: u64,
+    r9: u64,
+    rip: u64,
+}
+
+const RECORD_SIZE: u64 = std::mem::size_of::<ChildRecord>() as u64;
+
+// SyscallRegsのシステムコールを実行し、子ではChildRecordからアプリケーションに戻る
+std::arch::global_asm!(
+    ".pushsection .text.zpoline_clone_on_stack, \"ax\", @progbits",
+    ".globl zpoline_clone_on_stack",
+    ".hidden zpoline_clone_on_stack",
+    "zpoline_clone_on_stack:",
+    "mov rax, [rdi]",
+    "mov rsi, [rdi + 16]",
+    "mov rdx, [rdi + 24]",
+    "mov r10, [rdi + 32]",
+    "mov r8, [rdi + 40]",
+    "mov r9, [rdi + 48]",
+    "mov rdi, [rdi + 8]",
+    "syscall",
+    "test rax, rax",
+    "jz 2f",
+    "ret",
+    // 子: rsp = ChildRecord
+    "2:",
+    "mov rax, [rsp]",
+    "test rax, rax",
+    "jz 3f",
+    "mov rbx, rsp",
+    "mov rdi, rsp",
+    "and rsp, -16",
+    "call rax",
+    "mov rsp, rbx",
+    "3:",
+    "mov rbx, [rsp + 24]",
+    "mov rbp, [rsp + 32]",
+    "mov r12, [rsp + 40]",
+    "mov r13, [rsp + 48]",
+    "mov r14, [rsp + 56]",
+    "mov r15, [rsp + 64]",
+    // 引数レジスタはアプリケーションが子で使うことがある（glibcのclone3など）
+    "mov rdi, [rsp + 72]",
+    "mov rsi, [rsp + 80]",
+    "mov rdx, [rsp + 88]",
+    "mov r10, [rsp + 96]",
+    "mov r8, [rsp + 104]",
+    "mov r9, [rsp + 112]",
+    "add rsp, 120",
+    "xor eax, eax",
+    "ret",
+    ".popsection",
+);
+
+extern "C" {
+    fn zpoline_clone_on_stack(regs: *const SyscallRegs) -> i64;
+}
+
+/// 新しいスタック上の子で`child_fn`を呼ぶ
+extern "C" fn child_entry(record: *const ChildRecord) {
+    let record = unsafe { *record };
+    let child_fn: CloneChildFn = unsafe { std::mem::transmute(record.child_fn as usize) };
+    child_fn(CloneFlags(record.flags));
+}
+
+/// 子を作るシステムコールを実行する（`raw_syscall`から呼ばれる）
+///
+/// # Safety
+///
+/// `raw_syscall`と同じ
+pub(crate) unsafe fn raw_clone(regs: &SyscallRegs) -> i64 {
+    let raw = |regs: &SyscallRegs| {
+        raw_syscall_impl(
+            regs.rax, regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9,
+        )
+    };
+    let child_fn = CHILD_FN.with(|current| current.get());
+
+    match Sysno::from_raw(regs.rax) {
+        Some(Sysno::vfork) => {
+            let vfork = SyscallRegs::new(Sysno::clone.nr(), vfork_flags().0, 0, 0, 0, 0, 0);
+            raw(&vfork)
+        }
+        Some(Sysno::clone) => {
+            let flags = CloneFlags(regs.rdi);
+            if regs.rsi == 0 {
+                return raw(&regs.with_arg(0, without_shared_stack(flags, 0).0));
+            }
+            // 呼び出し元のフレームがなければ子の戻り先がない
+            let Some(caller) = caller_frame() else {
+                return -libc::ENOSYS as i64;
+            };
+            match push_record(regs.rsi, flags, child_fn, &caller) {
+                Ok(sp) => zpoline_clone_on_stack(&regs.with_arg(1, sp)),
+                Err(err) => err,
+            }
+        }
+        Some(Sysno::clone3) => {
+            let ptr = CloneArgsPtr::new(regs.rdi as *mut CloneArgs, regs.rsi as size_t);
+            let Ok(mut args) = ptr.read() else {
+                // 不正な引数はカーネルにエラーを返させる
+                return raw(regs);
+            };
+            let size = ptr.copy_len() as u64;
+            let Some(top) = args.stack_top() else {
+                let flags = without_shared_stack(args.clone_flags(), 0);
+                args.flags = flags.0 & !CSIGNAL;
+                return raw(&regs
+                    .with_arg(0, &args as *const CloneArgs as u64)
+                    .with_arg(1, size));
+            };
+            let Some(caller) = caller_frame() else {
+                return -libc::ENOSYS as i64;
+            };
+            match push_record(top, args.clone_flags(), child_fn, &caller) {
+                Ok(sp) => {
+                    args.stack_size = sp - args.stack;
+                    let regs = regs
+                        .with_arg(0, &args as *const CloneArgs as u64)
+                        .with_arg(1, size);
+                    zpoline_clone_on_stack(&regs)
+                }
+                Err(err) => err,
+            }
+        }
+        _ => raw(regs),
+    }
+}
+
+/// 実行中のフックの呼び出し元（子が戻る先とそのときのレジスタ）
+fn caller_frame() -> Option<(SyscallContext, SyscallRegs)> {
+    Some((crate::context::current()?, crate::context::current_regs()?))
+}
+
+/// 子のスタックの先頭`top`の直下にレコードを書き、子のrspを返す
+fn push_record(
+    top: u64,
+    flags: CloneFlags,
+    child_fn: Option<CloneChildFn>,
+    (ctx, regs): &(SyscallContext, SyscallRegs),
+) -> Result<u64, i64> {
+    let sp = top.checked_sub(RECORD_SIZE).ok_or(-libc::EINVAL as i64)?;
+    let record = ChildRecord {
+        entry: child_fn.map_or(0, |_| child_entry as *const () as u64),
+        child_fn: child_fn.map_or(0, |f| f as *const () as u64),
+        flags: flags.0,
+        rbx: ctx.rbx,
+        rbp: ctx.rbp,
+        r12: ctx.r12,
+        r13: ctx.r13,
+        r14: ctx.r14,
+        r15: ctx.r15,
+        rdi: regs.rdi,
+        rsi: regs.rsi,
+        rdx: regs.rdx,
+        r10: regs.r10,
+        r8: regs.r8,
+        r9: regs.r9,
+        rip: ctx.return_address(),
+    };
+    try_write_val(sp as *mut ChildRecord, record).map_err(|_| -libc::EFAULT as i64)?;
+    Ok(sp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    #[test]
+    fn test_clone_flags() {
+        let thread = libc::CLONE_VM
+            | libc::CLONE_FS
+            | libc::CLONE_FILES
+            | libc::CLONE_SIGHAND
+            | libc::CLONE_THREAD
+            | libc::CLONE_SYSVSEM
+            | libc::CLONE_SETTLS
+            | libc::CLONE_PARENT_SETTID
+            | libc::CLONE_CHILD_CLEARTID;
+        let flags = CloneFlags::from_bits(thread as u64);
+        assert!(flags.is_thread() && flags.shares_vm() && !flags.is_vfork());
+        assert_eq!(flags.exit_signal(), 0);
+        assert_eq!(flags.names().len(), 9);
+
+        let fork = CloneFlags::from_bits(libc::SIGCHLD as u64 | 1 << 40);
+        assert_eq!(format!("{:?}", fork), "0x10000000000|sig=17");
+
+        // vforkはCLONE_VMを外して実行する
+        let vfork = SyscallRegs::new(Sysno::vfork.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(clone_flags(&vfork), Some(vfork_flags()));
+        assert!(vfork_flags().is_vfork() && !vfork_flags().shares_vm());
+        let spawn = (libc::CLONE_VM | libc::CLONE_VFORK | libc::SIGCHLD) as u64;
+        let clone = SyscallRegs::new(Sysno::clone.nr(), spawn, 0, 0, 0, 0, 0);
+        assert_eq!(clone_flags(&clone), Some(vfork_flags()));
+        let clone = clone.with_arg(1, 0x1000);
+        assert_eq!(clone_flags(&clone), Some(CloneFlags(spawn)));
+
+        // clone_argsは渡されたサイズだけ読む
+        let mut args = [u64::MAX; 11];
+        args[0] = libc::CLONE_VM as u64;
+        args[4] = libc::SIGCHLD as u64;
+        let ptr = CloneArgsPtr::new(args.as_mut_ptr() as *mut CloneArgs, 64);
+        let read = ptr.read().unwrap();
+        assert_eq!(
+            read.clone_flags().bits(),
+            (libc::CLONE_VM | libc::SIGCHLD) as u64
+        );
+        assert_eq!(read.set_tid, 0);
+        let short = CloneArgsPtr::new(args.as_mut_ptr() as *mut CloneArgs, 56);
+        assert_eq!(short.read(), Err(UserMemError::Os(libc::EINVAL)));
+    }
+
+    static CHILD_RBX: AtomicU64 = AtomicU64::new(0);
+    static CHILD_RDX: AtomicU64 = AtomicU64::new(0);
+    static CHILD_FN_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    // 子の戻り先: rbxとrdxを記録してスレッドを終了する
+    std::arch::global_asm!(
+        ".pushsection .text.zpoline_test_clone_landing, \"ax\", @progbits",
+        ".hidden zpoline_test_clone_landing",
+        "zpoline_test_clone_landing:",
+        "mov rdi, rbx",
+        "mov rsi, rdx",
+        "call {child_main}",
+        "ud2",
+        ".popsection",
+        child_main = sym child_main,
+    );
+
+    extern "C" {
+        fn zpoline_test_clone_landing();
+    }
+
+    extern "C" fn child_main(rbx: u64, rdx: u64) -> ! {
+        CHILD_RDX.store(rdx, Ordering::SeqCst);
+        CHILD_RBX.store(rbx, Ordering::SeqCst);
+        unsafe { crate::raw_syscall_bypass(Sysno::exit.nr(), 0, 0, 0, 0, 0, 0) };
+        unreachable!();
+    }
+
+    fn count_child(flags: CloneFlags) {
+        if flags.is_thread() {
+            CHILD_FN_CALLS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_clone_on_new_stack() {
+        // 子は新しいスタックで、呼び出し元のrbxと引数レジスタを復元して戻り先に着く
+        let stack = vec![0u8; 64 * 1024].leak();
+        let top = (stack.as_ptr() as u64 + stack.len() as u64) & !15;
+        let ctx = SyscallContext {
+            rip: zpoline_test_clone_landing as *const () as u64 - 2,
+            rsp: 0,
+            rbx: 0x1234_5678,
+            rbp: 0,
+            r12: 0,
+            r13: 0,
+            r14: 0,
+            r15: 0,
+        };
+        let flags = CloneFlags::from_bits(
+            (libc::CLONE_VM | libc::CLONE_SIGHAND | libc::CLONE_THREAD | libc::CLONE_FS) as u64,
+        );
+        // 呼び出し元のrdxは、フックが組み立て直したレジスタ（rdx=0）ではなくこちらが復元される
+        let caller = SyscallRegs::new(Sysno::clone.nr(), flags.bits(), top, 0x5555, 0, 0, 0);
+        let sp = push_record(top, flags, Some(count_child), &(ctx, caller)).unwrap();
+        let regs = SyscallRegs::new(Sysno::clone.nr(), flags.bits(), sp, 0, 0, 0, 0);
+        let tid = unsafe { zpoline_clone_on_stack(&regs) };
+        assert!(tid > 0);
+
+        let start = std::time::Instant::now();
+        while CHILD_RBX.load(Ordering::SeqCst) == 0 {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::yield_now();
+        }
+        assert_eq!(CHILD_RBX.load(Ordering::SeqCst), 0x1234_5678);
+        assert_eq!(CHILD_RDX.load(Ordering::SeqCst), 0x5555);
+        assert_eq!(CHILD_FN_CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_clone_on_new_stack_without_frame() {
+        // トランポリンを経由しない呼び出しでは子を作らずにENOSYS
+        assert_eq!(crate::context::current(), None);
+        let stack = vec![0u8; 4096];
+        let top = stack.as_ptr() as u64 + stack.len() as u64;
+        let flags = (libc::CLONE_VM | libc::CLONE_SIGHAND | libc::CLONE_THREAD) as u64;
+        let regs = SyscallRegs::new(Sysno::clone.nr(), flags, top, 0, 0, 0, 0);
+        assert_eq!(unsafe { raw_clone(&regs) }, -libc::ENOSYS as i64);
+
+        let mut args = CloneArgs {
+            flags,
+            stack: stack.as_ptr() as u64,
+            stack_size: stack.len() as u64,
+            ..CloneArgs::default()
+        };
+        let regs = SyscallRegs::new(
+            Sysno::clone3.nr(),
+            &mut args as *mut CloneArgs as u64,
+            std::mem::size_of::<CloneArgs>() as u64,
+            0,
+            0,
+            0,
+            0,
+        );
+        assert_eq!(unsafe { raw_clone(&regs) }, -libc::ENOSYS as i64);
+    }
+
+    #[test]
+    fn test_vfork_child_shares_tls() {
+        let spawn = CloneFlags((libc::CLONE_VM | libc::CLONE_VFORK | libc::SIGCHLD) as u64);
+        assert!(shares_caller_tls(spawn));
+        assert!(!shares_caller_tls(vfork_flags()));
+        assert!(!shares_caller_tls(CloneFlags(spawn.0 | libc::CLONE_SETTLS as u64)));
+
+        // 待っているスレッド自身は子ではない
+        assert!(!in_vfork_child());
+        while_sharing_tls(|| assert!(!in_vfork_child()));
+        assert!(!in_vfork_child());
+
+        // 別のtidから見れば子
+        VFORK_WAITER.with(|waiter| waiter.set(1));
+        assert!(in_vfork_child());
+        VFORK_WAITER.with(|waiter| waiter.set(0));
+
+        extern "C" fn child_hook(regs: &mut SyscallRegs) -> i64 {
+            regs.rax as i64 + 1000
+        }
+        let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        __set_vfork_child_hook(Some(child_hook));
+        assert_eq!(vfork_child_syscall(&mut regs), 1039);
+        __set_vfork_child_hook(None);
+        assert_eq!(vfork_child_syscall(&mut regs), std::process::id() as i64);
+    }
+}
@@ -0,0 +1,260 @@
+//! システムコールの呼び出し元のコンテキスト
+//!
+//! トランポリンは`SyscallRegs`に続けて残りの汎用レジスタと戻り先アドレスを
+//! スタックに積み（`SyscallFrame`）、`hook_entry_frame`を呼びます。
+//! フック関数の実行中は`current()`で呼び出し元のアドレスやrspを取得できるため、
+//! 呼び出し元のライブラリや関数ごとにフィルタしたり、トレースに含めたりできます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::{context, hook_log};
+//!
+//! if let Some(ctx) = context::current() {
+//!     if let Some((object, offset)) = ctx.caller_object() {
+//!         hook_log!("syscall from {:?}+{:#x}", object, offset);
+//!     }
+//! }
+//! ```
+
+use crate::SyscallRegs;
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// 書き換え後の`callq *%rax`の長さ
+const CALL_INSN_LEN: u64 = 2;
+
+/// トランポリンがスタックに積むフレーム
+///
+/// `regs`以外はフックから書き換えても呼び出し元には反映されません。
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallFrame {
+    pub regs: SyscallRegs,
+    pub rbx: u64,
+    pub rbp: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    /// 呼び出し元のレッドゾーン（トランポリンが避けた領域）
+    pub red_zone: [u64; 16],
+    /// `callq *%rax`が積んだ戻り先
+    pub return_address: u64,
+}
+
+/// フック実行中のシステムコールの呼び出し元
+///
+/// rcxとr11は`syscall`命令で破壊されるため含みません。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyscallContext {
+    /// `syscall`命令（書き換え後は`callq *%rax`）のアドレス
+    pub rip: u64,
+    /// `syscall`命令の時点のrsp
+    pub rsp: u64,
+    pub rbx: u64,
+    pub rbp: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+}
+
+impl SyscallContext {
+    /// # Safety
+    ///
+    /// `frame`は有効な`SyscallFrame`を指している必要がある
+    unsafe fn from_frame(frame: *const SyscallFrame) -> Self {
+        let return_address = std::ptr::addr_of!((*frame).return_address);
+        Self {
+            rip: (*return_address).wrapping_sub(CALL_INSN_LEN),
+            rsp: return_address as u64 + std::mem::size_of::<u64>() as u64,
+            rbx: (*frame).rbx,
+            rbp: (*frame).rbp,
+            r12: (*frame).r12,
+            r13: (*frame).r13,
+            r14: (*frame).r14,
+            r15: (*frame).r15,
+        }
+    }
+
+    /// `syscall`の次の命令（フックから戻る先）
+    pub fn return_address(&self) -> u64 {
+        self.rip.wrapping_add(CALL_INSN_LEN)
+    }
+
+    /// `rip`を含むオブジェクトのパスとオブジェクト内のオフセット（`dladdr`で調べる）
+    ///
+    /// ヒープ確保をしないのでフックの中でも使えます。ただし`dladdr`はld.soのロックを
+    /// 取るため、別のスレッドが`dlopen`している間は待たされます。パスは
+    /// オブジェクトがアンロードされるまで有効です。
+    pub fn caller_object(&self) -> Option<(&'static CStr, u64)> {
+        let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+        if unsafe { libc::dladdr(self.rip as *const libc::c_void, &mut info) } == 0 {
+            return None;
+        }
+        let object = if info.dli_fname.is_null() {
+            c""
+        } else {
+            unsafe { CStr::from_ptr(info.dli_fname) }
+        };
+        Some((object, self.rip.wrapping_sub(info.dli_fbase as u64)))
+    }
+
+    /// `rip`を含むオブジェクトとシンボル（`dladdr`で調べる）
+    ///
+    /// `String`を確保し、ld.soのロックも取るため、フックの中では使わないでください
+    /// （`malloc`の内部から発行されたシステムコールなどでデッドロックします）。
+    /// フックでは`rip`か`caller_object`を記録し、解決はフックの外で行います。
+    pub fn caller(&self) -> Option<Caller> {
+        let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+        if unsafe { libc::dladdr(self.rip as *const libc::c_void, &mut info) } == 0 {
+            return None;
+        }
+        let to_string = |s: *const libc::c_char| {
+            (!s.is_null()).then(|| unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned())
+        };
+        let base = info.dli_fbase as u64;
+        Some(Caller {
+            object: to_string(info.dli_fname).unwrap_or_default(),
+            base,
+            offset: self.rip.wrapping_sub(base),
+            symbol: to_string(info.dli_sname),
+            symbol_address: info.dli_saddr as u64,
+        })
+    }
+}
+
+/// 呼び出し元のオブジェクト
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// オブジェクトのパス（メインプログラムは空文字列のことがある）
+    pub object: String,
+    /// オブジェクトのロードアドレス
+    pub base: u64,
+    /// `rip`のオブジェクト内のオフセット
+    pub offset: u64,
+    /// `rip`を含むシンボル（動的シンボルのみ）
+    pub symbol: Option<String>,
+    pub symbol_address: u64,
+}
+
+thread_local! {
+    /// 実行中のフックのフレーム（ネストした場合は最も内側）
+    static CURRENT_FRAME: Cell<*const SyscallFrame> = const { Cell::new(std::ptr::null()) };
+}
+
+/// ローダー側のフレーム取得関数（`dlmopen`されたライブラリでのみ設定される）
+static HOST_CURRENT_FRAME: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// `frame`を現在のフレームとして`f`を実行する
+fn with_frame<R>(frame: *const SyscallFrame, f: impl FnOnce() -> R) -> R {
+    let prev = CURRENT_FRAME.with(|current| current.replace(frame));
+    let result = f();
+    CURRENT_FRAME.with(|current| current.set(prev));
+    result
+}
+
+/// トランポリンのエントリポイント
+///
+/// # Safety
+///
+/// `frame`はトランポリンが積んだ`SyscallFrame`を指している必要がある
+#[no_mangle]
+pub unsafe extern "C" fn hook_entry_frame(frame: *mut SyscallFrame) -> i64 {
+    with_frame(frame, || crate::hook_entry(&mut (*frame).regs))
+}
+
+/// 実行中のフックのフレームへのポインタ（zpoline_loader用）
+#[doc(hidden)]
+pub extern "C" fn __current_frame() -> *const SyscallFrame {
+    CURRENT_FRAME.with(|current| current.get())
+}
+
+/// ローダーのフレームを使うように切り替える（ABIハンドシェイクで呼ばれる）
+pub(crate) fn attach_host(current_frame: extern "C" fn() -> *const SyscallFrame) {
+    HOST_CURRENT_FRAME.store(current_frame as *mut (), Ordering::Release);
+}
+
+/// 実行中のフックの呼び出し元
+///
+/// フックの外や、トランポリンを経由しない呼び出し（ローダーが
+/// `CALL_CONTEXT`に対応していない場合など）では`None`を返します。
+pub fn current() -> Option<SyscallContext> {
+    let frame = current_frame();
+    (!frame.is_null()).then(|| unsafe { SyscallContext::from_frame(frame) })
+}
+
+/// 実行中のフックの呼び出し元が`syscall`命令に渡したレジスタ
+///
+/// フックから戻るとトランポリンがこの値を呼び出し元に復元します。
+pub(crate) fn current_regs() -> Option<SyscallRegs> {
+    let frame = current_frame();
+    (!frame.is_null()).then(|| unsafe { std::ptr::addr_of!((*frame).regs).read() })
+}
+
+fn current_frame() -> *const SyscallFrame {
+    let host = HOST_CURRENT_FRAME.load(Ordering::Acquire);
+    if host.is_null() {
+        __current_frame()
+    } else {
+        let host: extern "C" fn() -> *const SyscallFrame = unsafe { std::mem::transmute(host) };
+        host()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(return_address: u64) -> SyscallFrame {
+        SyscallFrame {
+            regs: SyscallRegs::zero(),
+            rbx: 1,
+            rbp: 2,
+            r12: 3,
+            r13: 4,
+            r14: 5,
+            r15: 6,
+            red_zone: [0; 16],
+            return_address,
+        }
+    }
+
+    #[test]
+    fn test_frame_layout() {
+        // トランポリンのpush順と一致している必要がある
+        assert_eq!(std::mem::offset_of!(SyscallFrame, rbx), 56);
+        assert_eq!(std::mem::offset_of!(SyscallFrame, r15), 96);
+        assert_eq!(
+            std::mem::offset_of!(SyscallFrame, return_address),
+            104 + 0x80
+        );
+        assert_eq!(std::mem::size_of::<SyscallFrame>(), 240);
+    }
+
+    #[test]
+    fn test_current() {
+        assert_eq!(current(), None);
+
+        let site = test_current as *const () as u64;
+        let outer = frame(site + CALL_INSN_LEN);
+        let inner = frame(0x1002);
+        with_frame(&outer, || {
+            let ctx = current().unwrap();
+            assert_eq!(ctx.rip, site);
+            assert_eq!(ctx.rsp, &outer.return_address as *const u64 as u64 + 8);
+            assert_eq!((ctx.rbx, ctx.rbp, ctx.r15), (1, 2, 6));
+
+            let caller = ctx.caller().unwrap();
+            assert_eq!(caller.base + caller.offset, site);
+            assert!(!caller.object.is_empty());
+            let (object, offset) = ctx.caller_object().unwrap();
+            assert_eq!(object.to_string_lossy(), caller.object);
+            assert_eq!(offset, caller.offset);
+
+            with_frame(&inner, || assert_eq!(current().unwrap().rip, 0x1000));
+            assert_eq!(current().unwrap().rip, site);
+        });
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,345 @@
+//! ディレクトリエントリ（getdents64）のバッファ
+//!
+//! `getdents64`が書き込む`struct linux_dirent64`の列を`DirEntry`として読み取り、
+//! 書き換えて書き戻すためのビューです。エントリを隠す・追加する・名前を
+//! 変えるフックに使います。アプリケーションメモリへのアクセスはすべてフォールトセーフです。
+//!
+//! 書き換えたエントリがバッファに収まらない場合は、収まらなかった最初のエントリから
+//! 次の`getdents64`で読み直せるようにディレクトリの位置を戻します。
+//!
+//! ```no_run
+//! use zpoline_hook_api::dirent::DirEntries;
+//! use zpoline_hook_api::syscall_hooks::default_getdents64;
+//!
+//! fn hook_getdents64(fd: libc::c_int, dirp: DirEntries) -> libc::ssize_t {
+//!     loop {
+//!         let n = default_getdents64(fd, dirp);
+//!         if n <= 0 {
+//!             return n;
+//!         }
+//!         match dirp.edit(fd, n as usize, |entries| entries.retain(|e| e.name != b"secret")) {
+//!             // すべて隠した場合に0を返すとディレクトリの終端になるので続きを読む
+//!             Ok(0) => continue,
+//!             Ok(len) => return len as libc::ssize_t,
+//!             Err(e) => return -e.errno() as libc::ssize_t,
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::user_mem::{try_read_bytes, try_write_bytes, UserMemError};
+use crate::{raw_syscall, SyscallRegs, Sysno};
+use libc::{c_int, c_uint, c_void};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+/// `d_ino`・`d_off`・`d_reclen`・`d_type`の長さ（`d_name`の開始位置）
+const HEADER_LEN: usize = 19;
+
+/// ディレクトリエントリ（`struct linux_dirent64`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub ino: u64,
+    /// 次のエントリの位置（`telldir`の値）。0のエントリは直前のエントリの値を引き継ぐ
+    pub off: i64,
+    /// `DT_REG`や`DT_DIR`など
+    pub d_type: u8,
+    /// 終端のNULを含まない名前
+    pub name: Vec<u8>,
+}
+
+impl DirEntry {
+    /// 追加するエントリを作成する
+    pub fn new(ino: u64, d_type: u8, name: impl AsRef<OsStr>) -> Self {
+        Self {
+            ino,
+            off: 0,
+            d_type,
+            name: name.as_ref().as_bytes().to_vec(),
+        }
+    }
+
+    pub fn name(&self) -> &OsStr {
+        OsStr::from_bytes(&self.name)
+    }
+
+    /// エンコードした長さ（`d_reclen`、8バイト境界に揃える）
+    pub fn reclen(&self) -> usize {
+        (HEADER_LEN + self.name.len() + 1).next_multiple_of(8)
+    }
+}
+
+/// `getdents64`に渡された出力バッファ
+#[derive(Debug, Clone, Copy)]
+pub struct DirEntries {
+    ptr: *mut c_void,
+    count: c_uint,
+}
+
+impl DirEntries {
+    pub fn new(ptr: *mut c_void, count: c_uint) -> Self {
+        Self { ptr, count }
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.ptr
+    }
+
+    /// バッファの長さ（`count`）
+    pub fn capacity(&self) -> usize {
+        self.count as usize
+    }
+
+    /// 先頭`len`バイトのエントリを読み取る（デフォルト実装の戻り値を渡す）
+    ///
+    /// エントリの途中で切らないよう、カーネルが書き込んだ分をすべて読み取ります。
+    pub fn read(&self, len: usize) -> Result<Vec<DirEntry>, UserMemError> {
+        let len = len.min(self.capacity());
+        let mut buf = vec![0u8; len];
+        if !buf.is_empty() {
+            try_read_bytes(self.ptr, &mut buf)?;
+        }
+        decode(&buf)
+    }
+
+    /// `fd`から読んだエントリを書き込み、書き込んだバイト数を返す（フックの戻り値になる）
+    ///
+    /// `capacity`に収まらないエントリがある場合は、書き込んだ最後のエントリの`off`へ
+    /// `fd`の位置を戻し、残りを次の`getdents64`でカーネルから読み直させます。
+    /// そのためエントリはカーネルが返した順序のままにしてください（追加したエントリは
+    /// 直前のエントリの`off`を引き継ぐため、読み直されません）。
+    ///
+    /// 位置を戻せない場合、つまり読んだエントリが1つも収まらない場合は
+    /// カーネルと同じ`EINVAL`（バッファが小さすぎる）を返します。
+    pub fn write(&self, fd: c_int, entries: &[DirEntry]) -> Result<usize, UserMemError> {
+        let (buf, written) = encode(entries, self.capacity());
+        if written < entries.len() {
+            let resume = entries[..written].iter().rev().find(|e| e.off != 0);
+            let Some(resume) = resume else {
+                return Err(UserMemError::Os(libc::EINVAL));
+            };
+            let regs = SyscallRegs::new(
+                Sysno::lseek.nr(),
+                fd as u64,
+                resume.off as u64,
+                libc::SEEK_SET as u64,
+                0,
+                0,
+                0,
+            );
+            let ret = unsafe { raw_syscall(&regs) };
+            if ret < 0 {
+                return Err(UserMemError::Os(-ret as i32));
+            }
+        }
+        if !buf.is_empty() {
+            try_write_bytes(self.ptr, &buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// 先頭`len`バイトのエントリを読み取り、`f`で書き換えて書き戻す（`write`を参照）
+    pub fn edit(
+        &self,
+        fd: c_int,
+        len: usize,
+        f: impl FnOnce(&mut Vec<DirEntry>),
+    ) -> Result<usize, UserMemError> {
+        let mut entries = self.read(len)?;
+        f(&mut entries);
+        self.write(fd, &entries)
+    }
+}
+
+/// `linux_dirent64`の列を解析する（壊れたエントリは`EIO`）
+fn decode(buf: &[u8]) -> Result<Vec<DirEntry>, UserMemError> {
+    let mut entries = Vec::new();
+    let mut rest = buf;
+    while !rest.is_empty() {
+        if rest.len() < HEADER_LEN {
+            return Err(UserMemError::Os(libc::EIO));
+        }
+        let reclen = u16::from_ne_bytes([rest[16], rest[17]]) as usize;
+        if reclen <= HEADER_LEN || reclen > rest.len() {
+            return Err(UserMemError::Os(libc::EIO));
+        }
+        let name = &rest[HEADER_LEN..reclen];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        entries.push(DirEntry {
+            ino: u64::from_ne_bytes(rest[0..8].try_into().unwrap()),
+            off: i64::from_ne_bytes(rest[8..16].try_into().unwrap()),
+            d_type: rest[18],
+            name: name[..name_len].to_vec(),
+        });
+        rest = &rest[reclen..];
+    }
+    Ok(entries)
+}
+
+/// `capacity`に収まるだけエンコードし、エンコードしたエントリの数も返す
+fn encode(entries: &[DirEntry], capacity: usize) -> (Vec<u8>, usize) {
+    let mut buf = Vec::new();
+    let mut off = 0;
+    let mut count = 0;
+    for entry in entries {
+        let reclen = entry.reclen();
+        if buf.len() + reclen > capacity || reclen > u16::MAX as usize {
+            break;
+        }
+        count += 1;
+        if entry.off != 0 {
+            off = entry.off;
+        }
+        let start = buf.len();
+        buf.extend_from_slice(&entry.ino.to_ne_bytes());
+        buf.extend_from_slice(&off.to_ne_bytes());
+        buf.extend_from_slice(&(reclen as u16).to_ne_bytes());
+        buf.push(entry.d_type);
+        buf.extend_from_slice(&entry.name);
+        buf.resize(start + reclen, 0);
+    }
+    (buf, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut entries = vec![
+            DirEntry::new(1, libc::DT_DIR, "."),
+            DirEntry::new(2, libc::DT_REG, "a-longer-file-name"),
+        ];
+        entries[0].off = 10;
+        let (buf, count) = encode(&entries, 4096);
+        assert_eq!(count, 2);
+        assert_eq!(buf.len(), 24 + 40);
+
+        let decoded = decode(&buf).unwrap();
+        assert_eq!(decoded[0], entries[0]);
+        // off=0のエントリは直前の値を引き継ぐ
+        assert_eq!(decoded[1].off, 10);
+        assert_eq!(decoded[1].name(), "a-longer-file-name");
+
+        // 収まるところまでエンコードする
+        let (buf63, count) = encode(&entries, 63);
+        assert_eq!((buf63.len(), count), (24, 1));
+        assert!(decode(&buf[..20]).is_err());
+    }
+
+    #[test]
+    fn test_read_large_buffer() {
+        // 1MiBを超えるバッファもエントリの途中で切らずにすべて読む
+        let entries: Vec<_> = (0..40_000)
+            .map(|i| DirEntry::new(i, libc::DT_REG, format!("file-{i:08}")))
+            .collect();
+        let (mut buf, count) = encode(&entries, usize::MAX);
+        assert_eq!(count, entries.len());
+        assert!(buf.len() > 1 << 20);
+
+        let dirp = DirEntries::new(buf.as_mut_ptr() as *mut c_void, buf.len() as c_uint);
+        let read = dirp.read(buf.len()).unwrap();
+        assert_eq!(read.len(), entries.len());
+        assert_eq!(read.last().unwrap().name, entries.last().unwrap().name);
+    }
+
+    #[test]
+    fn test_edit_getdents64() {
+        let dir = std::env::temp_dir().join(format!("zpoline-dirent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["keep", "hide"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let fd = unsafe {
+            libc::open(
+                std::ffi::CString::new(dir.as_os_str().as_bytes())
+                    .unwrap()
+                    .as_ptr(),
+                libc::O_RDONLY | libc::O_DIRECTORY,
+            )
+        };
+        let mut buf = vec![0u8; 4096];
+        let dirp = DirEntries::new(buf.as_mut_ptr() as *mut c_void, buf.len() as c_uint);
+        let n = unsafe { libc::syscall(libc::SYS_getdents64, fd, dirp.as_ptr(), buf.len()) };
+        unsafe { libc::close(fd) };
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let len = dirp
+            .edit(fd, n as usize, |entries| {
+                entries.retain(|e| e.name != b"hide");
+                for e in entries.iter_mut().filter(|e| e.name == b"keep") {
+                    e.name = b"renamed".to_vec();
+                }
+                entries.push(DirEntry::new(99, libc::DT_REG, "added"));
+            })
+            .unwrap();
+
+        let mut names: Vec<_> = dirp
+            .read(len)
+            .unwrap()
+            .into_iter()
+            .map(|e| String::from_utf8(e.name).unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, [".", "..", "added", "renamed"]);
+    }
+
+    #[test]
+    fn test_edit_overflow_rewinds() {
+        let dir = std::env::temp_dir().join(format!("zpoline-dirent-long-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = ["a", "b", "c", "d", "e", "f"];
+        for name in files {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).unwrap();
+        let open = || unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY) };
+        let mut buf = vec![0u8; 4096];
+        let getdents = |fd: c_int, buf: &mut [u8], count: usize| unsafe {
+            libc::syscall(libc::SYS_getdents64, fd, buf.as_mut_ptr(), count) as usize
+        };
+
+        // すべてのエントリがちょうど収まる大きさにする
+        let fd = open();
+        let capacity = getdents(fd, &mut buf, 4096);
+        unsafe { libc::close(fd) };
+
+        let fd = open();
+        let dirp = DirEntries::new(buf.as_mut_ptr() as *mut c_void, capacity as c_uint);
+        let long = "renamed-to-a-much-longer-name";
+        let mut names = Vec::new();
+        loop {
+            let n = getdents(fd, &mut buf, capacity);
+            if n == 0 {
+                break;
+            }
+            let len = dirp
+                .edit(fd, n, |entries| {
+                    for e in entries.iter_mut().filter(|e| e.name == b"c") {
+                        e.name = long.as_bytes().to_vec();
+                    }
+                })
+                .unwrap();
+            assert!(len <= capacity);
+            let entries = dirp.read(len).unwrap();
+            names.extend(entries.into_iter().map(|e| String::from_utf8(e.name).unwrap()));
+        }
+        unsafe { libc::close(fd) };
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // 名前が長くなって収まらなかったエントリも失われず、重複もしない
+        names.sort();
+        let mut expected = vec![".", "..", "a", "b", "d", "e", "f", long];
+        expected.sort();
+        assert_eq!(names, expected);
+
+        // 読んだエントリが1つも収まらなければEINVAL
+        let mut one = [0u8; 24];
+        let small = DirEntries::new(one.as_mut_ptr() as *mut c_void, one.len() as c_uint);
+        let mut entry = DirEntry::new(1, libc::DT_REG, long);
+        entry.off = 1;
+        assert_eq!(small.write(-1, &[entry]), Err(UserMemError::Os(libc::EINVAL)));
+    }
+}
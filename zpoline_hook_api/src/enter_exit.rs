@@ -0,0 +1,262 @@
+//! enter/exitモデルのフック
+//!
+//! `SyscallHooks`がシステムコールを丸ごと置き換えるのに対し、`EnterExitHooks`は
+//! strace/ptraceと同様に「実行前」と「実行後」の2点でシステムコールを観測します。
+//! 戻り値を見たいだけのフックが`default_*`関数を正しい型で呼び直す必要はありません。
+//!
+//! システムコールは`SyscallHooks`のデフォルト実装で実行されるため、仮想fdや
+//! 仮想化したシグナルは`register_syscall_hooks`と同じく反映されます。`clone`の
+//! 親での結果は`on_exit`で観測できますが、子スレッドは`on_exit`に戻りません
+//! （子で関数を呼ぶ場合は`SyscallHooks::clone_child_fn`を使ってください）。
+
+use crate::syscall_hooks::dispatch_defaults;
+use crate::{HookDispatcher, SyscallRegs, Sysno, SysnoSet};
+
+/// `on_enter`の結果としてシステムコールをどう扱うか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// 元の引数のままシステムコールを実行する
+    Continue,
+    /// 変更した引数でシステムコールを実行する
+    ///
+    /// アプリケーションのレジスタは書き換えられません（カーネルと同じく
+    /// 引数レジスタは保存されます）。
+    ContinueWith(SyscallRegs),
+    /// システムコールを実行せず、指定した値を戻り値とする（エラーは`-errno`）
+    Skip(i64),
+}
+
+/// 1回のシステムコール呼び出しのコンテキスト
+///
+/// `on_enter`から`on_exit`まで同じインスタンスが渡されるため、
+/// `scratch`に呼び出しごとの作業データを保持できます。
+#[derive(Debug)]
+pub struct SyscallCtx<S> {
+    orig: SyscallRegs,
+    regs: SyscallRegs,
+    skipped: bool,
+    /// 呼び出しごとの作業データ（`on_enter`の直前に`Default`で初期化される）
+    pub scratch: S,
+}
+
+impl<S: Default> SyscallCtx<S> {
+    /// レジスタ状態からコンテキストを作成
+    pub fn new(regs: SyscallRegs) -> Self {
+        Self {
+            orig: regs,
+            regs,
+            skipped: false,
+            scratch: S::default(),
+        }
+    }
+}
+
+impl<S> SyscallCtx<S> {
+    /// システムコール番号（rax）
+    pub fn nr(&self) -> u64 {
+        self.orig.rax
+    }
+
+    /// システムコール（未定義の番号は`None`）
+    pub fn sysno(&self) -> Option<Sysno> {
+        Sysno::from_raw(self.orig.rax)
+    }
+
+    /// アプリケーションが渡した元の引数
+    pub fn orig_regs(&self) -> &SyscallRegs {
+        &self.orig
+    }
+
+    /// 実際に実行された（される）引数
+    ///
+    /// `Action::ContinueWith`を返した場合、`on_exit`では変更後の値になります。
+    pub fn regs(&self) -> &SyscallRegs {
+        &self.regs
+    }
+
+    /// n番目（0始まり）の元の引数
+    pub fn arg(&self, n: usize) -> u64 {
+        self.orig.arg(n)
+    }
+
+    /// `on_enter`が`Action::Skip`を返したか
+    pub fn skipped(&self) -> bool {
+        self.skipped
+    }
+}
+
+/// enter/exitモデルのフックtrait
+///
+/// # 使用例
+///
+/// ```rust
+/// use zpoline_hook_api::{register_enter_exit_hooks, Action, EnterExitHooks, Sysno, SyscallCtx};
+///
+/// struct DenyUnlink;
+///
+/// impl EnterExitHooks for DenyUnlink {
+///     type Scratch = ();
+///
+///     fn on_enter(&mut self, ctx: &mut SyscallCtx<()>) -> Action {
+///         match ctx.sysno() {
+///             Some(Sysno::unlink | Sysno::unlinkat) => Action::Skip(-libc::EPERM as i64),
+///             _ => Action::Continue,
+///         }
+///     }
+/// }
+///
+/// register_enter_exit_hooks(DenyUnlink);
+/// ```
+pub trait EnterExitHooks: Send + Sync + 'static {
+    /// `on_enter`から`on_exit`へ受け渡す呼び出しごとの作業データ
+    type Scratch: Default;
+
+    /// フックするシステムコールの集合
+    ///
+    /// 登録時に1度だけ参照されます。含まれないシステムコールでは
+    /// `on_enter`/`on_exit`は呼ばれません。
+    fn interests(&self) -> SysnoSet {
+        SysnoSet::all()
+    }
+
+    /// fork後の子プロセスで、`on_exit`より前に呼ばれる
+    ///
+    /// `SyscallHooks::on_fork_child`と同じく、`interests`に含めていなくても呼ばれます。
+    fn on_fork_child(&mut self) {}
+
+    /// システムコールの実行前に呼ばれる
+    fn on_enter(&mut self, _ctx: &mut SyscallCtx<Self::Scratch>) -> Action {
+        Action::Continue
+    }
+
+    /// システムコールの実行後に呼ばれ、アプリケーションに返す値を決める
+    ///
+    /// `Action::Skip`の場合もその値を`ret`として呼ばれます。
+    /// `exit`/`exit_group`や成功した`execve`のように戻らないシステムコールでは
+    /// 呼ばれません。
+    fn on_exit(&mut self, _ctx: &mut SyscallCtx<Self::Scratch>, ret: i64) -> i64 {
+        ret
+    }
+}
+
+/// `EnterExitHooks`を`hook_entry`のディスパッチ形式に変換する
+pub(crate) struct EnterExitDispatcher<T>(pub(crate) T);
+
+impl<T: EnterExitHooks> HookDispatcher for EnterExitDispatcher<T> {
+    fn dispatch(&mut self, regs: &mut SyscallRegs) -> i64 {
+        let mut ctx = SyscallCtx::<T::Scratch>::new(*regs);
+
+        let ret = match self.0.on_enter(&mut ctx) {
+            Action::Continue => dispatch_defaults(&mut { ctx.regs }),
+            Action::ContinueWith(new_regs) => {
+                ctx.regs = new_regs;
+                dispatch_defaults(&mut { ctx.regs })
+            }
+            Action::Skip(ret) => {
+                ctx.skipped = true;
+                ret
+            }
+        };
+
+        // 子プロセスではランタイムを再初期化してからフック実装に通知
+        if !ctx.skipped
+            && ret == 0
+            && crate::fork::is_fork_like(&ctx.regs)
+            && crate::fork::child_in_dispatch()
+        {
+            self.0.on_fork_child();
+        }
+
+        self.0.on_exit(&mut ctx, ret)
+    }
+
+    fn interests(&self) -> SysnoSet {
+        self.0.interests()
+    }
+
+    fn on_fork_child(&mut self) {
+        self.0.on_fork_child()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Scratch {
+        entered: bool,
+    }
+
+    struct TestHooks;
+
+    impl EnterExitHooks for TestHooks {
+        type Scratch = Scratch;
+
+        fn on_enter(&mut self, ctx: &mut SyscallCtx<Scratch>) -> Action {
+            ctx.scratch.entered = true;
+            match ctx.sysno() {
+                Some(Sysno::getpid) => Action::Skip(1234),
+                // 存在しないfdに差し替える
+                Some(Sysno::close) => Action::ContinueWith(ctx.orig_regs().with_arg(0, u64::MAX)),
+                _ => Action::Continue,
+            }
+        }
+
+        fn on_exit(&mut self, ctx: &mut SyscallCtx<Scratch>, ret: i64) -> i64 {
+            assert!(ctx.scratch.entered);
+            if ctx.skipped() {
+                ret + 1
+            } else {
+                ret
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip_and_exit() {
+        let mut dispatcher = EnterExitDispatcher(TestHooks);
+        let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(dispatcher.dispatch(&mut regs), 1235);
+    }
+
+    #[test]
+    fn test_continue_with_modified_args() {
+        let mut dispatcher = EnterExitDispatcher(TestHooks);
+        // fd 0を閉じようとしても、差し替えた引数で実行されるため閉じられない
+        let mut regs = SyscallRegs::new(Sysno::close.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(dispatcher.dispatch(&mut regs), -libc::EBADF as i64);
+        assert_eq!(regs.rdi, 0);
+    }
+
+    #[test]
+    fn test_continue_uses_virtual_fds() {
+        use crate::interests::set_hook_interests;
+        use crate::vfd::{virtual_fds, VirtualFile};
+
+        /// 予約した`/dev/null`とは異なり、常に`POLLPRI`を返すファイル
+        struct Urgent;
+        impl VirtualFile for Urgent {
+            fn poll(&mut self, events: libc::c_short) -> libc::c_short {
+                events & libc::POLLPRI
+            }
+        }
+
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+        let fd = virtual_fds().insert(Urgent).unwrap();
+
+        let mut dispatcher = EnterExitDispatcher(TestHooks);
+        let mut pfd = libc::pollfd {
+            fd,
+            events: libc::POLLPRI,
+            revents: 0,
+        };
+        let fds = &mut pfd as *mut libc::pollfd as u64;
+        let mut regs = SyscallRegs::new(Sysno::poll.nr(), fds, 1, 0, 0, 0, 0);
+        assert_eq!(dispatcher.dispatch(&mut regs), 1);
+        assert_eq!(pfd.revents, libc::POLLPRI);
+
+        virtual_fds().close(fd).unwrap();
+        set_hook_interests(SysnoSet::all());
+    }
+}
@@ -0,0 +1,214 @@
+//! fork安全性
+//!
+//! `fork`/`vfork`や`CLONE_VM`なしの`clone`/`clone3`の子プロセスには呼び出したスレッドしか
+//! 残らないため、他のスレッドが保持していたロックは解放されないまま引き継がれます。
+//! ランタイムはこれらのシステムコールを関心集合にかかわらず監視し、子プロセスで
+//! 内部のロックとカウンタを再初期化してから、フック実装の`on_fork_child`を呼びます。
+//!
+//! フックライブラリは別ネームスペースに独自のランタイムを持つため、ローダーは
+//! エクスポート関数`zpoline_fork_child`を通じてライブラリ側にも子プロセスを通知します。
+
+use crate::{clone, raw_syscall_bypass, Sysno, SysnoSet, SyscallRegs};
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicI32, AtomicPtr, AtomicU32, Ordering};
+
+/// ランタイムが常に監視するシステムコール
+pub const FORK_SYSCALLS: SysnoSet =
+    SysnoSet::new(&[Sysno::fork, Sysno::vfork, Sysno::clone, Sysno::clone3]);
+
+/// アドレス空間を複製して子プロセスを作るシステムコールか
+///
+/// `CLONE_VM`付きの`clone`（スレッド生成）はアドレス空間を共有するため含みません。
+/// `vfork`は`CLONE_VM`を外して実行されるため含みます（`clone::clone_flags`）。
+pub fn is_fork_like(regs: &SyscallRegs) -> bool {
+    clone::clone_flags(regs).is_some_and(|flags| !flags.shares_vm())
+}
+
+/// 再初期化を済ませたプロセスのpid
+static HANDLED_PID: AtomicI32 = AtomicI32::new(0);
+
+/// 別ネームスペースのフックライブラリに子プロセスを通知する関数（ローダーで設定）
+static FORK_CHILD_CALLBACK: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// このプロセスで初めて呼ばれたときだけtrueを返す
+fn mark_child() -> bool {
+    let pid = unsafe { raw_syscall_bypass(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0) } as i32;
+    HANDLED_PID.swap(pid, Ordering::Relaxed) != pid
+}
+
+/// ディスパッチ中に子プロセスを検出したときの再初期化
+///
+/// 戻り値がtrueの場合、呼び出し側はフック実装の`on_fork_child`を呼ぶ。
+pub(crate) fn child_in_dispatch() -> bool {
+    if !mark_child() {
+        return false;
+    }
+    crate::reinit_after_fork();
+    true
+}
+
+/// `hook_entry`で子プロセスを検出したときの再初期化と通知
+pub(crate) fn after_fork_child() {
+    if !mark_child() {
+        return;
+    }
+    crate::reinit_after_fork();
+
+    let callback = FORK_CHILD_CALLBACK.load(Ordering::Acquire);
+    if !callback.is_null() {
+        let callback: extern "C" fn() = unsafe { std::mem::transmute(callback) };
+        callback();
+    }
+
+    crate::notify_fork_child();
+}
+
+/// 子プロセスの通知先を設定（zpoline_loader用）
+#[doc(hidden)]
+pub fn __set_fork_child_callback(callback: Option<extern "C" fn()>) {
+    let ptr = callback.map_or(std::ptr::null_mut(), |callback| callback as *mut ());
+    FORK_CHILD_CALLBACK.store(ptr, Ordering::Release);
+}
+
+/// ローダーが子プロセスでフックライブラリ側のランタイムを再初期化するための
+/// エクスポート関数
+#[no_mangle]
+pub extern "C" fn zpoline_fork_child() {
+    after_fork_child();
+}
+
+/// 子プロセスで強制的に解放できるロック
+///
+/// `std::sync::Mutex`は保持者がいなくなったロックを解放する手段がないため、
+/// futexで実装する。futexは`raw_syscall_bypass`で発行するのでフックを経由しない。
+pub(crate) struct ForkSafeMutex<T> {
+    /// 0: 未ロック, 1: ロック中, 2: ロック中で待機者あり
+    state: AtomicU32,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for ForkSafeMutex<T> {}
+
+impl<T> ForkSafeMutex<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self {
+            state: AtomicU32::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> ForkSafeGuard<'_, T> {
+        if self
+            .state
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.state.swap(2, Ordering::Acquire) != 0 {
+                self.futex(libc::FUTEX_WAIT, 2);
+            }
+        }
+        ForkSafeGuard { mutex: self }
+    }
+
+    /// 保持者のいないロックを解放する
+    ///
+    /// # Safety
+    ///
+    /// fork後の子プロセスで、呼び出したスレッド自身がロックを保持していないこと
+    pub(crate) unsafe fn force_unlock(&self) {
+        self.state.store(0, Ordering::Release);
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            self.futex(libc::FUTEX_WAKE, 1);
+        }
+    }
+
+    fn futex(&self, op: i32, val: u32) {
+        unsafe {
+            raw_syscall_bypass(
+                Sysno::futex.nr(),
+                self.state.as_ptr() as u64,
+                (op | libc::FUTEX_PRIVATE_FLAG) as u64,
+                val as u64,
+                0,
+                0,
+                0,
+            );
+        }
+    }
+}
+
+pub(crate) struct ForkSafeGuard<'a, T> {
+    mutex: &'a ForkSafeMutex<T>,
+}
+
+impl<T> Deref for ForkSafeGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T> DerefMut for ForkSafeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T> Drop for ForkSafeGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_fork_like() {
+        let fork = SyscallRegs::new(Sysno::fork.nr(), 0, 0, 0, 0, 0, 0);
+        assert!(is_fork_like(&fork));
+
+        let thread = libc::CLONE_VM | libc::CLONE_THREAD | libc::CLONE_SIGHAND;
+        let clone = SyscallRegs::new(Sysno::clone.nr(), thread as u64, 0, 0, 0, 0, 0);
+        assert!(!is_fork_like(&clone));
+        let clone = clone.with_arg(0, libc::SIGCHLD as u64);
+        assert!(is_fork_like(&clone));
+
+        let fork_args = [0u64; 11];
+        let thread_args = [thread as u64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let clone3 = SyscallRegs::new(Sysno::clone3.nr(), fork_args.as_ptr() as u64, 88, 0, 0, 0, 0);
+        assert!(is_fork_like(&clone3));
+        assert!(!is_fork_like(&clone3.with_arg(0, thread_args.as_ptr() as u64)));
+        assert!(!is_fork_like(&clone3.with_arg(0, 0)));
+    }
+
+    #[test]
+    fn test_fork_safe_mutex() {
+        static COUNTER: ForkSafeMutex<u64> = ForkSafeMutex::new(0);
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..1000 {
+                        *COUNTER.lock() += 1;
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(*COUNTER.lock(), 4000);
+
+        // 保持者のいないロックを解放できる
+        std::mem::forget(COUNTER.lock());
+        unsafe { COUNTER.force_unlock() };
+        assert_eq!(*COUNTER.lock(), 4000);
+    }
+}
@@ -0,0 +1,375 @@
+//! プロセスの資格情報と属性を偽装するための型
+//!
+//! `uname`・`prlimit64`・`capget`の引数のビューと、アプリケーションに
+//! 見せる値（ホスト名、リソース制限、ケーパビリティ）を保持するヘルパーです。
+//! カーネルの状態は変えずに、フックの結果だけを書き換えます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::identity::{CapDataPtr, CapHeaderPtr, CapSet, UtsnameOverride, UtsnamePtr};
+//! use zpoline_hook_api::syscall_hooks::{default_capget, default_uname};
+//!
+//! // rootに見せる
+//! fn hook_getuid() -> libc::uid_t {
+//!     0
+//! }
+//!
+//! fn hook_capget(hdr: CapHeaderPtr, data: CapDataPtr) -> libc::c_int {
+//!     let ret = default_capget(hdr, data);
+//!     match hdr.read() {
+//!         Ok(hdr) if ret == 0 && !data.is_null() => data
+//!             .write(hdr.version, &CapSet::all())
+//!             .map_or_else(|e| -e.errno(), |()| 0),
+//!         _ => ret,
+//!     }
+//! }
+//!
+//! fn hook_uname(buf: UtsnamePtr) -> libc::c_int {
+//!     let ret = default_uname(buf);
+//!     if ret == 0 {
+//!         let _ = buf.update(|uts| UtsnameOverride::hostname("sandbox").apply(uts));
+//!     }
+//!     ret
+//! }
+//! ```
+
+use crate::user_mem::{try_read_bytes, try_write_bytes, UserMemError, UserPtr};
+use libc::{c_char, c_int, c_uint, c_void, pid_t, rlimit64};
+
+/// `uname`の出力先
+pub type UtsnamePtr = UserPtr<libc::utsname>;
+
+/// `getrlimit`/`setrlimit`/`prlimit64`の`struct rlimit`（x86-64では`rlimit64`と同じ）
+pub type RlimitPtr = UserPtr<rlimit64>;
+
+/// `capget`/`capset`のヘッダ
+pub type CapHeaderPtr = UserPtr<CapUserHeader>;
+
+pub const LINUX_CAPABILITY_VERSION_1: u32 = 0x1998_0330;
+pub const LINUX_CAPABILITY_VERSION_2: u32 = 0x2007_1026;
+pub const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// 最後のケーパビリティ番号（`CAP_CHECKPOINT_RESTORE`）
+pub const CAP_LAST_CAP: u32 = 40;
+
+/// `struct __user_cap_header_struct`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapUserHeader {
+    pub version: u32,
+    pub pid: c_int,
+}
+
+/// `struct __user_cap_data_struct`（32ビット分）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CapUserData {
+    pub effective: u32,
+    pub permitted: u32,
+    pub inheritable: u32,
+}
+
+/// ケーパビリティの集合（ビット番号は`CAP_*`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CapSet {
+    pub effective: u64,
+    pub permitted: u64,
+    pub inheritable: u64,
+}
+
+impl CapSet {
+    /// すべてのケーパビリティを持つ集合（rootの`effective`と`permitted`）
+    pub fn all() -> Self {
+        let bits = (1u64 << (CAP_LAST_CAP + 1)) - 1;
+        Self {
+            effective: bits,
+            permitted: bits,
+            inheritable: 0,
+        }
+    }
+
+    /// `cap`が`effective`に含まれるか
+    pub fn has(&self, cap: u32) -> bool {
+        cap < 64 && self.effective & (1 << cap) != 0
+    }
+}
+
+/// バージョンごとのデータの要素数（未知のバージョンはカーネルと同じ`EINVAL`）
+fn cap_elements(version: u32) -> Result<usize, UserMemError> {
+    match version {
+        LINUX_CAPABILITY_VERSION_1 => Ok(1),
+        LINUX_CAPABILITY_VERSION_2 | LINUX_CAPABILITY_VERSION_3 => Ok(2),
+        _ => Err(UserMemError::Os(libc::EINVAL)),
+    }
+}
+
+/// `capget`/`capset`のデータ（バージョン1は1要素、2と3は2要素の配列）
+#[derive(Debug, Clone, Copy)]
+pub struct CapDataPtr {
+    ptr: *mut CapUserData,
+}
+
+impl CapDataPtr {
+    pub fn new(ptr: *mut CapUserData) -> Self {
+        Self { ptr }
+    }
+
+    pub fn as_ptr(&self) -> *mut CapUserData {
+        self.ptr
+    }
+
+    /// NULLのときは`capget`がサポートするバージョンの問い合わせ
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// ヘッダの`version`に従って読み取る
+    pub fn read(&self, version: u32) -> Result<CapSet, UserMemError> {
+        let mut data = [CapUserData::default(); 2];
+        let n = cap_elements(version)?;
+        try_read_bytes(self.ptr as *const c_void, as_bytes_mut(&mut data[..n]))?;
+        let join = |f: fn(&CapUserData) -> u32| {
+            data.iter()
+                .rev()
+                .fold(0u64, |bits, d| (bits << 32) | f(d) as u64)
+        };
+        Ok(CapSet {
+            effective: join(|d| d.effective),
+            permitted: join(|d| d.permitted),
+            inheritable: join(|d| d.inheritable),
+        })
+    }
+
+    /// ヘッダの`version`に従って書き込む（バージョン1では上位32ビットを捨てる）
+    pub fn write(&self, version: u32, caps: &CapSet) -> Result<(), UserMemError> {
+        let n = cap_elements(version)?;
+        let data: Vec<_> = (0..n)
+            .map(|i| CapUserData {
+                effective: (caps.effective >> (32 * i)) as u32,
+                permitted: (caps.permitted >> (32 * i)) as u32,
+                inheritable: (caps.inheritable >> (32 * i)) as u32,
+            })
+            .collect();
+        try_write_bytes(self.ptr as *mut c_void, as_bytes(&data))
+    }
+}
+
+fn as_bytes(data: &[CapUserData]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
+
+fn as_bytes_mut(data: &mut [CapUserData]) -> &mut [u8] {
+    unsafe {
+        std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, std::mem::size_of_val(data))
+    }
+}
+
+/// `uname`の結果に上書きするフィールド（`None`はカーネルの値のまま）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UtsnameOverride {
+    pub sysname: Option<String>,
+    /// ホスト名
+    pub nodename: Option<String>,
+    pub release: Option<String>,
+    pub version: Option<String>,
+    pub machine: Option<String>,
+    pub domainname: Option<String>,
+}
+
+impl UtsnameOverride {
+    /// ホスト名だけを上書きする
+    pub fn hostname(name: impl Into<String>) -> Self {
+        Self {
+            nodename: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// `uts`に上書きする（64バイトを超える値は切り詰める）
+    pub fn apply(&self, uts: &mut libc::utsname) {
+        let fields = [
+            (&self.sysname, &mut uts.sysname),
+            (&self.nodename, &mut uts.nodename),
+            (&self.release, &mut uts.release),
+            (&self.version, &mut uts.version),
+            (&self.machine, &mut uts.machine),
+            (&self.domainname, &mut uts.domainname),
+        ];
+        for (value, field) in fields {
+            if let Some(value) = value {
+                set_field(field, value);
+            }
+        }
+    }
+}
+
+/// `utsname`のフィールドを文字列として読み取る
+pub fn uts_field(field: &[c_char]) -> String {
+    let bytes: Vec<u8> = field
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn set_field(field: &mut [c_char], value: &str) {
+    let len = value.len().min(field.len() - 1);
+    field.fill(0);
+    for (dst, &src) in field.iter_mut().zip(&value.as_bytes()[..len]) {
+        *dst = src as c_char;
+    }
+}
+
+/// アプリケーションに見せるリソース制限
+///
+/// 登録した資源については、自プロセスに対する`prlimit64`をカーネルに渡さずに処理します。
+/// アプリケーションが設定した値は記録され、以降の取得で返りますが、実際の制限は変わりません。
+#[derive(Debug, Clone, Default)]
+pub struct FakeLimits {
+    limits: Vec<(c_uint, rlimit64)>,
+}
+
+impl FakeLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `resource`（`RLIMIT_*`）の値を設定する
+    pub fn set(&mut self, resource: c_uint, cur: u64, max: u64) -> &mut Self {
+        let limit = rlimit64 {
+            rlim_cur: cur,
+            rlim_max: max,
+        };
+        match self.limits.iter_mut().find(|(r, _)| *r == resource) {
+            Some((_, l)) => *l = limit,
+            None => self.limits.push((resource, limit)),
+        }
+        self
+    }
+
+    pub fn get(&self, resource: c_uint) -> Option<rlimit64> {
+        self.limits
+            .iter()
+            .find(|(r, _)| *r == resource)
+            .map(|&(_, l)| l)
+    }
+
+    /// `prlimit64`を処理する（対象外の場合は`None`でデフォルト実装に任せる）
+    ///
+    /// `getrlimit`/`setrlimit`は`pid`を0として呼び出せます。
+    pub fn prlimit64(
+        &mut self,
+        pid: pid_t,
+        resource: c_uint,
+        new_limit: RlimitPtr,
+        old_limit: RlimitPtr,
+    ) -> Option<c_int> {
+        if pid != 0 && pid != std::process::id() as pid_t {
+            return None;
+        }
+        let current = self.get(resource)?;
+
+        let new = if new_limit.is_null() {
+            None
+        } else {
+            match new_limit.read() {
+                Ok(l) if l.rlim_cur > l.rlim_max => return Some(-libc::EINVAL),
+                Ok(l) => Some(l),
+                Err(e) => return Some(-e.errno()),
+            }
+        };
+        if !old_limit.is_null() {
+            if let Err(e) = old_limit.write(current) {
+                return Some(-e.errno());
+            }
+        }
+        if let Some(l) = new {
+            self.set(resource, l.rlim_cur, l.rlim_max);
+        }
+        Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cap_data() {
+        let mut data = [CapUserData::default(); 2];
+        let ptr = CapDataPtr::new(data.as_mut_ptr());
+        let caps = CapSet::all();
+        assert!(caps.has(CAP_LAST_CAP) && !caps.has(CAP_LAST_CAP + 1));
+
+        ptr.write(LINUX_CAPABILITY_VERSION_3, &caps).unwrap();
+        assert_eq!(data[1].effective, 0x1ff);
+        assert_eq!(ptr.read(LINUX_CAPABILITY_VERSION_3).unwrap(), caps);
+
+        // バージョン1は下位32ビットだけ
+        data = [CapUserData::default(); 2];
+        let ptr = CapDataPtr::new(data.as_mut_ptr());
+        ptr.write(LINUX_CAPABILITY_VERSION_1, &caps).unwrap();
+        assert_eq!(data[1], CapUserData::default());
+        assert_eq!(
+            ptr.read(LINUX_CAPABILITY_VERSION_1).unwrap().effective,
+            0xffff_ffff
+        );
+
+        assert_eq!(ptr.read(0).unwrap_err().errno(), libc::EINVAL);
+    }
+
+    #[test]
+    fn test_utsname_override() {
+        let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { libc::uname(&mut uts) }, 0);
+        let sysname = uts_field(&uts.sysname);
+
+        UtsnameOverride::hostname("sandbox").apply(&mut uts);
+        assert_eq!(uts_field(&uts.nodename), "sandbox");
+        assert_eq!(uts_field(&uts.sysname), sysname);
+
+        UtsnameOverride::hostname("x".repeat(100)).apply(&mut uts);
+        assert_eq!(uts_field(&uts.nodename).len(), 64);
+    }
+
+    #[test]
+    fn test_fake_limits() {
+        let nofile = libc::RLIMIT_NOFILE as c_uint;
+        let mut limits = FakeLimits::new();
+        limits.set(nofile, 64, 128);
+
+        let mut old = rlimit64 {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let mut new = rlimit64 {
+            rlim_cur: 100,
+            rlim_max: 128,
+        };
+        let null = RlimitPtr::new(std::ptr::null_mut());
+        assert_eq!(
+            limits.prlimit64(
+                0,
+                nofile,
+                RlimitPtr::new(&mut new),
+                RlimitPtr::new(&mut old)
+            ),
+            Some(0)
+        );
+        assert_eq!((old.rlim_cur, old.rlim_max), (64, 128));
+        assert_eq!(limits.get(nofile).unwrap().rlim_cur, 100);
+
+        new.rlim_cur = 200;
+        assert_eq!(
+            limits.prlimit64(0, nofile, RlimitPtr::new(&mut new), null),
+            Some(-libc::EINVAL)
+        );
+
+        // 登録していない資源と他のプロセスは対象外
+        assert_eq!(
+            limits.prlimit64(0, libc::RLIMIT_CORE as c_uint, null, null),
+            None
+        );
+        assert_eq!(limits.prlimit64(1, nofile, null, null), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! フックライブラリが処理するシステムコールの集合（関心集合）
+//!
+//! 関心集合に含まれないシステムコールは、トランポリンと`hook_entry`の段階で
+//! 直接実行され、TLSガードやフック関数を経由しません。ただしfork安全性のため、
+//! `fork::FORK_SYSCALLS`は常に`hook_entry`まで届きます。
+//!
+//! フックライブラリは`dlmopen`で別ネームスペースにロードされ、独自の
+//! `zpoline_hook_api`のコピーを持ちます。そのためライブラリ側で宣言した集合は
+//! エクスポート関数`zpoline_get_hook_interests`を通じてローダー側のコピーへ
+//! 転送されます。初期化の後に`extend_hook_interests`で追加した分は、
+//! ABIハンドシェイクで受け取ったローダーのコールバックで転送されます。
+
+use crate::fork::FORK_SYSCALLS;
+use crate::sysno::{SysnoSet, MAX_SYSNO};
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+
+const WORDS: usize = MAX_SYSNO / 64;
+
+/// 関心集合のビットマップ（初期状態はすべてのシステムコール）
+static HOOK_INTERESTS: [AtomicU64; WORDS] = [const { AtomicU64::new(u64::MAX) }; WORDS];
+
+/// `hook_entry`に届けるシステムコールのビットマップ（関心集合とランタイムが監視するもの）
+///
+/// トランポリンが`bt`命令で直接参照するため、`u64`配列と同じレイアウトである必要がある
+static ENTRY_FILTER: [AtomicU64; WORDS] = [const { AtomicU64::new(u64::MAX) }; WORDS];
+
+/// ローダー側の関心集合を広げる関数（`dlmopen`されたライブラリでのみ設定される）
+static HOST_EXTEND_INTERESTS: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// フック関数へディスパッチするシステムコールの集合を設定
+///
+/// フックライブラリの`zpoline_hook_init`から呼ぶか、`register_syscall_hooks`が
+/// `SyscallHooks::interests`の値で自動的に呼び出します。
+pub fn set_hook_interests(set: SysnoSet) {
+    for (word, bits) in HOOK_INTERESTS.iter().zip(set.as_words()) {
+        word.store(bits, Ordering::Release);
+    }
+    let filter = set.union(&FORK_SYSCALLS);
+    for (word, bits) in ENTRY_FILTER.iter().zip(filter.as_words()) {
+        word.store(bits, Ordering::Release);
+    }
+}
+
+/// 関心集合に`set`を加える
+///
+/// 仮想fdやシグナルの仮想化のように、初期化の後で必要になったシステムコールを
+/// 追加します。ローダーにロードされたライブラリでは、ローダー側の関心集合にも
+/// 反映されます（減らす方向の変更は`zpoline_hook_init`の中でのみ反映されます）。
+pub fn extend_hook_interests(set: &SysnoSet) {
+    let words = HOOK_INTERESTS.iter().zip(ENTRY_FILTER.iter());
+    for ((interests, filter), bits) in words.zip(set.as_words()) {
+        interests.fetch_or(bits, Ordering::AcqRel);
+        filter.fetch_or(bits, Ordering::AcqRel);
+    }
+
+    let host = HOST_EXTEND_INTERESTS.load(Ordering::Acquire);
+    if !host.is_null() {
+        let host: extern "C" fn(*const SysnoSet) = unsafe { std::mem::transmute(host) };
+        host(set);
+    }
+}
+
+/// ローダーの関心集合も広げるように切り替える（ABIハンドシェイクで呼ばれる）
+pub(crate) fn attach_host(extend_interests: extern "C" fn(*const SysnoSet)) {
+    HOST_EXTEND_INTERESTS.store(extend_interests as *mut (), Ordering::Release);
+}
+
+/// 現在の関心集合を取得
+pub fn hook_interests() -> SysnoSet {
+    let mut words = [0u64; WORDS];
+    for (bits, word) in words.iter_mut().zip(HOOK_INTERESTS.iter()) {
+        *bits = word.load(Ordering::Acquire);
+    }
+    SysnoSet::from_words(words)
+}
+
+/// raxの値が関心集合に含まれるか
+#[inline]
+pub fn is_hook_interested(nr: u64) -> bool {
+    if nr >= MAX_SYSNO as u64 {
+        return false;
+    }
+    let nr = nr as usize;
+    HOOK_INTERESTS[nr / 64].load(Ordering::Relaxed) & (1 << (nr % 64)) != 0
+}
+
+/// トランポリンが参照するビットマップのアドレス（zpoline_loader用）
+#[doc(hidden)]
+pub fn __hook_interests_bitmap() -> *const u64 {
+    ENTRY_FILTER.as_ptr() as *const u64
+}
+
+/// ローダーが関心集合を取得するためのエクスポート関数
+///
+/// ローダーはフックライブラリの`zpoline_hook_init`を呼んだ後にこの関数を
+/// `dlsym`で探し、結果を自身のネームスペースに反映します。
+///
+/// # Safety
+///
+/// `out`は有効な`SysnoSet`を指している必要がある
+#[no_mangle]
+pub unsafe extern "C" fn zpoline_get_hook_interests(out: *mut SysnoSet) {
+    if let Some(out) = out.as_mut() {
+        *out = hook_interests();
+    }
+}
@@ -0,0 +1,402 @@
+//! 読み書き系システムコールの統合レイヤー
+//!
+//! `read`/`pread64`/`readv`/`preadv`/`preadv2`と対応する書き込み系を
+//! `IoRequest`にまとめ、`UnifiedIoHooks`の`on_read`/`on_write`の2つで扱えるようにします。
+//! `UnifiedIo`で包んで`register_syscall_hooks`に渡します。
+//!
+//! ```no_run
+//! use zpoline_hook_api::io::{IoRequest, UnifiedIo, UnifiedIoHooks};
+//! use zpoline_hook_api::register_syscall_hooks;
+//!
+//! struct ByteCounter(usize);
+//!
+//! impl UnifiedIoHooks for ByteCounter {
+//!     fn on_write(&mut self, req: &IoRequest) -> isize {
+//!         let ret = req.perform();
+//!         if ret > 0 {
+//!             self.0 += ret as usize;
+//!         }
+//!         ret
+//!     }
+//! }
+//!
+//! register_syscall_hooks(UnifiedIo(ByteCounter(0)));
+//! ```
+
+use crate::syscall_hooks::SyscallHooks;
+use crate::user_mem::{gather_iovecs, scatter_iovecs, IoVecs, UserMemError};
+use crate::{raw_syscall, SyscallRegs, Sysno, SysnoSet};
+use libc::{c_int, c_void, iovec, off_t, size_t, ssize_t};
+
+/// 読み込み系のシステムコール
+pub const READ_SYSCALLS: [Sysno; 5] = [
+    Sysno::read,
+    Sysno::pread64,
+    Sysno::readv,
+    Sysno::preadv,
+    Sysno::preadv2,
+];
+
+/// 書き込み系のシステムコール
+pub const WRITE_SYSCALLS: [Sysno; 5] = [
+    Sysno::write,
+    Sysno::pwrite64,
+    Sysno::writev,
+    Sysno::pwritev,
+    Sysno::pwritev2,
+];
+
+/// 読み書きのバッファ
+///
+/// ベクタI/Oのiovec配列はコピーせずアプリケーションメモリのまま参照します。
+#[derive(Debug, Clone, Copy)]
+pub enum IoBufs {
+    /// `read`/`write`/`pread64`/`pwrite64`のバッファ
+    Single(iovec),
+    /// ベクタI/Oのiovec配列
+    Vectored(IoVecs),
+}
+
+impl IoBufs {
+    /// バッファを先頭から順に返す
+    pub fn iter(&self) -> impl Iterator<Item = Result<iovec, UserMemError>> {
+        let (single, vecs) = match *self {
+            IoBufs::Single(iov) => (Some(iov), IoVecs::new(std::ptr::null(), 0)),
+            IoBufs::Vectored(vecs) => (None, vecs),
+        };
+        single.into_iter().map(Ok).chain(vecs.iter())
+    }
+}
+
+/// 読み書きの要求
+///
+/// 作成も`len`/`perform`/`scatter`も割り当てを行わないため、mallocの内部や
+/// vforkの子から呼ばれたフックでも使えます（`gather`は`Vec`を返します）。
+#[derive(Debug, Clone)]
+pub struct IoRequest {
+    /// 元のシステムコール
+    pub sysno: Sysno,
+    pub fd: c_int,
+    /// 明示的なオフセット（`None`は現在位置）
+    pub offset: Option<off_t>,
+    /// `RWF_*`フラグ（`preadv2`/`pwritev2`以外は0）
+    pub flags: c_int,
+    /// バッファ
+    pub bufs: IoBufs,
+    len: usize,
+    regs: SyscallRegs,
+}
+
+impl IoRequest {
+    /// `read`/`write`/`pread64`/`pwrite64`（引数は`fd, buf, count, offset`）
+    fn single(sysno: Sysno, args: [u64; 4], offset: Option<off_t>) -> Self {
+        Self {
+            sysno,
+            fd: args[0] as c_int,
+            offset,
+            flags: 0,
+            bufs: IoBufs::Single(iovec {
+                iov_base: args[1] as *mut c_void,
+                iov_len: args[2] as size_t,
+            }),
+            len: args[2] as usize,
+            regs: SyscallRegs::new(sysno.nr(), args[0], args[1], args[2], args[3], 0, 0),
+        }
+    }
+
+    /// ベクタI/O（引数は`fd, iov, iovcnt, offset, 0, flags`）
+    ///
+    /// iovec配列を読み取れない場合はエラーを返す。
+    fn vectored(sysno: Sysno, args: [u64; 6], offset: Option<off_t>) -> Result<Self, UserMemError> {
+        let bufs = IoVecs::new(args[1] as *const iovec, args[2] as usize);
+        Ok(Self {
+            sysno,
+            fd: args[0] as c_int,
+            offset,
+            flags: args[5] as c_int,
+            bufs: IoBufs::Vectored(bufs),
+            len: bufs.total_len()?,
+            regs: SyscallRegs::new(
+                sysno.nr(),
+                args[0],
+                args[1],
+                args[2],
+                args[3],
+                args[4],
+                args[5],
+            ),
+        })
+    }
+
+    /// バッファの合計長
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 元のシステムコールを実行する
+    pub fn perform(&self) -> ssize_t {
+        unsafe { raw_syscall(&self.regs) as ssize_t }
+    }
+
+    /// 書き込むデータを読み取る（書き込み系、先頭`max_len`バイトまで）
+    pub fn gather(&self, max_len: usize) -> Result<Vec<u8>, UserMemError> {
+        match self.bufs {
+            IoBufs::Single(iov) => gather_iovecs(&[iov], max_len),
+            IoBufs::Vectored(vecs) => vecs.gather(max_len),
+        }
+    }
+
+    /// 読み込んだデータとして`data`をバッファに書き込む（読み込み系）
+    ///
+    /// 書き込んだバイト数を返します。`on_read`からはこの値を返します。
+    pub fn scatter(&self, data: &[u8]) -> Result<usize, UserMemError> {
+        match self.bufs {
+            IoBufs::Single(iov) => scatter_iovecs(&[iov], data),
+            IoBufs::Vectored(vecs) => vecs.scatter(data),
+        }
+    }
+}
+
+/// 読み書きをまとめて扱うフック
+///
+/// 戻り値はシステムコールの戻り値（エラーは`-errno`）です。
+pub trait UnifiedIoHooks: Send + Sync + 'static {
+    /// 読み込み系のシステムコール
+    fn on_read(&mut self, req: &IoRequest) -> ssize_t {
+        req.perform()
+    }
+
+    /// 書き込み系のシステムコール
+    fn on_write(&mut self, req: &IoRequest) -> ssize_t {
+        req.perform()
+    }
+
+    /// fork後の子プロセスで呼ばれる
+    fn on_fork_child(&mut self) {}
+}
+
+/// `UnifiedIoHooks`を`SyscallHooks`として登録するためのアダプタ
+///
+/// 関心集合は`READ_SYSCALLS`と`WRITE_SYSCALLS`です。
+pub struct UnifiedIo<T>(pub T);
+
+impl<T: UnifiedIoHooks> UnifiedIo<T> {
+    fn read(&mut self, sysno: Sysno, args: [u64; 4], offset: Option<off_t>) -> ssize_t {
+        self.0.on_read(&IoRequest::single(sysno, args, offset))
+    }
+
+    fn write(&mut self, sysno: Sysno, args: [u64; 4], offset: Option<off_t>) -> ssize_t {
+        self.0.on_write(&IoRequest::single(sysno, args, offset))
+    }
+
+    fn readv(&mut self, sysno: Sysno, args: [u64; 6], offset: Option<off_t>) -> ssize_t {
+        match IoRequest::vectored(sysno, args, offset) {
+            Ok(req) => self.0.on_read(&req),
+            Err(e) => -(e.errno() as ssize_t),
+        }
+    }
+
+    fn writev(&mut self, sysno: Sysno, args: [u64; 6], offset: Option<off_t>) -> ssize_t {
+        match IoRequest::vectored(sysno, args, offset) {
+            Ok(req) => self.0.on_write(&req),
+            Err(e) => -(e.errno() as ssize_t),
+        }
+    }
+}
+
+/// ベクタI/Oの引数
+fn iov_args(fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> [u64; 6] {
+    [
+        fd as u64,
+        iov.as_ptr() as u64,
+        iov.len() as u64,
+        offset as u64,
+        0,
+        flags as u64,
+    ]
+}
+
+/// `preadv2`/`pwritev2`のオフセット（-1は現在位置）
+fn offset_v2(offset: off_t) -> Option<off_t> {
+    (offset != -1).then_some(offset)
+}
+
+impl<T: UnifiedIoHooks> SyscallHooks for UnifiedIo<T> {
+    fn interests(&self) -> SysnoSet {
+        SysnoSet::new(&READ_SYSCALLS).union(&SysnoSet::new(&WRITE_SYSCALLS))
+    }
+
+    fn on_fork_child(&mut self) {
+        self.0.on_fork_child()
+    }
+
+    fn hook_read(&mut self, fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
+        let args = [fd as u64, buf as u64, count as u64, 0];
+        self.read(Sysno::read, args, None)
+    }
+
+    fn hook_write(&mut self, fd: c_int, buf: *const c_void, count: size_t) -> ssize_t {
+        let args = [fd as u64, buf as u64, count as u64, 0];
+        self.write(Sysno::write, args, None)
+    }
+
+    fn hook_pread64(
+        &mut self,
+        fd: c_int,
+        buf: *mut c_void,
+        count: size_t,
+        offset: off_t,
+    ) -> ssize_t {
+        let args = [fd as u64, buf as u64, count as u64, offset as u64];
+        self.read(Sysno::pread64, args, Some(offset))
+    }
+
+    fn hook_pwrite64(
+        &mut self,
+        fd: c_int,
+        buf: *const c_void,
+        count: size_t,
+        offset: off_t,
+    ) -> ssize_t {
+        let args = [fd as u64, buf as u64, count as u64, offset as u64];
+        self.write(Sysno::pwrite64, args, Some(offset))
+    }
+
+    fn hook_readv(&mut self, fd: c_int, iov: IoVecs) -> ssize_t {
+        self.readv(Sysno::readv, iov_args(fd, iov, 0, 0), None)
+    }
+
+    fn hook_writev(&mut self, fd: c_int, iov: IoVecs) -> ssize_t {
+        self.writev(Sysno::writev, iov_args(fd, iov, 0, 0), None)
+    }
+
+    fn hook_preadv(&mut self, fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+        self.readv(Sysno::preadv, iov_args(fd, iov, offset, 0), Some(offset))
+    }
+
+    fn hook_pwritev(&mut self, fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+        self.writev(Sysno::pwritev, iov_args(fd, iov, offset, 0), Some(offset))
+    }
+
+    fn hook_preadv2(&mut self, fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+        let args = iov_args(fd, iov, offset, flags);
+        self.readv(Sysno::preadv2, args, offset_v2(offset))
+    }
+
+    fn hook_pwritev2(&mut self, fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+        let args = iov_args(fd, iov, offset, flags);
+        self.writev(Sysno::pwritev2, args, offset_v2(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syscall_hooks::dispatch_syscall_hooks;
+
+    /// 書き込みを記録し、fd 1000からの読み込みを偽装する
+    #[derive(Default)]
+    struct Recorder {
+        writes: Vec<(Sysno, Option<off_t>, Vec<u8>)>,
+    }
+
+    const FAKE_FD: c_int = 1000;
+
+    impl UnifiedIoHooks for Recorder {
+        fn on_read(&mut self, req: &IoRequest) -> ssize_t {
+            if req.fd != FAKE_FD {
+                return req.perform();
+            }
+            match req.scatter(b"hello, world") {
+                Ok(n) => n as ssize_t,
+                Err(e) => -(e.errno() as ssize_t),
+            }
+        }
+
+        fn on_write(&mut self, req: &IoRequest) -> ssize_t {
+            let data = req.gather(usize::MAX).unwrap();
+            assert_eq!(req.len(), data.len());
+            self.writes.push((req.sysno, req.offset, data));
+            req.perform()
+        }
+    }
+
+    fn regs(sysno: Sysno, args: [u64; 3]) -> SyscallRegs {
+        SyscallRegs::new(sysno.nr(), args[0], args[1], args[2], 0, 0, 0)
+    }
+
+    #[test]
+    fn test_unified_io() {
+        let mut hooks = UnifiedIo(Recorder::default());
+        assert!(hooks.interests().contains(Sysno::pwritev2));
+        assert!(!hooks.interests().contains(Sysno::openat));
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (rfd, wfd) = (fds[0] as u64, fds[1] as u64);
+
+        // writevは複数のバッファを連結して1つの要求にする
+        let (a, b) = (*b"abc", *b"defg");
+        let iov = [
+            iovec {
+                iov_base: a.as_ptr() as *mut c_void,
+                iov_len: a.len(),
+            },
+            iovec {
+                iov_base: b.as_ptr() as *mut c_void,
+                iov_len: b.len(),
+            },
+        ];
+        let mut writev = regs(Sysno::writev, [wfd, iov.as_ptr() as u64, 2]);
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut writev), 7);
+        let mut pwritev2 = regs(Sysno::pwritev2, [wfd, iov[1..].as_ptr() as u64, 1]);
+        pwritev2.r10 = -1i64 as u64;
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut pwritev2), 4);
+        assert_eq!(
+            hooks.0.writes,
+            vec![
+                (Sysno::writev, None, b"abcdefg".to_vec()),
+                (Sysno::pwritev2, None, b"defg".to_vec()),
+            ]
+        );
+
+        // readvは偽装したデータを複数のバッファに分配する
+        let (mut x, mut y) = ([0u8; 5], [0u8; 16]);
+        let iov = [
+            iovec {
+                iov_base: x.as_mut_ptr() as *mut c_void,
+                iov_len: x.len(),
+            },
+            iovec {
+                iov_base: y.as_mut_ptr() as *mut c_void,
+                iov_len: y.len(),
+            },
+        ];
+        let mut readv = regs(Sysno::readv, [FAKE_FD as u64, iov.as_ptr() as u64, 2]);
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut readv), 12);
+        assert_eq!(&x, b"hello");
+        assert_eq!(&y[..7], b", world");
+
+        // 実際のfdからの読み込みはそのまま実行される
+        let mut buf = [0u8; 16];
+        let mut read = regs(Sysno::read, [rfd, buf.as_mut_ptr() as u64, 16]);
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut read), 11);
+        assert_eq!(&buf[..11], b"abcdefgdefg");
+
+        // iovec配列が読めなければカーネルと同じEFAULT
+        let mut bad = regs(Sysno::readv, [FAKE_FD as u64, 8, 1]);
+        assert_eq!(
+            dispatch_syscall_hooks(&mut hooks, &mut bad),
+            -(libc::EFAULT as i64)
+        );
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+}
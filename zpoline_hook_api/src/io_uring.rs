@@ -0,0 +1,795 @@
+//! io_uringの送信キュー・完了キューの監視
+//!
+//! io_uringはSQE（送信キューのエントリ）を共有メモリに書き込むため、
+//! 操作ごとのシステムコールがなくzpolineのフックからは見えません。
+//! `IoUringRings`は`io_uring_setup`で作られたリングを自分でもmmapし、
+//! `io_uring_enter`の前にカーネルが未消費のSQEを、戻った後に新しいCQE
+//! （完了キューのエントリ）を読み取ります。
+//!
+//! `IoUringHooks`を`IoUringTracer`で包んで登録すると、SQEとCQEが
+//! `on_submit`/`on_complete`に渡されます。フックで書き換えた内容は
+//! リングに書き戻されるため、操作の変更や結果の偽装にも使えます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::io_uring::{Cqe, IoUringHooks, IoUringTracer, Sqe};
+//! use zpoline_hook_api::{hook_log, register_syscall_hooks};
+//!
+//! struct Trace;
+//!
+//! impl IoUringHooks for Trace {
+//!     fn on_submit(&mut self, ring_fd: i32, sqe: &mut Sqe) {
+//!         hook_log!("ring {} {} fd={} len={}", ring_fd, sqe.opcode_name(), sqe.fd, sqe.len);
+//!     }
+//!
+//!     fn on_complete(&mut self, ring_fd: i32, cqe: &mut Cqe) {
+//!         hook_log!("ring {} done {:#x} res={}", ring_fd, cqe.user_data, cqe.res);
+//!     }
+//! }
+//!
+//! register_syscall_hooks(IoUringTracer::new(Trace));
+//! ```
+//!
+//! 制限:
+//!
+//! - `IORING_SETUP_SQPOLL`ではカーネルのスレッドが`io_uring_enter`を待たずに
+//!   SQEを消費するため、`on_submit`が間に合わないことがあります
+//! - 完了は`io_uring_enter`の戻り時に通知されます。アプリケーションが
+//!   システムコールを使わずにCQEを刈り取った場合は、次の`io_uring_enter`で
+//!   （書き換えても反映されない状態で）通知されます
+//! - `IORING_SETUP_NO_MMAP`と、`IORING_FEAT_SINGLE_MMAP`のないカーネル（5.4未満）の
+//!   リングは監視しません
+
+use crate::syscall_hooks::{
+    default_close, default_io_uring_enter, default_io_uring_register, default_io_uring_setup,
+    IoUringParamsPtr, SyscallHooks,
+};
+use crate::user_mem::try_read_bytes;
+use crate::{raw_syscall_bypass, Sysno, SysnoSet};
+use libc::{c_int, c_uint, c_void, pid_t, size_t};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// `IoUringTracer`の関心集合
+pub const IO_URING_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::io_uring_setup,
+    Sysno::io_uring_enter,
+    Sysno::io_uring_register,
+    Sysno::close,
+]);
+
+pub const IORING_SETUP_SQPOLL: u32 = 1 << 1;
+pub const IORING_SETUP_SQE128: u32 = 1 << 10;
+pub const IORING_SETUP_CQE32: u32 = 1 << 11;
+pub const IORING_SETUP_NO_MMAP: u32 = 1 << 14;
+pub const IORING_SETUP_NO_SQARRAY: u32 = 1 << 16;
+pub const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+pub const IORING_ENTER_REGISTERED_RING: u32 = 1 << 4;
+pub const IORING_REGISTER_RING_FDS: c_uint = 20;
+pub const IORING_UNREGISTER_RING_FDS: c_uint = 21;
+
+const IORING_OFF_SQ_RING: u64 = 0;
+const IORING_OFF_SQES: u64 = 0x1000_0000;
+
+/// `struct io_sqring_offsets`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoSqringOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// `struct io_cqring_offsets`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoCqringOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// `struct io_uring_params`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoUringParams {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: IoSqringOffsets,
+    pub cq_off: IoCqringOffsets,
+}
+
+/// `struct io_uring_sqe`（共用体のフィールドは代表的な名前で表す）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sqe {
+    pub opcode: u8,
+    /// `IOSQE_*`
+    pub flags: u8,
+    pub ioprio: u16,
+    pub fd: i32,
+    /// ファイルオフセット（`addr2`）
+    pub off: u64,
+    /// バッファのアドレス（`splice_off_in`）
+    pub addr: u64,
+    /// バッファの長さ
+    pub len: u32,
+    /// `rw_flags`/`msg_flags`/`open_flags`など
+    pub op_flags: u32,
+    pub user_data: u64,
+    /// `buf_index`/`buf_group`
+    pub buf_index: u16,
+    pub personality: u16,
+    /// `splice_fd_in`/`file_index`
+    pub splice_fd_in: i32,
+    pub addr3: u64,
+    pub pad: u64,
+}
+
+/// `struct io_uring_cqe`（`IORING_SETUP_CQE32`の追加フィールドは含まない）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cqe {
+    pub user_data: u64,
+    /// 結果（エラーは`-errno`）
+    pub res: i32,
+    /// `IORING_CQE_F_*`
+    pub flags: u32,
+}
+
+/// `IORING_OP_*`
+pub mod opcode {
+    pub const NOP: u8 = 0;
+    pub const READV: u8 = 1;
+    pub const WRITEV: u8 = 2;
+    pub const FSYNC: u8 = 3;
+    pub const READ_FIXED: u8 = 4;
+    pub const WRITE_FIXED: u8 = 5;
+    pub const POLL_ADD: u8 = 6;
+    pub const POLL_REMOVE: u8 = 7;
+    pub const SYNC_FILE_RANGE: u8 = 8;
+    pub const SENDMSG: u8 = 9;
+    pub const RECVMSG: u8 = 10;
+    pub const TIMEOUT: u8 = 11;
+    pub const TIMEOUT_REMOVE: u8 = 12;
+    pub const ACCEPT: u8 = 13;
+    pub const ASYNC_CANCEL: u8 = 14;
+    pub const LINK_TIMEOUT: u8 = 15;
+    pub const CONNECT: u8 = 16;
+    pub const FALLOCATE: u8 = 17;
+    pub const OPENAT: u8 = 18;
+    pub const CLOSE: u8 = 19;
+    pub const FILES_UPDATE: u8 = 20;
+    pub const STATX: u8 = 21;
+    pub const READ: u8 = 22;
+    pub const WRITE: u8 = 23;
+    pub const FADVISE: u8 = 24;
+    pub const MADVISE: u8 = 25;
+    pub const SEND: u8 = 26;
+    pub const RECV: u8 = 27;
+    pub const OPENAT2: u8 = 28;
+    pub const EPOLL_CTL: u8 = 29;
+    pub const SPLICE: u8 = 30;
+    pub const PROVIDE_BUFFERS: u8 = 31;
+    pub const REMOVE_BUFFERS: u8 = 32;
+    pub const TEE: u8 = 33;
+    pub const SHUTDOWN: u8 = 34;
+    pub const RENAMEAT: u8 = 35;
+    pub const UNLINKAT: u8 = 36;
+    pub const MKDIRAT: u8 = 37;
+    pub const SYMLINKAT: u8 = 38;
+    pub const LINKAT: u8 = 39;
+    pub const MSG_RING: u8 = 40;
+    pub const FSETXATTR: u8 = 41;
+    pub const SETXATTR: u8 = 42;
+    pub const FGETXATTR: u8 = 43;
+    pub const GETXATTR: u8 = 44;
+    pub const SOCKET: u8 = 45;
+    pub const URING_CMD: u8 = 46;
+    pub const SEND_ZC: u8 = 47;
+    pub const SENDMSG_ZC: u8 = 48;
+
+    pub(super) const NAMES: [&str; 49] = [
+        "nop",
+        "readv",
+        "writev",
+        "fsync",
+        "read_fixed",
+        "write_fixed",
+        "poll_add",
+        "poll_remove",
+        "sync_file_range",
+        "sendmsg",
+        "recvmsg",
+        "timeout",
+        "timeout_remove",
+        "accept",
+        "async_cancel",
+        "link_timeout",
+        "connect",
+        "fallocate",
+        "openat",
+        "close",
+        "files_update",
+        "statx",
+        "read",
+        "write",
+        "fadvise",
+        "madvise",
+        "send",
+        "recv",
+        "openat2",
+        "epoll_ctl",
+        "splice",
+        "provide_buffers",
+        "remove_buffers",
+        "tee",
+        "shutdown",
+        "renameat",
+        "unlinkat",
+        "mkdirat",
+        "symlinkat",
+        "linkat",
+        "msg_ring",
+        "fsetxattr",
+        "setxattr",
+        "fgetxattr",
+        "getxattr",
+        "socket",
+        "uring_cmd",
+        "send_zc",
+        "sendmsg_zc",
+    ];
+}
+
+impl Sqe {
+    /// オペコードの名前（未知のものは`"unknown"`）
+    pub fn opcode_name(&self) -> &'static str {
+        opcode::NAMES
+            .get(self.opcode as usize)
+            .copied()
+            .unwrap_or("unknown")
+    }
+}
+
+/// 監視しているリング（自分でmmapした領域）
+struct Ring {
+    flags: u32,
+    sq_entries: u32,
+    cq_entries: u32,
+    sq_off: IoSqringOffsets,
+    cq_off: IoCqringOffsets,
+    rings: *mut u8,
+    rings_len: usize,
+    sqes: *mut u8,
+    sqes_len: usize,
+    /// 次に通知するCQEの位置
+    cq_seen: u32,
+}
+
+// 共有メモリへのポインタだけを持ち、アクセスは`&mut`を通して直列化される
+unsafe impl Send for Ring {}
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    fn map(fd: c_int, params: &IoUringParams) -> Result<Self, c_int> {
+        if params.flags & IORING_SETUP_NO_MMAP != 0
+            || params.features & IORING_FEAT_SINGLE_MMAP == 0
+        {
+            return Err(libc::ENOTSUP);
+        }
+        let sqe_size = if params.flags & IORING_SETUP_SQE128 != 0 {
+            128
+        } else {
+            64
+        };
+        let cqe_size = if params.flags & IORING_SETUP_CQE32 != 0 {
+            32
+        } else {
+            16
+        };
+        let sq_len = if params.flags & IORING_SETUP_NO_SQARRAY != 0 {
+            0
+        } else {
+            params.sq_off.array as usize + params.sq_entries as usize * 4
+        };
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * cqe_size;
+        let rings_len = sq_len.max(cq_len);
+        let sqes_len = params.sq_entries as usize * sqe_size;
+
+        let rings = mmap(fd, rings_len, IORING_OFF_SQ_RING)?;
+        let sqes = match mmap(fd, sqes_len, IORING_OFF_SQES) {
+            Ok(sqes) => sqes,
+            Err(errno) => {
+                munmap(rings, rings_len);
+                return Err(errno);
+            }
+        };
+        Ok(Self {
+            flags: params.flags,
+            sq_entries: params.sq_entries,
+            cq_entries: params.cq_entries,
+            sq_off: params.sq_off,
+            cq_off: params.cq_off,
+            rings,
+            rings_len,
+            sqes,
+            sqes_len,
+            cq_seen: 0,
+        })
+    }
+
+    fn ring_u32(&self, offset: u32) -> &AtomicU32 {
+        unsafe { &*(self.rings.add(offset as usize) as *const AtomicU32) }
+    }
+
+    fn sqe_ptr(&self, index: u32) -> *mut Sqe {
+        let stride = self.sqes_len / self.sq_entries as usize;
+        unsafe { self.sqes.add((index % self.sq_entries) as usize * stride) as *mut Sqe }
+    }
+
+    fn cqe_ptr(&self, pos: u32) -> *mut Cqe {
+        let stride = if self.flags & IORING_SETUP_CQE32 != 0 {
+            32
+        } else {
+            16
+        };
+        let mask = self.ring_u32(self.cq_off.ring_mask).load(Ordering::Relaxed);
+        let offset = self.cq_off.cqes as usize + (pos & mask) as usize * stride;
+        unsafe { self.rings.add(offset) as *mut Cqe }
+    }
+
+    /// カーネルが未消費のSQE（先頭`max`個）を`f`に渡す
+    fn submissions(&mut self, max: u32, mut f: impl FnMut(&mut Sqe)) {
+        let head = self.ring_u32(self.sq_off.head).load(Ordering::Acquire);
+        let tail = self.ring_u32(self.sq_off.tail).load(Ordering::Acquire);
+        let mask = self.ring_u32(self.sq_off.ring_mask).load(Ordering::Relaxed);
+        let pending = tail.wrapping_sub(head).min(self.sq_entries).min(max);
+        for i in 0..pending {
+            let pos = head.wrapping_add(i) & mask;
+            let index = if self.flags & IORING_SETUP_NO_SQARRAY != 0 {
+                pos
+            } else {
+                self.ring_u32(self.sq_off.array + pos * 4)
+                    .load(Ordering::Relaxed)
+            };
+            let ptr = self.sqe_ptr(index);
+            let sqe = unsafe { std::ptr::read_volatile(ptr) };
+            let mut modified = sqe;
+            f(&mut modified);
+            if modified != sqe {
+                unsafe { std::ptr::write_volatile(ptr, modified) };
+            }
+        }
+    }
+
+    /// 前回から増えたCQEを`f`に渡す（リングを一周して上書きされた分は飛ばす）
+    fn completions(&mut self, mut f: impl FnMut(&mut Cqe)) {
+        let tail = self.ring_u32(self.cq_off.tail).load(Ordering::Acquire);
+        if tail.wrapping_sub(self.cq_seen) > self.cq_entries {
+            self.cq_seen = tail.wrapping_sub(self.cq_entries);
+        }
+        while self.cq_seen != tail {
+            let ptr = self.cqe_ptr(self.cq_seen);
+            let cqe = unsafe { std::ptr::read_volatile(ptr) };
+            let mut modified = cqe;
+            f(&mut modified);
+            if modified != cqe {
+                unsafe { std::ptr::write_volatile(ptr, modified) };
+            }
+            self.cq_seen = self.cq_seen.wrapping_add(1);
+        }
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        munmap(self.rings, self.rings_len);
+        munmap(self.sqes, self.sqes_len);
+    }
+}
+
+fn mmap(fd: c_int, len: usize, offset: u64) -> Result<*mut u8, c_int> {
+    let ret = unsafe {
+        raw_syscall_bypass(
+            Sysno::mmap.nr(),
+            0,
+            len as u64,
+            (libc::PROT_READ | libc::PROT_WRITE) as u64,
+            (libc::MAP_SHARED | libc::MAP_POPULATE) as u64,
+            fd as u64,
+            offset,
+        )
+    };
+    if ret < 0 {
+        return Err(-ret as c_int);
+    }
+    Ok(ret as *mut u8)
+}
+
+fn munmap(ptr: *mut u8, len: usize) {
+    unsafe { raw_syscall_bypass(Sysno::munmap.nr(), ptr as u64, len as u64, 0, 0, 0, 0) };
+}
+
+/// 監視しているio_uringの表
+///
+/// `IoUringTracer`を使わずに自分の`SyscallHooks`から使う場合は、
+/// `io_uring_setup`の成功後に`track`、`io_uring_enter`の前後に
+/// `submissions`/`completions`、`close`で`untrack`を呼びます。
+#[derive(Default)]
+pub struct IoUringRings {
+    rings: BTreeMap<c_int, Ring>,
+    /// `IORING_REGISTER_RING_FDS`で登録された（スレッドID, インデックス）とリングのfd
+    ///
+    /// 登録はカーネルのタスクごとなので、同じインデックスでもスレッドごとに別のリング。
+    registered: BTreeMap<(pid_t, u32), c_int>,
+}
+
+/// 1回の`IORING_REGISTER_RING_FDS`で登録できる数（カーネルのIO_RINGFD_REG_MAX）
+const MAX_REGISTERED_RINGS: usize = 16;
+
+fn gettid() -> pid_t {
+    unsafe { raw_syscall_bypass(Sysno::gettid.nr(), 0, 0, 0, 0, 0, 0) as pid_t }
+}
+
+/// `struct io_uring_rsrc_update`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct RsrcUpdate {
+    offset: u32,
+    resv: u32,
+    data: u64,
+}
+
+impl IoUringRings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `io_uring_setup`が返したリングを監視する（エラーは`errno`）
+    pub fn track(&mut self, fd: c_int, params: &IoUringParams) -> Result<(), c_int> {
+        let ring = Ring::map(fd, params)?;
+        self.rings.insert(fd, ring);
+        Ok(())
+    }
+
+    /// 監視をやめる
+    pub fn untrack(&mut self, fd: c_int) {
+        self.rings.remove(&fd);
+        self.registered.retain(|_, ring_fd| *ring_fd != fd);
+    }
+
+    /// 監視しているか
+    pub fn contains(&self, fd: c_int) -> bool {
+        self.rings.contains_key(&fd)
+    }
+
+    /// `io_uring_enter`の`fd`引数をリングのfdに変換する
+    ///
+    /// `IORING_ENTER_REGISTERED_RING`では`fd`は呼び出したスレッドが登録したインデックスです。
+    pub fn ring_fd(&self, fd: c_int, enter_flags: u32) -> Option<c_int> {
+        if enter_flags & IORING_ENTER_REGISTERED_RING != 0 {
+            self.registered.get(&(gettid(), fd as u32)).copied()
+        } else {
+            self.contains(fd).then_some(fd)
+        }
+    }
+
+    /// 未消費のSQE（先頭`to_submit`個）を`f`に渡す（`io_uring_enter`の前に呼ぶ）
+    pub fn submissions(&mut self, fd: c_int, to_submit: u32, f: impl FnMut(&mut Sqe)) {
+        if let Some(ring) = self.rings.get_mut(&fd) {
+            ring.submissions(to_submit, f);
+        }
+    }
+
+    /// 新しいCQEを`f`に渡す（`io_uring_enter`の後に呼ぶ）
+    pub fn completions(&mut self, fd: c_int, f: impl FnMut(&mut Cqe)) {
+        if let Some(ring) = self.rings.get_mut(&fd) {
+            ring.completions(f);
+        }
+    }
+
+    /// `io_uring_register`の成功後に呼ぶ（登録したリングのfdを呼び出したスレッドに記録する）
+    pub fn registered(&mut self, opcode: c_uint, arg: *const c_void, nr_args: c_uint) {
+        if !matches!(
+            opcode,
+            IORING_REGISTER_RING_FDS | IORING_UNREGISTER_RING_FDS
+        ) {
+            return;
+        }
+        let mut updates = [RsrcUpdate::default(); MAX_REGISTERED_RINGS];
+        let updates = &mut updates[..(nr_args as usize).min(MAX_REGISTERED_RINGS)];
+        let out = unsafe {
+            std::slice::from_raw_parts_mut(
+                updates.as_mut_ptr() as *mut u8,
+                std::mem::size_of_val(updates),
+            )
+        };
+        if try_read_bytes(arg, out).is_err() {
+            return;
+        }
+        let tid = gettid();
+        for update in updates.iter() {
+            if opcode == IORING_REGISTER_RING_FDS {
+                self.registered.insert((tid, update.offset), update.data as c_int);
+            } else {
+                self.registered.remove(&(tid, update.offset));
+            }
+        }
+    }
+
+    /// fork後の子プロセスで呼ぶ（登録したリングは子のタスクに引き継がれない）
+    pub fn forget_registered(&mut self) {
+        self.registered.clear();
+    }
+}
+
+/// io_uringのSQEとCQEを受け取るフック
+pub trait IoUringHooks: Send + Sync + 'static {
+    /// カーネルに渡る前のSQE（書き換えるとリングに反映される）
+    fn on_submit(&mut self, ring_fd: c_int, sqe: &mut Sqe) {
+        let _ = (ring_fd, sqe);
+    }
+
+    /// `io_uring_enter`から戻った時点で新しく見つかったCQE
+    fn on_complete(&mut self, ring_fd: c_int, cqe: &mut Cqe) {
+        let _ = (ring_fd, cqe);
+    }
+
+    /// fork後の子プロセスで呼ばれる
+    fn on_fork_child(&mut self) {}
+}
+
+/// `IoUringHooks`を`SyscallHooks`として登録するためのアダプタ
+///
+/// 関心集合は`IO_URING_SYSCALLS`です。
+pub struct IoUringTracer<T> {
+    pub hooks: T,
+    rings: IoUringRings,
+}
+
+impl<T: IoUringHooks> IoUringTracer<T> {
+    pub fn new(hooks: T) -> Self {
+        Self {
+            hooks,
+            rings: IoUringRings::new(),
+        }
+    }
+
+    /// 監視しているリング
+    pub fn rings(&self) -> &IoUringRings {
+        &self.rings
+    }
+}
+
+impl<T: IoUringHooks> SyscallHooks for IoUringTracer<T> {
+    fn interests(&self) -> SysnoSet {
+        IO_URING_SYSCALLS
+    }
+
+    fn on_fork_child(&mut self) {
+        self.rings.forget_registered();
+        self.hooks.on_fork_child()
+    }
+
+    fn hook_io_uring_setup(&mut self, entries: u32, params: IoUringParamsPtr) -> c_int {
+        let fd = default_io_uring_setup(entries, params);
+        if fd >= 0 {
+            if let Ok(p) = params.read() {
+                // 監視できないリングもアプリケーションにはそのまま返す
+                let _ = self.rings.track(fd, &p);
+            }
+        }
+        fd
+    }
+
+    fn hook_io_uring_enter(
+        &mut self,
+        fd: c_int,
+        to_submit: u32,
+        min_complete: u32,
+        flags: u32,
+        arg: *const c_void,
+        argsz: size_t,
+    ) -> c_int {
+        let Some(ring_fd) = self.rings.ring_fd(fd, flags) else {
+            return default_io_uring_enter(fd, to_submit, min_complete, flags, arg, argsz);
+        };
+        let hooks = &mut self.hooks;
+        self.rings
+            .submissions(ring_fd, to_submit, |sqe| hooks.on_submit(ring_fd, sqe));
+        let ret = default_io_uring_enter(fd, to_submit, min_complete, flags, arg, argsz);
+        self.rings
+            .completions(ring_fd, |cqe| hooks.on_complete(ring_fd, cqe));
+        ret
+    }
+
+    fn hook_io_uring_register(
+        &mut self,
+        fd: c_int,
+        opcode: c_uint,
+        arg: *mut c_void,
+        nr_args: c_uint,
+    ) -> c_int {
+        let ret = default_io_uring_register(fd, opcode, arg, nr_args);
+        if ret >= 0 && self.rings.contains(fd) {
+            self.rings.registered(opcode, arg, nr_args);
+        }
+        ret
+    }
+
+    fn hook_close(&mut self, fd: c_int) -> c_int {
+        let ret = default_close(fd);
+        if ret == 0 {
+            self.rings.untrack(fd);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syscall_hooks::dispatch_syscall_hooks;
+    use crate::SyscallRegs;
+
+    #[derive(Default)]
+    struct Recorder {
+        submitted: Vec<(u8, i32, u64)>,
+        completed: Vec<(u64, i32)>,
+    }
+
+    impl IoUringHooks for Recorder {
+        fn on_submit(&mut self, _ring_fd: c_int, sqe: &mut Sqe) {
+            self.submitted.push((sqe.opcode, sqe.fd, sqe.user_data));
+        }
+
+        fn on_complete(&mut self, _ring_fd: c_int, cqe: &mut Cqe) {
+            self.completed.push((cqe.user_data, cqe.res));
+            // NOPの結果を偽装する
+            if cqe.user_data == 1 {
+                cqe.res = -libc::EPERM;
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(std::mem::size_of::<IoUringParams>(), 120);
+        assert_eq!(std::mem::size_of::<Sqe>(), 64);
+        assert_eq!(std::mem::offset_of!(Sqe, user_data), 32);
+        assert_eq!(std::mem::size_of::<Cqe>(), 16);
+        assert_eq!(opcode::NAMES[opcode::SENDMSG_ZC as usize], "sendmsg_zc");
+    }
+
+    #[test]
+    fn test_registered_rings_per_thread() {
+        let mut rings = IoUringRings::new();
+        let update = RsrcUpdate {
+            offset: 3,
+            resv: 0,
+            data: 42,
+        };
+        let arg = &update as *const RsrcUpdate as *const c_void;
+        rings.registered(IORING_REGISTER_RING_FDS, arg, 1);
+        assert_eq!(rings.ring_fd(3, IORING_ENTER_REGISTERED_RING), Some(42));
+        assert_eq!(rings.ring_fd(42, 0), None);
+
+        // 登録したスレッド以外では同じインデックスでも別のリング
+        std::thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(rings.ring_fd(3, IORING_ENTER_REGISTERED_RING), None));
+        });
+
+        rings.registered(IORING_UNREGISTER_RING_FDS, arg, 1);
+        assert_eq!(rings.ring_fd(3, IORING_ENTER_REGISTERED_RING), None);
+    }
+
+    #[test]
+    fn test_io_uring_tracer() {
+        let mut tracer = IoUringTracer::new(Recorder::default());
+        let mut params = IoUringParams::default();
+        let mut regs = SyscallRegs::new(
+            Sysno::io_uring_setup.nr(),
+            4,
+            &mut params as *mut IoUringParams as u64,
+            0,
+            0,
+            0,
+            0,
+        );
+        let fd = dispatch_syscall_hooks(&mut tracer, &mut regs) as c_int;
+        // io_uringが無効な環境（未対応のカーネル、io_uring_disabled、seccomp）だけ飛ばす
+        if fd == -libc::ENOSYS || fd == -libc::EPERM {
+            return;
+        }
+        assert!(fd >= 0, "io_uring_setup failed: {}", -fd);
+        assert!(tracer.rings().contains(fd));
+
+        // アプリケーション側としてリングをmmapし、SQEを2つ積む
+        let app = Ring::map(fd, &params).unwrap();
+        let mut pipe = [0; 2];
+        assert_eq!(unsafe { libc::pipe(pipe.as_mut_ptr()) }, 0);
+        let data = *b"ring";
+        let write = Sqe {
+            opcode: opcode::WRITE,
+            fd: pipe[1],
+            addr: data.as_ptr() as u64,
+            len: data.len() as u32,
+            user_data: 2,
+            ..Sqe::default()
+        };
+        let nop = Sqe {
+            opcode: opcode::NOP,
+            user_data: 1,
+            ..Sqe::default()
+        };
+        let mask = params.sq_entries - 1;
+        for (i, sqe) in [nop, write].iter().enumerate() {
+            unsafe { std::ptr::write_volatile(app.sqe_ptr(i as u32), *sqe) };
+            if params.flags & IORING_SETUP_NO_SQARRAY == 0 {
+                app.ring_u32(params.sq_off.array + (i as u32 & mask) * 4)
+                    .store(i as u32, Ordering::Relaxed);
+            }
+        }
+        app.ring_u32(params.sq_off.tail).store(2, Ordering::Release);
+
+        const IORING_ENTER_GETEVENTS: u64 = 1;
+        let mut regs = SyscallRegs::new(
+            Sysno::io_uring_enter.nr(),
+            fd as u64,
+            2,
+            2,
+            IORING_ENTER_GETEVENTS,
+            0,
+            0,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut tracer, &mut regs), 2);
+        assert_eq!(
+            tracer.hooks.submitted,
+            vec![(opcode::NOP, 0, 1), (opcode::WRITE, pipe[1], 2)]
+        );
+        let mut completed = tracer.hooks.completed.clone();
+        completed.sort();
+        assert_eq!(completed, vec![(1, 0), (2, 4)]);
+
+        // 書き換えた結果はアプリケーションから見える
+        let cqes: Vec<Cqe> = (0..2)
+            .map(|i| unsafe { std::ptr::read_volatile(app.cqe_ptr(i)) })
+            .collect();
+        let nop_cqe = cqes.iter().find(|cqe| cqe.user_data == 1).unwrap();
+        assert_eq!(nop_cqe.res, -libc::EPERM);
+
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            unsafe { libc::read(pipe[0], buf.as_mut_ptr() as *mut c_void, 4) },
+            4
+        );
+        assert_eq!(&buf, b"ring");
+
+        drop(app);
+        let mut regs = SyscallRegs::new(Sysno::close.nr(), fd as u64, 0, 0, 0, 0, 0);
+        assert_eq!(dispatch_syscall_hooks(&mut tracer, &mut regs), 0);
+        assert!(!tracer.rings().contains(fd));
+        unsafe {
+            libc::close(pipe[0]);
+            libc::close(pipe[1]);
+        }
+    }
+}
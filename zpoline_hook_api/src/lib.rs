@@ -1,17 +1,45 @@
 use std::cell::Cell;
 use std::sync::atomic::{AtomicPtr, Ordering};
-use std::sync::Mutex;
 
+pub mod abi;
+pub mod args;
+pub mod clone;
+pub mod context;
+pub mod dirent;
+pub mod enter_exit;
+pub mod fork;
+pub mod identity;
+pub mod interests;
+pub mod io;
+pub mod io_uring;
+pub mod log;
+pub mod nesting;
+pub mod poll;
+pub mod reload;
+pub mod signal;
+pub mod socket;
 pub mod syscall_hooks;
-
+pub mod sysno;
+pub mod user_mem;
+pub mod vfd;
+
+pub use enter_exit::{Action, EnterExitHooks, SyscallCtx};
+pub use interests::{extend_hook_interests, hook_interests, set_hook_interests};
+pub use nesting::{
+    disable_hooks_for_current_thread, enable_hooks_for_current_thread, hook_depth,
+    hooks_enabled_for_current_thread, nesting_policy, set_nesting_policy, without_hooks,
+    NestingPolicy,
+};
 pub use syscall_hooks::SyscallHooks;
+pub use zpoline_hook_macros::hook_library;
+pub use sysno::{ParseSysnoError, SyscallCategory, Sysno, SysnoSet};
 
 /// システムコールのレジスタ状態
 /// x86-64のシステムコール呼び出し規約に従う
 /// syscall番号: rax
 /// 引数: rdi, rsi, rdx, r10, r8, r9
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SyscallRegs {
     pub rax: u64, // syscall番号
     pub rdi: u64, // 第1引数
@@ -48,6 +76,46 @@ impl SyscallRegs {
             r9: 0,
         }
     }
+
+    /// n番目（0始まり）の引数を取得
+    ///
+    /// # Panics
+    ///
+    /// `n`が6以上の場合
+    pub fn arg(&self, n: usize) -> u64 {
+        match n {
+            0 => self.rdi,
+            1 => self.rsi,
+            2 => self.rdx,
+            3 => self.r10,
+            4 => self.r8,
+            5 => self.r9,
+            _ => panic!("syscall argument index out of range: {}", n),
+        }
+    }
+
+    /// n番目（0始まり）の引数を設定
+    ///
+    /// # Panics
+    ///
+    /// `n`が6以上の場合
+    pub fn set_arg(&mut self, n: usize, value: u64) {
+        match n {
+            0 => self.rdi = value,
+            1 => self.rsi = value,
+            2 => self.rdx = value,
+            3 => self.r10 = value,
+            4 => self.r8 = value,
+            5 => self.r9 = value,
+            _ => panic!("syscall argument index out of range: {}", n),
+        }
+    }
+
+    /// n番目の引数を差し替えたコピーを返す
+    pub fn with_arg(mut self, n: usize, value: u64) -> Self {
+        self.set_arg(n, value);
+        self
+    }
 }
 
 /// フック関数の型
@@ -64,9 +132,49 @@ pub extern "C" fn default_hook(regs: &mut SyscallRegs) -> i64 {
 /// グローバルなフック関数ポインタ
 static HOOK_FUNCTION: AtomicPtr<()> = AtomicPtr::new(default_hook as *mut ());
 
-/// SyscallHooksを実装した型のグローバルな保持
-/// Mutexで保護されたBox<dyn SyscallHooks>
-static HOOK_TRAIT_OBJECT: Mutex<Option<Box<dyn SyscallHooks>>> = Mutex::new(None);
+/// 登録されたフック実装（`SyscallHooks`または`EnterExitHooks`）への
+/// ディスパッチを抽象化する内部trait
+pub(crate) trait HookDispatcher: Send {
+    fn dispatch(&mut self, regs: &mut SyscallRegs) -> i64;
+
+    /// フック実装が宣言した関心集合
+    fn interests(&self) -> SysnoSet;
+
+    /// fork後の子プロセスで呼ばれる
+    fn on_fork_child(&mut self);
+}
+
+/// `SyscallHooks`を`hook_entry`のディスパッチ形式に変換する
+///
+/// `on_clone_parent`/`clone_child_fn`のため、`fork::FORK_SYSCALLS`は関心集合に
+/// 含まれなくてもディスパッチし、フックメソッドの代わりにデフォルト実装で実行する。
+struct SyscallHooksDispatcher<T> {
+    hooks: T,
+    interests: SysnoSet,
+}
+
+impl<T: SyscallHooks> HookDispatcher for SyscallHooksDispatcher<T> {
+    fn dispatch(&mut self, regs: &mut SyscallRegs) -> i64 {
+        if self.interests.contains_raw(regs.rax) {
+            syscall_hooks::dispatch_syscall_hooks(&mut self.hooks, regs)
+        } else {
+            syscall_hooks::dispatch_clone_callbacks(&mut self.hooks, regs)
+        }
+    }
+
+    fn interests(&self) -> SysnoSet {
+        self.interests.union(&fork::FORK_SYSCALLS)
+    }
+
+    fn on_fork_child(&mut self) {
+        self.hooks.on_fork_child()
+    }
+}
+
+/// 登録されたフック実装のグローバルな保持
+/// fork後の子プロセスで解放できるロックで保護されたBox<dyn HookDispatcher>
+static HOOK_TRAIT_OBJECT: fork::ForkSafeMutex<Option<Box<dyn HookDispatcher>>> =
+    fork::ForkSafeMutex::new(None);
 
 /// フック関数を設定
 #[no_mangle]
@@ -80,11 +188,6 @@ pub fn get_hook_fn() -> HookFn {
     unsafe { std::mem::transmute(ptr) }
 }
 
-// TLSによる再入ガード
-thread_local! {
-    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
-}
-
 static HOOK_ENTRY_CALL_COUNT: AtomicUsize = AtomicUsize::new(0);
 
 /// フックエントリポイント
@@ -94,26 +197,40 @@ pub extern "C" fn hook_entry(regs: &mut SyscallRegs) -> i64 {
     // デバッグ用: hook_entryが呼ばれたことを記録
     HOOK_ENTRY_CALL_COUNT.fetch_add(1, Ordering::Relaxed);
 
-    // 再入チェック
-    if IN_HOOK.with(|in_hook| {
-        if in_hook.get() {
-            true
+    // 親とTLSを共有する子では、execveで戻らずに終わるフックの状態が親に残る
+    if clone::in_vfork_child() {
+        return clone::vfork_child_syscall(regs);
+    }
+
+    let fork_like = fork::is_fork_like(regs);
+    let dispatch = |regs: &mut SyscallRegs| {
+        if !interests::is_hook_interested(regs.rax) {
+            // 関心集合に含まれないsyscallはフックを経由せずに実行
+            unsafe { raw_syscall(regs) }
+        } else if let Some(_depth) = nesting::enter_hook() {
+            // フック関数を呼び出し（ガードのdropでネスト深さと実行中の数を戻す）
+            // 切り替え後の古いフック関数の完了を待てるよう、読み出す前に数える
+            let _in_flight = reload::enter();
+            let hook_fn = get_hook_fn();
+            hook_fn(regs)
         } else {
-            in_hook.set(true);
-            false
+            // 許可された深さを超えた再入 - 元のsyscallを直接実行
+            unsafe { raw_syscall(regs) }
         }
-    }) {
-        // 再入検出 - 元のsyscallを直接実行
-        let result = unsafe { raw_syscall(regs) };
-        return result;
-    }
+    };
 
-    // フック関数を呼び出し
-    let hook_fn = get_hook_fn();
-    let result = hook_fn(regs);
+    let shares_tls = clone::is_clone(regs.rax)
+        && clone::clone_flags(regs).is_some_and(clone::shares_caller_tls);
+    let result = if shares_tls {
+        clone::while_sharing_tls(|| dispatch(regs))
+    } else {
+        dispatch(regs)
+    };
 
-    // フラグをリセット
-    IN_HOOK.with(|in_hook| in_hook.set(false));
+    // fork/cloneの子プロセスではランタイムを再初期化する
+    if fork_like && result == 0 {
+        fork::after_fork_child();
+    }
 
     result
 }
@@ -129,8 +246,17 @@ pub extern "C" fn get_hook_entry_call_count() -> usize {
 ///
 /// 注意: この実装は簡易版です。実際には専用のページに配置した
 /// syscall命令を使用するか、asmマクロで実装する必要があります。
+///
+/// # Safety
+///
+/// `regs`の内容はそのままカーネルに渡されるため、ポインタ引数は
+/// 対象のシステムコールが要求する有効なメモリを指している必要がある
 #[no_mangle]
 pub unsafe extern "C" fn raw_syscall(regs: &SyscallRegs) -> i64 {
+    // 新しいスタックで作られた子はこのフレームに戻れない
+    if clone::is_clone(regs.rax) {
+        return clone::raw_clone(regs);
+    }
     raw_syscall_impl(
         regs.rax, regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9,
     )
@@ -139,7 +265,7 @@ pub unsafe extern "C" fn raw_syscall(regs: &SyscallRegs) -> i64 {
 /// 実際のsyscall命令を含む関数
 /// この関数は別途書き換え除外リストに追加される必要がある
 #[inline(never)]
-unsafe fn raw_syscall_impl(
+pub(crate) unsafe fn raw_syscall_impl(
     nr: u64,
     arg1: u64,
     arg2: u64,
@@ -167,6 +293,10 @@ unsafe fn raw_syscall_impl(
 
 /// raw syscallのバイパス（C ABIバージョン）
 /// 引数を個別に受け取る
+///
+/// # Safety
+///
+/// `raw_syscall`と同様、引数はそのままカーネルに渡される
 #[no_mangle]
 pub unsafe extern "C" fn raw_syscall_bypass(
     nr: u64,
@@ -180,9 +310,9 @@ pub unsafe extern "C" fn raw_syscall_bypass(
     raw_syscall_impl(nr, arg1, arg2, arg3, arg4, arg5, arg6)
 }
 
-/// 便利な関数: 再入ガードの状態を取得
+/// 便利な関数: 現在のスレッドがフック実行中か
 pub fn is_in_hook() -> bool {
-    IN_HOOK.with(|in_hook| in_hook.get())
+    nesting::hook_depth() > 0
 }
 
 /// SyscallHooksトレイトを実装した型を登録する
@@ -198,9 +328,9 @@ pub fn is_in_hook() -> bool {
 /// struct MyHooks;
 ///
 /// impl SyscallHooks for MyHooks {
-///     fn hook_write(&mut self, fd: i32, buf: *const u8, count: usize) -> isize {
+///     fn hook_write(&mut self, fd: i32, buf: *const std::ffi::c_void, count: usize) -> isize {
 ///         eprintln!("[CUSTOM] write called");
-///         Self::default_write(fd, buf as *const _, count)
+///         zpoline_hook_api::syscall_hooks::default_write(fd, buf, count)
 ///     }
 /// }
 ///
@@ -210,9 +340,35 @@ pub fn is_in_hook() -> bool {
 /// }
 /// ```
 pub fn register_syscall_hooks<T: SyscallHooks>(hooks: T) {
-    // SyscallHooksをグローバルに保存
-    let mut guard = HOOK_TRAIT_OBJECT.lock().unwrap();
-    *guard = Some(Box::new(hooks));
+    let interests = hooks.interests();
+    register_dispatcher(Box::new(SyscallHooksDispatcher { hooks, interests }));
+}
+
+/// EnterExitHooksトレイトを実装した型を登録する
+///
+/// `register_syscall_hooks`と同じくtraitベースのフック機構を有効にします。
+/// 後から登録したものが以前の登録（どちらのtraitでも）を置き換えます。
+pub fn register_enter_exit_hooks<T: EnterExitHooks>(hooks: T) {
+    register_dispatcher(Box::new(enter_exit::EnterExitDispatcher(hooks)));
+}
+
+fn register_dispatcher(dispatcher: Box<dyn HookDispatcher>) {
+    // フック実装が処理するsyscallだけをディスパッチ対象にする
+    // 仮想化したシグナルや仮想fdがあれば、その処理に必要なsyscallも含める
+    let mut interests = dispatcher.interests();
+    signal::set_dispatcher_interests(&interests);
+    vfd::set_dispatcher_interests(&interests);
+    if signal::virtual_signals().is_active() {
+        interests = interests.union(&signal::SIGNAL_HOOK_SYSCALLS);
+    }
+    if !vfd::virtual_fds().is_empty() {
+        interests = interests.union(&vfd::VFD_HOOK_SYSCALLS);
+    }
+    set_hook_interests(interests);
+
+    // ディスパッチャをグローバルに保存
+    let mut guard = HOOK_TRAIT_OBJECT.lock();
+    *guard = Some(dispatcher);
     drop(guard); // 明示的にロックを解放
 
     // フック関数として trait_based_hook を設定
@@ -222,29 +378,73 @@ pub fn register_syscall_hooks<T: SyscallHooks>(hooks: T) {
 use std::sync::atomic::AtomicUsize;
 static TRAIT_HOOK_CALL_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+// ディスパッチャ実行中のスレッド（ネストを許可しても同じディスパッチャには再入しない）
+thread_local! {
+    static IN_DISPATCHER: Cell<bool> = const { Cell::new(false) };
+}
+
 /// traitベースのフック関数
-/// SyscallHooksトレイトのメソッドにディスパッチする
+/// 登録されたSyscallHooks/EnterExitHooksにディスパッチする
 extern "C" fn trait_based_hook(regs: &mut SyscallRegs) -> i64 {
     // デバッグ用: この関数が呼ばれたことを記録
     TRAIT_HOOK_CALL_COUNT.fetch_add(1, Ordering::Relaxed);
 
+    // ロック保持中の再入はデッドロックするため直接実行
+    if IN_DISPATCHER.with(|flag| flag.replace(true)) {
+        return unsafe { raw_syscall(regs) };
+    }
+
+    let result = dispatch_trait_hooks(regs);
+    IN_DISPATCHER.with(|flag| flag.set(false));
+    result
+}
+
+fn dispatch_trait_hooks(regs: &mut SyscallRegs) -> i64 {
+    // シグナルの仮想化のためだけに届いたものはロックを取らずに処理する
+    // （rt_sigsuspendで待つ間に他のスレッドのフックを止めない）
+    if let Some(ret) = signal::dispatch_unlocked(regs) {
+        return ret;
+    }
+    // 仮想fdも同様（仮想fdを待つ間に他のスレッドが書き込めるように）
+    if let Some(ret) = vfd::dispatch_unlocked(regs) {
+        return ret;
+    }
+
     // HOOK_TRAIT_OBJECTからフックオブジェクトを取得
-    let mut guard = match HOOK_TRAIT_OBJECT.lock() {
-        Ok(g) => g,
-        Err(_e) => {
-            // ロック失敗時はデフォルトのsyscallを実行
-            return unsafe { raw_syscall(regs) };
-        }
-    };
+    let mut guard = HOOK_TRAIT_OBJECT.lock();
 
     if let Some(ref mut hooks) = *guard {
-        syscall_hooks::dispatch_syscall_hooks(hooks.as_mut(), regs)
+        hooks.dispatch(regs)
     } else {
         // フックが登録されていない場合はデフォルトのsyscallを実行
         unsafe { raw_syscall(regs) }
     }
 }
 
+/// fork後の子プロセスで内部のロックとカウンタを再初期化する
+pub(crate) fn reinit_after_fork() {
+    HOOK_ENTRY_CALL_COUNT.store(0, Ordering::Relaxed);
+    TRAIT_HOOK_CALL_COUNT.store(0, Ordering::Relaxed);
+    reload::reset_after_fork();
+    vfd::reinit_after_fork();
+    signal::reinit_after_fork();
+
+    // ディスパッチ中でなければ、ロックを保持していたのは子プロセスに存在しないスレッド
+    if !IN_DISPATCHER.with(|flag| flag.get()) {
+        unsafe { HOOK_TRAIT_OBJECT.force_unlock() };
+    }
+}
+
+/// 登録されたフック実装に子プロセスを通知する
+pub(crate) fn notify_fork_child() {
+    if IN_DISPATCHER.with(|flag| flag.get()) {
+        return;
+    }
+    if let Some(ref mut hooks) = *HOOK_TRAIT_OBJECT.lock() {
+        hooks.on_fork_child();
+    }
+}
+
 /// デバッグ用: trait_based_hookが呼ばれた回数を取得
 #[no_mangle]
 pub extern "C" fn get_trait_hook_call_count() -> usize {
@@ -284,6 +484,10 @@ pub extern "C" fn get_trait_dispatch_hook() -> *const () {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+
+    // HOOK_FUNCTIONなどのグローバルな状態を変更するテストを直列化する
+    pub(crate) static GLOBAL_HOOK_STATE: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
     #[test]
     fn test_syscall_regs() {
@@ -295,6 +499,8 @@ mod tests {
 
     #[test]
     fn test_hook_function_ptr() {
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+
         extern "C" fn test_hook(_regs: &mut SyscallRegs) -> i64 {
             42
         }
@@ -307,12 +513,259 @@ mod tests {
         assert_eq!(result, 42);
     }
 
+    #[test]
+    fn test_hook_entry_skips_uninterested_syscalls() {
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+
+        extern "C" fn test_hook(_regs: &mut SyscallRegs) -> i64 {
+            42
+        }
+
+        __hook_init(test_hook);
+        set_hook_interests(SysnoSet::new(&[Sysno::getppid]));
+
+        let mut regs = SyscallRegs::new(Sysno::getppid.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(hook_entry(&mut regs), 42);
+        let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(hook_entry(&mut regs), std::process::id() as i64);
+
+        set_hook_interests(SysnoSet::all());
+    }
+
+    /// 子プロセスの終了ステータスを待つ（デッドロックした場合はNone）
+    pub(crate) fn wait_child(pid: i32) -> Option<i32> {
+        let mut status = 0;
+        for _ in 0..500 {
+            if unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } == pid {
+                return Some(libc::WEXITSTATUS(status));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+            libc::waitpid(pid, &mut status, 0);
+        }
+        None
+    }
+
+    #[test]
+    fn test_fork_from_multithreaded_process() {
+        use std::sync::atomic::AtomicBool;
+
+        static FORK_CHILD_CALLS: AtomicUsize = AtomicUsize::new(0);
+        static STOP: AtomicBool = AtomicBool::new(false);
+
+        struct ForkHooks;
+
+        impl SyscallHooks for ForkHooks {
+            fn on_fork_child(&mut self) {
+                FORK_CHILD_CALLS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+        register_syscall_hooks(ForkHooks);
+
+        // ディスパッチャのロックを保持したままスリープし続けるスレッド
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    let ts = libc::timespec {
+                        tv_sec: 0,
+                        tv_nsec: 200_000,
+                    };
+                    let ts_ptr = &ts as *const libc::timespec as u64;
+                    while !STOP.load(Ordering::Relaxed) {
+                        let mut regs = SyscallRegs::new(Sysno::nanosleep.nr(), ts_ptr, 0, 0, 0, 0, 0);
+                        hook_entry(&mut regs);
+                    }
+                })
+            })
+            .collect();
+        std::thread::sleep(Duration::from_millis(5));
+
+        // forkをディスパッチする場合と、関心集合から外して直接実行する場合
+        let dispatched = SysnoSet::all();
+        let direct = SysnoSet::new(&[Sysno::nanosleep, Sysno::getppid]);
+        for interests in [dispatched, direct] {
+            set_hook_interests(interests);
+
+            let mut regs = SyscallRegs::new(Sysno::fork.nr(), 0, 0, 0, 0, 0, 0);
+            let pid = hook_entry(&mut regs);
+            if pid == 0 {
+                // 子プロセス: on_fork_childが1回だけ呼ばれ、フック経由のsyscallが詰まらない
+                let mut regs = SyscallRegs::new(Sysno::getppid.nr(), 0, 0, 0, 0, 0, 0);
+                let ok = FORK_CHILD_CALLS.load(Ordering::Relaxed) == 1 && hook_entry(&mut regs) > 0;
+                unsafe { raw_syscall_bypass(Sysno::exit_group.nr(), !ok as u64, 0, 0, 0, 0, 0) };
+            }
+            assert!(pid > 0);
+            assert_eq!(wait_child(pid as i32), Some(0));
+        }
+
+        STOP.store(true, Ordering::Relaxed);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        *HOOK_TRAIT_OBJECT.lock() = None;
+        set_hook_interests(SysnoSet::all());
+        __hook_init(default_hook);
+    }
+
+    #[test]
+    fn test_clone_callbacks() {
+        use clone::{CloneChildFn, CloneFlags};
+        use std::sync::atomic::{AtomicBool, AtomicI32};
+
+        static PARENT_CHILD: AtomicI32 = AtomicI32::new(0);
+        static CHILD_TAGGED: AtomicBool = AtomicBool::new(false);
+
+        /// コールバックだけを実装するフック（`#[hook_library]`では関心集合が空になる）
+        struct CloneHooks;
+
+        impl SyscallHooks for CloneHooks {
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::empty()
+            }
+
+            fn on_clone_parent(&mut self, child: libc::pid_t, flags: CloneFlags) {
+                assert_eq!(flags.exit_signal(), libc::SIGCHLD);
+                PARENT_CHILD.store(child, Ordering::SeqCst);
+            }
+
+            fn clone_child_fn(&mut self, _flags: CloneFlags) -> Option<CloneChildFn> {
+                Some(|flags| CHILD_TAGGED.store(!flags.shares_vm(), Ordering::SeqCst))
+            }
+        }
+
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+        register_syscall_hooks(CloneHooks);
+
+        // vforkはCLONE_VMを外して実行され、子では戻る前にclone_child_fnの関数が呼ばれる
+        let mut regs = SyscallRegs::new(Sysno::vfork.nr(), 0, 0, 0, 0, 0, 0);
+        let pid = hook_entry(&mut regs);
+        if pid == 0 {
+            let ok = CHILD_TAGGED.load(Ordering::SeqCst);
+            unsafe { raw_syscall_bypass(Sysno::exit_group.nr(), !ok as u64, 0, 0, 0, 0, 0) };
+        }
+        assert!(pid > 0);
+        assert_eq!(PARENT_CHILD.load(Ordering::SeqCst), pid as i32);
+        assert!(!CHILD_TAGGED.load(Ordering::SeqCst));
+        assert_eq!(wait_child(pid as i32), Some(0));
+
+        *HOOK_TRAIT_OBJECT.lock() = None;
+        set_hook_interests(SysnoSet::all());
+        __hook_init(default_hook);
+    }
+
+    #[test]
+    fn test_hot_swap_waits_for_in_flight_hooks() {
+        use std::sync::atomic::AtomicBool;
+
+        static STARTED: AtomicBool = AtomicBool::new(false);
+        static RELEASE: AtomicBool = AtomicBool::new(false);
+
+        extern "C" fn old_hook(_regs: &mut SyscallRegs) -> i64 {
+            STARTED.store(true, Ordering::SeqCst);
+            while !RELEASE.load(Ordering::SeqCst) {
+                std::thread::yield_now();
+            }
+            1
+        }
+
+        extern "C" fn new_hook(_regs: &mut SyscallRegs) -> i64 {
+            2
+        }
+
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+        __hook_init(old_hook);
+        set_hook_interests(SysnoSet::new(&[Sysno::getppid]));
+
+        // 切り替え時点で古いフック関数を実行中のスレッド
+        let worker = std::thread::spawn(|| {
+            let mut regs = SyscallRegs::new(Sysno::getppid.nr(), 0, 0, 0, 0, 0, 0);
+            hook_entry(&mut regs)
+        });
+        while !STARTED.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+
+        // 切り替え後の呼び出しは新しいフック関数に届く
+        __hook_init(new_hook);
+        let mut regs = SyscallRegs::new(Sysno::getppid.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(hook_entry(&mut regs), 2);
+
+        // 古いフック関数が終わるまでは完了しない
+        assert!(!reload::synchronize(Duration::from_millis(20)));
+        RELEASE.store(true, Ordering::SeqCst);
+        assert_eq!(worker.join().unwrap(), 1);
+        assert!(reload::synchronize(Duration::from_secs(1)));
+
+        set_hook_interests(SysnoSet::all());
+        __hook_init(default_hook);
+    }
+
+    #[test]
+    fn test_requested_reload_wakes_loader_thread() {
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+        reload::request_reload();
+        assert!(reload::reload_pending());
+
+        // システムコールの経路ではリロードしない
+        let mut regs = SyscallRegs::new(Sysno::getppid.nr(), 0, 0, 0, 0, 0, 0);
+        hook_entry(&mut regs);
+        assert!(reload::reload_pending());
+        reload::wait_reload_request();
+        assert!(!reload::reload_pending());
+
+        // 待っているスレッドは要求で起きる
+        let (tx, rx) = std::sync::mpsc::channel();
+        let waiter = std::thread::spawn(move || {
+            reload::wait_reload_request();
+            tx.send(()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        reload::request_reload();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        waiter.join().unwrap();
+        assert!(!reload::reload_pending());
+    }
+
+    #[test]
+    fn test_trait_hooks_ignore_nesting_policy() {
+        let _global = GLOBAL_HOOK_STATE.lock().unwrap();
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        // フック内のgetpidをhook_entryに渡す（アプリケーションからのsyscallと同じ経路）
+        struct Nested;
+        impl SyscallHooks for Nested {
+            fn hook_getpid(&mut self) -> libc::pid_t {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+                let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+                hook_entry(&mut regs) as libc::pid_t
+            }
+        }
+
+        CALLS.store(0, Ordering::SeqCst);
+        register_syscall_hooks(Nested);
+        set_nesting_policy(NestingPolicy::MaxDepth(2));
+
+        // 深さ2まで許可してもフック実装には再入せず、内側は直接実行される
+        let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        assert_eq!(hook_entry(&mut regs), std::process::id() as i64);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        set_nesting_policy(NestingPolicy::Disabled);
+        *HOOK_TRAIT_OBJECT.lock() = None;
+    }
+
     #[test]
     fn test_reentry_guard() {
         assert!(!is_in_hook());
-        IN_HOOK.with(|in_hook| in_hook.set(true));
+        let depth = nesting::enter_hook().unwrap();
         assert!(is_in_hook());
-        IN_HOOK.with(|in_hook| in_hook.set(false));
+        drop(depth);
         assert!(!is_in_hook());
     }
 }
@@ -0,0 +1,205 @@
+//! フック内で安全に使えるログ出力
+//!
+//! `eprintln!`はヒープ確保とstderrのロックを伴い、mallocの内部やシグナル
+//! ハンドラから来たシステムコールをフックしているとデッドロックします。
+//! このモジュールはスレッドごとの固定長バッファに整形し、`raw_syscall_bypass`の
+//! `write`で1行ずつ出力します。ヒープ確保もロックも行わないため、
+//! シグナルハンドラ内のフックからも呼べます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::hook_log;
+//!
+//! hook_log!("[my_hooks] write(fd={}, count={})", 1, 42);
+//! ```
+
+use crate::{raw_syscall_bypass, Sysno};
+use std::cell::{Cell, UnsafeCell};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// 1行の最大長（超えた分は切り詰める）
+pub const LOG_LINE_MAX: usize = 512;
+
+/// 出力先のfd（負の値で出力しない）
+static LOG_FD: AtomicI32 = AtomicI32::new(libc::STDERR_FILENO);
+
+/// ログの出力先fdを設定する（負の値でログを無効化）
+pub fn set_log_fd(fd: i32) {
+    LOG_FD.store(fd, Ordering::Relaxed);
+}
+
+/// 現在のログの出力先fd
+pub fn log_fd() -> i32 {
+    LOG_FD.load(Ordering::Relaxed)
+}
+
+/// スレッドごとの行バッファ
+struct LineBuf {
+    busy: Cell<bool>,
+    buf: UnsafeCell<[u8; LOG_LINE_MAX]>,
+}
+
+thread_local! {
+    static LINE_BUF: LineBuf = const {
+        LineBuf {
+            busy: Cell::new(false),
+            buf: UnsafeCell::new([0; LOG_LINE_MAX]),
+        }
+    };
+}
+
+/// 固定長バッファへの書き込み（溢れた分は捨てる）
+struct LineWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl LineWriter<'_> {
+    /// 改行を付けて1回の`write`で出力する
+    fn flush_line(&mut self, fd: i32) {
+        // 改行の分は常に確保しておく
+        self.buf[self.len] = b'\n';
+        write_all(fd, &self.buf[..self.len + 1]);
+    }
+}
+
+impl Write for LineWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = self.buf.len() - 1 - self.len;
+        let n = s.len().min(room);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+fn write_all(fd: i32, mut data: &[u8]) {
+    while !data.is_empty() {
+        let ret = unsafe {
+            raw_syscall_bypass(
+                Sysno::write.nr(),
+                fd as u64,
+                data.as_ptr() as u64,
+                data.len() as u64,
+                0,
+                0,
+                0,
+            )
+        };
+        if ret == -(libc::EINTR as i64) {
+            continue;
+        }
+        if ret <= 0 {
+            return;
+        }
+        data = &data[ret as usize..];
+    }
+}
+
+fn format_into(buf: &mut [u8], fd: i32, args: fmt::Arguments<'_>) {
+    let mut writer = LineWriter { buf, len: 0 };
+    let _ = writer.write_fmt(args);
+    writer.flush_line(fd);
+}
+
+/// 整形済みの1行を出力する（`hook_log!`から呼ばれる）
+pub fn write_fmt(args: fmt::Arguments<'_>) {
+    write_fmt_to(log_fd(), args);
+}
+
+/// 整形済みの1行を`fd`に出力する（負の値なら何もしない）
+///
+/// `set_log_fd`の設定とは別に、フックごとのトレースファイルなどへ書くときに
+/// 使います。同じスレッドで出力中にシグナルハンドラから再入した場合や、
+/// スレッドの終了処理中はスタック上のバッファを使います。
+pub fn write_fmt_to(fd: i32, args: fmt::Arguments<'_>) {
+    if fd < 0 {
+        return;
+    }
+
+    let used_tls = LINE_BUF
+        .try_with(|line| {
+            if line.busy.replace(true) {
+                return false;
+            }
+            format_into(unsafe { &mut *line.buf.get() }, fd, args);
+            line.busy.set(false);
+            true
+        })
+        .unwrap_or(false);
+
+    if !used_tls {
+        let mut buf = [0u8; LOG_LINE_MAX];
+        format_into(&mut buf, fd, args);
+    }
+}
+
+/// 文字列をそのまま1行として出力する
+pub fn write_line(s: &str) {
+    write_fmt(format_args!("{}", s));
+}
+
+/// フック内で安全に使える`eprintln!`の代替
+///
+/// ヒープ確保をせず、`LOG_LINE_MAX`バイトを超える行は切り詰めます。
+#[macro_export]
+macro_rules! hook_log {
+    ($($arg:tt)*) => {
+        $crate::log::write_fmt(format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_writer_truncates() {
+        let mut buf = [0u8; 8];
+        let mut writer = LineWriter {
+            buf: &mut buf,
+            len: 0,
+        };
+        let name = "abc";
+        write!(writer, "{}-{}", name, 12345).unwrap();
+        assert_eq!(&writer.buf[..writer.len], b"abc-123");
+    }
+
+    #[test]
+    fn test_write_fmt_to() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        write_fmt_to(fds[1], format_args!("[test] nr={} name={}", 39, "getpid"));
+        write_fmt_to(-1, format_args!("dropped"));
+        let long = "x".repeat(LOG_LINE_MAX * 2);
+        write_fmt_to(fds[1], format_args!("{}", long));
+        unsafe { libc::close(fds[1]) };
+
+        let mut out = [0u8; LOG_LINE_MAX * 2];
+        let mut len = 0;
+        loop {
+            let n = unsafe {
+                libc::read(
+                    fds[0],
+                    out[len..].as_mut_ptr() as *mut libc::c_void,
+                    out.len() - len,
+                )
+            };
+            if n <= 0 {
+                break;
+            }
+            len += n as usize;
+        }
+        unsafe { libc::close(fds[0]) };
+
+        // 長い行は改行を残して切り詰められる
+        let expected = b"[test] nr=39 name=getpid\n";
+        let (first, rest) = out[..len].split_at(expected.len());
+        assert_eq!(first, expected);
+        assert_eq!(rest.len(), LOG_LINE_MAX);
+        let (line, newline) = rest.split_at(LOG_LINE_MAX - 1);
+        assert_eq!(line, &long.as_bytes()[..LOG_LINE_MAX - 1]);
+        assert_eq!(newline, b"\n");
+    }
+}
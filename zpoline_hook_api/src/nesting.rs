@@ -0,0 +1,264 @@
+//! フックのネスト制御とスレッド単位の有効/無効
+//!
+//! `hook_entry`はスレッドごとにフックのネスト深さを数えます。フック内から
+//! 発行されたシステムコールは、ネストポリシーで許された深さまでは再びフックに
+//! ディスパッチされ、それを超えると直接実行されます。
+//!
+//! ネストポリシーが効くのは`__hook_init`で設定したフック関数（`HookFn`）だけです。
+//! `register_syscall_hooks`/`register_enter_exit_hooks`で登録したフック実装は
+//! `&mut self`で呼ばれるため同じ実装には再入できず、そのディスパッチ中の
+//! システムコールはポリシーにかかわらず直接実行されます。
+//!
+//! フックライブラリは`dlmopen`で別ネームスペースにロードされるため、TLSも
+//! ネームスペースごとに独立しています。ローダーはABIハンドシェイク（`abi`）で
+//! 自身の状態をライブラリに渡し、ライブラリ側の`without_hooks`などの呼び出しは
+//! `hook_entry`を実行するローダー側の状態に反映されます。
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+
+/// スレッドごとのフック状態
+#[repr(C)]
+pub struct ThreadHookState {
+    /// 実行中のフックのネスト深さ
+    depth: Cell<u32>,
+    /// `without_hooks`のネスト数
+    suppressed: Cell<u32>,
+    /// `disable_hooks_for_current_thread`で無効化されているか
+    disabled: Cell<bool>,
+}
+
+thread_local! {
+    static THREAD_STATE: ThreadHookState = const {
+        ThreadHookState {
+            depth: Cell::new(0),
+            suppressed: Cell::new(0),
+            disabled: Cell::new(false),
+        }
+    };
+}
+
+/// フック内から発行されたシステムコールの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NestingPolicy {
+    /// フック内のシステムコールは常に直接実行する
+    #[default]
+    Disabled,
+    /// 指定した深さまでフックをネストする（`MaxDepth(1)`は`Disabled`と同じ）
+    ///
+    /// traitベースのフック実装には再入しないため、`HookFn`のフックでのみ意味を持つ
+    MaxDepth(u32),
+}
+
+impl NestingPolicy {
+    fn max_depth(self) -> u32 {
+        match self {
+            NestingPolicy::Disabled => 1,
+            NestingPolicy::MaxDepth(depth) => depth.max(1),
+        }
+    }
+}
+
+/// 許可するネスト深さ
+static MAX_DEPTH: AtomicU32 = AtomicU32::new(1);
+
+/// ローダー側のスレッド状態を返す関数（`dlmopen`されたライブラリでのみ設定される）
+static HOST_THREAD_STATE: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+static HOST_MAX_DEPTH: AtomicPtr<AtomicU32> = AtomicPtr::new(std::ptr::null_mut());
+
+pub(crate) type ThreadStateFn = extern "C" fn() -> *const ThreadHookState;
+
+fn max_depth() -> &'static AtomicU32 {
+    let host = HOST_MAX_DEPTH.load(Ordering::Acquire);
+    if host.is_null() {
+        &MAX_DEPTH
+    } else {
+        unsafe { &*host }
+    }
+}
+
+fn with_state<R>(f: impl FnOnce(&ThreadHookState) -> R) -> R {
+    let host = HOST_THREAD_STATE.load(Ordering::Acquire);
+    if host.is_null() {
+        THREAD_STATE.with(f)
+    } else {
+        let state_fn: ThreadStateFn = unsafe { std::mem::transmute(host) };
+        f(unsafe { &*state_fn() })
+    }
+}
+
+/// ネストポリシーを設定
+pub fn set_nesting_policy(policy: NestingPolicy) {
+    max_depth().store(policy.max_depth(), Ordering::Relaxed);
+}
+
+/// 現在のネストポリシーを取得
+pub fn nesting_policy() -> NestingPolicy {
+    match max_depth().load(Ordering::Relaxed) {
+        1 => NestingPolicy::Disabled,
+        depth => NestingPolicy::MaxDepth(depth),
+    }
+}
+
+/// 現在のスレッドで実行中のフックのネスト深さ
+pub fn hook_depth() -> u32 {
+    with_state(|state| state.depth.get())
+}
+
+/// 現在のスレッドのフックを無効化する
+///
+/// 無効化中のシステムコールはフックを経由せずに実行されます。
+pub fn disable_hooks_for_current_thread() {
+    with_state(|state| state.disabled.set(true));
+}
+
+/// `disable_hooks_for_current_thread`で無効化したフックを再度有効にする
+pub fn enable_hooks_for_current_thread() {
+    with_state(|state| state.disabled.set(false));
+}
+
+/// 現在のスレッドでフックが有効か
+pub fn hooks_enabled_for_current_thread() -> bool {
+    with_state(|state| !state.disabled.get() && state.suppressed.get() == 0)
+}
+
+/// フックを無効にしてクロージャを実行する
+///
+/// ネスト可能で、クロージャがパニックしても元の状態に戻ります。
+///
+/// ```no_run
+/// use zpoline_hook_api::without_hooks;
+///
+/// // ログ出力自体のwriteはフックされない
+/// without_hooks(|| eprintln!("[my_hooks] flushing"));
+/// ```
+pub fn without_hooks<R>(f: impl FnOnce() -> R) -> R {
+    struct Restore;
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            with_state(|state| state.suppressed.set(state.suppressed.get() - 1));
+        }
+    }
+
+    with_state(|state| state.suppressed.set(state.suppressed.get() + 1));
+    let _restore = Restore;
+    f()
+}
+
+/// フック実行中であることを示すガード（dropでネスト深さを戻す）
+pub(crate) struct HookDepthGuard(());
+
+impl Drop for HookDepthGuard {
+    fn drop(&mut self) {
+        THREAD_STATE.with(|state| state.depth.set(state.depth.get() - 1));
+    }
+}
+
+/// フックに入れるか判定し、入れる場合はネスト深さを1つ増やす
+///
+/// `hook_entry`から呼ばれるため、常に自ネームスペースの状態を使う。
+pub(crate) fn enter_hook() -> Option<HookDepthGuard> {
+    THREAD_STATE.with(|state| {
+        if state.disabled.get() || state.suppressed.get() > 0 {
+            return None;
+        }
+        let depth = state.depth.get();
+        if depth >= MAX_DEPTH.load(Ordering::Relaxed) {
+            return None;
+        }
+        state.depth.set(depth + 1);
+        Some(HookDepthGuard(()))
+    })
+}
+
+/// 現在のスレッドの状態へのポインタ（zpoline_loader用）
+#[doc(hidden)]
+pub extern "C" fn __thread_hook_state() -> *const ThreadHookState {
+    THREAD_STATE.with(|state| state as *const ThreadHookState)
+}
+
+/// ネスト深さの上限へのポインタ（zpoline_loader用）
+#[doc(hidden)]
+pub fn __max_depth() -> *const AtomicU32 {
+    &MAX_DEPTH
+}
+
+/// ローダーのスレッド状態とネストポリシーを使うように切り替える
+///
+/// ハンドシェイク（`zpoline_hook_init`より前）で呼ばれます。それまでに
+/// ライブラリ側で設定されたネストポリシーはローダー側に引き継がれます。
+///
+/// # Safety
+///
+/// `thread_state`と`max_depth`はプロセスの終了まで有効である必要がある
+pub(crate) unsafe fn attach_guard_host(thread_state: ThreadStateFn, max_depth: *const AtomicU32) {
+    if max_depth.is_null() {
+        return;
+    }
+    let local = MAX_DEPTH.load(Ordering::Relaxed);
+    if local != 1 {
+        (*max_depth).store(local, Ordering::Relaxed);
+    }
+    HOST_MAX_DEPTH.store(max_depth as *mut AtomicU32, Ordering::Release);
+    HOST_THREAD_STATE.store(thread_state as *mut (), Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting_policy() {
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+        assert_eq!(NestingPolicy::MaxDepth(0).max_depth(), 1);
+
+        let outer = enter_hook().unwrap();
+        assert_eq!(hook_depth(), 1);
+        assert!(enter_hook().is_none());
+
+        set_nesting_policy(NestingPolicy::MaxDepth(2));
+        assert_eq!(nesting_policy(), NestingPolicy::MaxDepth(2));
+        let inner = enter_hook().unwrap();
+        assert_eq!(hook_depth(), 2);
+        assert!(enter_hook().is_none());
+
+        drop(inner);
+        drop(outer);
+        assert_eq!(hook_depth(), 0);
+        set_nesting_policy(NestingPolicy::Disabled);
+        assert_eq!(nesting_policy(), NestingPolicy::Disabled);
+    }
+
+    #[test]
+    fn test_without_hooks() {
+        let value = without_hooks(|| {
+            assert!(!hooks_enabled_for_current_thread());
+            assert!(enter_hook().is_none());
+            without_hooks(|| assert!(enter_hook().is_none()));
+            assert!(enter_hook().is_none());
+            42
+        });
+        assert_eq!(value, 42);
+        assert!(hooks_enabled_for_current_thread());
+
+        // パニックしても元に戻る
+        let _ = std::panic::catch_unwind(|| without_hooks(|| panic!("in without_hooks")));
+        assert!(hooks_enabled_for_current_thread());
+        assert!(enter_hook().is_some());
+    }
+
+    #[test]
+    fn test_disable_for_current_thread() {
+        disable_hooks_for_current_thread();
+        assert!(enter_hook().is_none());
+
+        // 他のスレッドには影響しない
+        std::thread::spawn(|| assert!(enter_hook().is_some()))
+            .join()
+            .unwrap();
+
+        enable_hooks_for_current_thread();
+        assert!(enter_hook().is_some());
+    }
+}
@@ -0,0 +1,264 @@
+//! イベント多重化（poll / select / epoll）の引数
+//!
+//! `pollfd`配列・`fd_set`・`epoll_event`バッファをフォールトセーフに
+//! 読み書きするビューです。`syscall_hooks`のフックはこれらを受け取ります。
+
+use crate::user_mem::{try_read_bytes, try_read_val, try_write_bytes, UserMemError};
+use libc::{c_int, c_void, epoll_event, fd_set, nfds_t, pollfd, timespec, timeval};
+use std::time::Duration;
+
+/// 1回の`poll`で扱うfdの最大数（カーネルの`nr_open`の上限）
+pub const POLL_MAX: usize = 1 << 20;
+
+/// `fd_set`で扱えるfdの上限
+pub const FD_SETSIZE: usize = libc::FD_SETSIZE;
+
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// `poll`/`ppoll`に渡された`pollfd`配列
+#[derive(Debug, Clone, Copy)]
+pub struct PollFds {
+    ptr: *mut pollfd,
+    nfds: nfds_t,
+}
+
+impl PollFds {
+    pub fn new(ptr: *mut pollfd, nfds: nfds_t) -> Self {
+        Self { ptr, nfds }
+    }
+
+    pub fn as_ptr(&self) -> *mut pollfd {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.nfds as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nfds == 0
+    }
+
+    /// 配列をコピーする（`POLL_MAX`を超える場合はカーネルと同じ`EINVAL`）
+    pub fn to_vec(&self) -> Result<Vec<pollfd>, UserMemError> {
+        if self.len() > POLL_MAX {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        let mut fds = vec![
+            pollfd {
+                fd: -1,
+                events: 0,
+                revents: 0,
+            };
+            self.len()
+        ];
+        if !fds.is_empty() {
+            try_read_bytes(self.ptr as *const c_void, as_bytes_mut(&mut fds))?;
+        }
+        Ok(fds)
+    }
+
+    /// 配列を書き戻す（`revents`を設定する場合など）
+    pub fn write(&self, fds: &[pollfd]) -> Result<(), UserMemError> {
+        let fds = &fds[..fds.len().min(self.len())];
+        if fds.is_empty() {
+            return Ok(());
+        }
+        try_write_bytes(self.ptr as *mut c_void, as_bytes(fds))
+    }
+}
+
+/// `select`/`pselect6`に渡された`fd_set`（先頭`nfds`ビットだけを読み書きする）
+///
+/// カーネルと同じく`nfds`ビット分のワードしか触れないため、
+/// アプリケーションが`fd_set`より小さいビット列を渡しても安全です。
+#[derive(Debug, Clone, Copy)]
+pub struct FdSet {
+    ptr: *mut fd_set,
+    nfds: c_int,
+}
+
+impl FdSet {
+    pub fn new(ptr: *mut fd_set, nfds: c_int) -> Self {
+        Self { ptr, nfds }
+    }
+
+    pub fn as_ptr(&self) -> *mut fd_set {
+        self.ptr
+    }
+
+    /// 集合が渡されていない
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    fn words(&self) -> usize {
+        (self.nfds.max(0) as usize).div_ceil(BITS_PER_WORD)
+    }
+
+    /// 含まれるfd（NULLの場合は空）
+    pub fn fds(&self) -> Result<Vec<c_int>, UserMemError> {
+        if self.is_null() {
+            return Ok(Vec::new());
+        }
+        let mut words = vec![0u64; self.words()];
+        try_read_bytes(self.ptr as *const c_void, as_bytes_mut(&mut words))?;
+        Ok((0..self.nfds)
+            .filter(|&fd| {
+                words[fd as usize / BITS_PER_WORD] & (1 << (fd as usize % BITS_PER_WORD)) != 0
+            })
+            .collect())
+    }
+
+    /// 集合を`fds`で置き換える（`nfds`以上のfdは無視する）
+    pub fn set_fds(&self, fds: &[c_int]) -> Result<(), UserMemError> {
+        if self.is_null() {
+            return Ok(());
+        }
+        let mut words = vec![0u64; self.words()];
+        for &fd in fds.iter().filter(|&&fd| fd >= 0 && fd < self.nfds) {
+            words[fd as usize / BITS_PER_WORD] |= 1 << (fd as usize % BITS_PER_WORD);
+        }
+        try_write_bytes(self.ptr as *mut c_void, as_bytes(&words))
+    }
+}
+
+/// `epoll_wait`/`epoll_pwait`の出力バッファ
+#[derive(Debug, Clone, Copy)]
+pub struct EpollEvents {
+    ptr: *mut epoll_event,
+    maxevents: c_int,
+}
+
+impl EpollEvents {
+    pub fn new(ptr: *mut epoll_event, maxevents: c_int) -> Self {
+        Self { ptr, maxevents }
+    }
+
+    pub fn as_ptr(&self) -> *mut epoll_event {
+        self.ptr
+    }
+
+    /// 受け取れるイベントの数（`maxevents`）
+    pub fn capacity(&self) -> usize {
+        self.maxevents.max(0) as usize
+    }
+
+    /// 先頭`n`個のイベントを読み取る（デフォルト実装の戻り値を渡す）
+    pub fn read(&self, n: usize) -> Result<Vec<epoll_event>, UserMemError> {
+        let mut events = vec![epoll_event { events: 0, u64: 0 }; n.min(self.capacity())];
+        if !events.is_empty() {
+            try_read_bytes(self.ptr as *const c_void, as_bytes_mut(&mut events))?;
+        }
+        Ok(events)
+    }
+
+    /// イベントを書き込み、書き込んだ数を返す（`capacity`を超える分は捨てる）
+    pub fn write(&self, events: &[epoll_event]) -> Result<usize, UserMemError> {
+        let events = &events[..events.len().min(self.capacity())];
+        if !events.is_empty() {
+            try_write_bytes(self.ptr as *mut c_void, as_bytes(events))?;
+        }
+        Ok(events.len())
+    }
+
+    /// 先頭`n`個を除いた残りのバッファ
+    pub(crate) fn skip(&self, n: usize) -> Self {
+        let n = n.min(self.capacity());
+        Self::new(self.ptr.wrapping_add(n), (self.capacity() - n) as c_int)
+    }
+}
+
+/// `struct timespec`のタイムアウトを読み取る（NULLは無期限で`None`）
+///
+/// 負の値はカーネルと同じ`EINVAL`です。
+pub fn read_timespec(ptr: *const timespec) -> Result<Option<Duration>, UserMemError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    let ts = try_read_val(ptr)?;
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(UserMemError::Os(libc::EINVAL));
+    }
+    Ok(Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)))
+}
+
+/// `struct timeval`のタイムアウトを読み取る（NULLは無期限で`None`）
+pub fn read_timeval(ptr: *const timeval) -> Result<Option<Duration>, UserMemError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    let tv = try_read_val(ptr)?;
+    if tv.tv_sec < 0 || tv.tv_usec < 0 {
+        return Err(UserMemError::Os(libc::EINVAL));
+    }
+    Ok(Some(
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64),
+    ))
+}
+
+/// ミリ秒のタイムアウト（負の値は無期限で`None`）
+pub fn millis_timeout(timeout: c_int) -> Option<Duration> {
+    (timeout >= 0).then(|| Duration::from_millis(timeout as u64))
+}
+
+fn as_bytes<T: Copy>(values: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+fn as_bytes_mut<T: Copy>(values: &mut [T]) -> &mut [u8] {
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            values.as_mut_ptr() as *mut u8,
+            std::mem::size_of_val(values),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fd_set() {
+        let mut set: fd_set = unsafe { std::mem::zeroed() };
+        let fds = FdSet::new(&mut set, 70);
+        fds.set_fds(&[0, 3, 65, 69, 70, 1000]).unwrap();
+        assert_eq!(fds.fds().unwrap(), vec![0, 3, 65, 69]);
+        unsafe {
+            assert!(libc::FD_ISSET(65, &set));
+            assert!(!libc::FD_ISSET(70, &set));
+        }
+
+        // nfdsビット分のワードだけを読み書きする
+        let mut small = [u64::MAX; 1];
+        let fds = FdSet::new(small.as_mut_ptr() as *mut fd_set, 64);
+        assert_eq!(fds.fds().unwrap().len(), 64);
+        fds.set_fds(&[1]).unwrap();
+        assert_eq!(small, [2]);
+
+        assert_eq!(FdSet::new(std::ptr::null_mut(), 8).fds(), Ok(Vec::new()));
+        assert_eq!(
+            FdSet::new(std::ptr::dangling_mut(), 8).fds(),
+            Err(UserMemError::Fault)
+        );
+    }
+
+    #[test]
+    fn test_epoll_events() {
+        let mut buf = [epoll_event { events: 0, u64: 0 }; 2];
+        let events = EpollEvents::new(buf.as_mut_ptr(), 2);
+        let ev = |data| epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: data,
+        };
+        assert_eq!(events.write(&[ev(1), ev(2), ev(3)]), Ok(2));
+        assert_eq!(events.skip(1).write(&[ev(4)]), Ok(1));
+        let read = events.read(5).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!({ read[0].u64 }, 1);
+        assert_eq!({ read[1].u64 }, 4);
+    }
+}
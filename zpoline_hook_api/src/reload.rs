@@ -0,0 +1,210 @@
+//! フックライブラリのホットリロード
+//!
+//! `hook_entry`は実行中のフック呼び出しを世代ごとに数えます。ローダーは新しい
+//! ライブラリのフック関数に切り替えた後、`synchronize`で切り替え前に始まった
+//! 呼び出しの完了を待ってから古いライブラリを退役させます。
+//!
+//! リロードの要求（`request_reload`やシグナル）はフラグを立ててローダーの
+//! リロード用スレッドを起こすだけで、システムコールの経路ではリロードしません。
+//! フックライブラリから呼んだ場合は、ABIハンドシェイクで受け取ったローダーの
+//! 関数に転送されます。
+
+use crate::raw_syscall_bypass;
+use crate::sysno::Sysno;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// 世代ごとの実行中のフック呼び出し数
+static ACTIVE: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// 現在の世代（下位1ビットで`ACTIVE`を選ぶ）
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+/// `synchronize`の排他
+static SYNCHRONIZING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// このスレッドが`ACTIVE`に加えている数（fork後の復元と自己待ちの検出用）
+    static HELD: Cell<[usize; 2]> = const { Cell::new([0, 0]) };
+}
+
+/// 実行中のフック呼び出しを表すガード（dropで数を戻す）
+pub(crate) struct InFlightGuard(usize);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        ACTIVE[self.0].fetch_sub(1, Ordering::Release);
+        HELD.with(|held| {
+            let mut counts = held.get();
+            counts[self.0] -= 1;
+            held.set(counts);
+        });
+    }
+}
+
+/// フック関数を呼ぶ前に実行中として数える
+///
+/// フック関数のポインタはこの後で読み出す必要がある。
+pub(crate) fn enter() -> InFlightGuard {
+    let slot = EPOCH.load(Ordering::SeqCst) & 1;
+    ACTIVE[slot].fetch_add(1, Ordering::SeqCst);
+    HELD.with(|held| {
+        let mut counts = held.get();
+        counts[slot] += 1;
+        held.set(counts);
+    });
+    InFlightGuard(slot)
+}
+
+/// この呼び出しより前に始まったフック呼び出しがすべて終わるまで待つ
+///
+/// `timeout`までに終わらなかった場合（ブロックするシステムコールのフック中など）や、
+/// 呼び出し元のスレッド自身がフック実行中の場合は`false`を返します。
+/// その場合、古いフック関数のコードはまだ使われている可能性があります。
+pub fn synchronize(timeout: Duration) -> bool {
+    if HELD.with(|held| held.get() != [0, 0]) {
+        return false;
+    }
+
+    let deadline = Instant::now() + timeout;
+    while SYNCHRONIZING.swap(true, Ordering::Acquire) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        pause();
+    }
+
+    // 世代を2回進め、どちらの世代で数えられた呼び出しも終わるのを待つ
+    let mut drained = true;
+    for _ in 0..2 {
+        let slot = EPOCH.fetch_add(1, Ordering::SeqCst) & 1;
+        while ACTIVE[slot].load(Ordering::SeqCst) != 0 {
+            if Instant::now() >= deadline {
+                drained = false;
+                break;
+            }
+            pause();
+        }
+        if !drained {
+            break;
+        }
+    }
+
+    SYNCHRONIZING.store(false, Ordering::Release);
+    drained
+}
+
+/// フックを経由せずに少し待つ
+fn pause() {
+    let ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 100_000,
+    };
+    unsafe {
+        raw_syscall_bypass(
+            Sysno::nanosleep.nr(),
+            &ts as *const libc::timespec as u64,
+            0,
+            0,
+            0,
+            0,
+            0,
+        )
+    };
+}
+
+/// fork後の子プロセスで、存在しないスレッドの分の数を捨てる
+pub(crate) fn reset_after_fork() {
+    let counts = HELD.with(|held| held.get());
+    for (active, count) in ACTIVE.iter().zip(counts) {
+        active.store(count, Ordering::Relaxed);
+    }
+    SYNCHRONIZING.store(false, Ordering::Relaxed);
+}
+
+/// リロードが要求されているか（1なら処理待ち。futexで待つ）
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+/// ローダー側の`request_reload`（`dlmopen`されたライブラリでのみ設定される）
+static HOST_REQUEST_RELOAD: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// フックライブラリのリロードを要求する
+///
+/// 非同期シグナル安全で、フック内からも呼べます。リロードは`wait_reload_request`で
+/// 待っているローダーのスレッドが実行します。
+pub extern "C" fn request_reload() {
+    let host = HOST_REQUEST_RELOAD.load(Ordering::Acquire);
+    if host.is_null() {
+        PENDING.store(1, Ordering::Release);
+        futex(libc::FUTEX_WAKE, 1);
+    } else {
+        let host: extern "C" fn() = unsafe { std::mem::transmute(host) };
+        host();
+    }
+}
+
+/// リロードの要求が処理待ちか
+pub fn reload_pending() -> bool {
+    PENDING.load(Ordering::Relaxed) != 0
+}
+
+/// リロードが要求されるまで待つ（zpoline_loaderのリロード用スレッドが呼ぶ）
+///
+/// futexを`raw_syscall_bypass`で待つのでフックを経由しません。
+#[doc(hidden)]
+pub fn wait_reload_request() {
+    while PENDING.swap(0, Ordering::Acquire) == 0 {
+        futex(libc::FUTEX_WAIT, 0);
+    }
+}
+
+fn futex(op: i32, val: u32) {
+    unsafe {
+        raw_syscall_bypass(
+            Sysno::futex.nr(),
+            PENDING.as_ptr() as u64,
+            (op | libc::FUTEX_PRIVATE_FLAG) as u64,
+            val as u64,
+            0,
+            0,
+            0,
+        );
+    }
+}
+
+/// ローダー側の`request_reload`を使うように切り替える（ABIハンドシェイクで呼ばれる）
+pub(crate) fn attach_host(request_reload: extern "C" fn()) {
+    HOST_REQUEST_RELOAD.store(request_reload as *mut (), Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synchronize_inside_hook() {
+        // 自分自身の呼び出しは待てない
+        let guard = enter();
+        assert!(!synchronize(Duration::from_secs(1)));
+        drop(guard);
+        assert!(synchronize(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_synchronize_waits_for_other_thread() {
+        let (entered_tx, entered_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let worker = std::thread::spawn(move || {
+            let _guard = enter();
+            entered_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        });
+        entered_rx.recv().unwrap();
+
+        assert!(!synchronize(Duration::from_millis(20)));
+        release_tx.send(()).unwrap();
+        worker.join().unwrap();
+        assert!(synchronize(Duration::from_secs(1)));
+    }
+}
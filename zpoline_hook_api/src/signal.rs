@@ -0,0 +1,740 @@
+//! シグナル管理とハンドラの仮想化
+//!
+//! フックライブラリ自身がタイマーやSyscall User Dispatchのためにシグナルを使うと、
+//! アプリケーションが同じシグナルに設定したハンドラと衝突します。`VirtualSignals`は
+//! 次の2つの方法でシグナルを仮想化します。
+//!
+//! - `reserve`: シグナルをフックライブラリ専用にする。アプリケーションのハンドラは
+//!   記録されるだけで呼ばれず、アプリケーションはこのシグナルをブロックできない
+//! - `wrap`: フックライブラリのハンドラを先に呼び、処理済みでなければ
+//!   アプリケーションのハンドラ（`SIG_DFL`/`SIG_IGN`を含む）に渡す
+//!
+//! どちらの場合も`rt_sigaction`はアプリケーションが設定した内容を返します。
+//! 仮想化は`SyscallHooks`のデフォルト実装に組み込まれているため、
+//! `reserve`/`wrap`は`zpoline_hook_init`の中で呼んでください。
+//! 関心集合には`SIGNAL_HOOK_SYSCALLS`が自動的に追加されます。
+//! フック実装の関心集合に含まれないものは、ディスパッチャのロックを取らずに
+//! デフォルト実装で処理されます（`rt_sigsuspend`で待つ間も他のスレッドを止めない）。
+//!
+//! ```no_run
+//! use zpoline_hook_api::signal::virtual_signals;
+//!
+//! fn on_timer(_signum: i32, _info: *mut libc::siginfo_t, _ctx: *mut libc::c_void) -> bool {
+//!     true
+//! }
+//!
+//! virtual_signals().reserve(libc::SIGRTMIN() + 2, on_timer).unwrap();
+//! ```
+
+use crate::fork::ForkSafeMutex;
+use crate::interests::extend_hook_interests;
+use crate::syscall_hooks::{
+    default_rt_sigaction, default_rt_sigprocmask, default_rt_sigsuspend, default_signalfd4,
+};
+use crate::user_mem::UserPtr;
+use crate::{raw_syscall_bypass, SyscallRegs, Sysno, SysnoSet};
+use libc::{c_int, c_void, siginfo_t, size_t};
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+/// カーネルが扱うシグナル番号の上限
+pub const NSIG: c_int = 64;
+
+/// カーネルの`sigset_t`のサイズ（`sigsetsize`引数に渡す値）
+pub const SIGSET_SIZE: size_t = 8;
+
+const SIGNAL_SYSNOS: [Sysno; 4] = [
+    Sysno::rt_sigaction,
+    Sysno::rt_sigprocmask,
+    Sysno::rt_sigsuspend,
+    Sysno::signalfd4,
+];
+
+/// 仮想化に必要なシステムコール
+pub const SIGNAL_HOOK_SYSCALLS: SysnoSet = SysnoSet::new(&SIGNAL_SYSNOS);
+
+/// フック実装の関心集合に含まれず、ロックを取らずに処理するもの（`SIGNAL_SYSNOS`の添字のビット）
+static UNLOCKED: AtomicU8 = AtomicU8::new(0);
+
+/// カーネルの`struct sigaction`（x86_64）
+///
+/// glibcの`struct sigaction`とはフィールドの順序と`sa_mask`のサイズが異なります。
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KernelSigaction {
+    pub sa_handler: usize,
+    pub sa_flags: u64,
+    pub sa_restorer: usize,
+    pub sa_mask: u64,
+}
+
+/// `rt_sigaction`の`act`/`oldact`
+pub type SigactionPtr = UserPtr<KernelSigaction>;
+
+/// `rt_sigprocmask`などのシグナルマスク（カーネルの`sigset_t`は64ビット）
+pub type SigsetPtr = UserPtr<u64>;
+
+/// `sigaltstack`の`stack_t`
+pub type StackPtr = UserPtr<libc::stack_t>;
+
+/// フックライブラリのシグナルハンドラ
+///
+/// シグナルハンドラの中で呼ばれるため、async-signal-safeな処理だけを行うこと。
+/// `true`を返すと処理済みとして、アプリケーションのハンドラを呼びません。
+pub type SignalHandler = fn(signum: c_int, info: *mut siginfo_t, ucontext: *mut c_void) -> bool;
+
+/// シグナル番号のマスクビット
+pub fn sigbit(signum: c_int) -> u64 {
+    1 << (signum - 1)
+}
+
+/// x86_64では必須の`SA_RESTORER`（`libc`クレートには定義がない）
+pub const SA_RESTORER: u64 = 0x0400_0000;
+
+const MODE_NONE: u8 = 0;
+const MODE_WRAP: u8 = 1;
+const MODE_RESERVE: u8 = 2;
+
+/// アプリケーションから引き継ぐ`sa_flags`
+const APP_FLAGS: u64 = (libc::SA_RESTART
+    | libc::SA_ONSTACK
+    | libc::SA_NOCLDSTOP
+    | libc::SA_NOCLDWAIT
+    | libc::SA_NODEFER) as u64;
+
+/// デフォルト動作が無視のシグナル
+const IGNORED_BY_DEFAULT: u64 = (1 << (libc::SIGCHLD - 1))
+    | (1 << (libc::SIGURG - 1))
+    | (1 << (libc::SIGWINCH - 1))
+    | (1 << (libc::SIGCONT - 1));
+
+/// 1つのシグナルの状態
+///
+/// シグナルハンドラから読むため、ロックを使わずにアトミック変数で保持する。
+/// アプリケーションのハンドラはseqlockで読み書きする。
+struct Slot {
+    mode: AtomicU8,
+    handler: AtomicUsize,
+    seq: AtomicU32,
+    app: [AtomicU64; 4],
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            mode: AtomicU8::new(MODE_NONE),
+            handler: AtomicUsize::new(0),
+            seq: AtomicU32::new(0),
+            app: [const { AtomicU64::new(0) }; 4],
+        }
+    }
+
+    fn app_action(&self) -> KernelSigaction {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq & 1 == 0 {
+                let [handler, flags, restorer, mask] =
+                    [0, 1, 2, 3].map(|i| self.app[i].load(Ordering::Relaxed));
+                fence(Ordering::Acquire);
+                if self.seq.load(Ordering::Relaxed) == seq {
+                    return KernelSigaction {
+                        sa_handler: handler as usize,
+                        sa_flags: flags,
+                        sa_restorer: restorer as usize,
+                        sa_mask: mask,
+                    };
+                }
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// 書き込み中のシグナルハンドラが待ち続けないよう、全シグナルをブロックして呼ぶこと
+    fn set_app_action(&self, act: &KernelSigaction) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        let values = [
+            act.sa_handler as u64,
+            act.sa_flags,
+            act.sa_restorer as u64,
+            act.sa_mask,
+        ];
+        for (word, value) in self.app.iter().zip(values) {
+            word.store(value, Ordering::Relaxed);
+        }
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// 仮想化したシグナルの表
+pub struct VirtualSignals {
+    slots: [Slot; NSIG as usize + 1],
+    /// 書き込みを直列化するロック（シグナルハンドラからは取らない）
+    lock: ForkSafeMutex<()>,
+}
+
+impl VirtualSignals {
+    const fn new() -> Self {
+        Self {
+            slots: [const { Slot::new() }; NSIG as usize + 1],
+            lock: ForkSafeMutex::new(()),
+        }
+    }
+
+    fn slot(&self, signum: c_int) -> Option<&Slot> {
+        (1..=NSIG)
+            .contains(&signum)
+            .then(|| &self.slots[signum as usize])
+    }
+
+    fn mode(&self, signum: c_int) -> u8 {
+        self.slot(signum)
+            .map_or(MODE_NONE, |slot| slot.mode.load(Ordering::Acquire))
+    }
+
+    /// シグナルをフックライブラリ専用にする
+    ///
+    /// アプリケーションのハンドラは呼ばれず、`SIGKILL`/`SIGSTOP`と同様に
+    /// アプリケーションからはブロックできなくなります。
+    pub fn reserve(&self, signum: c_int, handler: SignalHandler) -> Result<(), c_int> {
+        self.virtualize(signum, handler, MODE_RESERVE)
+    }
+
+    /// アプリケーションのハンドラをラップする
+    ///
+    /// `handler`が`false`を返した場合はアプリケーションのハンドラに渡します。
+    pub fn wrap(&self, signum: c_int, handler: SignalHandler) -> Result<(), c_int> {
+        self.virtualize(signum, handler, MODE_WRAP)
+    }
+
+    fn virtualize(&self, signum: c_int, handler: SignalHandler, mode: u8) -> Result<(), c_int> {
+        if signum == libc::SIGKILL || signum == libc::SIGSTOP {
+            return Err(libc::EINVAL);
+        }
+        let slot = self.slot(signum).ok_or(libc::EINVAL)?;
+        let _guard = self.lock.lock();
+        if slot.mode.load(Ordering::Acquire) != MODE_NONE {
+            return Err(libc::EBUSY);
+        }
+
+        // 現在の設定をアプリケーションから見た状態として引き継ぐ
+        let current = kernel_sigaction(signum, None)?;
+        slot.handler.store(handler as usize, Ordering::Release);
+        with_signals_blocked(|| slot.set_app_action(&current));
+        slot.mode.store(mode, Ordering::Release);
+        if let Err(err) = kernel_sigaction(signum, Some(&virtual_action(&current, mode))) {
+            slot.mode.store(MODE_NONE, Ordering::Release);
+            return Err(err);
+        }
+        extend_hook_interests(&SIGNAL_HOOK_SYSCALLS);
+        Ok(())
+    }
+
+    /// 仮想化をやめ、アプリケーションのハンドラをカーネルに設定する
+    pub fn release(&self, signum: c_int) -> Result<(), c_int> {
+        let slot = self.slot(signum).ok_or(libc::EINVAL)?;
+        let _guard = self.lock.lock();
+        if slot.mode.load(Ordering::Acquire) == MODE_NONE {
+            return Err(libc::EINVAL);
+        }
+        kernel_sigaction(signum, Some(&slot.app_action()))?;
+        slot.mode.store(MODE_NONE, Ordering::Release);
+        slot.handler.store(0, Ordering::Release);
+        Ok(())
+    }
+
+    /// 仮想化しているか
+    pub fn is_virtualized(&self, signum: c_int) -> bool {
+        self.mode(signum) != MODE_NONE
+    }
+
+    /// `reserve`したシグナルか
+    pub fn is_reserved(&self, signum: c_int) -> bool {
+        self.mode(signum) == MODE_RESERVE
+    }
+
+    /// 仮想化しているシグナルがあるか
+    pub fn is_active(&self) -> bool {
+        (1..=NSIG).any(|signum| self.is_virtualized(signum))
+    }
+
+    /// `reserve`したシグナルのマスク
+    pub fn reserved_mask(&self) -> u64 {
+        (1..=NSIG)
+            .filter(|&signum| self.is_reserved(signum))
+            .fold(0, |mask, signum| mask | sigbit(signum))
+    }
+
+    /// アプリケーションから見たハンドラ（仮想化していなければ`None`）
+    pub fn app_action(&self, signum: c_int) -> Option<KernelSigaction> {
+        let slot = self.slot(signum)?;
+        (slot.mode.load(Ordering::Acquire) != MODE_NONE).then(|| slot.app_action())
+    }
+
+    /// アプリケーションの`rt_sigaction`を処理する（仮想化していなければ`None`）
+    ///
+    /// 戻り値はシステムコールと同じく失敗時に負のerrnoです。
+    pub(crate) fn sigaction(
+        &self,
+        signum: c_int,
+        act: SigactionPtr,
+        oldact: SigactionPtr,
+        sigsetsize: size_t,
+    ) -> Option<c_int> {
+        if sigsetsize != SIGSET_SIZE || !self.is_virtualized(signum) {
+            return None;
+        }
+        let slot = self.slot(signum)?;
+        let new = match (!act.is_null()).then(|| act.read()).transpose() {
+            Ok(new) => new,
+            Err(_) => return Some(-libc::EFAULT),
+        };
+
+        let _guard = self.lock.lock();
+        let mode = slot.mode.load(Ordering::Acquire);
+        if mode == MODE_NONE {
+            // releaseと競合した
+            return None;
+        }
+        let old = slot.app_action();
+        if let Some(new) = new {
+            with_signals_blocked(|| slot.set_app_action(&new));
+            if let Err(err) = kernel_sigaction(signum, Some(&virtual_action(&new, mode))) {
+                return Some(-err);
+            }
+        }
+        if !oldact.is_null() && oldact.write(old).is_err() {
+            return Some(-libc::EFAULT);
+        }
+        Some(0)
+    }
+
+    /// アプリケーションが渡したマスクから`reserve`したシグナルを除く
+    pub fn filter_mask(&self, mask: u64) -> u64 {
+        mask & !self.reserved_mask()
+    }
+}
+
+/// `dispatch_syscall_hooks`が参照するグローバルな表
+static VIRTUAL_SIGNALS: VirtualSignals = VirtualSignals::new();
+
+/// グローバルな仮想シグナルの表
+pub fn virtual_signals() -> &'static VirtualSignals {
+    &VIRTUAL_SIGNALS
+}
+
+/// 登録されたフック実装の関心集合を記録する
+pub(crate) fn set_dispatcher_interests(interests: &SysnoSet) {
+    let bits = SIGNAL_SYSNOS
+        .iter()
+        .enumerate()
+        .filter(|(_, sysno)| !interests.contains(**sysno))
+        .fold(0, |bits, (i, _)| bits | 1 << i);
+    UNLOCKED.store(bits, Ordering::Release);
+}
+
+/// 仮想化のためだけに届いたシステムコールを処理する（ディスパッチャのロックを取らない）
+///
+/// フック実装が関心を持つもの、または仮想化と関係ないものは`None`です。
+pub(crate) fn dispatch_unlocked(regs: &SyscallRegs) -> Option<i64> {
+    let index = SIGNAL_SYSNOS
+        .iter()
+        .position(|sysno| sysno.nr() == regs.rax)?;
+    if UNLOCKED.load(Ordering::Acquire) & (1 << index) == 0 {
+        return None;
+    }
+    let ret = match SIGNAL_SYSNOS[index] {
+        Sysno::rt_sigaction => default_rt_sigaction(
+            regs.rdi as c_int,
+            SigactionPtr::new(regs.rsi as *mut KernelSigaction),
+            SigactionPtr::new(regs.rdx as *mut KernelSigaction),
+            regs.r10 as size_t,
+        ),
+        Sysno::rt_sigprocmask => default_rt_sigprocmask(
+            regs.rdi as c_int,
+            SigsetPtr::new(regs.rsi as *mut u64),
+            SigsetPtr::new(regs.rdx as *mut u64),
+            regs.r10 as size_t,
+        ),
+        Sysno::rt_sigsuspend => {
+            default_rt_sigsuspend(SigsetPtr::new(regs.rdi as *mut u64), regs.rsi as size_t)
+        }
+        _ => default_signalfd4(
+            regs.rdi as c_int,
+            SigsetPtr::new(regs.rsi as *mut u64),
+            regs.rdx as size_t,
+            regs.r10 as c_int,
+        ),
+    };
+    Some(ret as i64)
+}
+
+/// fork後の子プロセスで表のロックを解放する
+pub(crate) fn reinit_after_fork() {
+    unsafe { VIRTUAL_SIGNALS.lock.force_unlock() };
+}
+
+/// 仮想化したシグナルにカーネルへ設定する内容
+fn virtual_action(app: &KernelSigaction, mode: u8) -> KernelSigaction {
+    let flags = if mode == MODE_RESERVE {
+        (libc::SA_RESTART | libc::SA_ONSTACK) as u64
+    } else {
+        app.sa_flags & APP_FLAGS
+    };
+    KernelSigaction {
+        sa_handler: virtual_handler as *const () as usize,
+        sa_flags: flags | libc::SA_SIGINFO as u64 | SA_RESTORER,
+        sa_restorer: zpoline_signal_restorer as *const () as usize,
+        sa_mask: 0,
+    }
+}
+
+// rt_sigreturnを呼ぶだけのリストア関数（SA_RESTORERで必須）
+std::arch::global_asm!(
+    ".pushsection .text.zpoline_signal_restorer, \"ax\", @progbits",
+    ".globl zpoline_signal_restorer",
+    ".hidden zpoline_signal_restorer",
+    "zpoline_signal_restorer:",
+    "mov eax, {nr}",
+    "syscall",
+    ".popsection",
+    nr = const libc::SYS_rt_sigreturn,
+);
+
+extern "C" {
+    fn zpoline_signal_restorer();
+}
+
+/// 仮想化したシグナルのハンドラ
+extern "C" fn virtual_handler(signum: c_int, info: *mut siginfo_t, ucontext: *mut c_void) {
+    let signals = virtual_signals();
+    let Some(slot) = signals.slot(signum) else {
+        return;
+    };
+    let mode = slot.mode.load(Ordering::Acquire);
+    let handler = slot.handler.load(Ordering::Acquire);
+    if handler != 0 {
+        let handler: SignalHandler = unsafe { std::mem::transmute(handler) };
+        if handler(signum, info, ucontext) || mode == MODE_RESERVE {
+            return;
+        }
+    }
+    deliver_to_app(slot, signum, info, ucontext);
+}
+
+/// アプリケーションのハンドラに渡す
+fn deliver_to_app(slot: &Slot, signum: c_int, info: *mut siginfo_t, ucontext: *mut c_void) {
+    let act = slot.app_action();
+    match act.sa_handler {
+        h if h == libc::SIG_IGN => {}
+        h if h == libc::SIG_DFL => default_action(slot, signum),
+        h => {
+            if act.sa_flags & libc::SA_RESETHAND as u64 != 0 {
+                reset_handler(slot);
+            }
+            // ハンドラ実行中はアプリケーションのsa_maskもブロックする
+            let mut old = 0u64;
+            sigprocmask(libc::SIG_BLOCK, act.sa_mask, Some(&mut old));
+            if act.sa_flags & libc::SA_SIGINFO as u64 != 0 {
+                let h: extern "C" fn(c_int, *mut siginfo_t, *mut c_void) =
+                    unsafe { std::mem::transmute(h) };
+                h(signum, info, ucontext);
+            } else {
+                let h: extern "C" fn(c_int) = unsafe { std::mem::transmute(h) };
+                h(signum);
+            }
+            sigprocmask(libc::SIG_SETMASK, old, None);
+        }
+    }
+}
+
+/// `SIG_DFL`の動作を再現する（カーネルのデフォルト動作で再送する）
+fn default_action(slot: &Slot, signum: c_int) {
+    if IGNORED_BY_DEFAULT & sigbit(signum) != 0 {
+        return;
+    }
+    let dfl = KernelSigaction::default();
+    let _ = kernel_sigaction(signum, Some(&dfl));
+    let mut old = 0u64;
+    sigprocmask(libc::SIG_UNBLOCK, sigbit(signum), Some(&mut old));
+    unsafe {
+        let pid = raw_syscall_bypass(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        let tid = raw_syscall_bypass(Sysno::gettid.nr(), 0, 0, 0, 0, 0, 0);
+        raw_syscall_bypass(
+            Sysno::tgkill.nr(),
+            pid as u64,
+            tid as u64,
+            signum as u64,
+            0,
+            0,
+            0,
+        );
+    }
+    // 停止シグナルの場合は再開後にここへ戻る
+    sigprocmask(libc::SIG_SETMASK, old, None);
+    let mode = slot.mode.load(Ordering::Acquire);
+    if mode != MODE_NONE {
+        let _ = kernel_sigaction(signum, Some(&virtual_action(&slot.app_action(), mode)));
+    }
+}
+
+/// `SA_RESETHAND`: ハンドラを`SIG_DFL`に戻す
+///
+/// シグナルハンドラの中なのでロックは取らず、書き込み中であれば諦める
+fn reset_handler(slot: &Slot) {
+    let seq = slot.seq.load(Ordering::Acquire);
+    if seq & 1 != 0
+        || slot
+            .seq
+            .compare_exchange(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+    {
+        return;
+    }
+    fence(Ordering::Release);
+    slot.app[0].store(libc::SIG_DFL as u64, Ordering::Relaxed);
+    slot.seq.fetch_add(1, Ordering::Release);
+}
+
+/// カーネルのハンドラを設定し、以前の設定を返す（フックを経由しない）
+fn kernel_sigaction(
+    signum: c_int,
+    act: Option<&KernelSigaction>,
+) -> Result<KernelSigaction, c_int> {
+    let mut old = KernelSigaction::default();
+    let ret = unsafe {
+        raw_syscall_bypass(
+            Sysno::rt_sigaction.nr(),
+            signum as u64,
+            act.map_or(0, |act| act as *const KernelSigaction as u64),
+            &mut old as *mut KernelSigaction as u64,
+            SIGSET_SIZE as u64,
+            0,
+            0,
+        )
+    };
+    if ret < 0 {
+        Err(-ret as c_int)
+    } else {
+        Ok(old)
+    }
+}
+
+/// 現在のスレッドのシグナルマスクを変更する（フックを経由しない）
+fn sigprocmask(how: c_int, set: u64, old: Option<&mut u64>) {
+    unsafe {
+        raw_syscall_bypass(
+            Sysno::rt_sigprocmask.nr(),
+            how as u64,
+            &set as *const u64 as u64,
+            old.map_or(0, |old| old as *mut u64 as u64),
+            SIGSET_SIZE as u64,
+            0,
+            0,
+        );
+    }
+}
+
+fn with_signals_blocked<R>(f: impl FnOnce() -> R) -> R {
+    let mut old = 0u64;
+    sigprocmask(libc::SIG_BLOCK, u64::MAX, Some(&mut old));
+    let result = f();
+    sigprocmask(libc::SIG_SETMASK, old, None);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    // テストは並行に実行されるため、シグナルごとに数える
+    static LIB_CALLS: [AtomicUsize; NSIG as usize + 1] = [const { AtomicUsize::new(0) }; 65];
+    static APP_CALLS: [AtomicUsize; NSIG as usize + 1] = [const { AtomicUsize::new(0) }; 65];
+    static HANDLED: AtomicBool = AtomicBool::new(false);
+
+    fn lib_handler(signum: c_int, _info: *mut siginfo_t, _ctx: *mut c_void) -> bool {
+        LIB_CALLS[signum as usize].fetch_add(1, Ordering::SeqCst);
+        HANDLED.load(Ordering::SeqCst)
+    }
+
+    extern "C" fn app_handler(signum: c_int) {
+        APP_CALLS[signum as usize].fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn app_action(handler: usize) -> KernelSigaction {
+        KernelSigaction {
+            sa_handler: handler,
+            sa_flags: SA_RESTORER,
+            sa_restorer: zpoline_signal_restorer as *const () as usize,
+            sa_mask: 0,
+        }
+    }
+
+    fn raise(signum: c_int) {
+        unsafe { libc::syscall(libc::SYS_tgkill, libc::getpid(), libc::gettid(), signum) };
+    }
+
+    fn counts(signum: c_int) -> (usize, usize) {
+        (
+            LIB_CALLS[signum as usize].load(Ordering::SeqCst),
+            APP_CALLS[signum as usize].load(Ordering::SeqCst),
+        )
+    }
+
+    #[test]
+    fn test_virtual_signals() {
+        assert_eq!(std::mem::size_of::<KernelSigaction>(), 32);
+        let signals = virtual_signals();
+        assert_eq!(
+            signals.reserve(libc::SIGKILL, lib_handler),
+            Err(libc::EINVAL)
+        );
+        assert_eq!(signals.wrap(NSIG + 1, lib_handler), Err(libc::EINVAL));
+
+        // reserve: アプリケーションのハンドラは記録されるだけ
+        let sig = libc::SIGRTMIN() + 5;
+        signals.reserve(sig, lib_handler).unwrap();
+        assert_eq!(signals.wrap(sig, lib_handler), Err(libc::EBUSY));
+        let mut act = app_action(app_handler as *const () as usize);
+        let mut old = KernelSigaction::default();
+        assert_eq!(
+            default_rt_sigaction(
+                sig,
+                SigactionPtr::new(&mut act),
+                SigactionPtr::new(&mut old),
+                8
+            ),
+            0
+        );
+        assert_eq!(old.sa_handler, libc::SIG_DFL);
+        assert_eq!(
+            default_rt_sigaction(
+                sig,
+                SigactionPtr::new(std::ptr::null_mut()),
+                SigactionPtr::new(&mut old),
+                8
+            ),
+            0
+        );
+        assert_eq!(old, act);
+        assert_eq!(
+            kernel_sigaction(sig, None).unwrap().sa_handler,
+            virtual_handler as *const () as usize
+        );
+
+        // アプリケーションはreserveしたシグナルをブロックできない
+        let mut set = sigbit(sig);
+        let mut oldset = 0u64;
+        let how = libc::SIG_BLOCK;
+        default_rt_sigprocmask(
+            how,
+            SigsetPtr::new(&mut set),
+            SigsetPtr::new(&mut oldset),
+            8,
+        );
+        let mut current = 0u64;
+        sigprocmask(libc::SIG_BLOCK, 0, Some(&mut current));
+        assert_eq!(current & sigbit(sig), 0);
+
+        let (lib, app) = counts(sig);
+        raise(sig);
+        assert_eq!(counts(sig), (lib + 1, app));
+
+        // release後はアプリケーションのハンドラが直接呼ばれる
+        signals.release(sig).unwrap();
+        assert!(!signals.is_virtualized(sig));
+        raise(sig);
+        assert_eq!(counts(sig), (lib + 1, app + 1));
+    }
+
+    #[test]
+    fn test_wrap_signal() {
+        let signals = virtual_signals();
+        let sig = libc::SIGRTMIN() + 6;
+        let mut act = app_action(app_handler as *const () as usize);
+        kernel_sigaction(sig, Some(&act)).unwrap();
+        signals.wrap(sig, lib_handler).unwrap();
+        assert_eq!(signals.app_action(sig), Some(act));
+
+        // wrapはアプリケーションのハンドラにも渡す
+        let (lib, app) = counts(sig);
+        raise(sig);
+        assert_eq!(counts(sig), (lib + 1, app + 1));
+
+        // 処理済みならアプリケーションのハンドラは呼ばない
+        HANDLED.store(true, Ordering::SeqCst);
+        raise(sig);
+        HANDLED.store(false, Ordering::SeqCst);
+        assert_eq!(counts(sig), (lib + 2, app + 1));
+
+        act.sa_handler = libc::SIG_IGN;
+        let null = SigactionPtr::new(std::ptr::null_mut());
+        assert_eq!(
+            default_rt_sigaction(sig, SigactionPtr::new(&mut act), null, 8),
+            0
+        );
+        raise(sig);
+        assert_eq!(counts(sig), (lib + 3, app + 1));
+        signals.release(sig).unwrap();
+        assert_eq!(
+            kernel_sigaction(sig, None).unwrap().sa_handler,
+            libc::SIG_IGN
+        );
+    }
+
+    #[test]
+    fn test_sigsuspend_does_not_block_other_threads() {
+        use crate::{hook_entry, register_syscall_hooks, set_hook_interests, SyscallHooks};
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        struct PpidHooks;
+        impl SyscallHooks for PpidHooks {
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::new(&[Sysno::getppid])
+            }
+        }
+
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+        let signals = virtual_signals();
+        let reserved = libc::SIGRTMIN() + 8;
+        let wake = libc::SIGRTMIN() + 9;
+        signals.reserve(reserved, lib_handler).unwrap();
+        kernel_sigaction(wake, Some(&app_action(app_handler as *const () as usize))).unwrap();
+        register_syscall_hooks(PpidHooks);
+
+        // 待つスレッド: wakeをブロックしておき、sigsuspendの間だけ受け取る
+        let (tid_tx, tid_rx) = mpsc::channel();
+        let waiter = std::thread::spawn(move || {
+            let mut old = 0u64;
+            sigprocmask(libc::SIG_BLOCK, sigbit(wake), Some(&mut old));
+            tid_tx.send(unsafe { libc::gettid() }).unwrap();
+            let mut mask = old & !sigbit(wake);
+            let nr = Sysno::rt_sigsuspend.nr();
+            let mut regs = SyscallRegs::new(nr, &mut mask as *mut u64 as u64, 8, 0, 0, 0, 0);
+            hook_entry(&mut regs)
+        });
+        let tid = tid_rx.recv().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        // sigsuspendの間も他のスレッドのシグナル関連syscallは進む
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut old = 0u64;
+            let nr = Sysno::rt_sigprocmask.nr();
+            let oldset = &mut old as *mut u64 as u64;
+            let mut regs = SyscallRegs::new(nr, libc::SIG_BLOCK as u64, 0, oldset, 8, 0, 0);
+            done_tx.send(hook_entry(&mut regs)).unwrap();
+        });
+        let done = done_rx.recv_timeout(Duration::from_secs(5));
+
+        let app = counts(wake).1;
+        unsafe { libc::syscall(libc::SYS_tgkill, libc::getpid(), tid, wake) };
+        assert_eq!(waiter.join().unwrap(), -libc::EINTR as i64);
+        assert_eq!(counts(wake).1, app + 1);
+        assert_eq!(done, Ok(0));
+
+        signals.release(reserved).unwrap();
+        set_hook_interests(SysnoSet::all());
+    }
+}
@@ -0,0 +1,729 @@
+//! ソケットアドレスとメッセージヘッダ
+//!
+//! `sendto`/`recvfrom`/`sendmsg`/`recvmsg`などのフックが受け取る
+//! sockaddrや`struct msghdr`を型付きのビューとして扱います。
+//! アプリケーションメモリへのアクセスはすべてフォールトセーフです
+//! （`user_mem`の`try_`系関数と同じ）。
+//!
+//! ```no_run
+//! use zpoline_hook_api::socket::{ControlMessage, MsgHdr};
+//! use zpoline_hook_api::syscall_hooks::default_sendmsg;
+//!
+//! fn hook_sendmsg(sockfd: libc::c_int, msg: MsgHdr, flags: libc::c_int) -> libc::ssize_t {
+//!     if let Ok(cmsgs) = msg.control() {
+//!         for cmsg in cmsgs {
+//!             if let ControlMessage::Rights(fds) = cmsg {
+//!                 zpoline_hook_api::hook_log!("passing fds {:?}", fds);
+//!             }
+//!         }
+//!     }
+//!     default_sendmsg(sockfd, msg, flags)
+//! }
+//! ```
+
+use crate::user_mem::{
+    try_read_bytes, try_read_val, try_write_bytes, try_write_val, IoVecs, UserMemError,
+};
+use libc::{c_int, c_uint, c_void};
+use std::ffi::OsStr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
+
+/// 制御メッセージバッファとして読み取る最大長
+pub const CONTROL_MAX: usize = 64 * 1024;
+
+const SA_FAMILY_LEN: usize = std::mem::size_of::<libc::sa_family_t>();
+const SUN_PATH_LEN: usize = 108;
+const CMSG_HDR_LEN: usize = std::mem::size_of::<libc::cmsghdr>();
+
+/// UNIXドメインソケットのアドレス
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnixAddr {
+    /// 名前なし（`socketpair`や未バインドのソケット）
+    Unnamed,
+    /// ファイルシステム上のパス
+    Pathname(PathBuf),
+    /// 抽象名前空間（先頭のNULを除いた名前）
+    Abstract(Vec<u8>),
+}
+
+/// ソケットアドレス（AF_INET / AF_INET6 / AF_UNIX）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SocketAddr {
+    Inet(std::net::SocketAddr),
+    Unix(UnixAddr),
+}
+
+impl From<std::net::SocketAddr> for SocketAddr {
+    fn from(addr: std::net::SocketAddr) -> Self {
+        SocketAddr::Inet(addr)
+    }
+}
+
+impl From<UnixAddr> for SocketAddr {
+    fn from(addr: UnixAddr) -> Self {
+        SocketAddr::Unix(addr)
+    }
+}
+
+/// バイト列から構造体を読み取る（`bytes`は`T`以上の長さであること）
+fn read_struct<T: Copy>(bytes: &[u8]) -> T {
+    assert!(bytes.len() >= std::mem::size_of::<T>());
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+fn struct_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+impl SocketAddr {
+    /// sockaddrのバイト列をデコードする
+    ///
+    /// 対応していないアドレスファミリや短すぎる場合は`None`を返します。
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < SA_FAMILY_LEN {
+            return None;
+        }
+        let family = libc::sa_family_t::from_ne_bytes([bytes[0], bytes[1]]);
+        match family as c_int {
+            libc::AF_INET if bytes.len() >= std::mem::size_of::<libc::sockaddr_in>() => {
+                let sin: libc::sockaddr_in = read_struct(bytes);
+                Some(SocketAddr::Inet(std::net::SocketAddr::V4(
+                    SocketAddrV4::new(
+                        Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)),
+                        u16::from_be(sin.sin_port),
+                    ),
+                )))
+            }
+            libc::AF_INET6 if bytes.len() >= std::mem::size_of::<libc::sockaddr_in6>() => {
+                let sin6: libc::sockaddr_in6 = read_struct(bytes);
+                Some(SocketAddr::Inet(std::net::SocketAddr::V6(
+                    SocketAddrV6::new(
+                        Ipv6Addr::from(sin6.sin6_addr.s6_addr),
+                        u16::from_be(sin6.sin6_port),
+                        u32::from_be(sin6.sin6_flowinfo),
+                        sin6.sin6_scope_id,
+                    ),
+                )))
+            }
+            libc::AF_UNIX => {
+                let path = &bytes[SA_FAMILY_LEN..bytes.len().min(SA_FAMILY_LEN + SUN_PATH_LEN)];
+                Some(SocketAddr::Unix(match path.first() {
+                    None => UnixAddr::Unnamed,
+                    Some(0) => UnixAddr::Abstract(path[1..].to_vec()),
+                    Some(_) => {
+                        let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                        UnixAddr::Pathname(PathBuf::from(OsStr::from_bytes(&path[..end])))
+                    }
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// sockaddrのバイト列にエンコードする（長さがそのままaddrlenになる）
+    ///
+    /// `sun_path`に収まらないUNIXアドレスは`None`を返します。
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        match self {
+            SocketAddr::Inet(std::net::SocketAddr::V4(addr)) => {
+                let mut sin: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+                sin.sin_family = libc::AF_INET as libc::sa_family_t;
+                sin.sin_port = addr.port().to_be();
+                sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+                Some(struct_bytes(&sin).to_vec())
+            }
+            SocketAddr::Inet(std::net::SocketAddr::V6(addr)) => {
+                let mut sin6: libc::sockaddr_in6 = unsafe { std::mem::zeroed() };
+                sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sin6.sin6_port = addr.port().to_be();
+                sin6.sin6_flowinfo = addr.flowinfo().to_be();
+                sin6.sin6_addr.s6_addr = addr.ip().octets();
+                sin6.sin6_scope_id = addr.scope_id();
+                Some(struct_bytes(&sin6).to_vec())
+            }
+            SocketAddr::Unix(addr) => {
+                let mut bytes = (libc::AF_UNIX as libc::sa_family_t).to_ne_bytes().to_vec();
+                match addr {
+                    UnixAddr::Unnamed => {}
+                    UnixAddr::Pathname(path) => {
+                        let path = path.as_os_str().as_bytes();
+                        if path.len() >= SUN_PATH_LEN {
+                            return None;
+                        }
+                        bytes.extend_from_slice(path);
+                        bytes.push(0);
+                    }
+                    UnixAddr::Abstract(name) => {
+                        if name.len() >= SUN_PATH_LEN {
+                            return None;
+                        }
+                        bytes.push(0);
+                        bytes.extend_from_slice(name);
+                    }
+                }
+                Some(bytes)
+            }
+        }
+    }
+
+    /// アプリケーションメモリのsockaddrを読み取る（フォールトセーフ）
+    ///
+    /// 対応していないアドレスファミリは`Os(EINVAL)`を返します。
+    pub fn read(ptr: *const c_void, len: u32) -> Result<Self, UserMemError> {
+        let mut storage = [0u8; std::mem::size_of::<libc::sockaddr_storage>()];
+        let len = (len as usize).min(storage.len());
+        try_read_bytes(ptr, &mut storage[..len])?;
+        Self::decode(&storage[..len]).ok_or(UserMemError::Os(libc::EINVAL))
+    }
+
+    /// IPv4/IPv6アドレスとして取り出す
+    pub fn as_inet(&self) -> Option<std::net::SocketAddr> {
+        match self {
+            SocketAddr::Inet(addr) => Some(*addr),
+            SocketAddr::Unix(_) => None,
+        }
+    }
+}
+
+/// `connect`/`sendto`などの入力sockaddr（ポインタと長さ）
+#[derive(Debug, Clone, Copy)]
+pub struct SockAddrArg {
+    ptr: *const c_void,
+    len: u32,
+}
+
+impl SockAddrArg {
+    pub fn new(ptr: *const c_void, len: u32) -> Self {
+        Self { ptr, len }
+    }
+
+    pub fn as_ptr(&self) -> *const c_void {
+        self.ptr
+    }
+
+    pub fn addrlen(&self) -> u32 {
+        self.len
+    }
+
+    /// アドレスが指定されていない（接続済みソケットへの`sendto`など）
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    pub fn read(&self) -> Result<SocketAddr, UserMemError> {
+        SocketAddr::read(self.ptr, self.len)
+    }
+}
+
+/// `accept4`/`recvfrom`/`getsockname`などの出力sockaddr
+///
+/// `len`は呼び出し時はバッファの大きさ、戻り時はアドレスの実際の長さです。
+/// デフォルト実装が`len`を書き換えても元の大きさで書き込めるように、
+/// 作成時のバッファの大きさを保持します。
+#[derive(Debug, Clone, Copy)]
+pub struct SockAddrOut {
+    ptr: *mut c_void,
+    len: *mut u32,
+    cap: u32,
+}
+
+impl SockAddrOut {
+    pub fn new(ptr: *mut c_void, len: *mut u32) -> Self {
+        let cap = if ptr.is_null() {
+            0
+        } else {
+            try_read_val(len).unwrap_or(0)
+        };
+        Self::with_capacity(ptr, len, cap)
+    }
+
+    fn with_capacity(ptr: *mut c_void, len: *mut u32, cap: u32) -> Self {
+        Self { ptr, len, cap }
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.ptr
+    }
+
+    pub fn len_ptr(&self) -> *mut u32 {
+        self.len
+    }
+
+    /// 作成時のバッファの大きさ
+    pub fn capacity(&self) -> u32 {
+        self.cap
+    }
+
+    /// アドレスを受け取らない（`addr`がNULL）
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// 書き込まれたアドレスを読み取る（デフォルト実装を呼んだ後に使う）
+    pub fn read(&self) -> Result<SocketAddr, UserMemError> {
+        let len = try_read_val(self.len)?;
+        SocketAddr::read(self.ptr, len)
+    }
+
+    /// アドレスを書き込む
+    ///
+    /// カーネルと同じく、バッファに収まらない分は切り詰め、`len`には
+    /// アドレスの実際の長さを書き込みます。`addr`がNULLの場合は何もしません。
+    pub fn write(&self, addr: &SocketAddr) -> Result<(), UserMemError> {
+        if self.is_null() {
+            return Ok(());
+        }
+        let bytes = addr.encode().ok_or(UserMemError::Os(libc::EINVAL))?;
+        try_write_bytes(self.ptr, &bytes[..bytes.len().min(self.cap as usize)])?;
+        try_write_val(self.len, bytes.len() as u32)
+    }
+}
+
+/// 制御メッセージ（補助データ）
+#[derive(Debug, Clone)]
+pub enum ControlMessage {
+    /// `SCM_RIGHTS` - 受け渡すファイルディスクリプタ
+    Rights(Vec<RawFd>),
+    /// `SCM_CREDENTIALS` - 送信元の資格情報
+    Credentials(libc::ucred),
+    /// その他のメッセージ
+    Other {
+        level: c_int,
+        ty: c_int,
+        data: Vec<u8>,
+    },
+}
+
+const fn cmsg_align(len: usize) -> usize {
+    (len + std::mem::size_of::<usize>() - 1) & !(std::mem::size_of::<usize>() - 1)
+}
+
+impl ControlMessage {
+    fn level_and_type(&self) -> (c_int, c_int) {
+        match self {
+            ControlMessage::Rights(_) => (libc::SOL_SOCKET, libc::SCM_RIGHTS),
+            ControlMessage::Credentials(_) => (libc::SOL_SOCKET, libc::SCM_CREDENTIALS),
+            ControlMessage::Other { level, ty, .. } => (*level, *ty),
+        }
+    }
+
+    fn data(&self) -> Vec<u8> {
+        match self {
+            ControlMessage::Rights(fds) => fds.iter().flat_map(|fd| fd.to_ne_bytes()).collect(),
+            ControlMessage::Credentials(cred) => struct_bytes(cred).to_vec(),
+            ControlMessage::Other { data, .. } => data.clone(),
+        }
+    }
+
+    /// 制御メッセージバッファ（`msg_control`の内容）をデコードする
+    ///
+    /// 途中で壊れたヘッダがあればそこで打ち切ります。
+    pub fn decode_all(buf: &[u8]) -> Vec<Self> {
+        let mut msgs = Vec::new();
+        let mut offset = 0;
+        while offset + CMSG_HDR_LEN <= buf.len() {
+            let hdr: libc::cmsghdr = read_struct(&buf[offset..]);
+            let len = hdr.cmsg_len;
+            if len < CMSG_HDR_LEN || offset + len > buf.len() {
+                break;
+            }
+            let data = &buf[offset + CMSG_HDR_LEN..offset + len];
+            msgs.push(match (hdr.cmsg_level, hdr.cmsg_type) {
+                (libc::SOL_SOCKET, libc::SCM_RIGHTS) => ControlMessage::Rights(
+                    data.chunks_exact(std::mem::size_of::<RawFd>())
+                        .map(|b| RawFd::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                        .collect(),
+                ),
+                (libc::SOL_SOCKET, libc::SCM_CREDENTIALS)
+                    if data.len() >= std::mem::size_of::<libc::ucred>() =>
+                {
+                    ControlMessage::Credentials(read_struct(data))
+                }
+                (level, ty) => ControlMessage::Other {
+                    level,
+                    ty,
+                    data: data.to_vec(),
+                },
+            });
+            offset += cmsg_align(len);
+        }
+        msgs
+    }
+
+    /// 制御メッセージバッファにエンコードする
+    pub fn encode_all(msgs: &[Self]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for msg in msgs {
+            let data = msg.data();
+            let (level, ty) = msg.level_and_type();
+            let mut hdr: libc::cmsghdr = unsafe { std::mem::zeroed() };
+            hdr.cmsg_len = CMSG_HDR_LEN + data.len();
+            hdr.cmsg_level = level;
+            hdr.cmsg_type = ty;
+            buf.extend_from_slice(struct_bytes(&hdr));
+            buf.extend_from_slice(&data);
+            buf.resize(cmsg_align(buf.len()), 0);
+        }
+        buf
+    }
+}
+
+/// `sendmsg`/`recvmsg`に渡された`struct msghdr`
+///
+/// 構造体と、それが指す名前・iovec・制御メッセージをフォールトセーフに読み書きします。
+/// `SockAddrOut`と同じく、作成時の`msg_namelen`と`msg_controllen`を
+/// 書き込み先の大きさとして保持します。
+#[derive(Debug, Clone, Copy)]
+pub struct MsgHdr {
+    ptr: *mut libc::msghdr,
+    namelen: u32,
+    controllen: usize,
+}
+
+impl MsgHdr {
+    pub fn new(ptr: *mut libc::msghdr) -> Self {
+        let (namelen, controllen) = match try_read_val(ptr) {
+            Ok(hdr) => (
+                if hdr.msg_name.is_null() {
+                    0
+                } else {
+                    hdr.msg_namelen
+                },
+                if hdr.msg_control.is_null() {
+                    0
+                } else {
+                    hdr.msg_controllen
+                },
+            ),
+            Err(_) => (0, 0),
+        };
+        Self {
+            ptr,
+            namelen,
+            controllen,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut libc::msghdr {
+        self.ptr
+    }
+
+    /// フィールドのアドレス（アプリケーションメモリは参照しない）
+    fn field<T>(&self, offset: usize) -> *mut T {
+        self.ptr.wrapping_byte_add(offset) as *mut T
+    }
+
+    /// 構造体をコピーする
+    pub fn read(&self) -> Result<libc::msghdr, UserMemError> {
+        try_read_val(self.ptr)
+    }
+
+    /// 宛先（`sendmsg`）または送信元（`recvmsg`の戻り後）のアドレス
+    ///
+    /// `msg_name`がNULLまたは`msg_namelen`が0の場合は`None`を返します。
+    pub fn name(&self) -> Result<Option<SocketAddr>, UserMemError> {
+        let hdr = self.read()?;
+        if hdr.msg_name.is_null() || hdr.msg_namelen == 0 {
+            return Ok(None);
+        }
+        SocketAddr::read(hdr.msg_name, hdr.msg_namelen).map(Some)
+    }
+
+    /// `recvmsg`の送信元アドレスを書き込む（`SockAddrOut::write`と同じ規則）
+    pub fn set_name(&self, addr: &SocketAddr) -> Result<(), UserMemError> {
+        let hdr = self.read()?;
+        let namelen = self.field(std::mem::offset_of!(libc::msghdr, msg_namelen));
+        if hdr.msg_name.is_null() {
+            return try_write_val(namelen, 0);
+        }
+        SockAddrOut::with_capacity(hdr.msg_name, namelen, self.namelen).write(addr)
+    }
+
+    /// データバッファ
+    pub fn iovecs(&self) -> Result<IoVecs, UserMemError> {
+        let hdr = self.read()?;
+        Ok(IoVecs::new(hdr.msg_iov, hdr.msg_iovlen))
+    }
+
+    /// 制御メッセージをデコードする
+    pub fn control(&self) -> Result<Vec<ControlMessage>, UserMemError> {
+        let hdr = self.read()?;
+        if hdr.msg_control.is_null() || hdr.msg_controllen == 0 {
+            return Ok(Vec::new());
+        }
+        let mut buf = vec![0u8; hdr.msg_controllen.min(CONTROL_MAX)];
+        try_read_bytes(hdr.msg_control, &mut buf)?;
+        Ok(ControlMessage::decode_all(&buf))
+    }
+
+    /// `recvmsg`の制御メッセージを書き込む
+    ///
+    /// バッファに収まるメッセージだけを書き込み、収まらなかった場合は
+    /// カーネルと同じく`msg_flags`に`MSG_CTRUNC`を立てます。
+    pub fn set_control(&self, msgs: &[ControlMessage]) -> Result<(), UserMemError> {
+        let hdr = self.read()?;
+        let cap = self.controllen;
+        let mut buf = Vec::new();
+        let mut truncated = false;
+        for msg in msgs {
+            let encoded = ControlMessage::encode_all(std::slice::from_ref(msg));
+            if buf.len() + encoded.len() > cap {
+                truncated = true;
+                break;
+            }
+            buf.extend_from_slice(&encoded);
+        }
+        if !buf.is_empty() {
+            try_write_bytes(hdr.msg_control, &buf)?;
+        }
+        try_write_val(
+            self.field(std::mem::offset_of!(libc::msghdr, msg_controllen)),
+            buf.len(),
+        )?;
+        if truncated {
+            self.set_flags(hdr.msg_flags | libc::MSG_CTRUNC)?;
+        }
+        Ok(())
+    }
+
+    /// `recvmsg`が返すフラグ
+    pub fn flags(&self) -> Result<c_int, UserMemError> {
+        try_read_val(self.field(std::mem::offset_of!(libc::msghdr, msg_flags)))
+    }
+
+    pub fn set_flags(&self, flags: c_int) -> Result<(), UserMemError> {
+        try_write_val(
+            self.field(std::mem::offset_of!(libc::msghdr, msg_flags)),
+            flags,
+        )
+    }
+}
+
+/// `sendmmsg`/`recvmmsg`に渡された`struct mmsghdr`の配列
+#[derive(Debug, Clone, Copy)]
+pub struct MMsgHdrs {
+    ptr: *mut libc::mmsghdr,
+    len: c_uint,
+}
+
+impl MMsgHdrs {
+    pub fn new(ptr: *mut libc::mmsghdr, len: c_uint) -> Self {
+        Self { ptr, len }
+    }
+
+    pub fn as_ptr(&self) -> *mut libc::mmsghdr {
+        self.ptr
+    }
+
+    /// メッセージの数（`vlen`）
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `i`番目のメッセージヘッダ（デフォルト実装を呼ぶ前に取得すること）
+    pub fn get(&self, i: usize) -> Option<MsgHdr> {
+        (i < self.len()).then(|| MsgHdr::new(self.ptr.wrapping_add(i) as *mut libc::msghdr))
+    }
+
+    fn msg_len_ptr(&self, i: usize) -> *mut c_uint {
+        self.ptr
+            .wrapping_add(i)
+            .wrapping_byte_add(std::mem::offset_of!(libc::mmsghdr, msg_len)) as *mut c_uint
+    }
+
+    /// `i`番目のメッセージで送受信したバイト数
+    pub fn msg_len(&self, i: usize) -> Result<c_uint, UserMemError> {
+        if i >= self.len() {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        try_read_val(self.msg_len_ptr(i))
+    }
+
+    pub fn set_msg_len(&self, i: usize, len: c_uint) -> Result<(), UserMemError> {
+        if i >= self.len() {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        try_write_val(self.msg_len_ptr(i), len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Sysno, SyscallRegs};
+
+    #[test]
+    fn test_socket_addr_roundtrip() {
+        let addrs = [
+            SocketAddr::from("127.0.0.1:8080".parse::<std::net::SocketAddr>().unwrap()),
+            SocketAddr::from("[::1]:53".parse::<std::net::SocketAddr>().unwrap()),
+            SocketAddr::Unix(UnixAddr::Pathname("/tmp/zpoline.sock".into())),
+            SocketAddr::Unix(UnixAddr::Abstract(b"zpoline".to_vec())),
+            SocketAddr::Unix(UnixAddr::Unnamed),
+        ];
+        for addr in &addrs {
+            let bytes = addr.encode().unwrap();
+            assert_eq!(SocketAddr::decode(&bytes).as_ref(), Some(addr));
+            assert_eq!(
+                SocketAddr::read(bytes.as_ptr() as *const c_void, bytes.len() as u32).as_ref(),
+                Ok(addr)
+            );
+        }
+        assert_eq!(
+            addrs[1].encode().unwrap().len(),
+            std::mem::size_of::<libc::sockaddr_in6>()
+        );
+        assert_eq!(addrs[3].encode().unwrap().len(), 2 + 1 + 7);
+
+        let long = SocketAddr::Unix(UnixAddr::Pathname("x".repeat(SUN_PATH_LEN).into()));
+        assert_eq!(long.encode(), None);
+        assert_eq!(SocketAddr::decode(&[0xff, 0xff, 0, 0]), None);
+
+        // 出力先が小さい場合は切り詰めて実際の長さを返す
+        let mut buf = [0u8; 4];
+        let mut len = buf.len() as u32;
+        let out = SockAddrOut::new(buf.as_mut_ptr() as *mut c_void, &mut len);
+        out.write(&addrs[0]).unwrap();
+        assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in>());
+        assert_eq!(&buf[..2], &(libc::AF_INET as u16).to_ne_bytes());
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let cred = libc::ucred {
+            pid: 1,
+            uid: 2,
+            gid: 3,
+        };
+        let msgs = [
+            ControlMessage::Rights(vec![3, 4, 5]),
+            ControlMessage::Credentials(cred),
+        ];
+        let buf = ControlMessage::encode_all(&msgs);
+        assert_eq!(buf.len(), 2 * cmsg_align(CMSG_HDR_LEN + 12));
+        match ControlMessage::decode_all(&buf).as_slice() {
+            [ControlMessage::Rights(fds), ControlMessage::Credentials(c)] => {
+                assert_eq!(fds, &[3, 4, 5]);
+                assert_eq!((c.pid, c.uid, c.gid), (1, 2, 3));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // 制御バッファが足りない場合はMSG_CTRUNC
+        let mut control = [0u8; 32];
+        let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = control.len();
+        let msg = MsgHdr::new(&mut hdr);
+        msg.set_control(&msgs).unwrap();
+        assert_eq!(hdr.msg_controllen, 32);
+        assert_eq!(hdr.msg_flags & libc::MSG_CTRUNC, libc::MSG_CTRUNC);
+        assert!(matches!(
+            ControlMessage::decode_all(&control).as_slice(),
+            [ControlMessage::Rights(_)]
+        ));
+    }
+
+    #[test]
+    fn test_socket_message_hooks() {
+        use std::os::fd::AsRawFd;
+        use crate::syscall_hooks::*;
+
+        #[derive(Default)]
+        struct FakeSocket {
+            passed_fds: Vec<i32>,
+        }
+
+        impl SyscallHooks for FakeSocket {
+            fn hook_sendmsg(&mut self, sockfd: i32, msg: MsgHdr, flags: i32) -> isize {
+                for cmsg in msg.control().unwrap() {
+                    if let ControlMessage::Rights(fds) = cmsg {
+                        self.passed_fds.extend(fds);
+                    }
+                }
+                default_sendmsg(sockfd, msg, flags)
+            }
+
+            fn hook_recvfrom(
+                &mut self,
+                sockfd: i32,
+                buf: *mut libc::c_void,
+                len: usize,
+                flags: i32,
+                src_addr: SockAddrOut,
+            ) -> isize {
+                let ret = default_recvfrom(sockfd, buf, len, flags, src_addr);
+                if ret >= 0 {
+                    let from = "10.0.0.1:53".parse::<std::net::SocketAddr>().unwrap();
+                    src_addr.write(&SocketAddr::from(from)).unwrap();
+                }
+                ret
+            }
+        }
+
+        let mut fds = [0; 2];
+        assert_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+        let file = std::fs::File::open("Cargo.toml").unwrap();
+        let mut hooks = FakeSocket::default();
+
+        let mut data = *b"hi";
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        let mut control =
+            ControlMessage::encode_all(&[ControlMessage::Rights(vec![file.as_raw_fd()])]);
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = control.len();
+        let mut regs = SyscallRegs::new(
+            Sysno::sendmsg.nr(),
+            fds[0] as u64,
+            &mut msg as *mut libc::msghdr as u64,
+            0,
+            0,
+            0,
+            0,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut regs), 2);
+        assert_eq!(hooks.passed_fds, vec![file.as_raw_fd()]);
+
+        let mut buf = [0u8; 16];
+        let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let mut addrlen = std::mem::size_of::<libc::sockaddr_storage>() as u32;
+        let mut regs = SyscallRegs::new(
+            Sysno::recvfrom.nr(),
+            fds[1] as u64,
+            buf.as_mut_ptr() as u64,
+            buf.len() as u64,
+            0,
+            &mut addr as *mut libc::sockaddr_storage as u64,
+            &mut addrlen as *mut u32 as u64,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut regs), 2);
+        assert_eq!(&buf[..2], b"hi");
+        assert_eq!(
+            SocketAddr::read(&addr as *const libc::sockaddr_storage as *const libc::c_void, addrlen)
+                .unwrap()
+                .as_inet(),
+            Some("10.0.0.1:53".parse().unwrap())
+        );
+
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+    }
+}
@@ -1,5 +1,37 @@
-use crate::{raw_syscall, SyscallRegs};
-use libc::{c_char, c_int, c_uint, c_ulong, c_void, off_t, pid_t, size_t, ssize_t};
+use crate::poll::{millis_timeout, read_timespec, read_timeval};
+use crate::signal::virtual_signals;
+use crate::socket::{MMsgHdrs, MsgHdr, SockAddrArg, SockAddrOut};
+use crate::user_mem::{try_read_val, IoVecs, UserPtr};
+use crate::vfd::virtual_fds;
+use crate::{raw_syscall, Sysno, SysnoSet, SyscallRegs};
+use libc::{
+    c_char, c_int, c_uint, c_ulong, c_void, gid_t, mode_t, off_t, pid_t, sigset_t, size_t, ssize_t,
+    uid_t,
+};
+
+pub use crate::poll::{EpollEvents, FdSet, PollFds};
+pub use crate::clone::{CloneArgsPtr, CloneChildFn, CloneFlags};
+pub use crate::dirent::DirEntries;
+pub use crate::identity::{CapDataPtr, CapHeaderPtr, RlimitPtr, UtsnamePtr};
+pub use crate::signal::{SigactionPtr, SigsetPtr, StackPtr};
+
+/// `stat`/`fstat`/`lstat`/`newfstatat`の出力先
+pub type StatBuf = UserPtr<libc::stat>;
+
+/// `statx`の出力先
+pub type StatxBuf = UserPtr<libc::statx>;
+
+/// `epoll_ctl`の`struct epoll_event`
+pub type EpollEventPtr = UserPtr<libc::epoll_event>;
+
+/// `io_uring_setup`の`struct io_uring_params`
+pub type IoUringParamsPtr = UserPtr<crate::io_uring::IoUringParams>;
+
+/// `clock_gettime`/`clock_getres`の出力先
+pub type TimespecPtr = UserPtr<libc::timespec>;
+
+/// `gettimeofday`の出力先
+pub type TimevalPtr = UserPtr<libc::timeval>;
 
 /// システムコールフックのためのtrait
 ///
@@ -10,15 +42,15 @@ use libc::{c_char, c_int, c_uint, c_ulong, c_void, off_t, pid_t, size_t, ssize_t
 /// # 使用例
 ///
 /// ```rust
-/// use zpoline_hook_api::{SyscallHooks, register_syscall_hooks};
+/// use zpoline_hook_api::{syscall_hooks::*, SyscallHooks};
 ///
 /// struct MyHooks;
 ///
 /// impl SyscallHooks for MyHooks {
-///     fn hook_write(&mut self, fd: i32, buf: *const u8, count: usize) -> isize {
+///     fn hook_write(&mut self, fd: i32, buf: *const std::ffi::c_void, count: usize) -> isize {
 ///         eprintln!("[CUSTOM] write called: fd={}, count={}", fd, count);
 ///         // デフォルトの実装を呼ぶ
-///         default_write(fd, buf as *const _, count)
+///         default_write(fd, buf, count)
 ///     }
 ///
 ///     fn hook_open(&mut self, pathname: *const i8, flags: i32, mode: u32) -> i32 {
@@ -27,7 +59,73 @@ use libc::{c_char, c_int, c_uint, c_ulong, c_void, off_t, pid_t, size_t, ssize_t
 ///     }
 /// }
 /// ```
+///
+/// `#[hook_library]`を使うと登録と初期化関数の生成を省略できます。
+/// シグネチャがこのtraitと合わないメソッドはコンパイルエラーになります。
+///
+/// ```compile_fail
+/// use zpoline_hook_api::{hook_library, SyscallHooks};
+///
+/// #[derive(Default)]
+/// struct MyHooks;
+///
+/// #[hook_library]
+/// impl SyscallHooks for MyHooks {
+///     fn hook_close(&mut self, fd: i32, extra: i32) -> i32 {
+///         fd + extra
+///     }
+/// }
+/// ```
 pub trait SyscallHooks: Send + Sync + 'static {
+    /// フックするシステムコールの集合
+    ///
+    /// 登録時に1度だけ参照され、含まれないシステムコールはトランポリンで
+    /// 直接実行されます（TLSガードやディスパッチを経由しない）。
+    /// デフォルトはすべてのシステムコールです。オーバーライドしたメソッドに
+    /// 対応するシステムコールを列挙してください。
+    ///
+    /// ```rust
+    /// # use zpoline_hook_api::{SyscallHooks, Sysno, SysnoSet};
+    /// # struct OpenOnly;
+    /// impl SyscallHooks for OpenOnly {
+    ///     fn interests(&self) -> SysnoSet {
+    ///         SysnoSet::new(&[Sysno::open, Sysno::openat])
+    ///     }
+    ///     // hook_open, hook_openat ...
+    /// }
+    /// ```
+    fn interests(&self) -> SysnoSet {
+        SysnoSet::all()
+    }
+
+    /// fork後の子プロセスで呼ばれる
+    ///
+    /// `fork`/`vfork`や`CLONE_VM`なしの`clone`/`clone3`から子プロセスに戻った直後、
+    /// ランタイム内部のロックとカウンタを再初期化した後に呼ばれます。
+    /// `interests`に含めていなくても呼ばれます。親から引き継いだフック実装自身の
+    /// 状態（ロックや統計など）をここでリセットしてください。
+    fn on_fork_child(&mut self) {}
+
+    /// `fork`/`vfork`/`clone`/`clone3`で子を作った直後に親で呼ばれる
+    ///
+    /// `child`は子のpid（スレッドの場合はtid）です。フックメソッドの後に呼ばれるため、
+    /// フック実装が子を作らなかった場合は呼ばれません。`interests`に含めていなくても
+    /// 呼ばれます（その場合はデフォルト実装で子を作ります）。
+    fn on_clone_parent(&mut self, child: pid_t, flags: CloneFlags) {
+        let _ = (child, flags);
+    }
+
+    /// 子がアプリケーションに戻る前に子で呼ぶ関数を返す
+    ///
+    /// システムコールの実行前に親で呼ばれます。子スレッドは親とフック実装を
+    /// 共有するため、子では`self`ではなく返した関数が呼ばれます（`clone`モジュール）。
+    /// 子プロセスでは`on_fork_child`の後に呼ばれます。`interests`に含めていなくても
+    /// 呼ばれます。
+    fn clone_child_fn(&mut self, flags: CloneFlags) -> Option<CloneChildFn> {
+        let _ = flags;
+        None
+    }
+
     // ========================================================================
     // ファイルI/O関連
     // ========================================================================
@@ -57,6 +155,65 @@ pub trait SyscallHooks: Send + Sync + 'static {
         default_lseek(fd, offset, whence)
     }
 
+    // ========================================================================
+    // 位置指定・ベクタI/O
+    // ========================================================================
+    //
+    // glibcのstdioやデータベースはread/writeではなくこれらを使うことが多い。
+    // まとめて扱う場合は`io::UnifiedIo`を使う。
+
+    /// pread64(2) - オフセットを指定して読み込み
+    fn hook_pread64(
+        &mut self,
+        fd: c_int,
+        buf: *mut c_void,
+        count: size_t,
+        offset: off_t,
+    ) -> ssize_t {
+        default_pread64(fd, buf, count, offset)
+    }
+
+    /// pwrite64(2) - オフセットを指定して書き込み
+    fn hook_pwrite64(
+        &mut self,
+        fd: c_int,
+        buf: *const c_void,
+        count: size_t,
+        offset: off_t,
+    ) -> ssize_t {
+        default_pwrite64(fd, buf, count, offset)
+    }
+
+    /// readv(2) - 複数のバッファに読み込み
+    fn hook_readv(&mut self, fd: c_int, iov: IoVecs) -> ssize_t {
+        default_readv(fd, iov)
+    }
+
+    /// writev(2) - 複数のバッファから書き込み
+    fn hook_writev(&mut self, fd: c_int, iov: IoVecs) -> ssize_t {
+        default_writev(fd, iov)
+    }
+
+    /// preadv(2) - オフセットを指定して複数のバッファに読み込み
+    fn hook_preadv(&mut self, fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+        default_preadv(fd, iov, offset)
+    }
+
+    /// pwritev(2) - オフセットを指定して複数のバッファから書き込み
+    fn hook_pwritev(&mut self, fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+        default_pwritev(fd, iov, offset)
+    }
+
+    /// preadv2(2) - `RWF_*`フラグ付きのpreadv（`offset`が-1なら現在位置）
+    fn hook_preadv2(&mut self, fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+        default_preadv2(fd, iov, offset, flags)
+    }
+
+    /// pwritev2(2) - `RWF_*`フラグ付きのpwritev（`offset`が-1なら現在位置）
+    fn hook_pwritev2(&mut self, fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+        default_pwritev2(fd, iov, offset, flags)
+    }
+
     /// openat(2) - ディレクトリファイルディスクリプタに相対的にファイルを開く
     fn hook_openat(
         &mut self,
@@ -83,6 +240,247 @@ pub trait SyscallHooks: Send + Sync + 'static {
         default_pipe(pipefd)
     }
 
+    // ========================================================================
+    // ファイル属性関連
+    // ========================================================================
+    //
+    // 結果を書き換える場合はデフォルト実装を呼んだ後に`statbuf.update()`を使う。
+
+    /// stat(2) - ファイルの状態を取得
+    fn hook_stat(&mut self, pathname: *const c_char, statbuf: StatBuf) -> c_int {
+        default_stat(pathname, statbuf)
+    }
+
+    /// fstat(2) - ファイルディスクリプタの状態を取得
+    fn hook_fstat(&mut self, fd: c_int, statbuf: StatBuf) -> c_int {
+        default_fstat(fd, statbuf)
+    }
+
+    /// lstat(2) - シンボリックリンク自体の状態を取得
+    fn hook_lstat(&mut self, pathname: *const c_char, statbuf: StatBuf) -> c_int {
+        default_lstat(pathname, statbuf)
+    }
+
+    /// newfstatat(2) - ディレクトリfdからの相対パスで状態を取得（glibcの`fstatat`）
+    fn hook_newfstatat(
+        &mut self,
+        dirfd: c_int,
+        pathname: *const c_char,
+        statbuf: StatBuf,
+        flags: c_int,
+    ) -> c_int {
+        default_newfstatat(dirfd, pathname, statbuf, flags)
+    }
+
+    /// statx(2) - 拡張されたファイルの状態を取得
+    ///
+    /// 書き換えたフィールドに対応するビットを`stx_mask`にも立ててください。
+    fn hook_statx(
+        &mut self,
+        dirfd: c_int,
+        pathname: *const c_char,
+        flags: c_int,
+        mask: c_uint,
+        statxbuf: StatxBuf,
+    ) -> c_int {
+        default_statx(dirfd, pathname, flags, mask, statxbuf)
+    }
+
+    // ========================================================================
+    // ディレクトリとパス操作
+    // ========================================================================
+    //
+    // ディレクトリの一覧を書き換える場合は`dirp.edit()`を使う（`dirent`モジュール参照）。
+
+    /// getdents64(2) - ディレクトリエントリを読み取る
+    fn hook_getdents64(&mut self, fd: c_int, dirp: DirEntries) -> ssize_t {
+        default_getdents64(fd, dirp)
+    }
+
+    /// mkdir(2) - ディレクトリを作成
+    fn hook_mkdir(&mut self, pathname: *const c_char, mode: mode_t) -> c_int {
+        default_mkdir(pathname, mode)
+    }
+
+    /// mkdirat(2) - ディレクトリfdからの相対パスでディレクトリを作成
+    fn hook_mkdirat(&mut self, dirfd: c_int, pathname: *const c_char, mode: mode_t) -> c_int {
+        default_mkdirat(dirfd, pathname, mode)
+    }
+
+    /// rmdir(2) - ディレクトリを削除
+    fn hook_rmdir(&mut self, pathname: *const c_char) -> c_int {
+        default_rmdir(pathname)
+    }
+
+    /// unlink(2) - ファイルを削除
+    fn hook_unlink(&mut self, pathname: *const c_char) -> c_int {
+        default_unlink(pathname)
+    }
+
+    /// unlinkat(2) - ディレクトリfdからの相対パスでファイルまたはディレクトリを削除
+    fn hook_unlinkat(&mut self, dirfd: c_int, pathname: *const c_char, flags: c_int) -> c_int {
+        default_unlinkat(dirfd, pathname, flags)
+    }
+
+    /// rename(2) - ファイルの名前を変更
+    fn hook_rename(&mut self, oldpath: *const c_char, newpath: *const c_char) -> c_int {
+        default_rename(oldpath, newpath)
+    }
+
+    /// renameat(2) - ディレクトリfdからの相対パスで名前を変更
+    fn hook_renameat(
+        &mut self,
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+    ) -> c_int {
+        default_renameat(olddirfd, oldpath, newdirfd, newpath)
+    }
+
+    /// renameat2(2) - フラグを指定して名前を変更
+    fn hook_renameat2(
+        &mut self,
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+        flags: c_uint,
+    ) -> c_int {
+        default_renameat2(olddirfd, oldpath, newdirfd, newpath, flags)
+    }
+
+    /// link(2) - ハードリンクを作成
+    fn hook_link(&mut self, oldpath: *const c_char, newpath: *const c_char) -> c_int {
+        default_link(oldpath, newpath)
+    }
+
+    /// linkat(2) - ディレクトリfdからの相対パスでハードリンクを作成
+    fn hook_linkat(
+        &mut self,
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+        flags: c_int,
+    ) -> c_int {
+        default_linkat(olddirfd, oldpath, newdirfd, newpath, flags)
+    }
+
+    /// symlink(2) - シンボリックリンクを作成
+    fn hook_symlink(&mut self, target: *const c_char, linkpath: *const c_char) -> c_int {
+        default_symlink(target, linkpath)
+    }
+
+    /// symlinkat(2) - ディレクトリfdからの相対パスでシンボリックリンクを作成
+    fn hook_symlinkat(
+        &mut self,
+        target: *const c_char,
+        newdirfd: c_int,
+        linkpath: *const c_char,
+    ) -> c_int {
+        default_symlinkat(target, newdirfd, linkpath)
+    }
+
+    /// readlink(2) - シンボリックリンクの内容を読み取る
+    fn hook_readlink(
+        &mut self,
+        pathname: *const c_char,
+        buf: *mut c_char,
+        bufsiz: size_t,
+    ) -> ssize_t {
+        default_readlink(pathname, buf, bufsiz)
+    }
+
+    /// readlinkat(2) - ディレクトリfdからの相対パスでシンボリックリンクを読み取る
+    fn hook_readlinkat(
+        &mut self,
+        dirfd: c_int,
+        pathname: *const c_char,
+        buf: *mut c_char,
+        bufsiz: size_t,
+    ) -> ssize_t {
+        default_readlinkat(dirfd, pathname, buf, bufsiz)
+    }
+
+    /// chdir(2) - 作業ディレクトリを変更
+    fn hook_chdir(&mut self, path: *const c_char) -> c_int {
+        default_chdir(path)
+    }
+
+    /// fchdir(2) - ディレクトリfdで作業ディレクトリを変更
+    fn hook_fchdir(&mut self, fd: c_int) -> c_int {
+        default_fchdir(fd)
+    }
+
+    /// getcwd(2) - 作業ディレクトリを取得
+    fn hook_getcwd(&mut self, buf: *mut c_char, size: size_t) -> ssize_t {
+        default_getcwd(buf, size)
+    }
+
+    /// truncate(2) - パスを指定してファイルを切り詰める
+    fn hook_truncate(&mut self, path: *const c_char, length: off_t) -> c_int {
+        default_truncate(path, length)
+    }
+
+    /// ftruncate(2) - ファイルディスクリプタのファイルを切り詰める
+    fn hook_ftruncate(&mut self, fd: c_int, length: off_t) -> c_int {
+        default_ftruncate(fd, length)
+    }
+
+    /// chmod(2) - パーミッションを変更
+    fn hook_chmod(&mut self, pathname: *const c_char, mode: mode_t) -> c_int {
+        default_chmod(pathname, mode)
+    }
+
+    /// fchmod(2) - ファイルディスクリプタのパーミッションを変更
+    fn hook_fchmod(&mut self, fd: c_int, mode: mode_t) -> c_int {
+        default_fchmod(fd, mode)
+    }
+
+    /// fchmodat(2) - ディレクトリfdからの相対パスでパーミッションを変更
+    fn hook_fchmodat(&mut self, dirfd: c_int, pathname: *const c_char, mode: mode_t) -> c_int {
+        default_fchmodat(dirfd, pathname, mode)
+    }
+
+    /// fchmodat2(2) - フラグを指定してパーミッションを変更
+    fn hook_fchmodat2(
+        &mut self,
+        dirfd: c_int,
+        pathname: *const c_char,
+        mode: mode_t,
+        flags: c_int,
+    ) -> c_int {
+        default_fchmodat2(dirfd, pathname, mode, flags)
+    }
+
+    /// chown(2) - 所有者を変更
+    fn hook_chown(&mut self, pathname: *const c_char, owner: uid_t, group: gid_t) -> c_int {
+        default_chown(pathname, owner, group)
+    }
+
+    /// fchown(2) - ファイルディスクリプタの所有者を変更
+    fn hook_fchown(&mut self, fd: c_int, owner: uid_t, group: gid_t) -> c_int {
+        default_fchown(fd, owner, group)
+    }
+
+    /// lchown(2) - シンボリックリンク自体の所有者を変更
+    fn hook_lchown(&mut self, pathname: *const c_char, owner: uid_t, group: gid_t) -> c_int {
+        default_lchown(pathname, owner, group)
+    }
+
+    /// fchownat(2) - ディレクトリfdからの相対パスで所有者を変更
+    fn hook_fchownat(
+        &mut self,
+        dirfd: c_int,
+        pathname: *const c_char,
+        owner: uid_t,
+        group: gid_t,
+        flags: c_int,
+    ) -> c_int {
+        default_fchownat(dirfd, pathname, owner, group, flags)
+    }
+
     // ========================================================================
     // メモリ管理関連
     // ========================================================================
@@ -134,6 +532,32 @@ pub trait SyscallHooks: Send + Sync + 'static {
         default_fork()
     }
 
+    /// vfork(2) - 親を止めて子プロセスを作成
+    ///
+    /// 子がフックのフレームを持つ親のスタックを壊さないよう、`CLONE_VM`を外した
+    /// `clone(CLONE_VFORK)`として実行されます。親は子の`execve`/終了まで止まりますが、
+    /// 子のメモリへの書き込みは親に見えません。
+    fn hook_vfork(&mut self) -> pid_t {
+        default_vfork()
+    }
+
+    /// clone(2) - スレッドまたは子プロセスを作成
+    fn hook_clone(
+        &mut self,
+        flags: CloneFlags,
+        stack: *mut c_void,
+        parent_tid: *mut pid_t,
+        child_tid: *mut pid_t,
+        tls: c_ulong,
+    ) -> pid_t {
+        default_clone(flags, stack, parent_tid, child_tid, tls)
+    }
+
+    /// clone3(2) - `struct clone_args`でスレッドまたは子プロセスを作成
+    fn hook_clone3(&mut self, args: CloneArgsPtr) -> pid_t {
+        default_clone3(args)
+    }
+
     /// execve(2) - プログラムを実行
     fn hook_execve(
         &mut self,
@@ -171,596 +595,4062 @@ pub trait SyscallHooks: Send + Sync + 'static {
     }
 
     // ========================================================================
-    // ネットワーク関連
+    // シグナル管理
     // ========================================================================
+    //
+    // デフォルト実装は`signal::virtual_signals`で仮想化したシグナルを
+    // アプリケーションから見た状態で扱う。
 
-    /// socket(2) - ソケットを作成
-    fn hook_socket(&mut self, domain: c_int, ty: c_int, protocol: c_int) -> c_int {
-        default_socket(domain, ty, protocol)
+    /// rt_sigaction(2) - シグナルハンドラを設定
+    fn hook_rt_sigaction(
+        &mut self,
+        signum: c_int,
+        act: SigactionPtr,
+        oldact: SigactionPtr,
+        sigsetsize: size_t,
+    ) -> c_int {
+        default_rt_sigaction(signum, act, oldact, sigsetsize)
     }
 
-    /// connect(2) - ソケットを接続
-    fn hook_connect(&mut self, sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
-        default_connect(sockfd, addr, addrlen)
+    /// rt_sigprocmask(2) - シグナルマスクを変更
+    fn hook_rt_sigprocmask(
+        &mut self,
+        how: c_int,
+        set: SigsetPtr,
+        oldset: SigsetPtr,
+        sigsetsize: size_t,
+    ) -> c_int {
+        default_rt_sigprocmask(how, set, oldset, sigsetsize)
     }
 
-    /// accept(2) - 接続を受け入れ
-    fn hook_accept(&mut self, sockfd: c_int, addr: *mut c_void, addrlen: *mut u32) -> c_int {
-        default_accept(sockfd, addr, addrlen)
+    /// sigaltstack(2) - シグナルスタックを設定
+    fn hook_sigaltstack(&mut self, ss: StackPtr, old_ss: StackPtr) -> c_int {
+        default_sigaltstack(ss, old_ss)
     }
 
-    /// bind(2) - ソケットにアドレスをバインド
-    fn hook_bind(&mut self, sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
-        default_bind(sockfd, addr, addrlen)
+    /// rt_sigsuspend(2) - マスクを置き換えてシグナルを待つ
+    ///
+    /// オーバーライドするとディスパッチャのロックを保持したまま待つため、
+    /// その間は他のスレッドのフックも止まります。
+    fn hook_rt_sigsuspend(&mut self, mask: SigsetPtr, sigsetsize: size_t) -> c_int {
+        default_rt_sigsuspend(mask, sigsetsize)
     }
 
-    /// listen(2) - ソケットで接続を待つ
-    fn hook_listen(&mut self, sockfd: c_int, backlog: c_int) -> c_int {
-        default_listen(sockfd, backlog)
+    /// signalfd4(2) - シグナルを受け取るfdを作成
+    fn hook_signalfd4(
+        &mut self,
+        fd: c_int,
+        mask: SigsetPtr,
+        sizemask: size_t,
+        flags: c_int,
+    ) -> c_int {
+        default_signalfd4(fd, mask, sizemask, flags)
     }
 
     // ========================================================================
-    // その他
+    // 時刻
     // ========================================================================
+    //
+    // 通常はvDSOで処理されるため、`ZPOLINE_VDSO=hook`を設定したときだけ届く。
 
-    /// ioctl(2) - デバイス制御
-    fn hook_ioctl(&mut self, fd: c_int, request: c_ulong, arg: *mut c_void) -> c_int {
-        default_ioctl(fd, request, arg)
+    /// clock_gettime(2) - 時計の現在時刻を取得
+    fn hook_clock_gettime(&mut self, clockid: libc::clockid_t, tp: TimespecPtr) -> c_int {
+        default_clock_gettime(clockid, tp)
     }
 
-    /// access(2) - ファイルアクセス権限をチェック
-    fn hook_access(&mut self, pathname: *const c_char, mode: c_int) -> c_int {
-        default_access(pathname, mode)
+    /// clock_getres(2) - 時計の分解能を取得
+    fn hook_clock_getres(&mut self, clockid: libc::clockid_t, res: TimespecPtr) -> c_int {
+        default_clock_getres(clockid, res)
     }
-}
 
-// ========================================================================
-// デフォルト実装を提供する独立した関数
-// これらの関数はユーザーのカスタムフック実装内で呼び出すことができます
-// ========================================================================
+    /// gettimeofday(2) - 現在時刻を取得
+    fn hook_gettimeofday(&mut self, tv: TimevalPtr, tz: *mut c_void) -> c_int {
+        default_gettimeofday(tv, tz)
+    }
 
-pub fn default_read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
-    unsafe {
-        let mut regs = SyscallRegs {
-            rax: 0, // SYS_read
-            rdi: fd as u64,
-            rsi: buf as u64,
-            rdx: count as u64,
-            r10: 0,
-            r8: 0,
-            r9: 0,
-        };
-        raw_syscall(&mut regs) as ssize_t
+    /// time(2) - 現在時刻を秒単位で取得
+    fn hook_time(&mut self, tloc: *mut libc::time_t) -> libc::time_t {
+        default_time(tloc)
     }
-}
 
-pub fn default_write(fd: c_int, buf: *const c_void, count: size_t) -> ssize_t {
-    unsafe {
-        let mut regs = SyscallRegs {
-            rax: 1, // SYS_write
-            rdi: fd as u64,
-            rsi: buf as u64,
-            rdx: count as u64,
-            r10: 0,
-            r8: 0,
-            r9: 0,
-        };
-        raw_syscall(&mut regs) as ssize_t
+    /// getcpu(2) - 実行中のCPUとNUMAノードを取得
+    fn hook_getcpu(&mut self, cpu: *mut c_uint, node: *mut c_uint, cache: *mut c_void) -> c_int {
+        default_getcpu(cpu, node, cache)
     }
-}
 
-pub fn default_open(pathname: *const c_char, flags: c_int, mode: c_uint) -> c_int {
-    unsafe {
-        let mut regs = SyscallRegs {
-            rax: 2, // SYS_open
-            rdi: pathname as u64,
-            rsi: flags as u64,
-            rdx: mode as u64,
-            r10: 0,
-            r8: 0,
-            r9: 0,
-        };
-        raw_syscall(&mut regs) as c_int
+    // ========================================================================
+    // 資格情報とプロセス属性
+    // ========================================================================
+    //
+    // rootやホスト名、リソース制限を偽装する場合は`identity`モジュールの型を使う。
+
+    /// getuid(2) - 実ユーザーIDを取得
+    fn hook_getuid(&mut self) -> uid_t {
+        default_getuid()
     }
-}
 
-pub fn default_close(fd: c_int) -> c_int {
-    unsafe {
-        let mut regs = SyscallRegs {
-            rax: 3, // SYS_close
-            rdi: fd as u64,
-            rsi: 0,
-            rdx: 0,
-            r10: 0,
-            r8: 0,
-            r9: 0,
-        };
-        raw_syscall(&mut regs) as c_int
+    /// geteuid(2) - 実効ユーザーIDを取得
+    fn hook_geteuid(&mut self) -> uid_t {
+        default_geteuid()
     }
-}
 
-pub fn default_lseek(fd: c_int, offset: off_t, whence: c_int) -> off_t {
-    unsafe {
-        let mut regs = SyscallRegs {
-            rax: 8, // SYS_lseek
-            rdi: fd as u64,
-            rsi: offset as u64,
-            rdx: whence as u64,
-            r10: 0,
-            r8: 0,
-            r9: 0,
-        };
-        raw_syscall(&mut regs) as off_t
+    /// getgid(2) - 実グループIDを取得
+    fn hook_getgid(&mut self) -> gid_t {
+        default_getgid()
     }
-}
 
-pub fn default_openat(
-    dirfd: c_int,
-    pathname: *const c_char,
-    flags: c_int,
-    mode: c_uint,
-) -> c_int {
+    /// getegid(2) - 実効グループIDを取得
+    fn hook_getegid(&mut self) -> gid_t {
+        default_getegid()
+    }
+
+    /// getresuid(2) - 実・実効・保存ユーザーIDを取得
+    fn hook_getresuid(&mut self, ruid: *mut uid_t, euid: *mut uid_t, suid: *mut uid_t) -> c_int {
+        default_getresuid(ruid, euid, suid)
+    }
+
+    /// getresgid(2) - 実・実効・保存グループIDを取得
+    fn hook_getresgid(&mut self, rgid: *mut gid_t, egid: *mut gid_t, sgid: *mut gid_t) -> c_int {
+        default_getresgid(rgid, egid, sgid)
+    }
+
+    /// getgroups(2) - 補助グループを取得
+    fn hook_getgroups(&mut self, size: c_int, list: *mut gid_t) -> c_int {
+        default_getgroups(size, list)
+    }
+
+    /// setuid(2) - ユーザーIDを設定
+    fn hook_setuid(&mut self, uid: uid_t) -> c_int {
+        default_setuid(uid)
+    }
+
+    /// setgid(2) - グループIDを設定
+    fn hook_setgid(&mut self, gid: gid_t) -> c_int {
+        default_setgid(gid)
+    }
+
+    /// setresuid(2) - 実・実効・保存ユーザーIDを設定
+    fn hook_setresuid(&mut self, ruid: uid_t, euid: uid_t, suid: uid_t) -> c_int {
+        default_setresuid(ruid, euid, suid)
+    }
+
+    /// setresgid(2) - 実・実効・保存グループIDを設定
+    fn hook_setresgid(&mut self, rgid: gid_t, egid: gid_t, sgid: gid_t) -> c_int {
+        default_setresgid(rgid, egid, sgid)
+    }
+
+    /// setgroups(2) - 補助グループを設定
+    fn hook_setgroups(&mut self, size: size_t, list: *const gid_t) -> c_int {
+        default_setgroups(size, list)
+    }
+
+    /// capget(2) - ケーパビリティを取得
+    fn hook_capget(&mut self, hdr: CapHeaderPtr, data: CapDataPtr) -> c_int {
+        default_capget(hdr, data)
+    }
+
+    /// capset(2) - ケーパビリティを設定
+    fn hook_capset(&mut self, hdr: CapHeaderPtr, data: CapDataPtr) -> c_int {
+        default_capset(hdr, data)
+    }
+
+    /// prctl(2) - プロセスの属性を操作
+    fn hook_prctl(
+        &mut self,
+        option: c_int,
+        arg2: c_ulong,
+        arg3: c_ulong,
+        arg4: c_ulong,
+        arg5: c_ulong,
+    ) -> c_int {
+        default_prctl(option, arg2, arg3, arg4, arg5)
+    }
+
+    /// arch_prctl(2) - アーキテクチャ固有の状態を操作
+    fn hook_arch_prctl(&mut self, code: c_int, addr: c_ulong) -> c_int {
+        default_arch_prctl(code, addr)
+    }
+
+    /// getrlimit(2) - リソース制限を取得
+    fn hook_getrlimit(&mut self, resource: c_uint, rlim: RlimitPtr) -> c_int {
+        default_getrlimit(resource, rlim)
+    }
+
+    /// setrlimit(2) - リソース制限を設定
+    fn hook_setrlimit(&mut self, resource: c_uint, rlim: RlimitPtr) -> c_int {
+        default_setrlimit(resource, rlim)
+    }
+
+    /// prlimit64(2) - プロセスのリソース制限を取得・設定
+    fn hook_prlimit64(
+        &mut self,
+        pid: pid_t,
+        resource: c_uint,
+        new_limit: RlimitPtr,
+        old_limit: RlimitPtr,
+    ) -> c_int {
+        default_prlimit64(pid, resource, new_limit, old_limit)
+    }
+
+    /// uname(2) - システムの名前を取得
+    fn hook_uname(&mut self, buf: UtsnamePtr) -> c_int {
+        default_uname(buf)
+    }
+
+    /// sched_yield(2) - CPUを明け渡す
+    fn hook_sched_yield(&mut self) -> c_int {
+        default_sched_yield()
+    }
+
+    /// sched_setaffinity(2) - CPUアフィニティを設定
+    fn hook_sched_setaffinity(
+        &mut self,
+        pid: pid_t,
+        cpusetsize: size_t,
+        mask: *const c_ulong,
+    ) -> c_int {
+        default_sched_setaffinity(pid, cpusetsize, mask)
+    }
+
+    /// sched_getaffinity(2) - CPUアフィニティを取得
+    fn hook_sched_getaffinity(
+        &mut self,
+        pid: pid_t,
+        cpusetsize: size_t,
+        mask: *mut c_ulong,
+    ) -> c_int {
+        default_sched_getaffinity(pid, cpusetsize, mask)
+    }
+
+    /// sched_setscheduler(2) - スケジューリングポリシーを設定
+    fn hook_sched_setscheduler(
+        &mut self,
+        pid: pid_t,
+        policy: c_int,
+        param: *const libc::sched_param,
+    ) -> c_int {
+        default_sched_setscheduler(pid, policy, param)
+    }
+
+    /// sched_getscheduler(2) - スケジューリングポリシーを取得
+    fn hook_sched_getscheduler(&mut self, pid: pid_t) -> c_int {
+        default_sched_getscheduler(pid)
+    }
+
+    /// sched_setparam(2) - スケジューリングパラメータを設定
+    fn hook_sched_setparam(&mut self, pid: pid_t, param: *const libc::sched_param) -> c_int {
+        default_sched_setparam(pid, param)
+    }
+
+    /// sched_getparam(2) - スケジューリングパラメータを取得
+    fn hook_sched_getparam(&mut self, pid: pid_t, param: *mut libc::sched_param) -> c_int {
+        default_sched_getparam(pid, param)
+    }
+
+    /// sched_get_priority_max(2) - 優先度の最大値を取得
+    fn hook_sched_get_priority_max(&mut self, policy: c_int) -> c_int {
+        default_sched_get_priority_max(policy)
+    }
+
+    /// sched_get_priority_min(2) - 優先度の最小値を取得
+    fn hook_sched_get_priority_min(&mut self, policy: c_int) -> c_int {
+        default_sched_get_priority_min(policy)
+    }
+
+    // ========================================================================
+    // ネットワーク関連
+    // ========================================================================
+
+    /// socket(2) - ソケットを作成
+    fn hook_socket(&mut self, domain: c_int, ty: c_int, protocol: c_int) -> c_int {
+        default_socket(domain, ty, protocol)
+    }
+
+    /// connect(2) - ソケットを接続
+    fn hook_connect(&mut self, sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+        default_connect(sockfd, addr, addrlen)
+    }
+
+    /// accept(2) - 接続を受け入れ
+    fn hook_accept(&mut self, sockfd: c_int, addr: *mut c_void, addrlen: *mut u32) -> c_int {
+        default_accept(sockfd, addr, addrlen)
+    }
+
+    /// bind(2) - ソケットにアドレスをバインド
+    fn hook_bind(&mut self, sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+        default_bind(sockfd, addr, addrlen)
+    }
+
+    /// listen(2) - ソケットで接続を待つ
+    fn hook_listen(&mut self, sockfd: c_int, backlog: c_int) -> c_int {
+        default_listen(sockfd, backlog)
+    }
+
+    /// accept4(2) - フラグを指定して接続を受け入れ
+    fn hook_accept4(&mut self, sockfd: c_int, addr: SockAddrOut, flags: c_int) -> c_int {
+        default_accept4(sockfd, addr, flags)
+    }
+
+    /// sendto(2) - メッセージを送信
+    fn hook_sendto(
+        &mut self,
+        sockfd: c_int,
+        buf: *const c_void,
+        len: size_t,
+        flags: c_int,
+        dest_addr: SockAddrArg,
+    ) -> ssize_t {
+        default_sendto(sockfd, buf, len, flags, dest_addr)
+    }
+
+    /// recvfrom(2) - メッセージを受信
+    fn hook_recvfrom(
+        &mut self,
+        sockfd: c_int,
+        buf: *mut c_void,
+        len: size_t,
+        flags: c_int,
+        src_addr: SockAddrOut,
+    ) -> ssize_t {
+        default_recvfrom(sockfd, buf, len, flags, src_addr)
+    }
+
+    /// sendmsg(2) - msghdrでメッセージを送信
+    fn hook_sendmsg(&mut self, sockfd: c_int, msg: MsgHdr, flags: c_int) -> ssize_t {
+        default_sendmsg(sockfd, msg, flags)
+    }
+
+    /// recvmsg(2) - msghdrでメッセージを受信
+    fn hook_recvmsg(&mut self, sockfd: c_int, msg: MsgHdr, flags: c_int) -> ssize_t {
+        default_recvmsg(sockfd, msg, flags)
+    }
+
+    /// sendmmsg(2) - 複数のメッセージを送信
+    fn hook_sendmmsg(&mut self, sockfd: c_int, msgvec: MMsgHdrs, flags: c_int) -> c_int {
+        default_sendmmsg(sockfd, msgvec, flags)
+    }
+
+    /// recvmmsg(2) - 複数のメッセージを受信
+    fn hook_recvmmsg(
+        &mut self,
+        sockfd: c_int,
+        msgvec: MMsgHdrs,
+        flags: c_int,
+        timeout: *mut libc::timespec,
+    ) -> c_int {
+        default_recvmmsg(sockfd, msgvec, flags, timeout)
+    }
+
+    /// shutdown(2) - 接続の送受信を停止
+    fn hook_shutdown(&mut self, sockfd: c_int, how: c_int) -> c_int {
+        default_shutdown(sockfd, how)
+    }
+
+    /// getsockopt(2) - ソケットオプションを取得
+    fn hook_getsockopt(
+        &mut self,
+        sockfd: c_int,
+        level: c_int,
+        optname: c_int,
+        optval: *mut c_void,
+        optlen: *mut u32,
+    ) -> c_int {
+        default_getsockopt(sockfd, level, optname, optval, optlen)
+    }
+
+    /// setsockopt(2) - ソケットオプションを設定
+    fn hook_setsockopt(
+        &mut self,
+        sockfd: c_int,
+        level: c_int,
+        optname: c_int,
+        optval: *const c_void,
+        optlen: u32,
+    ) -> c_int {
+        default_setsockopt(sockfd, level, optname, optval, optlen)
+    }
+
+    /// getsockname(2) - ソケットのローカルアドレスを取得
+    fn hook_getsockname(&mut self, sockfd: c_int, addr: SockAddrOut) -> c_int {
+        default_getsockname(sockfd, addr)
+    }
+
+    /// getpeername(2) - 接続先のアドレスを取得
+    fn hook_getpeername(&mut self, sockfd: c_int, addr: SockAddrOut) -> c_int {
+        default_getpeername(sockfd, addr)
+    }
+
+    // ========================================================================
+    // イベント多重化
+    // ========================================================================
+
+    /// poll(2) - fdの準備ができるのを待つ
+    fn hook_poll(&mut self, fds: PollFds, timeout: c_int) -> c_int {
+        default_poll(fds, timeout)
+    }
+
+    /// ppoll(2) - シグナルマスクを指定してpoll
+    fn hook_ppoll(
+        &mut self,
+        fds: PollFds,
+        tmo_p: *const libc::timespec,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> c_int {
+        default_ppoll(fds, tmo_p, sigmask, sigsetsize)
+    }
+
+    /// select(2) - 複数のfdの準備ができるのを待つ
+    fn hook_select(
+        &mut self,
+        nfds: c_int,
+        readfds: FdSet,
+        writefds: FdSet,
+        exceptfds: FdSet,
+        timeout: *mut libc::timeval,
+    ) -> c_int {
+        default_select(nfds, readfds, writefds, exceptfds, timeout)
+    }
+
+    /// pselect6(2) - `sigmask`は`{ const sigset_t *ss; size_t ss_len; }`へのポインタ
+    fn hook_pselect6(
+        &mut self,
+        nfds: c_int,
+        readfds: FdSet,
+        writefds: FdSet,
+        exceptfds: FdSet,
+        timeout: *mut libc::timespec,
+        sigmask: *const c_void,
+    ) -> c_int {
+        default_pselect6(nfds, readfds, writefds, exceptfds, timeout, sigmask)
+    }
+
+    /// epoll_create1(2) - epollインスタンスを作成
+    fn hook_epoll_create1(&mut self, flags: c_int) -> c_int {
+        default_epoll_create1(flags)
+    }
+
+    /// epoll_ctl(2) - 監視するfdを登録・変更・削除
+    fn hook_epoll_ctl(&mut self, epfd: c_int, op: c_int, fd: c_int, event: EpollEventPtr) -> c_int {
+        default_epoll_ctl(epfd, op, fd, event)
+    }
+
+    /// epoll_wait(2) - イベントを待つ
+    fn hook_epoll_wait(&mut self, epfd: c_int, events: EpollEvents, timeout: c_int) -> c_int {
+        default_epoll_wait(epfd, events, timeout)
+    }
+
+    /// epoll_pwait(2) - シグナルマスクを指定してepoll_wait
+    fn hook_epoll_pwait(
+        &mut self,
+        epfd: c_int,
+        events: EpollEvents,
+        timeout: c_int,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> c_int {
+        default_epoll_pwait(epfd, events, timeout, sigmask, sigsetsize)
+    }
+
+    /// epoll_pwait2(2) - ナノ秒単位のタイムアウトでepoll_pwait（NULLは無期限）
+    fn hook_epoll_pwait2(
+        &mut self,
+        epfd: c_int,
+        events: EpollEvents,
+        timeout: *const libc::timespec,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> c_int {
+        default_epoll_pwait2(epfd, events, timeout, sigmask, sigsetsize)
+    }
+
+    // ========================================================================
+    // io_uring
+    // ========================================================================
+
+    /// io_uring_setup(2) - io_uringインスタンスを作成
+    fn hook_io_uring_setup(&mut self, entries: u32, params: IoUringParamsPtr) -> c_int {
+        default_io_uring_setup(entries, params)
+    }
+
+    /// io_uring_enter(2) - SQEの送信と完了の待機
+    fn hook_io_uring_enter(
+        &mut self,
+        fd: c_int,
+        to_submit: u32,
+        min_complete: u32,
+        flags: u32,
+        arg: *const c_void,
+        argsz: size_t,
+    ) -> c_int {
+        default_io_uring_enter(fd, to_submit, min_complete, flags, arg, argsz)
+    }
+
+    /// io_uring_register(2) - バッファやファイルを登録
+    fn hook_io_uring_register(
+        &mut self,
+        fd: c_int,
+        opcode: c_uint,
+        arg: *mut c_void,
+        nr_args: c_uint,
+    ) -> c_int {
+        default_io_uring_register(fd, opcode, arg, nr_args)
+    }
+
+    // ========================================================================
+    // その他
+    // ========================================================================
+
+    /// ioctl(2) - デバイス制御
+    fn hook_ioctl(&mut self, fd: c_int, request: c_ulong, arg: *mut c_void) -> c_int {
+        default_ioctl(fd, request, arg)
+    }
+
+    /// access(2) - ファイルアクセス権限をチェック
+    fn hook_access(&mut self, pathname: *const c_char, mode: c_int) -> c_int {
+        default_access(pathname, mode)
+    }
+}
+
+// ========================================================================
+// デフォルト実装を提供する独立した関数
+// これらの関数はユーザーのカスタムフック実装内で呼び出すことができます
+// ========================================================================
+
+pub fn default_read(fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 257, // SYS_openat
-            rdi: dirfd as u64,
-            rsi: pathname as u64,
-            rdx: flags as u64,
-            r10: mode as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::read.nr(),
+            rdi: fd as u64,
+            rsi: buf as u64,
+            rdx: count as u64,
+            r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-pub fn default_dup(oldfd: c_int) -> c_int {
+pub fn default_write(fd: c_int, buf: *const c_void, count: size_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 32, // SYS_dup
-            rdi: oldfd as u64,
-            rsi: 0,
-            rdx: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::write.nr(),
+            rdi: fd as u64,
+            rsi: buf as u64,
+            rdx: count as u64,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-pub fn default_dup2(oldfd: c_int, newfd: c_int) -> c_int {
+pub fn default_open(pathname: *const c_char, flags: c_int, mode: c_uint) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 33, // SYS_dup2
-            rdi: oldfd as u64,
-            rsi: newfd as u64,
-            rdx: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::open.nr(),
+            rdi: pathname as u64,
+            rsi: flags as u64,
+            rdx: mode as u64,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_pipe(pipefd: *mut c_int) -> c_int {
+pub fn default_close(fd: c_int) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 22, // SYS_pipe
-            rdi: pipefd as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::close.nr(),
+            rdi: fd as u64,
             rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_mmap(
-    addr: *mut c_void,
-    length: size_t,
-    prot: c_int,
-    flags: c_int,
-    fd: c_int,
-    offset: off_t,
-) -> *mut c_void {
+pub fn default_lseek(fd: c_int, offset: off_t, whence: c_int) -> off_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 9, // SYS_mmap
-            rdi: addr as u64,
-            rsi: length as u64,
-            rdx: prot as u64,
-            r10: flags as u64,
-            r8: fd as u64,
-            r9: offset as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::lseek.nr(),
+            rdi: fd as u64,
+            rsi: offset as u64,
+            rdx: whence as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
         };
-        raw_syscall(&mut regs) as *mut c_void
+        raw_syscall(&regs) as off_t
     }
 }
 
-pub fn default_munmap(addr: *mut c_void, length: size_t) -> c_int {
+pub fn default_pread64(fd: c_int, buf: *mut c_void, count: size_t, offset: off_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 11, // SYS_munmap
-            rdi: addr as u64,
-            rsi: length as u64,
-            rdx: 0,
-            r10: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::pread64.nr(),
+            rdi: fd as u64,
+            rsi: buf as u64,
+            rdx: count as u64,
+            r10: offset as u64,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-pub fn default_mprotect(addr: *mut c_void, len: size_t, prot: c_int) -> c_int {
+pub fn default_pwrite64(fd: c_int, buf: *const c_void, count: size_t, offset: off_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 10, // SYS_mprotect
-            rdi: addr as u64,
-            rsi: len as u64,
-            rdx: prot as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::pwrite64.nr(),
+            rdi: fd as u64,
+            rsi: buf as u64,
+            rdx: count as u64,
+            r10: offset as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_readv(fd: c_int, iov: IoVecs) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::readv.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-pub fn default_brk(addr: *mut c_void) -> c_int {
+pub fn default_writev(fd: c_int, iov: IoVecs) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 12, // SYS_brk
-            rdi: addr as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::writev.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_preadv(fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::preadv.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
+            r10: offset as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_pwritev(fd: c_int, iov: IoVecs, offset: off_t) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::pwritev.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
+            r10: offset as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_preadv2(fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::preadv2.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
+            r10: offset as u64,
+            r8: 0,
+            r9: flags as u64,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_pwritev2(fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::pwritev2.nr(),
+            rdi: fd as u64,
+            rsi: iov.as_ptr() as u64,
+            rdx: iov.len() as u64,
+            r10: offset as u64,
+            r8: 0,
+            r9: flags as u64,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_openat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    flags: c_int,
+    mode: c_uint,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::openat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: flags as u64,
+            r10: mode as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_dup(oldfd: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::dup.nr(),
+            rdi: oldfd as u64,
             rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_getpid() -> pid_t {
+pub fn default_dup2(oldfd: c_int, newfd: c_int) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 39, // SYS_getpid
-            rdi: 0,
-            rsi: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::dup2.nr(),
+            rdi: oldfd as u64,
+            rsi: newfd as u64,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as pid_t
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_gettid() -> pid_t {
+pub fn default_pipe(pipefd: *mut c_int) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 186, // SYS_gettid
-            rdi: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::pipe.nr(),
+            rdi: pipefd as u64,
             rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as pid_t
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_fork() -> pid_t {
+pub fn default_stat(pathname: *const c_char, statbuf: StatBuf) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 57, // SYS_fork
-            rdi: 0,
-            rsi: 0,
+        let regs = SyscallRegs {
+            rax: Sysno::stat.nr(),
+            rdi: pathname as u64,
+            rsi: statbuf.as_ptr() as u64,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as pid_t
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_execve(
+pub fn default_fstat(fd: c_int, statbuf: StatBuf) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fstat.nr(),
+            rdi: fd as u64,
+            rsi: statbuf.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_lstat(pathname: *const c_char, statbuf: StatBuf) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::lstat.nr(),
+            rdi: pathname as u64,
+            rsi: statbuf.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_newfstatat(
+    dirfd: c_int,
     pathname: *const c_char,
-    argv: *const *const c_char,
-    envp: *const *const c_char,
+    statbuf: StatBuf,
+    flags: c_int,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::newfstatat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: statbuf.as_ptr() as u64,
+            r10: flags as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_statx(
+    dirfd: c_int,
+    pathname: *const c_char,
+    flags: c_int,
+    mask: c_uint,
+    statxbuf: StatxBuf,
 ) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 59, // SYS_execve
+        let regs = SyscallRegs {
+            rax: Sysno::statx.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: flags as u64,
+            r10: mask as u64,
+            r8: statxbuf.as_ptr() as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getdents64(fd: c_int, dirp: DirEntries) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getdents64.nr(),
+            rdi: fd as u64,
+            rsi: dirp.as_ptr() as u64,
+            rdx: dirp.capacity() as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_mkdir(pathname: *const c_char, mode: mode_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::mkdir.nr(),
             rdi: pathname as u64,
-            rsi: argv as u64,
-            rdx: envp as u64,
+            rsi: mode as u64,
+            rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_exit(status: c_int) -> ! {
+pub fn default_mkdirat(dirfd: c_int, pathname: *const c_char, mode: mode_t) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 60, // SYS_exit
-            rdi: status as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::mkdirat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: mode as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_rmdir(pathname: *const c_char) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::rmdir.nr(),
+            rdi: pathname as u64,
             rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs);
-        core::hint::unreachable_unchecked()
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_exit_group(status: c_int) -> ! {
+pub fn default_unlink(pathname: *const c_char) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 231, // SYS_exit_group
-            rdi: status as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::unlink.nr(),
+            rdi: pathname as u64,
             rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs);
-        core::hint::unreachable_unchecked()
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_wait4(
-    pid: pid_t,
-    wstatus: *mut c_int,
-    options: c_int,
-    rusage: *mut c_void,
-) -> pid_t {
+pub fn default_unlinkat(dirfd: c_int, pathname: *const c_char, flags: c_int) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 61, // SYS_wait4
-            rdi: pid as u64,
-            rsi: wstatus as u64,
-            rdx: options as u64,
-            r10: rusage as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::unlinkat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: flags as u64,
+            r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as pid_t
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_kill(pid: pid_t, sig: c_int) -> c_int {
+pub fn default_rename(oldpath: *const c_char, newpath: *const c_char) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 62, // SYS_kill
-            rdi: pid as u64,
-            rsi: sig as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::rename.nr(),
+            rdi: oldpath as u64,
+            rsi: newpath as u64,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int {
+pub fn default_renameat(
+    olddirfd: c_int,
+    oldpath: *const c_char,
+    newdirfd: c_int,
+    newpath: *const c_char,
+) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 41, // SYS_socket
-            rdi: domain as u64,
-            rsi: ty as u64,
-            rdx: protocol as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::renameat.nr(),
+            rdi: olddirfd as u64,
+            rsi: oldpath as u64,
+            rdx: newdirfd as u64,
+            r10: newpath as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_renameat2(
+    olddirfd: c_int,
+    oldpath: *const c_char,
+    newdirfd: c_int,
+    newpath: *const c_char,
+    flags: c_uint,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::renameat2.nr(),
+            rdi: olddirfd as u64,
+            rsi: oldpath as u64,
+            rdx: newdirfd as u64,
+            r10: newpath as u64,
+            r8: flags as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_link(oldpath: *const c_char, newpath: *const c_char) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::link.nr(),
+            rdi: oldpath as u64,
+            rsi: newpath as u64,
+            rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_connect(sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+pub fn default_linkat(
+    olddirfd: c_int,
+    oldpath: *const c_char,
+    newdirfd: c_int,
+    newpath: *const c_char,
+    flags: c_int,
+) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 42, // SYS_connect
-            rdi: sockfd as u64,
-            rsi: addr as u64,
-            rdx: addrlen as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::linkat.nr(),
+            rdi: olddirfd as u64,
+            rsi: oldpath as u64,
+            rdx: newdirfd as u64,
+            r10: newpath as u64,
+            r8: flags as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::symlink.nr(),
+            rdi: target as u64,
+            rsi: linkpath as u64,
+            rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_accept(sockfd: c_int, addr: *mut c_void, addrlen: *mut u32) -> c_int {
+pub fn default_symlinkat(target: *const c_char, newdirfd: c_int, linkpath: *const c_char) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 43, // SYS_accept
-            rdi: sockfd as u64,
-            rsi: addr as u64,
-            rdx: addrlen as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::symlinkat.nr(),
+            rdi: target as u64,
+            rsi: newdirfd as u64,
+            rdx: linkpath as u64,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_bind(sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+pub fn default_readlink(pathname: *const c_char, buf: *mut c_char, bufsiz: size_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 49, // SYS_bind
-            rdi: sockfd as u64,
-            rsi: addr as u64,
-            rdx: addrlen as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::readlink.nr(),
+            rdi: pathname as u64,
+            rsi: buf as u64,
+            rdx: bufsiz as u64,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-pub fn default_listen(sockfd: c_int, backlog: c_int) -> c_int {
+pub fn default_readlinkat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    buf: *mut c_char,
+    bufsiz: size_t,
+) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 50, // SYS_listen
-            rdi: sockfd as u64,
-            rsi: backlog as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::readlinkat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: buf as u64,
+            r10: bufsiz as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_chdir(path: *const c_char) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::chdir.nr(),
+            rdi: path as u64,
+            rsi: 0,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_ioctl(fd: c_int, request: c_ulong, arg: *mut c_void) -> c_int {
+pub fn default_fchdir(fd: c_int) -> c_int {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 16, // SYS_ioctl
+        let regs = SyscallRegs {
+            rax: Sysno::fchdir.nr(),
             rdi: fd as u64,
-            rsi: request as u64,
-            rdx: arg as u64,
+            rsi: 0,
+            rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as c_int
     }
 }
 
-pub fn default_access(pathname: *const c_char, mode: c_int) -> c_int {
+pub fn default_getcwd(buf: *mut c_char, size: size_t) -> ssize_t {
     unsafe {
-        let mut regs = SyscallRegs {
-            rax: 21, // SYS_access
-            rdi: pathname as u64,
-            rsi: mode as u64,
+        let regs = SyscallRegs {
+            rax: Sysno::getcwd.nr(),
+            rdi: buf as u64,
+            rsi: size as u64,
             rdx: 0,
             r10: 0,
             r8: 0,
             r9: 0,
         };
-        raw_syscall(&mut regs) as c_int
+        raw_syscall(&regs) as ssize_t
     }
 }
 
-/// SyscallHooksを実装した型を登録するための内部処理
-///
-/// これはzpoline内部で使用されます。通常、ユーザーは`register_syscall_hooks`を
-/// 使用してフックを登録します。
-pub(crate) fn dispatch_syscall_hooks(
+pub fn default_truncate(path: *const c_char, length: off_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::truncate.nr(),
+            rdi: path as u64,
+            rsi: length as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_ftruncate(fd: c_int, length: off_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::ftruncate.nr(),
+            rdi: fd as u64,
+            rsi: length as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_chmod(pathname: *const c_char, mode: mode_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::chmod.nr(),
+            rdi: pathname as u64,
+            rsi: mode as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_fchmod(fd: c_int, mode: mode_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fchmod.nr(),
+            rdi: fd as u64,
+            rsi: mode as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_fchmodat(dirfd: c_int, pathname: *const c_char, mode: mode_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fchmodat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: mode as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_fchmodat2(
+    dirfd: c_int,
+    pathname: *const c_char,
+    mode: mode_t,
+    flags: c_int,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fchmodat2.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: mode as u64,
+            r10: flags as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_chown(pathname: *const c_char, owner: uid_t, group: gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::chown.nr(),
+            rdi: pathname as u64,
+            rsi: owner as u64,
+            rdx: group as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_fchown(fd: c_int, owner: uid_t, group: gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fchown.nr(),
+            rdi: fd as u64,
+            rsi: owner as u64,
+            rdx: group as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_lchown(pathname: *const c_char, owner: uid_t, group: gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::lchown.nr(),
+            rdi: pathname as u64,
+            rsi: owner as u64,
+            rdx: group as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_fchownat(
+    dirfd: c_int,
+    pathname: *const c_char,
+    owner: uid_t,
+    group: gid_t,
+    flags: c_int,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fchownat.nr(),
+            rdi: dirfd as u64,
+            rsi: pathname as u64,
+            rdx: owner as u64,
+            r10: group as u64,
+            r8: flags as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_mmap(
+    addr: *mut c_void,
+    length: size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    offset: off_t,
+) -> *mut c_void {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::mmap.nr(),
+            rdi: addr as u64,
+            rsi: length as u64,
+            rdx: prot as u64,
+            r10: flags as u64,
+            r8: fd as u64,
+            r9: offset as u64,
+        };
+        raw_syscall(&regs) as *mut c_void
+    }
+}
+
+pub fn default_munmap(addr: *mut c_void, length: size_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::munmap.nr(),
+            rdi: addr as u64,
+            rsi: length as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_mprotect(addr: *mut c_void, len: size_t, prot: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::mprotect.nr(),
+            rdi: addr as u64,
+            rsi: len as u64,
+            rdx: prot as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_brk(addr: *mut c_void) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::brk.nr(),
+            rdi: addr as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getpid() -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getpid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_gettid() -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::gettid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_vfork() -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::vfork.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_clone(
+    flags: CloneFlags,
+    stack: *mut c_void,
+    parent_tid: *mut pid_t,
+    child_tid: *mut pid_t,
+    tls: c_ulong,
+) -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::clone.nr(),
+            rdi: flags.bits(),
+            rsi: stack as u64,
+            rdx: parent_tid as u64,
+            r10: child_tid as u64,
+            r8: tls,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_clone3(args: CloneArgsPtr) -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::clone3.nr(),
+            rdi: args.as_ptr() as u64,
+            rsi: args.size() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_fork() -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::fork.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_execve(
+    pathname: *const c_char,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::execve.nr(),
+            rdi: pathname as u64,
+            rsi: argv as u64,
+            rdx: envp as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_exit(status: c_int) -> ! {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::exit.nr(),
+            rdi: status as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs);
+        core::hint::unreachable_unchecked()
+    }
+}
+
+pub fn default_exit_group(status: c_int) -> ! {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::exit_group.nr(),
+            rdi: status as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs);
+        core::hint::unreachable_unchecked()
+    }
+}
+
+pub fn default_wait4(
+    pid: pid_t,
+    wstatus: *mut c_int,
+    options: c_int,
+    rusage: *mut c_void,
+) -> pid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::wait4.nr(),
+            rdi: pid as u64,
+            rsi: wstatus as u64,
+            rdx: options as u64,
+            r10: rusage as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as pid_t
+    }
+}
+
+pub fn default_kill(pid: pid_t, sig: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::kill.nr(),
+            rdi: pid as u64,
+            rsi: sig as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_clock_gettime(clockid: libc::clockid_t, tp: TimespecPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::clock_gettime.nr(),
+            rdi: clockid as u64,
+            rsi: tp.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_clock_getres(clockid: libc::clockid_t, res: TimespecPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::clock_getres.nr(),
+            rdi: clockid as u64,
+            rsi: res.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_gettimeofday(tv: TimevalPtr, tz: *mut c_void) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::gettimeofday.nr(),
+            rdi: tv.as_ptr() as u64,
+            rsi: tz as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_time(tloc: *mut libc::time_t) -> libc::time_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::time.nr(),
+            rdi: tloc as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as libc::time_t
+    }
+}
+
+pub fn default_getcpu(cpu: *mut c_uint, node: *mut c_uint, cache: *mut c_void) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getcpu.nr(),
+            rdi: cpu as u64,
+            rsi: node as u64,
+            rdx: cache as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getuid() -> uid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getuid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as uid_t
+    }
+}
+
+pub fn default_geteuid() -> uid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::geteuid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as uid_t
+    }
+}
+
+pub fn default_getgid() -> gid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getgid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as gid_t
+    }
+}
+
+pub fn default_getegid() -> gid_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getegid.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as gid_t
+    }
+}
+
+pub fn default_getresuid(ruid: *mut uid_t, euid: *mut uid_t, suid: *mut uid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getresuid.nr(),
+            rdi: ruid as u64,
+            rsi: euid as u64,
+            rdx: suid as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getresgid(rgid: *mut gid_t, egid: *mut gid_t, sgid: *mut gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getresgid.nr(),
+            rdi: rgid as u64,
+            rsi: egid as u64,
+            rdx: sgid as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getgroups(size: c_int, list: *mut gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getgroups.nr(),
+            rdi: size as u64,
+            rsi: list as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setuid(uid: uid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setuid.nr(),
+            rdi: uid as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setgid(gid: gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setgid.nr(),
+            rdi: gid as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setresuid(ruid: uid_t, euid: uid_t, suid: uid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setresuid.nr(),
+            rdi: ruid as u64,
+            rsi: euid as u64,
+            rdx: suid as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setresgid(rgid: gid_t, egid: gid_t, sgid: gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setresgid.nr(),
+            rdi: rgid as u64,
+            rsi: egid as u64,
+            rdx: sgid as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setgroups(size: size_t, list: *const gid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setgroups.nr(),
+            rdi: size as u64,
+            rsi: list as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_capget(hdr: CapHeaderPtr, data: CapDataPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::capget.nr(),
+            rdi: hdr.as_ptr() as u64,
+            rsi: data.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_capset(hdr: CapHeaderPtr, data: CapDataPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::capset.nr(),
+            rdi: hdr.as_ptr() as u64,
+            rsi: data.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_prctl(
+    option: c_int,
+    arg2: c_ulong,
+    arg3: c_ulong,
+    arg4: c_ulong,
+    arg5: c_ulong,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::prctl.nr(),
+            rdi: option as u64,
+            rsi: arg2,
+            rdx: arg3,
+            r10: arg4,
+            r8: arg5,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_arch_prctl(code: c_int, addr: c_ulong) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::arch_prctl.nr(),
+            rdi: code as u64,
+            rsi: addr,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getrlimit(resource: c_uint, rlim: RlimitPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getrlimit.nr(),
+            rdi: resource as u64,
+            rsi: rlim.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setrlimit(resource: c_uint, rlim: RlimitPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setrlimit.nr(),
+            rdi: resource as u64,
+            rsi: rlim.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_prlimit64(
+    pid: pid_t,
+    resource: c_uint,
+    new_limit: RlimitPtr,
+    old_limit: RlimitPtr,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::prlimit64.nr(),
+            rdi: pid as u64,
+            rsi: resource as u64,
+            rdx: new_limit.as_ptr() as u64,
+            r10: old_limit.as_ptr() as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_uname(buf: UtsnamePtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::uname.nr(),
+            rdi: buf.as_ptr() as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_yield() -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_yield.nr(),
+            rdi: 0,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_setaffinity(pid: pid_t, cpusetsize: size_t, mask: *const c_ulong) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_setaffinity.nr(),
+            rdi: pid as u64,
+            rsi: cpusetsize as u64,
+            rdx: mask as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_getaffinity(pid: pid_t, cpusetsize: size_t, mask: *mut c_ulong) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_getaffinity.nr(),
+            rdi: pid as u64,
+            rsi: cpusetsize as u64,
+            rdx: mask as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_setscheduler(
+    pid: pid_t,
+    policy: c_int,
+    param: *const libc::sched_param,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_setscheduler.nr(),
+            rdi: pid as u64,
+            rsi: policy as u64,
+            rdx: param as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_getscheduler(pid: pid_t) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_getscheduler.nr(),
+            rdi: pid as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_setparam(pid: pid_t, param: *const libc::sched_param) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_setparam.nr(),
+            rdi: pid as u64,
+            rsi: param as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_getparam(pid: pid_t, param: *mut libc::sched_param) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_getparam.nr(),
+            rdi: pid as u64,
+            rsi: param as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_get_priority_max(policy: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_get_priority_max.nr(),
+            rdi: policy as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sched_get_priority_min(policy: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sched_get_priority_min.nr(),
+            rdi: policy as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::socket.nr(),
+            rdi: domain as u64,
+            rsi: ty as u64,
+            rdx: protocol as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_connect(sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::connect.nr(),
+            rdi: sockfd as u64,
+            rsi: addr as u64,
+            rdx: addrlen as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_accept(sockfd: c_int, addr: *mut c_void, addrlen: *mut u32) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::accept.nr(),
+            rdi: sockfd as u64,
+            rsi: addr as u64,
+            rdx: addrlen as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_bind(sockfd: c_int, addr: *const c_void, addrlen: u32) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::bind.nr(),
+            rdi: sockfd as u64,
+            rsi: addr as u64,
+            rdx: addrlen as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_listen(sockfd: c_int, backlog: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::listen.nr(),
+            rdi: sockfd as u64,
+            rsi: backlog as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_accept4(sockfd: c_int, addr: SockAddrOut, flags: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::accept4.nr(),
+            rdi: sockfd as u64,
+            rsi: addr.as_ptr() as u64,
+            rdx: addr.len_ptr() as u64,
+            r10: flags as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sendto(
+    sockfd: c_int,
+    buf: *const c_void,
+    len: size_t,
+    flags: c_int,
+    dest_addr: SockAddrArg,
+) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sendto.nr(),
+            rdi: sockfd as u64,
+            rsi: buf as u64,
+            rdx: len as u64,
+            r10: flags as u64,
+            r8: dest_addr.as_ptr() as u64,
+            r9: dest_addr.addrlen() as u64,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_recvfrom(
+    sockfd: c_int,
+    buf: *mut c_void,
+    len: size_t,
+    flags: c_int,
+    src_addr: SockAddrOut,
+) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::recvfrom.nr(),
+            rdi: sockfd as u64,
+            rsi: buf as u64,
+            rdx: len as u64,
+            r10: flags as u64,
+            r8: src_addr.as_ptr() as u64,
+            r9: src_addr.len_ptr() as u64,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_sendmsg(sockfd: c_int, msg: MsgHdr, flags: c_int) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sendmsg.nr(),
+            rdi: sockfd as u64,
+            rsi: msg.as_ptr() as u64,
+            rdx: flags as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_recvmsg(sockfd: c_int, msg: MsgHdr, flags: c_int) -> ssize_t {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::recvmsg.nr(),
+            rdi: sockfd as u64,
+            rsi: msg.as_ptr() as u64,
+            rdx: flags as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as ssize_t
+    }
+}
+
+pub fn default_sendmmsg(sockfd: c_int, msgvec: MMsgHdrs, flags: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sendmmsg.nr(),
+            rdi: sockfd as u64,
+            rsi: msgvec.as_ptr() as u64,
+            rdx: msgvec.len() as u64,
+            r10: flags as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_recvmmsg(
+    sockfd: c_int,
+    msgvec: MMsgHdrs,
+    flags: c_int,
+    timeout: *mut libc::timespec,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::recvmmsg.nr(),
+            rdi: sockfd as u64,
+            rsi: msgvec.as_ptr() as u64,
+            rdx: msgvec.len() as u64,
+            r10: flags as u64,
+            r8: timeout as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_shutdown(sockfd: c_int, how: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::shutdown.nr(),
+            rdi: sockfd as u64,
+            rsi: how as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getsockopt(
+    sockfd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut u32,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getsockopt.nr(),
+            rdi: sockfd as u64,
+            rsi: level as u64,
+            rdx: optname as u64,
+            r10: optval as u64,
+            r8: optlen as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_setsockopt(
+    sockfd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: u32,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::setsockopt.nr(),
+            rdi: sockfd as u64,
+            rsi: level as u64,
+            rdx: optname as u64,
+            r10: optval as u64,
+            r8: optlen as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getsockname(sockfd: c_int, addr: SockAddrOut) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getsockname.nr(),
+            rdi: sockfd as u64,
+            rsi: addr.as_ptr() as u64,
+            rdx: addr.len_ptr() as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_getpeername(sockfd: c_int, addr: SockAddrOut) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::getpeername.nr(),
+            rdi: sockfd as u64,
+            rsi: addr.as_ptr() as u64,
+            rdx: addr.len_ptr() as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+// 仮想fdを含む場合は`vfd`が準備状態を反映して待つ。タイムアウトなどを
+// 読み取れない場合はカーネルに渡してエラーを返させる。
+
+pub fn default_poll(fds: PollFds, timeout: c_int) -> c_int {
+    let vfd = virtual_fds().poll(fds, millis_timeout(timeout), std::ptr::null(), 0);
+    if let Some(ret) = vfd {
+        return ret as c_int;
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::poll.nr(),
+            rdi: fds.as_ptr() as u64,
+            rsi: fds.len() as u64,
+            rdx: timeout as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_ppoll(
+    fds: PollFds,
+    tmo_p: *const libc::timespec,
+    sigmask: *const sigset_t,
+    sigsetsize: size_t,
+) -> c_int {
+    if let Ok(timeout) = read_timespec(tmo_p) {
+        if let Some(ret) = virtual_fds().poll(fds, timeout, sigmask, sigsetsize) {
+            return ret as c_int;
+        }
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::ppoll.nr(),
+            rdi: fds.as_ptr() as u64,
+            rsi: fds.len() as u64,
+            rdx: tmo_p as u64,
+            r10: sigmask as u64,
+            r8: sigsetsize as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_select(
+    nfds: c_int,
+    readfds: FdSet,
+    writefds: FdSet,
+    exceptfds: FdSet,
+    timeout: *mut libc::timeval,
+) -> c_int {
+    if let Ok(tmo) = read_timeval(timeout) {
+        let sets = [readfds, writefds, exceptfds];
+        if let Some(ret) = virtual_fds().select(nfds, sets, tmo, std::ptr::null(), 0) {
+            return ret as c_int;
+        }
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::select.nr(),
+            rdi: nfds as u64,
+            rsi: readfds.as_ptr() as u64,
+            rdx: writefds.as_ptr() as u64,
+            r10: exceptfds.as_ptr() as u64,
+            r8: timeout as u64,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_pselect6(
+    nfds: c_int,
+    readfds: FdSet,
+    writefds: FdSet,
+    exceptfds: FdSet,
+    timeout: *mut libc::timespec,
+    sigmask: *const c_void,
+) -> c_int {
+    let mask = if sigmask.is_null() {
+        Ok([0u64; 2])
+    } else {
+        try_read_val(sigmask as *const [u64; 2])
+    };
+    if let (Ok(tmo), Ok([ss, ss_len])) = (read_timespec(timeout), mask) {
+        let sets = [readfds, writefds, exceptfds];
+        let vfd = virtual_fds().select(nfds, sets, tmo, ss as *const sigset_t, ss_len as size_t);
+        if let Some(ret) = vfd {
+            return ret as c_int;
+        }
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::pselect6.nr(),
+            rdi: nfds as u64,
+            rsi: readfds.as_ptr() as u64,
+            rdx: writefds.as_ptr() as u64,
+            r10: exceptfds.as_ptr() as u64,
+            r8: timeout as u64,
+            r9: sigmask as u64,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_epoll_create1(flags: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::epoll_create1.nr(),
+            rdi: flags as u64,
+            rsi: 0,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_epoll_ctl(epfd: c_int, op: c_int, fd: c_int, event: EpollEventPtr) -> c_int {
+    if let Some(ret) = virtual_fds().epoll_ctl(epfd, op, fd, event) {
+        return ret as c_int;
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::epoll_ctl.nr(),
+            rdi: epfd as u64,
+            rsi: op as u64,
+            rdx: fd as u64,
+            r10: event.as_ptr() as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_epoll_wait(epfd: c_int, events: EpollEvents, timeout: c_int) -> c_int {
+    let tmo = millis_timeout(timeout);
+    if let Some(ret) = virtual_fds().epoll_wait(epfd, events, tmo, std::ptr::null(), 0) {
+        return ret as c_int;
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::epoll_wait.nr(),
+            rdi: epfd as u64,
+            rsi: events.as_ptr() as u64,
+            rdx: events.capacity() as u64,
+            r10: timeout as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_epoll_pwait(
+    epfd: c_int,
+    events: EpollEvents,
+    timeout: c_int,
+    sigmask: *const sigset_t,
+    sigsetsize: size_t,
+) -> c_int {
+    let tmo = millis_timeout(timeout);
+    if let Some(ret) = virtual_fds().epoll_wait(epfd, events, tmo, sigmask, sigsetsize) {
+        return ret as c_int;
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::epoll_pwait.nr(),
+            rdi: epfd as u64,
+            rsi: events.as_ptr() as u64,
+            rdx: events.capacity() as u64,
+            r10: timeout as u64,
+            r8: sigmask as u64,
+            r9: sigsetsize as u64,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_epoll_pwait2(
+    epfd: c_int,
+    events: EpollEvents,
+    timeout: *const libc::timespec,
+    sigmask: *const sigset_t,
+    sigsetsize: size_t,
+) -> c_int {
+    if let Ok(tmo) = read_timespec(timeout) {
+        if let Some(ret) = virtual_fds().epoll_wait(epfd, events, tmo, sigmask, sigsetsize) {
+            return ret as c_int;
+        }
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::epoll_pwait2.nr(),
+            rdi: epfd as u64,
+            rsi: events.as_ptr() as u64,
+            rdx: events.capacity() as u64,
+            r10: timeout as u64,
+            r8: sigmask as u64,
+            r9: sigsetsize as u64,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+/// `reserve`したシグナルを除いたマスク（除く必要がなければ`None`）
+fn filter_sigset(set: SigsetPtr, sigsetsize: size_t) -> Result<Option<u64>, c_int> {
+    let reserved = virtual_signals().reserved_mask();
+    if reserved == 0 || set.is_null() || sigsetsize != crate::signal::SIGSET_SIZE {
+        return Ok(None);
+    }
+    let mask = set.read().map_err(|_| -libc::EFAULT)?;
+    Ok((mask & reserved != 0).then_some(mask & !reserved))
+}
+
+pub fn default_rt_sigaction(
+    signum: c_int,
+    act: SigactionPtr,
+    oldact: SigactionPtr,
+    sigsetsize: size_t,
+) -> c_int {
+    if let Some(ret) = virtual_signals().sigaction(signum, act, oldact, sigsetsize) {
+        return ret;
+    }
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::rt_sigaction.nr(),
+            rdi: signum as u64,
+            rsi: act.as_ptr() as u64,
+            rdx: oldact.as_ptr() as u64,
+            r10: sigsetsize as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_rt_sigprocmask(
+    how: c_int,
+    set: SigsetPtr,
+    oldset: SigsetPtr,
+    sigsetsize: size_t,
+) -> c_int {
+    // reserveしたシグナルはブロックさせない
+    let mut filtered = match filter_sigset(set, sigsetsize) {
+        Ok(filtered) => filtered,
+        Err(err) => return err,
+    };
+    let set = match filtered.as_mut() {
+        Some(mask) if how != libc::SIG_UNBLOCK => SigsetPtr::new(mask),
+        _ => set,
+    };
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::rt_sigprocmask.nr(),
+            rdi: how as u64,
+            rsi: set.as_ptr() as u64,
+            rdx: oldset.as_ptr() as u64,
+            r10: sigsetsize as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_sigaltstack(ss: StackPtr, old_ss: StackPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::sigaltstack.nr(),
+            rdi: ss.as_ptr() as u64,
+            rsi: old_ss.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_rt_sigsuspend(mask: SigsetPtr, sigsetsize: size_t) -> c_int {
+    let mut filtered = match filter_sigset(mask, sigsetsize) {
+        Ok(filtered) => filtered,
+        Err(err) => return err,
+    };
+    let mask = filtered.as_mut().map_or(mask, |mask| SigsetPtr::new(mask));
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::rt_sigsuspend.nr(),
+            rdi: mask.as_ptr() as u64,
+            rsi: sigsetsize as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_signalfd4(fd: c_int, mask: SigsetPtr, sizemask: size_t, flags: c_int) -> c_int {
+    // reserveしたシグナルはフックライブラリのハンドラが受け取る
+    let mut filtered = match filter_sigset(mask, sizemask) {
+        Ok(filtered) => filtered,
+        Err(err) => return err,
+    };
+    let mask = filtered.as_mut().map_or(mask, |mask| SigsetPtr::new(mask));
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::signalfd4.nr(),
+            rdi: fd as u64,
+            rsi: mask.as_ptr() as u64,
+            rdx: sizemask as u64,
+            r10: flags as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_io_uring_setup(entries: u32, params: IoUringParamsPtr) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::io_uring_setup.nr(),
+            rdi: entries as u64,
+            rsi: params.as_ptr() as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_io_uring_enter(
+    fd: c_int,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    arg: *const c_void,
+    argsz: size_t,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::io_uring_enter.nr(),
+            rdi: fd as u64,
+            rsi: to_submit as u64,
+            rdx: min_complete as u64,
+            r10: flags as u64,
+            r8: arg as u64,
+            r9: argsz as u64,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_io_uring_register(
+    fd: c_int,
+    opcode: c_uint,
+    arg: *mut c_void,
+    nr_args: c_uint,
+) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::io_uring_register.nr(),
+            rdi: fd as u64,
+            rsi: opcode as u64,
+            rdx: arg as u64,
+            r10: nr_args as u64,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_ioctl(fd: c_int, request: c_ulong, arg: *mut c_void) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::ioctl.nr(),
+            rdi: fd as u64,
+            rsi: request,
+            rdx: arg as u64,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+pub fn default_access(pathname: *const c_char, mode: c_int) -> c_int {
+    unsafe {
+        let regs = SyscallRegs {
+            rax: Sysno::access.nr(),
+            rdi: pathname as u64,
+            rsi: mode as u64,
+            rdx: 0,
+            r10: 0,
+            r8: 0,
+            r9: 0,
+        };
+        raw_syscall(&regs) as c_int
+    }
+}
+
+/// 第2・第3引数のiovec配列
+fn iovecs_arg(regs: &SyscallRegs) -> IoVecs {
+    IoVecs::new(regs.rsi as *const libc::iovec, regs.rdx as usize)
+}
+
+/// SyscallHooksを実装した型を登録するための内部処理
+///
+/// これはzpoline内部で使用されます。通常、ユーザーは`register_syscall_hooks`を
+/// 使用してフックを登録します。
+pub(crate) fn dispatch_syscall_hooks(
     hooks: &mut dyn SyscallHooks,
     regs: &mut SyscallRegs,
 ) -> i64 {
-    match regs.rax {
-        0 => hooks.hook_read(
+    with_clone_callbacks(hooks, regs, call_hook)
+}
+
+/// フックメソッドの代わりにデフォルト実装で実行し、fork/cloneのコールバックだけを呼ぶ
+///
+/// 関心集合に含まれない`fork::FORK_SYSCALLS`で使われます。
+pub(crate) fn dispatch_clone_callbacks(
+    hooks: &mut dyn SyscallHooks,
+    regs: &mut SyscallRegs,
+) -> i64 {
+    with_clone_callbacks(hooks, regs, |_, regs| dispatch_defaults(regs))
+}
+
+/// `call`の前後でfork/cloneのコールバックを呼ぶ
+fn with_clone_callbacks(
+    hooks: &mut dyn SyscallHooks,
+    regs: &mut SyscallRegs,
+    call: impl FnOnce(&mut dyn SyscallHooks, &mut SyscallRegs) -> i64,
+) -> i64 {
+    // clone3の引数はsyscall前に読んでおく
+    let fork_like = crate::fork::is_fork_like(regs);
+
+    // 子に渡す関数はシステムコールの前に決めておく
+    let clone_flags = crate::clone::clone_flags(regs);
+    let child_fn = clone_flags.and_then(|flags| hooks.clone_child_fn(flags));
+    let prev_child_fn = crate::clone::set_child_fn(child_fn);
+
+    let ret = call(hooks, regs);
+
+    crate::clone::set_child_fn(prev_child_fn);
+
+    // 子プロセスではランタイムを再初期化してからフック実装に通知
+    if fork_like && ret == 0 && crate::fork::child_in_dispatch() {
+        hooks.on_fork_child();
+    }
+
+    if let Some(flags) = clone_flags {
+        if ret > 0 {
+            hooks.on_clone_parent(ret as pid_t, flags);
+        } else if let (0, Some(child_fn)) = (ret, child_fn) {
+            child_fn(flags);
+        }
+    }
+
+    ret
+}
+
+/// デフォルト実装でシステムコールを実行する（仮想fdや仮想シグナルを反映する）
+///
+/// fork/cloneのコールバックは呼びません。
+pub(crate) fn dispatch_defaults(regs: &mut SyscallRegs) -> i64 {
+    struct Defaults;
+    impl SyscallHooks for Defaults {}
+    call_hook(&mut Defaults, regs)
+}
+
+/// システムコールに対応するフックメソッドを呼ぶ
+fn call_hook(hooks: &mut dyn SyscallHooks, regs: &mut SyscallRegs) -> i64 {
+    match Sysno::from_raw(regs.rax) {
+        Some(Sysno::read) => hooks.hook_read(
+            regs.rdi as c_int,
+            regs.rsi as *mut c_void,
+            regs.rdx as size_t,
+        ) as i64,
+        Some(Sysno::write) => hooks.hook_write(
+            regs.rdi as c_int,
+            regs.rsi as *const c_void,
+            regs.rdx as size_t,
+        ) as i64,
+        Some(Sysno::open) => hooks.hook_open(
+            regs.rdi as *const c_char,
+            regs.rsi as c_int,
+            regs.rdx as c_uint,
+        ) as i64,
+        Some(Sysno::close) => hooks.hook_close(regs.rdi as c_int) as i64,
+        Some(Sysno::lseek) => hooks.hook_lseek(
+            regs.rdi as c_int,
+            regs.rsi as off_t,
+            regs.rdx as c_int,
+        ),
+        Some(Sysno::pread64) => hooks.hook_pread64(
+            regs.rdi as c_int,
+            regs.rsi as *mut c_void,
+            regs.rdx as size_t,
+            regs.r10 as off_t,
+        ) as i64,
+        Some(Sysno::pwrite64) => hooks.hook_pwrite64(
+            regs.rdi as c_int,
+            regs.rsi as *const c_void,
+            regs.rdx as size_t,
+            regs.r10 as off_t,
+        ) as i64,
+        Some(Sysno::readv) => hooks.hook_readv(regs.rdi as c_int, iovecs_arg(regs)) as i64,
+        Some(Sysno::writev) => hooks.hook_writev(regs.rdi as c_int, iovecs_arg(regs)) as i64,
+        // x86-64ではオフセットの上位（pos_h）は使われない
+        Some(Sysno::preadv) => {
+            hooks.hook_preadv(regs.rdi as c_int, iovecs_arg(regs), regs.r10 as off_t) as i64
+        }
+        Some(Sysno::pwritev) => {
+            hooks.hook_pwritev(regs.rdi as c_int, iovecs_arg(regs), regs.r10 as off_t) as i64
+        }
+        Some(Sysno::preadv2) => hooks.hook_preadv2(
+            regs.rdi as c_int,
+            iovecs_arg(regs),
+            regs.r10 as off_t,
+            regs.r9 as c_int,
+        ) as i64,
+        Some(Sysno::pwritev2) => hooks.hook_pwritev2(
+            regs.rdi as c_int,
+            iovecs_arg(regs),
+            regs.r10 as off_t,
+            regs.r9 as c_int,
+        ) as i64,
+        Some(Sysno::mmap) => hooks.hook_mmap(
+            regs.rdi as *mut c_void,
+            regs.rsi as size_t,
+            regs.rdx as c_int,
+            regs.r10 as c_int,
+            regs.r8 as c_int,
+            regs.r9 as off_t,
+        ) as i64,
+        Some(Sysno::mprotect) => hooks.hook_mprotect(
+            regs.rdi as *mut c_void,
+            regs.rsi as size_t,
+            regs.rdx as c_int,
+        ) as i64,
+        Some(Sysno::munmap) => hooks.hook_munmap(regs.rdi as *mut c_void, regs.rsi as size_t) as i64,
+        Some(Sysno::brk) => hooks.hook_brk(regs.rdi as *mut c_void) as i64,
+        Some(Sysno::ioctl) => hooks.hook_ioctl(
+            regs.rdi as c_int,
+            regs.rsi as c_ulong,
+            regs.rdx as *mut c_void,
+        ) as i64,
+        Some(Sysno::access) => hooks.hook_access(regs.rdi as *const c_char, regs.rsi as c_int) as i64,
+        Some(Sysno::pipe) => hooks.hook_pipe(regs.rdi as *mut c_int) as i64,
+        Some(Sysno::dup) => hooks.hook_dup(regs.rdi as c_int) as i64,
+        Some(Sysno::dup2) => hooks.hook_dup2(regs.rdi as c_int, regs.rsi as c_int) as i64,
+        Some(Sysno::getpid) => hooks.hook_getpid() as i64,
+        Some(Sysno::socket) => hooks.hook_socket(
+            regs.rdi as c_int,
+            regs.rsi as c_int,
+            regs.rdx as c_int,
+        ) as i64,
+        Some(Sysno::connect) => hooks.hook_connect(
+            regs.rdi as c_int,
+            regs.rsi as *const c_void,
+            regs.rdx as u32,
+        ) as i64,
+        Some(Sysno::accept) => hooks.hook_accept(
+            regs.rdi as c_int,
+            regs.rsi as *mut c_void,
+            regs.rdx as *mut u32,
+        ) as i64,
+        Some(Sysno::bind) => hooks.hook_bind(
+            regs.rdi as c_int,
+            regs.rsi as *const c_void,
+            regs.rdx as u32,
+        ) as i64,
+        Some(Sysno::listen) => hooks.hook_listen(regs.rdi as c_int, regs.rsi as c_int) as i64,
+        Some(Sysno::accept4) => hooks.hook_accept4(
+            regs.rdi as c_int,
+            SockAddrOut::new(regs.rsi as *mut c_void, regs.rdx as *mut u32),
+            regs.r10 as c_int,
+        ) as i64,
+        Some(Sysno::sendto) => hooks.hook_sendto(
+            regs.rdi as c_int,
+            regs.rsi as *const c_void,
+            regs.rdx as size_t,
+            regs.r10 as c_int,
+            SockAddrArg::new(regs.r8 as *const c_void, regs.r9 as u32),
+        ) as i64,
+        Some(Sysno::recvfrom) => hooks.hook_recvfrom(
+            regs.rdi as c_int,
+            regs.rsi as *mut c_void,
+            regs.rdx as size_t,
+            regs.r10 as c_int,
+            SockAddrOut::new(regs.r8 as *mut c_void, regs.r9 as *mut u32),
+        ) as i64,
+        Some(Sysno::sendmsg) => hooks.hook_sendmsg(
+            regs.rdi as c_int,
+            MsgHdr::new(regs.rsi as *mut libc::msghdr),
+            regs.rdx as c_int,
+        ) as i64,
+        Some(Sysno::recvmsg) => hooks.hook_recvmsg(
             regs.rdi as c_int,
-            regs.rsi as *mut c_void,
-            regs.rdx as size_t,
+            MsgHdr::new(regs.rsi as *mut libc::msghdr),
+            regs.rdx as c_int,
         ) as i64,
-        1 => hooks.hook_write(
+        Some(Sysno::sendmmsg) => hooks.hook_sendmmsg(
             regs.rdi as c_int,
-            regs.rsi as *const c_void,
-            regs.rdx as size_t,
+            MMsgHdrs::new(regs.rsi as *mut libc::mmsghdr, regs.rdx as c_uint),
+            regs.r10 as c_int,
         ) as i64,
-        2 => hooks.hook_open(
-            regs.rdi as *const c_char,
-            regs.rsi as c_int,
-            regs.rdx as c_uint,
+        Some(Sysno::recvmmsg) => hooks.hook_recvmmsg(
+            regs.rdi as c_int,
+            MMsgHdrs::new(regs.rsi as *mut libc::mmsghdr, regs.rdx as c_uint),
+            regs.r10 as c_int,
+            regs.r8 as *mut libc::timespec,
         ) as i64,
-        3 => hooks.hook_close(regs.rdi as c_int) as i64,
-        8 => hooks.hook_lseek(
+        Some(Sysno::shutdown) => hooks.hook_shutdown(regs.rdi as c_int, regs.rsi as c_int) as i64,
+        Some(Sysno::getsockopt) => hooks.hook_getsockopt(
             regs.rdi as c_int,
-            regs.rsi as off_t,
+            regs.rsi as c_int,
             regs.rdx as c_int,
+            regs.r10 as *mut c_void,
+            regs.r8 as *mut u32,
         ) as i64,
-        9 => hooks.hook_mmap(
-            regs.rdi as *mut c_void,
-            regs.rsi as size_t,
+        Some(Sysno::setsockopt) => hooks.hook_setsockopt(
+            regs.rdi as c_int,
+            regs.rsi as c_int,
             regs.rdx as c_int,
-            regs.r10 as c_int,
-            regs.r8 as c_int,
-            regs.r9 as off_t,
+            regs.r10 as *const c_void,
+            regs.r8 as u32,
         ) as i64,
-        10 => hooks.hook_mprotect(
-            regs.rdi as *mut c_void,
-            regs.rsi as size_t,
-            regs.rdx as c_int,
+        Some(Sysno::getsockname) => hooks.hook_getsockname(
+            regs.rdi as c_int,
+            SockAddrOut::new(regs.rsi as *mut c_void, regs.rdx as *mut u32),
         ) as i64,
-        11 => hooks.hook_munmap(regs.rdi as *mut c_void, regs.rsi as size_t) as i64,
-        12 => hooks.hook_brk(regs.rdi as *mut c_void) as i64,
-        16 => hooks.hook_ioctl(
+        Some(Sysno::getpeername) => hooks.hook_getpeername(
             regs.rdi as c_int,
-            regs.rsi as c_ulong,
-            regs.rdx as *mut c_void,
+            SockAddrOut::new(regs.rsi as *mut c_void, regs.rdx as *mut u32),
+        ) as i64,
+        Some(Sysno::poll) => hooks.hook_poll(
+            PollFds::new(regs.rdi as *mut libc::pollfd, regs.rsi as libc::nfds_t),
+            regs.rdx as c_int,
+        ) as i64,
+        Some(Sysno::ppoll) => hooks.hook_ppoll(
+            PollFds::new(regs.rdi as *mut libc::pollfd, regs.rsi as libc::nfds_t),
+            regs.rdx as *const libc::timespec,
+            regs.r10 as *const sigset_t,
+            regs.r8 as size_t,
         ) as i64,
-        21 => hooks.hook_access(regs.rdi as *const c_char, regs.rsi as c_int) as i64,
-        22 => hooks.hook_pipe(regs.rdi as *mut c_int) as i64,
-        32 => hooks.hook_dup(regs.rdi as c_int) as i64,
-        33 => hooks.hook_dup2(regs.rdi as c_int, regs.rsi as c_int) as i64,
-        39 => hooks.hook_getpid() as i64,
-        41 => hooks.hook_socket(
+        Some(Sysno::select) => {
+            let nfds = regs.rdi as c_int;
+            hooks.hook_select(
+                nfds,
+                FdSet::new(regs.rsi as *mut libc::fd_set, nfds),
+                FdSet::new(regs.rdx as *mut libc::fd_set, nfds),
+                FdSet::new(regs.r10 as *mut libc::fd_set, nfds),
+                regs.r8 as *mut libc::timeval,
+            ) as i64
+        }
+        Some(Sysno::pselect6) => {
+            let nfds = regs.rdi as c_int;
+            hooks.hook_pselect6(
+                nfds,
+                FdSet::new(regs.rsi as *mut libc::fd_set, nfds),
+                FdSet::new(regs.rdx as *mut libc::fd_set, nfds),
+                FdSet::new(regs.r10 as *mut libc::fd_set, nfds),
+                regs.r8 as *mut libc::timespec,
+                regs.r9 as *const c_void,
+            ) as i64
+        }
+        Some(Sysno::epoll_create1) => hooks.hook_epoll_create1(regs.rdi as c_int) as i64,
+        Some(Sysno::epoll_ctl) => hooks.hook_epoll_ctl(
             regs.rdi as c_int,
             regs.rsi as c_int,
             regs.rdx as c_int,
+            EpollEventPtr::new(regs.r10 as *mut libc::epoll_event),
         ) as i64,
-        42 => hooks.hook_connect(
+        Some(Sysno::epoll_wait) => hooks.hook_epoll_wait(
             regs.rdi as c_int,
-            regs.rsi as *const c_void,
-            regs.rdx as u32,
+            EpollEvents::new(regs.rsi as *mut libc::epoll_event, regs.rdx as c_int),
+            regs.r10 as c_int,
         ) as i64,
-        43 => hooks.hook_accept(
+        Some(Sysno::epoll_pwait) => hooks.hook_epoll_pwait(
             regs.rdi as c_int,
-            regs.rsi as *mut c_void,
-            regs.rdx as *mut u32,
+            EpollEvents::new(regs.rsi as *mut libc::epoll_event, regs.rdx as c_int),
+            regs.r10 as c_int,
+            regs.r8 as *const sigset_t,
+            regs.r9 as size_t,
         ) as i64,
-        49 => hooks.hook_bind(
+        Some(Sysno::epoll_pwait2) => hooks.hook_epoll_pwait2(
             regs.rdi as c_int,
-            regs.rsi as *const c_void,
+            EpollEvents::new(regs.rsi as *mut libc::epoll_event, regs.rdx as c_int),
+            regs.r10 as *const libc::timespec,
+            regs.r8 as *const sigset_t,
+            regs.r9 as size_t,
+        ) as i64,
+        Some(Sysno::io_uring_setup) => hooks.hook_io_uring_setup(
+            regs.rdi as u32,
+            IoUringParamsPtr::new(regs.rsi as *mut crate::io_uring::IoUringParams),
+        ) as i64,
+        Some(Sysno::io_uring_enter) => hooks.hook_io_uring_enter(
+            regs.rdi as c_int,
+            regs.rsi as u32,
             regs.rdx as u32,
+            regs.r10 as u32,
+            regs.r8 as *const c_void,
+            regs.r9 as size_t,
+        ) as i64,
+        Some(Sysno::io_uring_register) => hooks.hook_io_uring_register(
+            regs.rdi as c_int,
+            regs.rsi as c_uint,
+            regs.rdx as *mut c_void,
+            regs.r10 as c_uint,
+        ) as i64,
+        Some(Sysno::fork) => hooks.hook_fork() as i64,
+        Some(Sysno::vfork) => hooks.hook_vfork() as i64,
+        Some(Sysno::clone) => hooks.hook_clone(
+            CloneFlags::from_bits(regs.rdi),
+            regs.rsi as *mut c_void,
+            regs.rdx as *mut pid_t,
+            regs.r10 as *mut pid_t,
+            regs.r8 as c_ulong,
         ) as i64,
-        50 => hooks.hook_listen(regs.rdi as c_int, regs.rsi as c_int) as i64,
-        57 => hooks.hook_fork() as i64,
-        59 => hooks.hook_execve(
+        Some(Sysno::clone3) => hooks.hook_clone3(CloneArgsPtr::new(
+            regs.rdi as *mut crate::clone::CloneArgs,
+            regs.rsi as size_t,
+        )) as i64,
+        Some(Sysno::execve) => hooks.hook_execve(
             regs.rdi as *const c_char,
             regs.rsi as *const *const c_char,
             regs.rdx as *const *const c_char,
         ) as i64,
-        60 => hooks.hook_exit(regs.rdi as c_int),
-        61 => hooks.hook_wait4(
+        Some(Sysno::exit) => hooks.hook_exit(regs.rdi as c_int),
+        Some(Sysno::wait4) => hooks.hook_wait4(
             regs.rdi as pid_t,
             regs.rsi as *mut c_int,
             regs.rdx as c_int,
             regs.r10 as *mut c_void,
         ) as i64,
-        62 => hooks.hook_kill(regs.rdi as pid_t, regs.rsi as c_int) as i64,
-        186 => hooks.hook_gettid() as i64,
-        231 => hooks.hook_exit_group(regs.rdi as c_int),
-        257 => hooks.hook_openat(
+        Some(Sysno::kill) => hooks.hook_kill(regs.rdi as pid_t, regs.rsi as c_int) as i64,
+        Some(Sysno::gettid) => hooks.hook_gettid() as i64,
+        Some(Sysno::rt_sigaction) => hooks.hook_rt_sigaction(
+            regs.rdi as c_int,
+            SigactionPtr::new(regs.rsi as *mut crate::signal::KernelSigaction),
+            SigactionPtr::new(regs.rdx as *mut crate::signal::KernelSigaction),
+            regs.r10 as size_t,
+        ) as i64,
+        Some(Sysno::rt_sigprocmask) => hooks.hook_rt_sigprocmask(
+            regs.rdi as c_int,
+            SigsetPtr::new(regs.rsi as *mut u64),
+            SigsetPtr::new(regs.rdx as *mut u64),
+            regs.r10 as size_t,
+        ) as i64,
+        Some(Sysno::sigaltstack) => hooks.hook_sigaltstack(
+            StackPtr::new(regs.rdi as *mut libc::stack_t),
+            StackPtr::new(regs.rsi as *mut libc::stack_t),
+        ) as i64,
+        Some(Sysno::rt_sigsuspend) => hooks
+            .hook_rt_sigsuspend(SigsetPtr::new(regs.rdi as *mut u64), regs.rsi as size_t)
+            as i64,
+        Some(Sysno::signalfd4) => hooks.hook_signalfd4(
+            regs.rdi as c_int,
+            SigsetPtr::new(regs.rsi as *mut u64),
+            regs.rdx as size_t,
+            regs.r10 as c_int,
+        ) as i64,
+        Some(Sysno::clock_gettime) => hooks.hook_clock_gettime(
+            regs.rdi as libc::clockid_t,
+            TimespecPtr::new(regs.rsi as *mut libc::timespec),
+        ) as i64,
+        Some(Sysno::clock_getres) => hooks.hook_clock_getres(
+            regs.rdi as libc::clockid_t,
+            TimespecPtr::new(regs.rsi as *mut libc::timespec),
+        ) as i64,
+        Some(Sysno::gettimeofday) => hooks.hook_gettimeofday(
+            TimevalPtr::new(regs.rdi as *mut libc::timeval),
+            regs.rsi as *mut c_void,
+        ) as i64,
+        Some(Sysno::time) => hooks.hook_time(regs.rdi as *mut libc::time_t),
+        Some(Sysno::getcpu) => hooks.hook_getcpu(
+            regs.rdi as *mut c_uint,
+            regs.rsi as *mut c_uint,
+            regs.rdx as *mut c_void,
+        ) as i64,
+        Some(Sysno::exit_group) => hooks.hook_exit_group(regs.rdi as c_int),
+        Some(Sysno::openat) => hooks.hook_openat(
             regs.rdi as c_int,
             regs.rsi as *const c_char,
             regs.rdx as c_int,
             regs.r10 as c_uint,
         ) as i64,
+        Some(Sysno::stat) => hooks.hook_stat(
+            regs.rdi as *const c_char,
+            StatBuf::new(regs.rsi as *mut libc::stat),
+        ) as i64,
+        Some(Sysno::fstat) => {
+            hooks.hook_fstat(regs.rdi as c_int, StatBuf::new(regs.rsi as *mut libc::stat)) as i64
+        }
+        Some(Sysno::lstat) => hooks.hook_lstat(
+            regs.rdi as *const c_char,
+            StatBuf::new(regs.rsi as *mut libc::stat),
+        ) as i64,
+        Some(Sysno::newfstatat) => hooks.hook_newfstatat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            StatBuf::new(regs.rdx as *mut libc::stat),
+            regs.r10 as c_int,
+        ) as i64,
+        Some(Sysno::statx) => hooks.hook_statx(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as c_int,
+            regs.r10 as c_uint,
+            StatxBuf::new(regs.r8 as *mut libc::statx),
+        ) as i64,
+        Some(Sysno::getdents64) => hooks.hook_getdents64(
+            regs.rdi as c_int,
+            DirEntries::new(regs.rsi as *mut c_void, regs.rdx as c_uint),
+        ) as i64,
+        Some(Sysno::mkdir) => hooks.hook_mkdir(
+            regs.rdi as *const c_char,
+            regs.rsi as mode_t,
+        ) as i64,
+        Some(Sysno::mkdirat) => hooks.hook_mkdirat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as mode_t,
+        ) as i64,
+        Some(Sysno::rmdir) => hooks.hook_rmdir(regs.rdi as *const c_char) as i64,
+        Some(Sysno::unlink) => hooks.hook_unlink(regs.rdi as *const c_char) as i64,
+        Some(Sysno::unlinkat) => hooks.hook_unlinkat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as c_int,
+        ) as i64,
+        Some(Sysno::rename) => hooks.hook_rename(
+            regs.rdi as *const c_char,
+            regs.rsi as *const c_char,
+        ) as i64,
+        Some(Sysno::renameat) => hooks.hook_renameat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as c_int,
+            regs.r10 as *const c_char,
+        ) as i64,
+        Some(Sysno::renameat2) => hooks.hook_renameat2(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as c_int,
+            regs.r10 as *const c_char,
+            regs.r8 as c_uint,
+        ) as i64,
+        Some(Sysno::link) => hooks.hook_link(
+            regs.rdi as *const c_char,
+            regs.rsi as *const c_char,
+        ) as i64,
+        Some(Sysno::linkat) => hooks.hook_linkat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as c_int,
+            regs.r10 as *const c_char,
+            regs.r8 as c_int,
+        ) as i64,
+        Some(Sysno::symlink) => hooks.hook_symlink(
+            regs.rdi as *const c_char,
+            regs.rsi as *const c_char,
+        ) as i64,
+        Some(Sysno::symlinkat) => hooks.hook_symlinkat(
+            regs.rdi as *const c_char,
+            regs.rsi as c_int,
+            regs.rdx as *const c_char,
+        ) as i64,
+        Some(Sysno::readlink) => hooks.hook_readlink(
+            regs.rdi as *const c_char,
+            regs.rsi as *mut c_char,
+            regs.rdx as size_t,
+        ) as i64,
+        Some(Sysno::readlinkat) => hooks.hook_readlinkat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as *mut c_char,
+            regs.r10 as size_t,
+        ) as i64,
+        Some(Sysno::chdir) => hooks.hook_chdir(regs.rdi as *const c_char) as i64,
+        Some(Sysno::fchdir) => hooks.hook_fchdir(regs.rdi as c_int) as i64,
+        Some(Sysno::getcwd) => hooks.hook_getcwd(
+            regs.rdi as *mut c_char,
+            regs.rsi as size_t,
+        ) as i64,
+        Some(Sysno::truncate) => hooks.hook_truncate(
+            regs.rdi as *const c_char,
+            regs.rsi as off_t,
+        ) as i64,
+        Some(Sysno::ftruncate) => hooks.hook_ftruncate(regs.rdi as c_int, regs.rsi as off_t) as i64,
+        Some(Sysno::chmod) => hooks.hook_chmod(
+            regs.rdi as *const c_char,
+            regs.rsi as mode_t,
+        ) as i64,
+        Some(Sysno::fchmod) => hooks.hook_fchmod(regs.rdi as c_int, regs.rsi as mode_t) as i64,
+        Some(Sysno::fchmodat) => hooks.hook_fchmodat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as mode_t,
+        ) as i64,
+        Some(Sysno::fchmodat2) => hooks.hook_fchmodat2(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as mode_t,
+            regs.r10 as c_int,
+        ) as i64,
+        Some(Sysno::chown) => hooks.hook_chown(
+            regs.rdi as *const c_char,
+            regs.rsi as uid_t,
+            regs.rdx as gid_t,
+        ) as i64,
+        Some(Sysno::fchown) => hooks.hook_fchown(
+            regs.rdi as c_int,
+            regs.rsi as uid_t,
+            regs.rdx as gid_t,
+        ) as i64,
+        Some(Sysno::lchown) => hooks.hook_lchown(
+            regs.rdi as *const c_char,
+            regs.rsi as uid_t,
+            regs.rdx as gid_t,
+        ) as i64,
+        Some(Sysno::fchownat) => hooks.hook_fchownat(
+            regs.rdi as c_int,
+            regs.rsi as *const c_char,
+            regs.rdx as uid_t,
+            regs.r10 as gid_t,
+            regs.r8 as c_int,
+        ) as i64,
+        Some(Sysno::getuid) => hooks.hook_getuid() as i64,
+        Some(Sysno::geteuid) => hooks.hook_geteuid() as i64,
+        Some(Sysno::getgid) => hooks.hook_getgid() as i64,
+        Some(Sysno::getegid) => hooks.hook_getegid() as i64,
+        Some(Sysno::getresuid) => hooks.hook_getresuid(
+            regs.rdi as *mut uid_t,
+            regs.rsi as *mut uid_t,
+            regs.rdx as *mut uid_t,
+        ) as i64,
+        Some(Sysno::getresgid) => hooks.hook_getresgid(
+            regs.rdi as *mut gid_t,
+            regs.rsi as *mut gid_t,
+            regs.rdx as *mut gid_t,
+        ) as i64,
+        Some(Sysno::getgroups) => hooks.hook_getgroups(
+            regs.rdi as c_int,
+            regs.rsi as *mut gid_t,
+        ) as i64,
+        Some(Sysno::setuid) => hooks.hook_setuid(regs.rdi as uid_t) as i64,
+        Some(Sysno::setgid) => hooks.hook_setgid(regs.rdi as gid_t) as i64,
+        Some(Sysno::setresuid) => hooks.hook_setresuid(
+            regs.rdi as uid_t,
+            regs.rsi as uid_t,
+            regs.rdx as uid_t,
+        ) as i64,
+        Some(Sysno::setresgid) => hooks.hook_setresgid(
+            regs.rdi as gid_t,
+            regs.rsi as gid_t,
+            regs.rdx as gid_t,
+        ) as i64,
+        Some(Sysno::setgroups) => hooks.hook_setgroups(
+            regs.rdi as size_t,
+            regs.rsi as *const gid_t,
+        ) as i64,
+        Some(Sysno::capget) => hooks.hook_capget(
+            CapHeaderPtr::new(regs.rdi as *mut crate::identity::CapUserHeader),
+            CapDataPtr::new(regs.rsi as *mut crate::identity::CapUserData),
+        ) as i64,
+        Some(Sysno::capset) => hooks.hook_capset(
+            CapHeaderPtr::new(regs.rdi as *mut crate::identity::CapUserHeader),
+            CapDataPtr::new(regs.rsi as *mut crate::identity::CapUserData),
+        ) as i64,
+        Some(Sysno::prctl) => hooks.hook_prctl(
+            regs.rdi as c_int,
+            regs.rsi as c_ulong,
+            regs.rdx as c_ulong,
+            regs.r10 as c_ulong,
+            regs.r8 as c_ulong,
+        ) as i64,
+        Some(Sysno::arch_prctl) => hooks.hook_arch_prctl(
+            regs.rdi as c_int,
+            regs.rsi as c_ulong,
+        ) as i64,
+        Some(Sysno::getrlimit) => hooks.hook_getrlimit(
+            regs.rdi as c_uint,
+            RlimitPtr::new(regs.rsi as *mut libc::rlimit64),
+        ) as i64,
+        Some(Sysno::setrlimit) => hooks.hook_setrlimit(
+            regs.rdi as c_uint,
+            RlimitPtr::new(regs.rsi as *mut libc::rlimit64),
+        ) as i64,
+        Some(Sysno::prlimit64) => hooks.hook_prlimit64(
+            regs.rdi as pid_t,
+            regs.rsi as c_uint,
+            RlimitPtr::new(regs.rdx as *mut libc::rlimit64),
+            RlimitPtr::new(regs.r10 as *mut libc::rlimit64),
+        ) as i64,
+        Some(Sysno::uname) => hooks.hook_uname(
+            UtsnamePtr::new(regs.rdi as *mut libc::utsname),
+        ) as i64,
+        Some(Sysno::sched_yield) => hooks.hook_sched_yield() as i64,
+        Some(Sysno::sched_setaffinity) => hooks.hook_sched_setaffinity(
+            regs.rdi as pid_t,
+            regs.rsi as size_t,
+            regs.rdx as *const c_ulong,
+        ) as i64,
+        Some(Sysno::sched_getaffinity) => hooks.hook_sched_getaffinity(
+            regs.rdi as pid_t,
+            regs.rsi as size_t,
+            regs.rdx as *mut c_ulong,
+        ) as i64,
+        Some(Sysno::sched_setscheduler) => hooks.hook_sched_setscheduler(
+            regs.rdi as pid_t,
+            regs.rsi as c_int,
+            regs.rdx as *const libc::sched_param,
+        ) as i64,
+        Some(Sysno::sched_getscheduler) => hooks.hook_sched_getscheduler(regs.rdi as pid_t) as i64,
+        Some(Sysno::sched_setparam) => hooks.hook_sched_setparam(
+            regs.rdi as pid_t,
+            regs.rsi as *const libc::sched_param,
+        ) as i64,
+        Some(Sysno::sched_getparam) => hooks.hook_sched_getparam(
+            regs.rdi as pid_t,
+            regs.rsi as *mut libc::sched_param,
+        ) as i64,
+        Some(Sysno::sched_get_priority_max) => hooks.hook_sched_get_priority_max(
+            regs.rdi as c_int,
+        ) as i64,
+        Some(Sysno::sched_get_priority_min) => hooks.hook_sched_get_priority_min(
+            regs.rdi as c_int,
+        ) as i64,
         // 未知のsyscallはデフォルトで実行
         _ => unsafe { raw_syscall(regs) },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 各引数レジスタの値（rdi, rsi, rdx, r10, r8, r9）
+    const ARGS: [u64; 6] = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+
+    /// 呼ばれたフックの名前と、受け取った引数を整数に戻したもの
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<(&'static str, Vec<u64>)>,
+    }
+
+    impl Recorder {
+        fn record(&mut self, name: &'static str, args: &[u64]) {
+            self.calls.push((name, args.to_vec()));
+        }
+
+        /// `ARGS`を引数にして`sysno`をディスパッチする
+        fn dispatch(&mut self, sysno: Sysno) -> i64 {
+            let [rdi, rsi, rdx, r10, r8, r9] = ARGS;
+            let mut regs = SyscallRegs::new(sysno.nr(), rdi, rsi, rdx, r10, r8, r9);
+            dispatch_syscall_hooks(self, &mut regs)
+        }
+
+        fn last(&self) -> (&'static str, &[u64]) {
+            let (name, args) = self.calls.last().unwrap();
+            (name, args)
+        }
+    }
+
+    // 族ごとに代表的なフックをオーバーライドし、デコードした引数を記録する
+    impl SyscallHooks for Recorder {
+        // ファイルI/O
+        fn hook_read(&mut self, fd: c_int, buf: *mut c_void, count: size_t) -> ssize_t {
+            self.record("read", &[fd as u64, buf as u64, count as u64]);
+            7
+        }
+
+        fn hook_pwrite64(
+            &mut self,
+            fd: c_int,
+            buf: *const c_void,
+            count: size_t,
+            offset: off_t,
+        ) -> ssize_t {
+            self.record(
+                "pwrite64",
+                &[fd as u64, buf as u64, count as u64, offset as u64],
+            );
+            8
+        }
+
+        fn hook_preadv2(&mut self, fd: c_int, iov: IoVecs, offset: off_t, flags: c_int) -> ssize_t {
+            let args = [
+                fd as u64,
+                iov.as_ptr() as u64,
+                iov.len() as u64,
+                offset as u64,
+            ];
+            self.record("preadv2", &[&args[..], &[flags as u64]].concat());
+            9
+        }
+
+        fn hook_lseek(&mut self, fd: c_int, offset: off_t, whence: c_int) -> off_t {
+            self.record("lseek", &[fd as u64, offset as u64, whence as u64]);
+            1 << 40
+        }
+
+        fn hook_openat(
+            &mut self,
+            dirfd: c_int,
+            pathname: *const c_char,
+            flags: c_int,
+            mode: c_uint,
+        ) -> c_int {
+            self.record(
+                "openat",
+                &[dirfd as u64, pathname as u64, flags as u64, mode as u64],
+            );
+            -libc::ENOENT
+        }
+
+        // ネットワーク
+        fn hook_sendto(
+            &mut self,
+            sockfd: c_int,
+            buf: *const c_void,
+            len: size_t,
+            flags: c_int,
+            dest_addr: SockAddrArg,
+        ) -> ssize_t {
+            let args = [sockfd as u64, buf as u64, len as u64, flags as u64];
+            let addr = [dest_addr.as_ptr() as u64, dest_addr.addrlen() as u64];
+            self.record("sendto", &[&args[..], &addr[..]].concat());
+            5
+        }
+
+        fn hook_recvmmsg(
+            &mut self,
+            sockfd: c_int,
+            msgvec: MMsgHdrs,
+            flags: c_int,
+            timeout: *mut libc::timespec,
+        ) -> c_int {
+            let args = [sockfd as u64, msgvec.as_ptr() as u64, msgvec.len() as u64];
+            self.record(
+                "recvmmsg",
+                &[&args[..], &[flags as u64, timeout as u64]].concat(),
+            );
+            2
+        }
+
+        fn hook_setsockopt(
+            &mut self,
+            sockfd: c_int,
+            level: c_int,
+            optname: c_int,
+            optval: *const c_void,
+            optlen: u32,
+        ) -> c_int {
+            let args = [sockfd as u64, level as u64, optname as u64, optval as u64];
+            self.record("setsockopt", &[&args[..], &[optlen as u64]].concat());
+            0
+        }
+
+        fn hook_accept4(&mut self, sockfd: c_int, addr: SockAddrOut, flags: c_int) -> c_int {
+            let args = [sockfd as u64, addr.as_ptr() as u64, addr.len_ptr() as u64];
+            self.record("accept4", &[&args[..], &[flags as u64]].concat());
+            4
+        }
+
+        // ポーリング
+        fn hook_pselect6(
+            &mut self,
+            nfds: c_int,
+            readfds: FdSet,
+            writefds: FdSet,
+            exceptfds: FdSet,
+            timeout: *mut libc::timespec,
+            sigmask: *const c_void,
+        ) -> c_int {
+            let sets = [readfds.as_ptr(), writefds.as_ptr(), exceptfds.as_ptr()].map(|p| p as u64);
+            let args = [
+                &[nfds as u64][..],
+                &sets[..],
+                &[timeout as u64, sigmask as u64],
+            ];
+            self.record("pselect6", &args.concat());
+            1
+        }
+
+        fn hook_ppoll(
+            &mut self,
+            fds: PollFds,
+            tmo_p: *const libc::timespec,
+            sigmask: *const sigset_t,
+            sigsetsize: size_t,
+        ) -> c_int {
+            let args = [fds.as_ptr() as u64, fds.len() as u64, tmo_p as u64];
+            self.record(
+                "ppoll",
+                &[&args[..], &[sigmask as u64, sigsetsize as u64]].concat(),
+            );
+            1
+        }
+
+        fn hook_epoll_ctl(
+            &mut self,
+            epfd: c_int,
+            op: c_int,
+            fd: c_int,
+            event: EpollEventPtr,
+        ) -> c_int {
+            self.record(
+                "epoll_ctl",
+                &[epfd as u64, op as u64, fd as u64, event.as_ptr() as u64],
+            );
+            0
+        }
+
+        // シグナル
+        fn hook_kill(&mut self, pid: pid_t, sig: c_int) -> c_int {
+            self.record("kill", &[pid as u64, sig as u64]);
+            0
+        }
+
+        fn hook_rt_sigprocmask(
+            &mut self,
+            how: c_int,
+            set: SigsetPtr,
+            oldset: SigsetPtr,
+            sigsetsize: size_t,
+        ) -> c_int {
+            let args = [how as u64, set.as_ptr() as u64, oldset.as_ptr() as u64];
+            self.record(
+                "rt_sigprocmask",
+                &[&args[..], &[sigsetsize as u64]].concat(),
+            );
+            0
+        }
+
+        // プロセス
+        fn hook_wait4(
+            &mut self,
+            pid: pid_t,
+            wstatus: *mut c_int,
+            options: c_int,
+            rusage: *mut c_void,
+        ) -> pid_t {
+            self.record(
+                "wait4",
+                &[pid as u64, wstatus as u64, options as u64, rusage as u64],
+            );
+            -libc::ECHILD
+        }
+
+        fn hook_clone(
+            &mut self,
+            flags: CloneFlags,
+            stack: *mut c_void,
+            parent_tid: *mut pid_t,
+            child_tid: *mut pid_t,
+            tls: c_ulong,
+        ) -> pid_t {
+            let args = [
+                flags.bits(),
+                stack as u64,
+                parent_tid as u64,
+                child_tid as u64,
+            ];
+            self.record("clone", &[&args[..], &[tls]].concat());
+            -libc::EAGAIN
+        }
+
+        // 時刻
+        fn hook_clock_gettime(&mut self, clockid: libc::clockid_t, tp: TimespecPtr) -> c_int {
+            self.record("clock_gettime", &[clockid as u64, tp.as_ptr() as u64]);
+            0
+        }
+
+        fn hook_time(&mut self, tloc: *mut libc::time_t) -> libc::time_t {
+            self.record("time", &[tloc as u64]);
+            1 << 33
+        }
+
+        // ファイルシステム
+        fn hook_renameat2(
+            &mut self,
+            olddirfd: c_int,
+            oldpath: *const c_char,
+            newdirfd: c_int,
+            newpath: *const c_char,
+            flags: c_uint,
+        ) -> c_int {
+            let args = [
+                olddirfd as u64,
+                oldpath as u64,
+                newdirfd as u64,
+                newpath as u64,
+            ];
+            self.record("renameat2", &[&args[..], &[flags as u64]].concat());
+            0
+        }
+
+        fn hook_fchownat(
+            &mut self,
+            dirfd: c_int,
+            pathname: *const c_char,
+            owner: uid_t,
+            group: gid_t,
+            flags: c_int,
+        ) -> c_int {
+            let args = [dirfd as u64, pathname as u64, owner as u64, group as u64];
+            self.record("fchownat", &[&args[..], &[flags as u64]].concat());
+            0
+        }
+
+        fn hook_readlinkat(
+            &mut self,
+            dirfd: c_int,
+            pathname: *const c_char,
+            buf: *mut c_char,
+            bufsiz: size_t,
+        ) -> ssize_t {
+            self.record(
+                "readlinkat",
+                &[dirfd as u64, pathname as u64, buf as u64, bufsiz as u64],
+            );
+            3
+        }
+
+        fn hook_symlinkat(
+            &mut self,
+            target: *const c_char,
+            newdirfd: c_int,
+            linkpath: *const c_char,
+        ) -> c_int {
+            self.record(
+                "symlinkat",
+                &[target as u64, newdirfd as u64, linkpath as u64],
+            );
+            0
+        }
+
+        // 識別情報
+        fn hook_getresuid(
+            &mut self,
+            ruid: *mut uid_t,
+            euid: *mut uid_t,
+            suid: *mut uid_t,
+        ) -> c_int {
+            self.record("getresuid", &[ruid as u64, euid as u64, suid as u64]);
+            0
+        }
+
+        fn hook_prlimit64(
+            &mut self,
+            pid: pid_t,
+            resource: c_uint,
+            new_limit: RlimitPtr,
+            old_limit: RlimitPtr,
+        ) -> c_int {
+            let args = [pid as u64, resource as u64, new_limit.as_ptr() as u64];
+            self.record(
+                "prlimit64",
+                &[&args[..], &[old_limit.as_ptr() as u64]].concat(),
+            );
+            0
+        }
+
+        fn hook_sched_setaffinity(
+            &mut self,
+            pid: pid_t,
+            cpusetsize: size_t,
+            mask: *const c_ulong,
+        ) -> c_int {
+            self.record(
+                "sched_setaffinity",
+                &[pid as u64, cpusetsize as u64, mask as u64],
+            );
+            0
+        }
+    }
+
+    #[test]
+    fn test_file_io_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::read), 7);
+        assert_eq!(hooks.last(), ("read", &ARGS[..3]));
+        assert_eq!(hooks.dispatch(Sysno::pwrite64), 8);
+        assert_eq!(hooks.last(), ("pwrite64", &ARGS[..4]));
+        // preadv2のフラグはr9（r8はオフセットの上位）
+        assert_eq!(hooks.dispatch(Sysno::preadv2), 9);
+        assert_eq!(
+            hooks.last(),
+            ("preadv2", &[0x11, 0x22, 0x33, 0x44, 0x66][..])
+        );
+        // off_tの戻り値は切り詰めない
+        assert_eq!(hooks.dispatch(Sysno::lseek), 1 << 40);
+        assert_eq!(hooks.last(), ("lseek", &ARGS[..3]));
+        assert_eq!(hooks.dispatch(Sysno::openat), -libc::ENOENT as i64);
+        assert_eq!(hooks.last(), ("openat", &ARGS[..4]));
+        assert_eq!(hooks.calls.len(), 5);
+    }
+
+    #[test]
+    fn test_network_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::sendto), 5);
+        assert_eq!(hooks.last(), ("sendto", &ARGS[..]));
+        assert_eq!(hooks.dispatch(Sysno::recvmmsg), 2);
+        assert_eq!(hooks.last(), ("recvmmsg", &ARGS[..5]));
+        assert_eq!(hooks.dispatch(Sysno::setsockopt), 0);
+        assert_eq!(hooks.last(), ("setsockopt", &ARGS[..5]));
+        assert_eq!(hooks.dispatch(Sysno::accept4), 4);
+        assert_eq!(hooks.last(), ("accept4", &ARGS[..4]));
+    }
+
+    #[test]
+    fn test_poll_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::pselect6), 1);
+        assert_eq!(hooks.last(), ("pselect6", &ARGS[..]));
+        assert_eq!(hooks.dispatch(Sysno::ppoll), 1);
+        assert_eq!(hooks.last(), ("ppoll", &ARGS[..5]));
+        assert_eq!(hooks.dispatch(Sysno::epoll_ctl), 0);
+        assert_eq!(hooks.last(), ("epoll_ctl", &ARGS[..4]));
+    }
+
+    #[test]
+    fn test_signal_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::kill), 0);
+        assert_eq!(hooks.last(), ("kill", &ARGS[..2]));
+        assert_eq!(hooks.dispatch(Sysno::rt_sigprocmask), 0);
+        assert_eq!(hooks.last(), ("rt_sigprocmask", &ARGS[..4]));
+    }
+
+    #[test]
+    fn test_process_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::wait4), -libc::ECHILD as i64);
+        assert_eq!(hooks.last(), ("wait4", &ARGS[..4]));
+        // cloneの引数はカーネルの順序（flags, stack, parent_tid, child_tid, tls）
+        assert_eq!(hooks.dispatch(Sysno::clone), -libc::EAGAIN as i64);
+        assert_eq!(hooks.last(), ("clone", &ARGS[..5]));
+    }
+
+    #[test]
+    fn test_time_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::clock_gettime), 0);
+        assert_eq!(hooks.last(), ("clock_gettime", &ARGS[..2]));
+        // time_tの戻り値は切り詰めない
+        assert_eq!(hooks.dispatch(Sysno::time), 1 << 33);
+        assert_eq!(hooks.last(), ("time", &ARGS[..1]));
+    }
+
+    #[test]
+    fn test_filesystem_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::renameat2), 0);
+        assert_eq!(hooks.last(), ("renameat2", &ARGS[..5]));
+        assert_eq!(hooks.dispatch(Sysno::fchownat), 0);
+        assert_eq!(hooks.last(), ("fchownat", &ARGS[..5]));
+        assert_eq!(hooks.dispatch(Sysno::readlinkat), 3);
+        assert_eq!(hooks.last(), ("readlinkat", &ARGS[..4]));
+        assert_eq!(hooks.dispatch(Sysno::symlinkat), 0);
+        assert_eq!(hooks.last(), ("symlinkat", &ARGS[..3]));
+    }
+
+    #[test]
+    fn test_identity_dispatch() {
+        let mut hooks = Recorder::default();
+        assert_eq!(hooks.dispatch(Sysno::getresuid), 0);
+        assert_eq!(hooks.last(), ("getresuid", &ARGS[..3]));
+        assert_eq!(hooks.dispatch(Sysno::prlimit64), 0);
+        assert_eq!(hooks.last(), ("prlimit64", &ARGS[..4]));
+        assert_eq!(hooks.dispatch(Sysno::sched_setaffinity), 0);
+        assert_eq!(hooks.last(), ("sched_setaffinity", &ARGS[..3]));
+    }
+
+    #[test]
+    fn test_stat_hooks() {
+        use crate::user_mem::UserMemError;
+        use std::os::fd::AsRawFd;
+
+        struct FakeStat;
+
+        impl SyscallHooks for FakeStat {
+            fn hook_fstat(&mut self, fd: i32, statbuf: StatBuf) -> i32 {
+                let ret = default_fstat(fd, statbuf);
+                if ret == 0 {
+                    statbuf.update(|st| st.st_size = 12345).unwrap();
+                }
+                ret
+            }
+
+            fn hook_statx(
+                &mut self,
+                dirfd: i32,
+                pathname: *const i8,
+                flags: i32,
+                mask: u32,
+                statxbuf: StatxBuf,
+            ) -> i32 {
+                let ret = default_statx(dirfd, pathname, flags, mask, statxbuf);
+                if ret == 0 {
+                    statxbuf
+                        .update(|stx| {
+                            stx.stx_ino = 7;
+                            stx.stx_mask |= libc::STATX_INO;
+                        })
+                        .unwrap();
+                }
+                ret
+            }
+        }
+
+        let file = std::fs::File::open("Cargo.toml").unwrap();
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        let mut regs = SyscallRegs::new(
+            Sysno::fstat.nr(),
+            file.as_raw_fd() as u64,
+            &mut st as *mut libc::stat as u64,
+            0,
+            0,
+            0,
+            0,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut FakeStat, &mut regs), 0);
+        assert_eq!(st.st_size, 12345);
+        assert_ne!(st.st_mode, 0);
+
+        let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+        let mut regs = SyscallRegs::new(
+            Sysno::statx.nr(),
+            libc::AT_FDCWD as u64,
+            c"Cargo.toml".as_ptr() as u64,
+            0,
+            libc::STATX_BASIC_STATS as u64,
+            &mut stx as *mut libc::statx as u64,
+            0,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut FakeStat, &mut regs), 0);
+        assert_eq!(stx.stx_ino, 7);
+        assert_eq!(stx.stx_size, std::fs::metadata("Cargo.toml").unwrap().len());
+
+        // 不正なアドレスはカーネルと同じくEFAULT（書き換えは行わない）
+        let mut regs = regs.with_arg(4, 8);
+        assert_eq!(
+            dispatch_syscall_hooks(&mut FakeStat, &mut regs),
+            -(libc::EFAULT as i64)
+        );
+        assert_eq!(
+            StatBuf::new(std::ptr::dangling_mut()).read().err(),
+            Some(UserMemError::Fault)
+        );
+    }
+
+    #[test]
+    fn test_epoll_pwait2_hooks() {
+        #[derive(Default)]
+        struct RecordTimeout {
+            timeout: Option<std::time::Duration>,
+        }
+
+        impl SyscallHooks for RecordTimeout {
+            fn hook_epoll_pwait2(
+                &mut self,
+                epfd: c_int,
+                events: EpollEvents,
+                timeout: *const libc::timespec,
+                sigmask: *const sigset_t,
+                sigsetsize: size_t,
+            ) -> c_int {
+                self.timeout = read_timespec(timeout).unwrap();
+                default_epoll_pwait2(epfd, events, timeout, sigmask, sigsetsize)
+            }
+        }
+
+        let mut pipe = [0; 2];
+        assert_eq!(unsafe { libc::pipe(pipe.as_mut_ptr()) }, 0);
+        let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        let mut ev = libc::epoll_event {
+            events: libc::EPOLLOUT as u32,
+            u64: 9,
+        };
+        assert_eq!(
+            unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, pipe[1], &mut ev) },
+            0
+        );
+
+        let mut hooks = RecordTimeout::default();
+        let mut buf = [libc::epoll_event { events: 0, u64: 0 }; 4];
+        let ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 5_000,
+        };
+        let mut regs = SyscallRegs::new(
+            Sysno::epoll_pwait2.nr(),
+            epfd as u64,
+            buf.as_mut_ptr() as u64,
+            buf.len() as u64,
+            &ts as *const libc::timespec as u64,
+            0,
+            8,
+        );
+        assert_eq!(dispatch_syscall_hooks(&mut hooks, &mut regs), 1);
+        assert_eq!(hooks.timeout, Some(std::time::Duration::from_nanos(5_000)));
+        assert_eq!({ buf[0].u64 }, 9);
+        assert!(crate::vfd::POLL_SYSCALLS.contains(Sysno::epoll_pwait2));
+
+        unsafe {
+            libc::close(epfd);
+            libc::close(pipe[0]);
+            libc::close(pipe[1]);
+        }
+    }
+
+    #[test]
+    fn test_default_routing() {
+        struct Passthrough;
+        impl SyscallHooks for Passthrough {}
+
+        let dispatch = |hooks: &mut dyn SyscallHooks, nr: u64, args: [u64; 3]| {
+            let mut regs = SyscallRegs::new(nr, args[0], args[1], args[2], 0, 0, 0);
+            dispatch_syscall_hooks(hooks, &mut regs)
+        };
+
+        // オーバーライドしていないフックはそのままシステムコールを実行する
+        let pid = std::process::id() as i64;
+        assert_eq!(dispatch(&mut Passthrough, Sysno::getpid.nr(), [0; 3]), pid);
+        assert_eq!(
+            dispatch(&mut Passthrough, Sysno::getuid.nr(), [0; 3]),
+            unsafe { libc::getuid() } as i64
+        );
+
+        let mut pipe = [0; 2];
+        assert_eq!(unsafe { libc::pipe(pipe.as_mut_ptr()) }, 0);
+        let data = b"zpoline";
+        let write = [pipe[1] as u64, data.as_ptr() as u64, data.len() as u64];
+        assert_eq!(dispatch(&mut Passthrough, Sysno::write.nr(), write), 7);
+        let mut buf = [0u8; 16];
+        let read = [pipe[0] as u64, buf.as_mut_ptr() as u64, buf.len() as u64];
+        assert_eq!(dispatch(&mut Passthrough, Sysno::read.nr(), read), 7);
+        assert_eq!(&buf[..7], data);
+
+        // ディスパッチ表にないシステムコールと未知の番号はカーネルに渡す
+        assert_eq!(
+            dispatch(&mut Passthrough, Sysno::getppid.nr(), [0; 3]),
+            unsafe { libc::getppid() } as i64
+        );
+        assert_eq!(
+            dispatch(&mut Passthrough, 1000, [0; 3]),
+            -libc::ENOSYS as i64
+        );
+
+        // オーバーライドした型でも他のシステムコールは既定の処理になる
+        let mut hooks = Recorder::default();
+        assert_eq!(dispatch(&mut hooks, Sysno::getpid.nr(), [0; 3]), pid);
+        assert!(hooks.calls.is_empty());
+
+        unsafe {
+            libc::close(pipe[0]);
+            libc::close(pipe[1]);
+        }
+    }
+}
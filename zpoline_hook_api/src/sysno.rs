@@ -0,0 +1,1056 @@
+//! x86-64 Linuxのシステムコール番号
+//!
+//! `Sysno`はシステムコール番号と名前の対応表です。トレーサやディスパッチャは
+//! 個別に`match`テーブルを持たず、この型を経由して番号を扱います。
+
+use std::fmt;
+use std::str::FromStr;
+
+/// `SysnoSet`が扱えるシステムコール番号の上限（トランポリンのNOP sledと同じ）
+pub const MAX_SYSNO: usize = 512;
+
+const SET_WORDS: usize = MAX_SYSNO / 64;
+
+macro_rules! syscall_table {
+    ($($name:ident = $nr:literal,)*) => {
+        /// x86-64のシステムコール番号
+        ///
+        /// バリアント名はカーネルのシステムコール名（`__NR_*`）と同じです。
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u32)]
+        pub enum Sysno {
+            $($name = $nr,)*
+        }
+
+        impl Sysno {
+            /// 定義されているすべてのシステムコール（番号順）
+            pub const ALL: &'static [Sysno] = &[$(Sysno::$name,)*];
+
+            /// raxの値からシステムコールを取得
+            pub const fn from_raw(nr: u64) -> Option<Sysno> {
+                match nr {
+                    $($nr => Some(Sysno::$name),)*
+                    _ => None,
+                }
+            }
+
+            /// システムコール名を取得
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Sysno::$name => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+syscall_table! {
+    read = 0,
+    write = 1,
+    open = 2,
+    close = 3,
+    stat = 4,
+    fstat = 5,
+    lstat = 6,
+    poll = 7,
+    lseek = 8,
+    mmap = 9,
+    mprotect = 10,
+    munmap = 11,
+    brk = 12,
+    rt_sigaction = 13,
+    rt_sigprocmask = 14,
+    rt_sigreturn = 15,
+    ioctl = 16,
+    pread64 = 17,
+    pwrite64 = 18,
+    readv = 19,
+    writev = 20,
+    access = 21,
+    pipe = 22,
+    select = 23,
+    sched_yield = 24,
+    mremap = 25,
+    msync = 26,
+    mincore = 27,
+    madvise = 28,
+    shmget = 29,
+    shmat = 30,
+    shmctl = 31,
+    dup = 32,
+    dup2 = 33,
+    pause = 34,
+    nanosleep = 35,
+    getitimer = 36,
+    alarm = 37,
+    setitimer = 38,
+    getpid = 39,
+    sendfile = 40,
+    socket = 41,
+    connect = 42,
+    accept = 43,
+    sendto = 44,
+    recvfrom = 45,
+    sendmsg = 46,
+    recvmsg = 47,
+    shutdown = 48,
+    bind = 49,
+    listen = 50,
+    getsockname = 51,
+    getpeername = 52,
+    socketpair = 53,
+    setsockopt = 54,
+    getsockopt = 55,
+    clone = 56,
+    fork = 57,
+    vfork = 58,
+    execve = 59,
+    exit = 60,
+    wait4 = 61,
+    kill = 62,
+    uname = 63,
+    semget = 64,
+    semop = 65,
+    semctl = 66,
+    shmdt = 67,
+    msgget = 68,
+    msgsnd = 69,
+    msgrcv = 70,
+    msgctl = 71,
+    fcntl = 72,
+    flock = 73,
+    fsync = 74,
+    fdatasync = 75,
+    truncate = 76,
+    ftruncate = 77,
+    getdents = 78,
+    getcwd = 79,
+    chdir = 80,
+    fchdir = 81,
+    rename = 82,
+    mkdir = 83,
+    rmdir = 84,
+    creat = 85,
+    link = 86,
+    unlink = 87,
+    symlink = 88,
+    readlink = 89,
+    chmod = 90,
+    fchmod = 91,
+    chown = 92,
+    fchown = 93,
+    lchown = 94,
+    umask = 95,
+    gettimeofday = 96,
+    getrlimit = 97,
+    getrusage = 98,
+    sysinfo = 99,
+    times = 100,
+    ptrace = 101,
+    getuid = 102,
+    syslog = 103,
+    getgid = 104,
+    setuid = 105,
+    setgid = 106,
+    geteuid = 107,
+    getegid = 108,
+    setpgid = 109,
+    getppid = 110,
+    getpgrp = 111,
+    setsid = 112,
+    setreuid = 113,
+    setregid = 114,
+    getgroups = 115,
+    setgroups = 116,
+    setresuid = 117,
+    getresuid = 118,
+    setresgid = 119,
+    getresgid = 120,
+    getpgid = 121,
+    setfsuid = 122,
+    setfsgid = 123,
+    getsid = 124,
+    capget = 125,
+    capset = 126,
+    rt_sigpending = 127,
+    rt_sigtimedwait = 128,
+    rt_sigqueueinfo = 129,
+    rt_sigsuspend = 130,
+    sigaltstack = 131,
+    utime = 132,
+    mknod = 133,
+    uselib = 134,
+    personality = 135,
+    ustat = 136,
+    statfs = 137,
+    fstatfs = 138,
+    sysfs = 139,
+    getpriority = 140,
+    setpriority = 141,
+    sched_setparam = 142,
+    sched_getparam = 143,
+    sched_setscheduler = 144,
+    sched_getscheduler = 145,
+    sched_get_priority_max = 146,
+    sched_get_priority_min = 147,
+    sched_rr_get_interval = 148,
+    mlock = 149,
+    munlock = 150,
+    mlockall = 151,
+    munlockall = 152,
+    vhangup = 153,
+    modify_ldt = 154,
+    pivot_root = 155,
+    _sysctl = 156,
+    prctl = 157,
+    arch_prctl = 158,
+    adjtimex = 159,
+    setrlimit = 160,
+    chroot = 161,
+    sync = 162,
+    acct = 163,
+    settimeofday = 164,
+    mount = 165,
+    umount2 = 166,
+    swapon = 167,
+    swapoff = 168,
+    reboot = 169,
+    sethostname = 170,
+    setdomainname = 171,
+    iopl = 172,
+    ioperm = 173,
+    create_module = 174,
+    init_module = 175,
+    delete_module = 176,
+    get_kernel_syms = 177,
+    query_module = 178,
+    quotactl = 179,
+    nfsservctl = 180,
+    getpmsg = 181,
+    putpmsg = 182,
+    afs_syscall = 183,
+    tuxcall = 184,
+    security = 185,
+    gettid = 186,
+    readahead = 187,
+    setxattr = 188,
+    lsetxattr = 189,
+    fsetxattr = 190,
+    getxattr = 191,
+    lgetxattr = 192,
+    fgetxattr = 193,
+    listxattr = 194,
+    llistxattr = 195,
+    flistxattr = 196,
+    removexattr = 197,
+    lremovexattr = 198,
+    fremovexattr = 199,
+    tkill = 200,
+    time = 201,
+    futex = 202,
+    sched_setaffinity = 203,
+    sched_getaffinity = 204,
+    set_thread_area = 205,
+    io_setup = 206,
+    io_destroy = 207,
+    io_getevents = 208,
+    io_submit = 209,
+    io_cancel = 210,
+    get_thread_area = 211,
+    lookup_dcookie = 212,
+    epoll_create = 213,
+    epoll_ctl_old = 214,
+    epoll_wait_old = 215,
+    remap_file_pages = 216,
+    getdents64 = 217,
+    set_tid_address = 218,
+    restart_syscall = 219,
+    semtimedop = 220,
+    fadvise64 = 221,
+    timer_create = 222,
+    timer_settime = 223,
+    timer_gettime = 224,
+    timer_getoverrun = 225,
+    timer_delete = 226,
+    clock_settime = 227,
+    clock_gettime = 228,
+    clock_getres = 229,
+    clock_nanosleep = 230,
+    exit_group = 231,
+    epoll_wait = 232,
+    epoll_ctl = 233,
+    tgkill = 234,
+    utimes = 235,
+    vserver = 236,
+    mbind = 237,
+    set_mempolicy = 238,
+    get_mempolicy = 239,
+    mq_open = 240,
+    mq_unlink = 241,
+    mq_timedsend = 242,
+    mq_timedreceive = 243,
+    mq_notify = 244,
+    mq_getsetattr = 245,
+    kexec_load = 246,
+    waitid = 247,
+    add_key = 248,
+    request_key = 249,
+    keyctl = 250,
+    ioprio_set = 251,
+    ioprio_get = 252,
+    inotify_init = 253,
+    inotify_add_watch = 254,
+    inotify_rm_watch = 255,
+    migrate_pages = 256,
+    openat = 257,
+    mkdirat = 258,
+    mknodat = 259,
+    fchownat = 260,
+    futimesat = 261,
+    newfstatat = 262,
+    unlinkat = 263,
+    renameat = 264,
+    linkat = 265,
+    symlinkat = 266,
+    readlinkat = 267,
+    fchmodat = 268,
+    faccessat = 269,
+    pselect6 = 270,
+    ppoll = 271,
+    unshare = 272,
+    set_robust_list = 273,
+    get_robust_list = 274,
+    splice = 275,
+    tee = 276,
+    sync_file_range = 277,
+    vmsplice = 278,
+    move_pages = 279,
+    utimensat = 280,
+    epoll_pwait = 281,
+    signalfd = 282,
+    timerfd_create = 283,
+    eventfd = 284,
+    fallocate = 285,
+    timerfd_settime = 286,
+    timerfd_gettime = 287,
+    accept4 = 288,
+    signalfd4 = 289,
+    eventfd2 = 290,
+    epoll_create1 = 291,
+    dup3 = 292,
+    pipe2 = 293,
+    inotify_init1 = 294,
+    preadv = 295,
+    pwritev = 296,
+    rt_tgsigqueueinfo = 297,
+    perf_event_open = 298,
+    recvmmsg = 299,
+    fanotify_init = 300,
+    fanotify_mark = 301,
+    prlimit64 = 302,
+    name_to_handle_at = 303,
+    open_by_handle_at = 304,
+    clock_adjtime = 305,
+    syncfs = 306,
+    sendmmsg = 307,
+    setns = 308,
+    getcpu = 309,
+    process_vm_readv = 310,
+    process_vm_writev = 311,
+    kcmp = 312,
+    finit_module = 313,
+    sched_setattr = 314,
+    sched_getattr = 315,
+    renameat2 = 316,
+    seccomp = 317,
+    getrandom = 318,
+    memfd_create = 319,
+    kexec_file_load = 320,
+    bpf = 321,
+    execveat = 322,
+    userfaultfd = 323,
+    membarrier = 324,
+    mlock2 = 325,
+    copy_file_range = 326,
+    preadv2 = 327,
+    pwritev2 = 328,
+    pkey_mprotect = 329,
+    pkey_alloc = 330,
+    pkey_free = 331,
+    statx = 332,
+    io_pgetevents = 333,
+    rseq = 334,
+    uretprobe = 335,
+    pidfd_send_signal = 424,
+    io_uring_setup = 425,
+    io_uring_enter = 426,
+    io_uring_register = 427,
+    open_tree = 428,
+    move_mount = 429,
+    fsopen = 430,
+    fsconfig = 431,
+    fsmount = 432,
+    fspick = 433,
+    pidfd_open = 434,
+    clone3 = 435,
+    close_range = 436,
+    openat2 = 437,
+    pidfd_getfd = 438,
+    faccessat2 = 439,
+    process_madvise = 440,
+    epoll_pwait2 = 441,
+    mount_setattr = 442,
+    quotactl_fd = 443,
+    landlock_create_ruleset = 444,
+    landlock_add_rule = 445,
+    landlock_restrict_self = 446,
+    memfd_secret = 447,
+    process_mrelease = 448,
+    futex_waitv = 449,
+    set_mempolicy_home_node = 450,
+    cachestat = 451,
+    fchmodat2 = 452,
+    map_shadow_stack = 453,
+    futex_wake = 454,
+    futex_wait = 455,
+    futex_requeue = 456,
+    statmount = 457,
+    listmount = 458,
+    lsm_get_self_attr = 459,
+    lsm_set_self_attr = 460,
+    lsm_list_modules = 461,
+    mseal = 462,
+    setxattrat = 463,
+    getxattrat = 464,
+    listxattrat = 465,
+    removexattrat = 466,
+    open_tree_attr = 467,
+    file_getattr = 468,
+    file_setattr = 469,
+}
+
+impl Sysno {
+    /// システムコール番号（raxに入る値）
+    pub const fn nr(self) -> u64 {
+        self as u64
+    }
+
+    /// 番号から名前を引く。未定義の番号は`None`
+    pub const fn name_of(nr: u64) -> Option<&'static str> {
+        match Sysno::from_raw(nr) {
+            Some(sysno) => Some(sysno.name()),
+            None => None,
+        }
+    }
+
+    /// このシステムコールが指定カテゴリに属するか
+    pub fn is_in(self, category: SyscallCategory) -> bool {
+        category.syscalls().contains(self)
+    }
+
+    /// このシステムコールが属するカテゴリの一覧
+    pub fn categories(self) -> impl Iterator<Item = SyscallCategory> {
+        SyscallCategory::ALL
+            .iter()
+            .copied()
+            .filter(move |category| self.is_in(*category))
+    }
+}
+
+impl fmt::Display for Sysno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<Sysno> for u64 {
+    fn from(sysno: Sysno) -> u64 {
+        sysno.nr()
+    }
+}
+
+impl TryFrom<u64> for Sysno {
+    type Error = u64;
+
+    fn try_from(nr: u64) -> Result<Self, Self::Error> {
+        Sysno::from_raw(nr).ok_or(nr)
+    }
+}
+
+/// システムコール名のパースエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSysnoError(String);
+
+impl fmt::Display for ParseSysnoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown syscall: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseSysnoError {}
+
+impl FromStr for Sysno {
+    type Err = ParseSysnoError;
+
+    /// システムコール名（`"openat"`）または番号（`"257"`）からパース
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(nr) = s.parse::<u64>() {
+            return Sysno::from_raw(nr).ok_or_else(|| ParseSysnoError(s.to_string()));
+        }
+        Sysno::ALL
+            .iter()
+            .copied()
+            .find(|sysno| sysno.name() == s)
+            .ok_or_else(|| ParseSysnoError(s.to_string()))
+    }
+}
+
+/// システムコール番号の集合（ビットセット）
+///
+/// `const`で構築できるため、カテゴリやフックライブラリの関心集合を
+/// 静的に宣言できます。
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SysnoSet {
+    bits: [u64; SET_WORDS],
+}
+
+impl SysnoSet {
+    /// 空の集合
+    pub const fn empty() -> Self {
+        Self {
+            bits: [0; SET_WORDS],
+        }
+    }
+
+    /// すべてのシステムコール番号を含む集合
+    pub const fn all() -> Self {
+        Self {
+            bits: [u64::MAX; SET_WORDS],
+        }
+    }
+
+    /// ビット列から集合を作成（bit nがシステムコール番号nに対応）
+    pub const fn from_words(bits: [u64; SET_WORDS]) -> Self {
+        Self { bits }
+    }
+
+    /// 集合のビット列
+    pub const fn as_words(&self) -> [u64; SET_WORDS] {
+        self.bits
+    }
+
+    /// システムコールのリストから集合を作成
+    pub const fn new(syscalls: &[Sysno]) -> Self {
+        let mut set = Self::empty();
+        let mut i = 0;
+        while i < syscalls.len() {
+            set = set.with(syscalls[i]);
+            i += 1;
+        }
+        set
+    }
+
+    /// システムコールを追加した集合を返す（const文脈用）
+    pub const fn with(mut self, sysno: Sysno) -> Self {
+        let nr = sysno as usize;
+        self.bits[nr / 64] |= 1 << (nr % 64);
+        self
+    }
+
+    /// 和集合
+    pub const fn union(mut self, other: &SysnoSet) -> Self {
+        let mut i = 0;
+        while i < SET_WORDS {
+            self.bits[i] |= other.bits[i];
+            i += 1;
+        }
+        self
+    }
+
+    /// 積集合
+    pub const fn intersection(mut self, other: &SysnoSet) -> Self {
+        let mut i = 0;
+        while i < SET_WORDS {
+            self.bits[i] &= other.bits[i];
+            i += 1;
+        }
+        self
+    }
+
+    /// システムコールを追加
+    pub fn insert(&mut self, sysno: Sysno) {
+        *self = self.with(sysno);
+    }
+
+    /// システムコールを削除
+    pub fn remove(&mut self, sysno: Sysno) {
+        let nr = sysno as usize;
+        self.bits[nr / 64] &= !(1 << (nr % 64));
+    }
+
+    /// システムコールが含まれるか
+    pub const fn contains(&self, sysno: Sysno) -> bool {
+        self.contains_raw(sysno as u64)
+    }
+
+    /// raxの値が含まれるか（範囲外の番号は常に`false`）
+    pub const fn contains_raw(&self, nr: u64) -> bool {
+        if nr >= MAX_SYSNO as u64 {
+            return false;
+        }
+        let nr = nr as usize;
+        self.bits[nr / 64] & (1 << (nr % 64)) != 0
+    }
+
+    /// 空かどうか
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+
+    /// 含まれる定義済みシステムコールの数
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// 含まれる定義済みシステムコールを番号順に列挙
+    pub fn iter(&self) -> impl Iterator<Item = Sysno> + '_ {
+        Sysno::ALL
+            .iter()
+            .copied()
+            .filter(move |sysno| self.contains(*sysno))
+    }
+}
+
+impl Default for SysnoSet {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for SysnoSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<Sysno> for SysnoSet {
+    fn from_iter<I: IntoIterator<Item = Sysno>>(iter: I) -> Self {
+        let mut set = SysnoSet::empty();
+        for sysno in iter {
+            set.insert(sysno);
+        }
+        set
+    }
+}
+
+/// システムコールのカテゴリ
+///
+/// straceの`%file`, `%network`等に相当する分類です。
+/// 1つのシステムコールが複数のカテゴリに属することがあります（例: `kill`は
+/// processとsignalの両方）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyscallCategory {
+    /// ファイル・ファイルディスクリプタ操作
+    File,
+    /// ソケット操作
+    Network,
+    /// プロセスの生成・終了・識別
+    Process,
+    /// メモリマッピング・メモリ管理
+    Memory,
+    /// シグナル
+    Signal,
+    /// System V IPC / POSIXメッセージキュー
+    Ipc,
+}
+
+impl SyscallCategory {
+    /// すべてのカテゴリ
+    pub const ALL: &'static [SyscallCategory] = &[
+        SyscallCategory::File,
+        SyscallCategory::Network,
+        SyscallCategory::Process,
+        SyscallCategory::Memory,
+        SyscallCategory::Signal,
+        SyscallCategory::Ipc,
+    ];
+
+    /// カテゴリ名
+    pub const fn name(self) -> &'static str {
+        match self {
+            SyscallCategory::File => "file",
+            SyscallCategory::Network => "network",
+            SyscallCategory::Process => "process",
+            SyscallCategory::Memory => "memory",
+            SyscallCategory::Signal => "signal",
+            SyscallCategory::Ipc => "ipc",
+        }
+    }
+
+    /// このカテゴリに属するシステムコールの集合
+    pub const fn syscalls(self) -> &'static SysnoSet {
+        match self {
+            SyscallCategory::File => &FILE_SYSCALLS,
+            SyscallCategory::Network => &NETWORK_SYSCALLS,
+            SyscallCategory::Process => &PROCESS_SYSCALLS,
+            SyscallCategory::Memory => &MEMORY_SYSCALLS,
+            SyscallCategory::Signal => &SIGNAL_SYSCALLS,
+            SyscallCategory::Ipc => &IPC_SYSCALLS,
+        }
+    }
+}
+
+impl fmt::Display for SyscallCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SyscallCategory {
+    type Err = ParseSysnoError;
+
+    /// カテゴリ名（`"file"`、strace形式の`"%file"`も可）からパース
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().trim_start_matches('%');
+        SyscallCategory::ALL
+            .iter()
+            .copied()
+            .find(|category| category.name() == name)
+            .ok_or_else(|| ParseSysnoError(s.to_string()))
+    }
+}
+
+static FILE_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    // パスを受け取るもの
+    Sysno::open,
+    Sysno::stat,
+    Sysno::lstat,
+    Sysno::access,
+    Sysno::execve,
+    Sysno::truncate,
+    Sysno::getcwd,
+    Sysno::chdir,
+    Sysno::rename,
+    Sysno::mkdir,
+    Sysno::rmdir,
+    Sysno::creat,
+    Sysno::link,
+    Sysno::unlink,
+    Sysno::symlink,
+    Sysno::readlink,
+    Sysno::chmod,
+    Sysno::chown,
+    Sysno::lchown,
+    Sysno::utime,
+    Sysno::mknod,
+    Sysno::uselib,
+    Sysno::statfs,
+    Sysno::pivot_root,
+    Sysno::chroot,
+    Sysno::acct,
+    Sysno::mount,
+    Sysno::umount2,
+    Sysno::swapon,
+    Sysno::swapoff,
+    Sysno::quotactl,
+    Sysno::setxattr,
+    Sysno::lsetxattr,
+    Sysno::getxattr,
+    Sysno::lgetxattr,
+    Sysno::listxattr,
+    Sysno::llistxattr,
+    Sysno::removexattr,
+    Sysno::lremovexattr,
+    Sysno::utimes,
+    Sysno::inotify_add_watch,
+    Sysno::openat,
+    Sysno::mkdirat,
+    Sysno::mknodat,
+    Sysno::fchownat,
+    Sysno::futimesat,
+    Sysno::newfstatat,
+    Sysno::unlinkat,
+    Sysno::renameat,
+    Sysno::linkat,
+    Sysno::symlinkat,
+    Sysno::readlinkat,
+    Sysno::fchmodat,
+    Sysno::faccessat,
+    Sysno::utimensat,
+    Sysno::fanotify_mark,
+    Sysno::name_to_handle_at,
+    Sysno::open_by_handle_at,
+    Sysno::renameat2,
+    Sysno::execveat,
+    Sysno::statx,
+    Sysno::open_tree,
+    Sysno::move_mount,
+    Sysno::fspick,
+    Sysno::openat2,
+    Sysno::faccessat2,
+    Sysno::mount_setattr,
+    Sysno::fchmodat2,
+    Sysno::setxattrat,
+    Sysno::getxattrat,
+    Sysno::listxattrat,
+    Sysno::removexattrat,
+    Sysno::open_tree_attr,
+    Sysno::file_getattr,
+    Sysno::file_setattr,
+    // ファイルディスクリプタを操作するもの
+    Sysno::read,
+    Sysno::write,
+    Sysno::close,
+    Sysno::fstat,
+    Sysno::lseek,
+    Sysno::ioctl,
+    Sysno::pread64,
+    Sysno::pwrite64,
+    Sysno::readv,
+    Sysno::writev,
+    Sysno::pipe,
+    Sysno::dup,
+    Sysno::dup2,
+    Sysno::sendfile,
+    Sysno::fcntl,
+    Sysno::flock,
+    Sysno::fsync,
+    Sysno::fdatasync,
+    Sysno::ftruncate,
+    Sysno::getdents,
+    Sysno::fchdir,
+    Sysno::fchmod,
+    Sysno::fchown,
+    Sysno::umask,
+    Sysno::fstatfs,
+    Sysno::sync,
+    Sysno::readahead,
+    Sysno::fsetxattr,
+    Sysno::fgetxattr,
+    Sysno::flistxattr,
+    Sysno::fremovexattr,
+    Sysno::getdents64,
+    Sysno::fadvise64,
+    Sysno::inotify_init,
+    Sysno::inotify_rm_watch,
+    Sysno::splice,
+    Sysno::tee,
+    Sysno::sync_file_range,
+    Sysno::vmsplice,
+    Sysno::fallocate,
+    Sysno::dup3,
+    Sysno::pipe2,
+    Sysno::inotify_init1,
+    Sysno::preadv,
+    Sysno::pwritev,
+    Sysno::fanotify_init,
+    Sysno::syncfs,
+    Sysno::memfd_create,
+    Sysno::copy_file_range,
+    Sysno::preadv2,
+    Sysno::pwritev2,
+    Sysno::close_range,
+    Sysno::quotactl_fd,
+    Sysno::cachestat,
+]);
+
+static NETWORK_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::socket,
+    Sysno::connect,
+    Sysno::accept,
+    Sysno::sendto,
+    Sysno::recvfrom,
+    Sysno::sendmsg,
+    Sysno::recvmsg,
+    Sysno::shutdown,
+    Sysno::bind,
+    Sysno::listen,
+    Sysno::getsockname,
+    Sysno::getpeername,
+    Sysno::socketpair,
+    Sysno::setsockopt,
+    Sysno::getsockopt,
+    Sysno::accept4,
+    Sysno::recvmmsg,
+    Sysno::sendmmsg,
+]);
+
+static PROCESS_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::getpid,
+    Sysno::clone,
+    Sysno::fork,
+    Sysno::vfork,
+    Sysno::execve,
+    Sysno::exit,
+    Sysno::wait4,
+    Sysno::kill,
+    Sysno::ptrace,
+    Sysno::setpgid,
+    Sysno::getppid,
+    Sysno::getpgrp,
+    Sysno::setsid,
+    Sysno::getpgid,
+    Sysno::getsid,
+    Sysno::prctl,
+    Sysno::arch_prctl,
+    Sysno::gettid,
+    Sysno::tkill,
+    Sysno::set_tid_address,
+    Sysno::exit_group,
+    Sysno::tgkill,
+    Sysno::waitid,
+    Sysno::unshare,
+    Sysno::rt_tgsigqueueinfo,
+    Sysno::setns,
+    Sysno::kcmp,
+    Sysno::execveat,
+    Sysno::pidfd_send_signal,
+    Sysno::pidfd_open,
+    Sysno::clone3,
+    Sysno::pidfd_getfd,
+]);
+
+static MEMORY_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::mmap,
+    Sysno::mprotect,
+    Sysno::munmap,
+    Sysno::brk,
+    Sysno::mremap,
+    Sysno::msync,
+    Sysno::mincore,
+    Sysno::madvise,
+    Sysno::shmat,
+    Sysno::shmdt,
+    Sysno::mlock,
+    Sysno::munlock,
+    Sysno::mlockall,
+    Sysno::munlockall,
+    Sysno::remap_file_pages,
+    Sysno::mbind,
+    Sysno::set_mempolicy,
+    Sysno::get_mempolicy,
+    Sysno::migrate_pages,
+    Sysno::move_pages,
+    Sysno::mlock2,
+    Sysno::pkey_mprotect,
+    Sysno::pkey_alloc,
+    Sysno::pkey_free,
+    Sysno::process_vm_readv,
+    Sysno::process_vm_writev,
+    Sysno::process_madvise,
+    Sysno::memfd_secret,
+    Sysno::process_mrelease,
+    Sysno::set_mempolicy_home_node,
+    Sysno::map_shadow_stack,
+    Sysno::mseal,
+]);
+
+static SIGNAL_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::rt_sigaction,
+    Sysno::rt_sigprocmask,
+    Sysno::rt_sigreturn,
+    Sysno::pause,
+    Sysno::kill,
+    Sysno::rt_sigpending,
+    Sysno::rt_sigtimedwait,
+    Sysno::rt_sigqueueinfo,
+    Sysno::rt_sigsuspend,
+    Sysno::sigaltstack,
+    Sysno::tkill,
+    Sysno::tgkill,
+    Sysno::signalfd,
+    Sysno::signalfd4,
+    Sysno::rt_tgsigqueueinfo,
+    Sysno::pidfd_send_signal,
+]);
+
+static IPC_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::shmget,
+    Sysno::shmat,
+    Sysno::shmctl,
+    Sysno::semget,
+    Sysno::semop,
+    Sysno::semctl,
+    Sysno::shmdt,
+    Sysno::msgget,
+    Sysno::msgsnd,
+    Sysno::msgrcv,
+    Sysno::msgctl,
+    Sysno::semtimedop,
+    Sysno::mq_open,
+    Sysno::mq_unlink,
+    Sysno::mq_timedsend,
+    Sysno::mq_timedreceive,
+    Sysno::mq_notify,
+    Sysno::mq_getsetattr,
+]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_raw_and_name() {
+        assert_eq!(Sysno::from_raw(0), Some(Sysno::read));
+        assert_eq!(Sysno::from_raw(257), Some(Sysno::openat));
+        assert_eq!(Sysno::from_raw(435), Some(Sysno::clone3));
+        assert_eq!(Sysno::from_raw(400), None);
+        assert_eq!(Sysno::openat.name(), "openat");
+        assert_eq!(Sysno::name_of(262), Some("newfstatat"));
+        assert_eq!(Sysno::openat.nr(), 257);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("write".parse::<Sysno>(), Ok(Sysno::write));
+        assert_eq!("39".parse::<Sysno>(), Ok(Sysno::getpid));
+        assert!("no_such_syscall".parse::<Sysno>().is_err());
+        assert_eq!(
+            "%network".parse::<SyscallCategory>(),
+            Ok(SyscallCategory::Network)
+        );
+    }
+
+    #[test]
+    fn test_table_is_consistent() {
+        for sysno in Sysno::ALL {
+            assert!((sysno.nr() as usize) < MAX_SYSNO);
+            assert_eq!(Sysno::from_raw(sysno.nr()), Some(*sysno));
+            assert_eq!(sysno.name().parse::<Sysno>(), Ok(*sysno));
+        }
+    }
+
+    #[test]
+    fn test_categories() {
+        assert!(Sysno::openat.is_in(SyscallCategory::File));
+        assert!(Sysno::connect.is_in(SyscallCategory::Network));
+        assert!(!Sysno::connect.is_in(SyscallCategory::File));
+        let kill: Vec<_> = Sysno::kill.categories().collect();
+        assert_eq!(
+            kill,
+            vec![SyscallCategory::Process, SyscallCategory::Signal]
+        );
+    }
+
+    #[test]
+    fn test_sysno_set() {
+        let mut set = SysnoSet::new(&[Sysno::read, Sysno::write]);
+        assert!(set.contains(Sysno::read));
+        assert!(set.contains_raw(1));
+        assert!(!set.contains(Sysno::open));
+        assert!(!set.contains_raw(10_000));
+        set.insert(Sysno::clone3);
+        set.remove(Sysno::read);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![Sysno::write, Sysno::clone3]
+        );
+        assert_eq!(set.len(), 2);
+
+        let file = SyscallCategory::File.syscalls();
+        assert_eq!(set.intersection(file).len(), 1);
+    }
+}
@@ -0,0 +1,900 @@
+//! システムコール引数が指すアプリケーションメモリへのアクセサ
+//!
+//! フックは`*const c_char`や`*const c_void`のような生ポインタを受け取ります。
+//! このモジュールはそれらをパス・バッファ・iovec・sockaddrとして読み書きする
+//! 型付きの関数を提供し、NULLチェックと長さの上限チェックを一箇所にまとめます。
+//!
+//! 参照を返す関数はポインタの有効性をアプリケーションがカーネルに渡した値を
+//! 信頼するため、すべて`unsafe`です。不正なアドレスを渡されるとフォールトします。
+//!
+//! `try_`で始まる関数はフォールトセーフなモードです。自プロセスに対する
+//! `process_vm_readv`/`process_vm_writev`でコピーするため、不正なアドレスでは
+//! カーネルと同じく`EFAULT`相当のエラーを返し、プロセスはクラッシュしません。
+
+use crate::{raw_syscall_bypass, SyscallRegs, Sysno};
+use libc::{c_char, c_void, iovec};
+use std::ffi::CStr;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+/// パス文字列として読み取る最大長（終端NULを含む）
+pub const PATH_MAX: usize = libc::PATH_MAX as usize;
+
+/// 1回のシステムコールで扱えるiovecの最大数（カーネルのUIO_MAXIOV）
+pub const IOV_MAX: usize = 1024;
+
+/// NUL終端文字列を読み取る
+///
+/// 先頭`max_len`バイト以内にNULが見つからない場合は`None`を返します。
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、NULまたは`max_len`バイトまで読み取り可能なメモリを指すこと
+pub unsafe fn c_str<'a>(ptr: *const c_char, max_len: usize) -> Option<&'a CStr> {
+    if ptr.is_null() {
+        return None;
+    }
+    let bytes = ptr as *const u8;
+    let len = (0..max_len).find(|&i| *bytes.add(i) == 0)?;
+    Some(CStr::from_bytes_with_nul_unchecked(
+        std::slice::from_raw_parts(bytes, len + 1),
+    ))
+}
+
+/// パス引数を読み取る（上限は`PATH_MAX`）
+///
+/// # Safety
+///
+/// `c_str`と同じ
+pub unsafe fn path<'a>(ptr: *const c_char) -> Option<&'a CStr> {
+    c_str(ptr, PATH_MAX)
+}
+
+/// バッファを読み取り専用スライスとして参照する
+///
+/// `len`が`max_len`を超える場合は先頭`max_len`バイトに切り詰めます。
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、`min(len, max_len)`バイト読み取り可能なメモリを指すこと
+pub unsafe fn buf<'a>(ptr: *const c_void, len: usize, max_len: usize) -> Option<&'a [u8]> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(std::slice::from_raw_parts(
+        ptr as *const u8,
+        len.min(max_len),
+    ))
+}
+
+/// バッファを書き込み可能スライスとして参照する
+///
+/// `len`が`max_len`を超える場合は先頭`max_len`バイトに切り詰めます。
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、`min(len, max_len)`バイト書き込み可能なメモリを指し、
+/// 返したスライスの使用中に他から参照されないこと
+pub unsafe fn buf_mut<'a>(ptr: *mut c_void, len: usize, max_len: usize) -> Option<&'a mut [u8]> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(std::slice::from_raw_parts_mut(
+        ptr as *mut u8,
+        len.min(max_len),
+    ))
+}
+
+/// 構造体を値として読み取る
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、読み取り可能な`T`を指すこと（アラインメント不問）
+pub unsafe fn read_val<T: Copy>(ptr: *const T) -> Option<T> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(std::ptr::read_unaligned(ptr))
+}
+
+/// 構造体に値を書き込む
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、書き込み可能な`T`を指すこと（アラインメント不問）
+pub unsafe fn write_val<T: Copy>(ptr: *mut T, value: T) -> bool {
+    if ptr.is_null() {
+        return false;
+    }
+    std::ptr::write_unaligned(ptr, value);
+    true
+}
+
+/// iovec配列を参照する
+///
+/// `cnt`が`IOV_MAX`を超える場合はカーネルと同様に不正（`None`）とします。
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、`cnt`個のiovecを読み取り可能なメモリを指すこと
+pub unsafe fn iovecs<'a>(ptr: *const iovec, cnt: usize) -> Option<&'a [iovec]> {
+    if ptr.is_null() || cnt > IOV_MAX {
+        return None;
+    }
+    Some(std::slice::from_raw_parts(ptr, cnt))
+}
+
+/// iovecが指すバッファを読み取り専用スライスとして参照する
+///
+/// # Safety
+///
+/// `iov`は`iov_len`バイト読み取り可能なメモリを指すこと
+pub unsafe fn iovec_buf<'a>(iov: &iovec, max_len: usize) -> Option<&'a [u8]> {
+    buf(iov.iov_base, iov.iov_len, max_len)
+}
+
+/// iovecが指すバッファを書き込み可能スライスとして参照する
+///
+/// # Safety
+///
+/// `iov`は`iov_len`バイト書き込み可能なメモリを指すこと
+pub unsafe fn iovec_buf_mut<'a>(iov: &iovec, max_len: usize) -> Option<&'a mut [u8]> {
+    buf_mut(iov.iov_base, iov.iov_len, max_len)
+}
+
+/// sockaddrをデコードする（AF_INET / AF_INET6）
+///
+/// それ以外のアドレスファミリや、`len`が構造体より短い場合は`None`を返します。
+///
+/// # Safety
+///
+/// `ptr`はNULLであるか、`len`バイト読み取り可能なメモリを指すこと
+pub unsafe fn sockaddr(ptr: *const c_void, len: u32) -> Option<SocketAddr> {
+    let len = len as usize;
+    if ptr.is_null() || len < std::mem::size_of::<libc::sa_family_t>() {
+        return None;
+    }
+
+    let family = read_val(ptr as *const libc::sa_family_t)?;
+    match family as i32 {
+        libc::AF_INET if len >= std::mem::size_of::<libc::sockaddr_in>() => {
+            let sin = read_val(ptr as *const libc::sockaddr_in)?;
+            Some(SocketAddr::V4(SocketAddrV4::new(
+                Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)),
+                u16::from_be(sin.sin_port),
+            )))
+        }
+        libc::AF_INET6 if len >= std::mem::size_of::<libc::sockaddr_in6>() => {
+            let sin6 = read_val(ptr as *const libc::sockaddr_in6)?;
+            Some(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(sin6.sin6_addr.s6_addr),
+                u16::from_be(sin6.sin6_port),
+                u32::from_be(sin6.sin6_flowinfo),
+                sin6.sin6_scope_id,
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// フォールトセーフなアクセスのエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserMemError {
+    /// ポインタがNULL
+    Null,
+    /// アドレスがマップされていない、または権限がない
+    Fault,
+    /// 上限内にNUL終端が見つからない
+    TooLong,
+    /// その他のエラー（errno）
+    Os(i32),
+}
+
+impl UserMemError {
+    /// カーネルが同じ引数に対して返すerrno
+    pub fn errno(&self) -> i32 {
+        match self {
+            UserMemError::Null | UserMemError::Fault => libc::EFAULT,
+            UserMemError::TooLong => libc::ENAMETOOLONG,
+            UserMemError::Os(errno) => *errno,
+        }
+    }
+}
+
+impl fmt::Display for UserMemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserMemError::Null => write!(f, "Null pointer"),
+            UserMemError::Fault => write!(f, "Bad address"),
+            UserMemError::TooLong => write!(f, "String too long"),
+            UserMemError::Os(errno) => write!(f, "process_vm_readv/writev failed: errno {}", errno),
+        }
+    }
+}
+
+impl std::error::Error for UserMemError {}
+
+/// ページサイズ（x86-64の基本ページ）
+const PAGE_SIZE: usize = 4096;
+
+/// 自プロセスのメモリを`process_vm_readv`/`process_vm_writev`でコピーする
+///
+/// カーネルはiovec要素の途中で部分転送しないため、呼び出し側は必要に応じて
+/// ページ境界で分割する。
+fn vm_copy(sysno: Sysno, local: &iovec, remote: &iovec) -> Result<(), UserMemError> {
+    if local.iov_len == 0 {
+        return Ok(());
+    }
+
+    let ret = unsafe {
+        let pid = raw_syscall_bypass(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+        raw_syscall_bypass(
+            sysno.nr(),
+            pid as u64,
+            local as *const iovec as u64,
+            1,
+            remote as *const iovec as u64,
+            1,
+            0,
+        )
+    };
+
+    if ret < 0 {
+        let errno = -ret as i32;
+        return Err(if errno == libc::EFAULT {
+            UserMemError::Fault
+        } else {
+            UserMemError::Os(errno)
+        });
+    }
+    if ret as usize != local.iov_len {
+        return Err(UserMemError::Fault);
+    }
+    Ok(())
+}
+
+/// アプリケーションメモリから`out`にコピーする（フォールトセーフ）
+pub fn try_read_bytes(ptr: *const c_void, out: &mut [u8]) -> Result<(), UserMemError> {
+    if ptr.is_null() {
+        return Err(UserMemError::Null);
+    }
+    let local = iovec {
+        iov_base: out.as_mut_ptr() as *mut c_void,
+        iov_len: out.len(),
+    };
+    let remote = iovec {
+        iov_base: ptr as *mut c_void,
+        iov_len: out.len(),
+    };
+    vm_copy(Sysno::process_vm_readv, &local, &remote)
+}
+
+/// `data`をアプリケーションメモリに書き込む（フォールトセーフ）
+pub fn try_write_bytes(ptr: *mut c_void, data: &[u8]) -> Result<(), UserMemError> {
+    if ptr.is_null() {
+        return Err(UserMemError::Null);
+    }
+    let local = iovec {
+        iov_base: data.as_ptr() as *mut c_void,
+        iov_len: data.len(),
+    };
+    let remote = iovec {
+        iov_base: ptr,
+        iov_len: data.len(),
+    };
+    vm_copy(Sysno::process_vm_writev, &local, &remote)
+}
+
+/// 構造体を値として読み取る（フォールトセーフ）
+///
+/// `T`はすべてのビットパターンが有効な型（Cの構造体や整数）であること。
+pub fn try_read_val<T: Copy>(ptr: *const T) -> Result<T, UserMemError> {
+    let mut value = std::mem::MaybeUninit::<T>::uninit();
+    let out = unsafe {
+        std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, std::mem::size_of::<T>())
+    };
+    try_read_bytes(ptr as *const c_void, out)?;
+    Ok(unsafe { value.assume_init() })
+}
+
+/// 構造体に値を書き込む（フォールトセーフ）
+pub fn try_write_val<T: Copy>(ptr: *mut T, value: T) -> Result<(), UserMemError> {
+    let data = unsafe {
+        std::slice::from_raw_parts(&value as *const T as *const u8, std::mem::size_of::<T>())
+    };
+    try_write_bytes(ptr as *mut c_void, data)
+}
+
+/// NUL終端文字列を`out`にコピーして返す（フォールトセーフ）
+///
+/// 読み取りはページ単位で行うため、文字列の直後が未マップでも失敗しません。
+/// `out`内にNULが収まらない場合は`TooLong`を返します。
+pub fn try_c_str(ptr: *const c_char, out: &mut [u8]) -> Result<&CStr, UserMemError> {
+    if ptr.is_null() {
+        return Err(UserMemError::Null);
+    }
+
+    let mut copied = 0;
+    while copied < out.len() {
+        let addr = ptr as usize + copied;
+        let chunk = (PAGE_SIZE - addr % PAGE_SIZE).min(out.len() - copied);
+        let dst = &mut out[copied..copied + chunk];
+        try_read_bytes(addr as *const c_void, dst)?;
+
+        if let Some(nul) = dst.iter().position(|&b| b == 0) {
+            let len = copied + nul + 1;
+            return Ok(unsafe { CStr::from_bytes_with_nul_unchecked(&out[..len]) });
+        }
+        copied += chunk;
+    }
+
+    Err(UserMemError::TooLong)
+}
+
+/// NUL終端文字列の長さ（NULを含まない）を返す（フォールトセーフ）
+///
+/// `try_c_str`と同じくページ単位で読み取ります。先頭`max_len`バイト以内に
+/// NULがない場合は`TooLong`を返します。
+pub fn try_c_str_len(ptr: *const c_char, max_len: usize) -> Result<usize, UserMemError> {
+    if ptr.is_null() {
+        return Err(UserMemError::Null);
+    }
+
+    let mut chunk_buf = [0u8; 512];
+    let mut scanned = 0;
+    while scanned < max_len {
+        let addr = ptr as usize + scanned;
+        let chunk = (PAGE_SIZE - addr % PAGE_SIZE)
+            .min(chunk_buf.len())
+            .min(max_len - scanned);
+        let dst = &mut chunk_buf[..chunk];
+        try_read_bytes(addr as *const c_void, dst)?;
+
+        if let Some(nul) = dst.iter().position(|&b| b == 0) {
+            return Ok(scanned + nul);
+        }
+        scanned += chunk;
+    }
+
+    Err(UserMemError::TooLong)
+}
+
+/// パス引数を`out`にコピーして返す（フォールトセーフ、上限は`PATH_MAX`）
+pub fn try_path(ptr: *const c_char, out: &mut [u8; PATH_MAX]) -> Result<&CStr, UserMemError> {
+    try_c_str(ptr, out)
+}
+
+/// sockaddrをデコードする（フォールトセーフ）
+///
+/// 対応していないアドレスファミリや短すぎる`len`は`Os(EINVAL)`を返します。
+pub fn try_sockaddr(ptr: *const c_void, len: u32) -> Result<SocketAddr, UserMemError> {
+    let mut storage = [0u8; std::mem::size_of::<libc::sockaddr_storage>()];
+    let len = (len as usize).min(storage.len());
+    try_read_bytes(ptr, &mut storage[..len])?;
+    unsafe { sockaddr(storage.as_ptr() as *const c_void, len as u32) }
+        .ok_or(UserMemError::Os(libc::EINVAL))
+}
+
+/// システムコールの出力先になる構造体へのポインタ
+///
+/// `struct stat`などカーネルが書き込む領域をフォールトセーフに読み書きします。
+/// コピー可能なので、デフォルト実装に渡した後で結果を書き換えられます。
+///
+/// ```no_run
+/// # use zpoline_hook_api::user_mem::UserPtr;
+/// # fn f(statbuf: UserPtr<libc::stat>) -> Result<(), zpoline_hook_api::user_mem::UserMemError> {
+/// statbuf.update(|st| st.st_size = 0)?;
+/// # Ok(())
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct UserPtr<T> {
+    ptr: *mut T,
+}
+
+impl<T> Clone for UserPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UserPtr<T> {}
+
+impl<T> fmt::Debug for UserPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserPtr({:p})", self.ptr)
+    }
+}
+
+impl<T: Copy> UserPtr<T> {
+    pub fn new(ptr: *mut T) -> Self {
+        Self { ptr }
+    }
+
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.ptr.is_null()
+    }
+
+    /// 現在の内容を読み取る（`T`はすべてのビットパターンが有効な型であること）
+    pub fn read(&self) -> Result<T, UserMemError> {
+        try_read_val(self.ptr)
+    }
+
+    pub fn write(&self, value: T) -> Result<(), UserMemError> {
+        try_write_val(self.ptr, value)
+    }
+
+    /// 読み取った内容を`f`で書き換えて書き戻す
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, UserMemError> {
+        let mut value = self.read()?;
+        let result = f(&mut value);
+        self.write(value)?;
+        Ok(result)
+    }
+}
+
+/// `readv`/`writev`などに渡されたiovec配列
+///
+/// 配列と各バッファはフォールトセーフに読み書きします。
+#[derive(Debug, Clone, Copy)]
+pub struct IoVecs {
+    ptr: *const iovec,
+    cnt: usize,
+}
+
+impl IoVecs {
+    pub fn new(ptr: *const iovec, cnt: usize) -> Self {
+        Self { ptr, cnt }
+    }
+
+    pub fn as_ptr(&self) -> *const iovec {
+        self.ptr
+    }
+
+    /// iovecの数
+    pub fn len(&self) -> usize {
+        self.cnt
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cnt == 0
+    }
+
+    /// iovec配列をコピーする（`IOV_MAX`を超える場合はカーネルと同じ`EINVAL`）
+    pub fn to_vec(&self) -> Result<Vec<iovec>, UserMemError> {
+        if self.cnt > IOV_MAX {
+            return Err(UserMemError::Os(libc::EINVAL));
+        }
+        if self.cnt == 0 {
+            return Ok(Vec::new());
+        }
+        let mut iovs = vec![
+            iovec {
+                iov_base: std::ptr::null_mut(),
+                iov_len: 0,
+            };
+            self.cnt
+        ];
+        let out = unsafe {
+            std::slice::from_raw_parts_mut(
+                iovs.as_mut_ptr() as *mut u8,
+                self.cnt * std::mem::size_of::<iovec>(),
+            )
+        };
+        try_read_bytes(self.ptr as *const c_void, out)?;
+        Ok(iovs)
+    }
+
+    /// iovecを先頭から順に読み取る（配列はコピーせず、少しずつスタックに読み込む）
+    pub fn iter(&self) -> IoVecsIter {
+        IoVecsIter {
+            vecs: *self,
+            pos: 0,
+            chunk: [EMPTY_IOVEC; IOVECS_CHUNK],
+            chunk_start: 0,
+            chunk_len: 0,
+        }
+    }
+
+    /// バッファの合計長
+    pub fn total_len(&self) -> Result<usize, UserMemError> {
+        self.iter().try_fold(0usize, |total, iov| Ok(total.saturating_add(iov?.iov_len)))
+    }
+
+    /// バッファの内容を連結して読み取る（先頭`max_len`バイトまで）
+    pub fn gather(&self, max_len: usize) -> Result<Vec<u8>, UserMemError> {
+        gather_from(self.iter(), self.total_len()?.min(max_len), max_len)
+    }
+
+    /// `data`を先頭のバッファから順に書き込み、書き込んだバイト数を返す
+    pub fn scatter(&self, data: &[u8]) -> Result<usize, UserMemError> {
+        scatter_into(self.iter(), data)
+    }
+}
+
+const EMPTY_IOVEC: iovec = iovec {
+    iov_base: std::ptr::null_mut(),
+    iov_len: 0,
+};
+
+/// `IoVecsIter`が一度に読み込むiovecの数
+const IOVECS_CHUNK: usize = 16;
+
+/// `IoVecs::iter`のイテレータ
+///
+/// 読み取りに失敗した場合はエラーを1度返して終了します。
+pub struct IoVecsIter {
+    vecs: IoVecs,
+    pos: usize,
+    chunk: [iovec; IOVECS_CHUNK],
+    chunk_start: usize,
+    chunk_len: usize,
+}
+
+impl Iterator for IoVecsIter {
+    type Item = Result<iovec, UserMemError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.vecs.cnt {
+            return None;
+        }
+        if self.vecs.cnt > IOV_MAX {
+            self.pos = self.vecs.cnt;
+            return Some(Err(UserMemError::Os(libc::EINVAL)));
+        }
+        if self.pos >= self.chunk_start + self.chunk_len {
+            let len = (self.vecs.cnt - self.pos).min(IOVECS_CHUNK);
+            let out = unsafe {
+                std::slice::from_raw_parts_mut(
+                    self.chunk.as_mut_ptr() as *mut u8,
+                    len * std::mem::size_of::<iovec>(),
+                )
+            };
+            let src = self.vecs.ptr.wrapping_add(self.pos);
+            if let Err(e) = try_read_bytes(src as *const c_void, out) {
+                self.pos = self.vecs.cnt;
+                return Some(Err(e));
+            }
+            self.chunk_start = self.pos;
+            self.chunk_len = len;
+        }
+        let iov = self.chunk[self.pos - self.chunk_start];
+        self.pos += 1;
+        Some(Ok(iov))
+    }
+}
+
+/// iovecの合計長
+pub fn iovecs_total_len(iovs: &[iovec]) -> usize {
+    iovs.iter().fold(0usize, |total, iov| total.saturating_add(iov.iov_len))
+}
+
+/// iovecが指すバッファの内容を連結して読み取る（フォールトセーフ）
+pub fn gather_iovecs(iovs: &[iovec], max_len: usize) -> Result<Vec<u8>, UserMemError> {
+    let capacity = iovecs_total_len(iovs).min(max_len);
+    gather_from(iovs.iter().copied().map(Ok), capacity, max_len)
+}
+
+fn gather_from(
+    iovs: impl Iterator<Item = Result<iovec, UserMemError>>,
+    capacity: usize,
+    max_len: usize,
+) -> Result<Vec<u8>, UserMemError> {
+    let mut data = Vec::with_capacity(capacity);
+    for iov in iovs {
+        let iov = iov?;
+        let len = iov.iov_len.min(max_len - data.len());
+        if len == 0 {
+            continue;
+        }
+        let start = data.len();
+        data.resize(start + len, 0);
+        try_read_bytes(iov.iov_base, &mut data[start..])?;
+    }
+    Ok(data)
+}
+
+/// `data`をiovecのバッファに順に書き込む（フォールトセーフ）
+pub fn scatter_iovecs(iovs: &[iovec], data: &[u8]) -> Result<usize, UserMemError> {
+    scatter_into(iovs.iter().copied().map(Ok), data)
+}
+
+fn scatter_into(
+    iovs: impl Iterator<Item = Result<iovec, UserMemError>>,
+    data: &[u8],
+) -> Result<usize, UserMemError> {
+    let mut written = 0;
+    for iov in iovs {
+        let iov = iov?;
+        let len = iov.iov_len.min(data.len() - written);
+        if len == 0 {
+            continue;
+        }
+        try_write_bytes(iov.iov_base, &data[written..written + len])?;
+        written += len;
+    }
+    Ok(written)
+}
+
+/// 引数番号を指定してアプリケーションメモリを参照するためのアクセサ
+///
+/// 引数番号は0始まりで、`SyscallRegs::arg`と同じです。
+impl SyscallRegs {
+    /// n番目の引数をパスとして読み取る
+    ///
+    /// # Safety
+    ///
+    /// `path`と同じ
+    pub unsafe fn path_arg(&self, n: usize) -> Option<&CStr> {
+        path(self.arg(n) as *const c_char)
+    }
+
+    /// `ptr_arg`番目の引数が指す、`len_arg`番目の引数の長さのバッファを読み取る
+    ///
+    /// # Safety
+    ///
+    /// `buf`と同じ
+    pub unsafe fn buf_arg(&self, ptr_arg: usize, len_arg: usize, max_len: usize) -> Option<&[u8]> {
+        buf(
+            self.arg(ptr_arg) as *const c_void,
+            self.arg(len_arg) as usize,
+            max_len,
+        )
+    }
+
+    /// `buf_arg`の書き込み可能版
+    ///
+    /// # Safety
+    ///
+    /// `buf_mut`と同じ
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn buf_arg_mut(
+        &self,
+        ptr_arg: usize,
+        len_arg: usize,
+        max_len: usize,
+    ) -> Option<&mut [u8]> {
+        buf_mut(
+            self.arg(ptr_arg) as *mut c_void,
+            self.arg(len_arg) as usize,
+            max_len,
+        )
+    }
+
+    /// `ptr_arg`番目の引数が指す、`cnt_arg`番目の引数の個数のiovec配列を参照する
+    ///
+    /// # Safety
+    ///
+    /// `iovecs`と同じ
+    pub unsafe fn iovecs_arg(&self, ptr_arg: usize, cnt_arg: usize) -> Option<&[iovec]> {
+        iovecs(
+            self.arg(ptr_arg) as *const iovec,
+            self.arg(cnt_arg) as usize,
+        )
+    }
+
+    /// `ptr_arg`番目の引数が指す、`len_arg`番目の引数の長さのsockaddrをデコードする
+    ///
+    /// # Safety
+    ///
+    /// `sockaddr`と同じ
+    pub unsafe fn sockaddr_arg(&self, ptr_arg: usize, len_arg: usize) -> Option<SocketAddr> {
+        sockaddr(self.arg(ptr_arg) as *const c_void, self.arg(len_arg) as u32)
+    }
+
+    /// n番目の引数をパスとして`out`にコピーする（フォールトセーフ）
+    pub fn try_path_arg<'a>(
+        &self,
+        n: usize,
+        out: &'a mut [u8; PATH_MAX],
+    ) -> Result<&'a CStr, UserMemError> {
+        try_path(self.arg(n) as *const c_char, out)
+    }
+
+    /// `ptr_arg`番目の引数が指すバッファを`out`にコピーする（フォールトセーフ）
+    ///
+    /// コピーするのは`len_arg`番目の引数と`out.len()`の小さい方で、
+    /// コピーしたバイト数を返します。
+    pub fn try_buf_arg(
+        &self,
+        ptr_arg: usize,
+        len_arg: usize,
+        out: &mut [u8],
+    ) -> Result<usize, UserMemError> {
+        let len = (self.arg(len_arg) as usize).min(out.len());
+        try_read_bytes(self.arg(ptr_arg) as *const c_void, &mut out[..len])?;
+        Ok(len)
+    }
+
+    /// `ptr_arg`番目の引数が指すsockaddrをデコードする（フォールトセーフ）
+    pub fn try_sockaddr_arg(
+        &self,
+        ptr_arg: usize,
+        len_arg: usize,
+    ) -> Result<SocketAddr, UserMemError> {
+        try_sockaddr(self.arg(ptr_arg) as *const c_void, self.arg(len_arg) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sysno;
+
+    #[test]
+    fn test_path_arg() {
+        let path = c"/etc/hostname";
+        let regs = SyscallRegs::new(Sysno::open.nr(), path.as_ptr() as u64, 0, 0, 0, 0, 0);
+        assert_eq!(unsafe { regs.path_arg(0) }, Some(path));
+        assert_eq!(unsafe { regs.path_arg(1) }, None);
+
+        // 上限内にNULがない場合は読み取らない
+        let unterminated = [b'a'; 8];
+        assert_eq!(
+            unsafe { c_str(unterminated.as_ptr() as *const c_char, 8) },
+            None
+        );
+    }
+
+    #[test]
+    fn test_buf_arg_truncates() {
+        let data = b"hello world";
+        let regs = SyscallRegs::new(
+            Sysno::write.nr(),
+            1,
+            data.as_ptr() as u64,
+            data.len() as u64,
+            0,
+            0,
+            0,
+        );
+        assert_eq!(unsafe { regs.buf_arg(1, 2, 1024) }, Some(&data[..]));
+        assert_eq!(unsafe { regs.buf_arg(1, 2, 5) }, Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn test_iovecs() {
+        let mut a = *b"abc";
+        let mut b = *b"de";
+        let iov = [
+            iovec {
+                iov_base: a.as_mut_ptr() as *mut c_void,
+                iov_len: a.len(),
+            },
+            iovec {
+                iov_base: b.as_mut_ptr() as *mut c_void,
+                iov_len: b.len(),
+            },
+        ];
+        let iovs = unsafe { iovecs(iov.as_ptr(), iov.len()) }.unwrap();
+        let joined: Vec<u8> = iovs
+            .iter()
+            .flat_map(|iov| {
+                unsafe { iovec_buf(iov, usize::MAX) }
+                    .unwrap()
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        assert_eq!(joined, b"abcde");
+
+        unsafe { iovec_buf_mut(&iovs[1], usize::MAX) }.unwrap()[0] = b'D';
+        assert_eq!(&b, b"De");
+        assert!(unsafe { iovecs(iov.as_ptr(), IOV_MAX + 1) }.is_none());
+    }
+
+    #[test]
+    fn test_iovecs_iter() {
+        // 1回に読み込む数を超えるiovecも順に読み取れる
+        let data: Vec<u8> = (0..40).collect();
+        let iov: Vec<iovec> = data
+            .iter()
+            .map(|b| iovec {
+                iov_base: b as *const u8 as *mut c_void,
+                iov_len: 1,
+            })
+            .collect();
+        let vecs = IoVecs::new(iov.as_ptr(), iov.len());
+        assert_eq!(vecs.iter().count(), 40);
+        assert_eq!(vecs.total_len(), Ok(40));
+        assert_eq!(vecs.gather(usize::MAX).unwrap(), data);
+
+        let mut out = [0u8; 20];
+        let iov = [iovec {
+            iov_base: out.as_mut_ptr() as *mut c_void,
+            iov_len: out.len(),
+        }];
+        assert_eq!(IoVecs::new(iov.as_ptr(), 1).scatter(&data), Ok(20));
+        assert_eq!(&out[..], &data[..20]);
+
+        assert_eq!(
+            IoVecs::new((usize::MAX - PAGE_SIZE) as *const iovec, 1).total_len(),
+            Err(UserMemError::Fault)
+        );
+        assert_eq!(
+            IoVecs::new(iov.as_ptr(), IOV_MAX + 1).total_len(),
+            Err(UserMemError::Os(libc::EINVAL))
+        );
+        assert_eq!(IoVecs::new(std::ptr::null(), 0).total_len(), Ok(0));
+    }
+
+    #[test]
+    fn test_try_read_bad_address() {
+        let value = 0x1234_5678u64;
+        assert_eq!(try_read_val(&value as *const u64), Ok(value));
+        assert_eq!(
+            try_read_val(std::ptr::null::<u64>()),
+            Err(UserMemError::Null)
+        );
+
+        // カーネル空間のアドレスはクラッシュせずにEFAULTになる
+        let bad = (usize::MAX - PAGE_SIZE) as *const u64;
+        assert_eq!(try_read_val(bad), Err(UserMemError::Fault));
+        assert_eq!(UserMemError::Fault.errno(), libc::EFAULT);
+
+        let mut target = 0u32;
+        try_write_val(&mut target as *mut u32, 7).unwrap();
+        assert_eq!(target, 7);
+        assert_eq!(try_write_val(bad as *mut u32, 7), Err(UserMemError::Fault));
+    }
+
+    #[test]
+    fn test_try_c_str_at_page_end() {
+        unsafe {
+            // 2ページ確保して2ページ目を解放し、1ページ目の末尾に文字列を置く
+            let base = libc::mmap(
+                std::ptr::null_mut(),
+                PAGE_SIZE * 2,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            ) as *mut u8;
+            assert_ne!(base as *mut c_void, libc::MAP_FAILED);
+            libc::munmap(base.add(PAGE_SIZE) as *mut c_void, PAGE_SIZE);
+
+            let s = base.add(PAGE_SIZE - 4);
+            s.copy_from_nonoverlapping(c"abc".as_ptr() as *const u8, 4);
+            let mut out = [0u8; PATH_MAX];
+            assert_eq!(try_path(s as *const c_char, &mut out), Ok(c"abc"));
+
+            // NULがないまま未マップ領域に到達したらEFAULT
+            s.add(3).write(b'd');
+            assert_eq!(
+                try_path(s as *const c_char, &mut out),
+                Err(UserMemError::Fault)
+            );
+
+            let mut small = [0u8; 2];
+            assert_eq!(
+                try_c_str(s as *const c_char, &mut small),
+                Err(UserMemError::TooLong)
+            );
+
+            libc::munmap(base as *mut c_void, PAGE_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_sockaddr() {
+        let sin = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: 8080u16.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(Ipv4Addr::new(127, 0, 0, 1)).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let len = std::mem::size_of::<libc::sockaddr_in>() as u32;
+        let ptr = &sin as *const _ as *const c_void;
+        assert_eq!(
+            unsafe { sockaddr(ptr, len) },
+            Some("127.0.0.1:8080".parse().unwrap())
+        );
+        assert_eq!(unsafe { sockaddr(ptr, len - 1) }, None);
+    }
+}
@@ -0,0 +1,1542 @@
+//! 仮想ファイルディスクリプタ
+//!
+//! インメモリのファイルや偽のソケットのように、カーネルに存在しないリソースを
+//! ユーザー空間でエミュレートするための仕組みです。`VirtualFdTable`は
+//! `/dev/null`を開いてfd番号をカーネルから予約するため、実際のfdと衝突しません。
+//!
+//! `register_syscall_hooks`で登録したフックでは、グローバルな表（`virtual_fds`）に
+//! 登録したfdへの`FD_SYSCALLS`は自動的に`VirtualFile`に渡され、`SyscallHooks`の
+//! メソッドは呼ばれません。それ以外のfdを取るシステムコールは予約した`/dev/null`に
+//! 対して実行されます。
+//!
+//! `poll`/`select`/`epoll`は`SyscallHooks`のデフォルト実装が`VirtualFile::poll`で
+//! 仮想fdの準備状態を反映します。グローバルな表に仮想fdがあれば、関心集合には
+//! `VFD_HOOK_SYSCALLS`が自動的に追加されます。
+//!
+//! 待っているスレッドは、表を通した仮想fdの操作（読み書きや`close`など）のたびに
+//! 起こされて準備状態を確認し直します。それ以外の理由で準備状態が変わる場合は
+//! `VirtualFdTable::notify`を呼びます。待つ間は実際のfdと一緒にカーネルで待つため、
+//! 遅延やCPUの消費はありません。
+//!
+//! 仮想fdへの読み書きはフックのディスパッチャのロックを取らずに処理されるため、
+//! 他のスレッドが仮想fdを待っている間も進みます。フック実装が関心集合に含めない
+//! `POLL_SYSCALLS`もロックを取らずに待ちます。
+//!
+//! ```no_run
+//! use zpoline_hook_api::vfd::{virtual_fds, VirtualFile};
+//!
+//! struct Zero;
+//!
+//! impl VirtualFile for Zero {
+//!     fn read(&mut self, buf: &mut [u8]) -> Result<usize, i32> {
+//!         buf.fill(0);
+//!         Ok(buf.len())
+//!     }
+//! }
+//!
+//! let fd = virtual_fds().insert(Zero).unwrap();
+//! ```
+
+use crate::fork::ForkSafeMutex;
+use crate::interests::extend_hook_interests;
+use crate::poll::{EpollEvents, FdSet, PollFds};
+use crate::syscall_hooks::dispatch_defaults;
+use crate::user_mem::{
+    try_read_bytes, try_read_val, try_write_bytes, try_write_val, UserPtr, IOV_MAX,
+};
+use crate::{raw_syscall, raw_syscall_bypass, SyscallRegs, Sysno, SysnoSet};
+use libc::{c_int, c_short, c_void, epoll_event, iovec, pollfd, sigset_t, size_t};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 仮想fdで処理するシステムコール
+pub const FD_SYSCALLS: SysnoSet = SysnoSet::new(&[
+    Sysno::read,
+    Sysno::write,
+    Sysno::pread64,
+    Sysno::pwrite64,
+    Sysno::readv,
+    Sysno::writev,
+    Sysno::preadv,
+    Sysno::pwritev,
+    Sysno::preadv2,
+    Sysno::pwritev2,
+    Sysno::lseek,
+    Sysno::close,
+    Sysno::fstat,
+    Sysno::newfstatat,
+    Sysno::statx,
+    Sysno::fstatfs,
+    Sysno::ftruncate,
+    Sysno::fsync,
+    Sysno::fdatasync,
+    Sysno::ioctl,
+    Sysno::fcntl,
+    Sysno::dup,
+    Sysno::dup2,
+    Sysno::dup3,
+]);
+
+const POLL_SYSNOS: [Sysno; 8] = [
+    Sysno::poll,
+    Sysno::ppoll,
+    Sysno::select,
+    Sysno::pselect6,
+    Sysno::epoll_ctl,
+    Sysno::epoll_wait,
+    Sysno::epoll_pwait,
+    Sysno::epoll_pwait2,
+];
+
+/// デフォルト実装が仮想fdの準備状態を反映するシステムコール
+pub const POLL_SYSCALLS: SysnoSet = SysnoSet::new(&POLL_SYSNOS);
+
+/// グローバルな表に仮想fdがあるときに関心集合へ追加されるシステムコール
+pub const VFD_HOOK_SYSCALLS: SysnoSet = FD_SYSCALLS.union(&POLL_SYSCALLS);
+
+/// 1回の呼び出しで`VirtualFile`に渡すバッファの上限（超える分は短い読み書きになる）
+const IO_CHUNK: usize = 64 * 1024;
+
+/// 仮想fdの実体
+///
+/// エラーは正の`errno`で返します。dupしたfdは同じオブジェクトを共有し、
+/// 最後のfdが閉じられたときに`close`が呼ばれます。
+pub trait VirtualFile: Send + 'static {
+    /// read(2) - 読み込んだバイト数を返す
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, c_int> {
+        let _ = buf;
+        Err(libc::EBADF)
+    }
+
+    /// write(2) - 書き込んだバイト数を返す
+    fn write(&mut self, buf: &[u8]) -> Result<usize, c_int> {
+        let _ = buf;
+        Err(libc::EBADF)
+    }
+
+    /// pread64(2)
+    fn pread(&mut self, buf: &mut [u8], offset: i64) -> Result<usize, c_int> {
+        let _ = (buf, offset);
+        Err(libc::ESPIPE)
+    }
+
+    /// pwrite64(2)
+    fn pwrite(&mut self, buf: &[u8], offset: i64) -> Result<usize, c_int> {
+        let _ = (buf, offset);
+        Err(libc::ESPIPE)
+    }
+
+    /// lseek(2) - 新しいオフセットを返す
+    fn lseek(&mut self, offset: i64, whence: c_int) -> Result<i64, c_int> {
+        let _ = (offset, whence);
+        Err(libc::ESPIPE)
+    }
+
+    /// fstat(2) - `st`はゼロで初期化されている（デフォルトは空の通常ファイル）
+    fn stat(&mut self, st: &mut libc::stat) -> Result<(), c_int> {
+        st.st_mode = libc::S_IFREG | 0o600;
+        Ok(())
+    }
+
+    /// ftruncate(2)
+    fn ftruncate(&mut self, len: i64) -> Result<(), c_int> {
+        let _ = len;
+        Err(libc::EINVAL)
+    }
+
+    /// fsync(2)/fdatasync(2)
+    fn fsync(&mut self) -> Result<(), c_int> {
+        Err(libc::EINVAL)
+    }
+
+    /// ioctl(2)
+    fn ioctl(&mut self, request: u64, arg: u64) -> Result<i64, c_int> {
+        let _ = (request, arg);
+        Err(libc::ENOTTY)
+    }
+
+    /// poll(2) - `events`のうち準備ができているものを返す（デフォルトは常に読み書き可能）
+    ///
+    /// 仮想fdの操作以外で準備状態が変わる場合は`VirtualFdTable::notify`を呼んでください。
+    fn poll(&mut self, events: c_short) -> c_short {
+        events & (libc::POLLIN | libc::POLLOUT | libc::POLLRDNORM | libc::POLLWRNORM)
+    }
+
+    /// 最後のfdが閉じられたときに呼ばれる
+    fn close(&mut self) {}
+}
+
+/// 開かれた仮想ファイル（dropで`VirtualFile::close`を呼ぶ）
+///
+/// 操作中にforkした他のスレッドのロックを子プロセスで解放できるよう、
+/// `ForkSafeMutex`で保護する。
+struct OpenFile(ForkSafeMutex<Box<dyn VirtualFile>>);
+
+impl OpenFile {
+    fn with<R>(&self, f: impl FnOnce(&mut dyn VirtualFile) -> R) -> R {
+        let mut file = self.0.lock();
+        f(file.as_mut())
+    }
+}
+
+impl Drop for OpenFile {
+    fn drop(&mut self) {
+        self.with(|file| file.close());
+    }
+}
+
+/// 仮想fdの表
+pub struct VirtualFdTable {
+    files: ForkSafeMutex<BTreeMap<c_int, Arc<OpenFile>>>,
+    len: AtomicUsize,
+    /// epollインスタンスごとに登録された仮想fd
+    epoll: ForkSafeMutex<BTreeMap<c_int, BTreeMap<c_int, epoll_event>>>,
+    /// 仮想fdを待っているスレッドのeventfd
+    waiters: ForkSafeMutex<Vec<c_int>>,
+    waiting: AtomicUsize,
+}
+
+impl Default for VirtualFdTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualFdTable {
+    pub const fn new() -> Self {
+        Self {
+            files: ForkSafeMutex::new(BTreeMap::new()),
+            len: AtomicUsize::new(0),
+            epoll: ForkSafeMutex::new(BTreeMap::new()),
+            waiters: ForkSafeMutex::new(Vec::new()),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// fd番号を予約して`file`を登録する（エラーは`errno`）
+    ///
+    /// グローバルな表では、関心集合に`VFD_HOOK_SYSCALLS`を追加します。
+    pub fn insert<F: VirtualFile>(&self, file: F) -> Result<c_int, c_int> {
+        let fd = reserve_fd()?;
+        self.set(fd, Some(Arc::new(OpenFile(ForkSafeMutex::new(Box::new(file))))));
+        if std::ptr::eq(self, &VIRTUAL_FDS) {
+            extend_hook_interests(&VFD_HOOK_SYSCALLS);
+        }
+        Ok(fd)
+    }
+
+    /// 仮想fdか
+    pub fn contains(&self, fd: c_int) -> bool {
+        !self.is_empty() && self.get(fd).is_some()
+    }
+
+    /// 仮想fdの実体にアクセスする（仮想fdでなければ`None`）
+    pub fn with_file<R>(&self, fd: c_int, f: impl FnOnce(&mut dyn VirtualFile) -> R) -> Option<R> {
+        Some(self.get(fd)?.with(f))
+    }
+
+    /// 仮想fdを閉じる（エラーは`errno`）
+    pub fn close(&self, fd: c_int) -> Result<(), c_int> {
+        if self.set(fd, None).is_none() {
+            return Err(libc::EBADF);
+        }
+        self.epoll.lock().retain(|_, registered| {
+            registered.remove(&fd);
+            !registered.is_empty()
+        });
+        let ret = unsafe { raw_syscall_bypass(Sysno::close.nr(), fd as u64, 0, 0, 0, 0, 0) };
+        if ret < 0 {
+            return Err(-ret as c_int);
+        }
+        Ok(())
+    }
+
+    /// 仮想fdの準備状態（仮想fdでなければ`None`）
+    ///
+    /// `POLLERR`/`POLLHUP`/`POLLNVAL`は`events`に関係なく返します。
+    pub fn poll_fd(&self, fd: c_int, events: c_short) -> Option<c_short> {
+        let revents = self.get(fd)?.with(|f| f.poll(events));
+        Some(revents & (events | libc::POLLERR | libc::POLLHUP | libc::POLLNVAL))
+    }
+
+    /// 仮想fdを`poll`/`select`/`epoll_wait`で待っているスレッドを起こす
+    ///
+    /// 仮想fdの操作以外で準備状態が変わったときに呼びます。
+    pub fn notify(&self) {
+        if self.waiting.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let one = 1u64;
+        for &efd in self.waiters.lock().iter() {
+            unsafe {
+                raw_syscall_bypass(
+                    Sysno::write.nr(),
+                    efd as u64,
+                    &one as *const u64 as u64,
+                    8,
+                    0,
+                    0,
+                    0,
+                )
+            };
+        }
+    }
+
+    /// 待つためのeventfdを作り、`notify`の対象に加える
+    fn waiter(&self) -> Result<Waiter<'_>, i64> {
+        let flags = libc::EFD_CLOEXEC | libc::EFD_NONBLOCK;
+        let efd = unsafe { raw_syscall_bypass(Sysno::eventfd2.nr(), 0, flags as u64, 0, 0, 0, 0) };
+        if efd < 0 {
+            return Err(efd);
+        }
+        self.waiters.lock().push(efd as c_int);
+        self.waiting.fetch_add(1, Ordering::Release);
+        Ok(Waiter {
+            table: self,
+            efd: efd as c_int,
+        })
+    }
+
+    /// 登録されている仮想fdの数
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, fd: c_int) -> Option<Arc<OpenFile>> {
+        self.files.lock().get(&fd).cloned()
+    }
+
+    /// fdの登録を差し替えて以前の値を返す
+    fn set(&self, fd: c_int, file: Option<Arc<OpenFile>>) -> Option<Arc<OpenFile>> {
+        let mut files = self.files.lock();
+        let old = match file {
+            Some(file) => files.insert(fd, file),
+            None => files.remove(&fd),
+        };
+        self.len.store(files.len(), Ordering::Relaxed);
+        old
+    }
+
+    /// 仮想fdへのシステムコールを処理する
+    ///
+    /// 仮想fdに関係しない場合は`None`を返します。戻り値のエラーは`-errno`です。
+    pub fn dispatch(&self, regs: &mut SyscallRegs) -> Option<i64> {
+        if self.is_empty() {
+            return None;
+        }
+        let sysno = Sysno::from_raw(regs.rax).filter(|sysno| FD_SYSCALLS.contains(*sysno))?;
+        let fd = regs.rdi as c_int;
+
+        // dup先が仮想fdの場合はdup元が実際のfdでも登録を外す
+        if matches!(sysno, Sysno::dup2 | Sysno::dup3) && self.get(fd).is_none() {
+            self.get(regs.rsi as c_int)?;
+            return Some(self.dup(regs, None));
+        }
+
+        // 閉じられたepollインスタンスの登録を外す
+        if sysno == Sysno::close && self.get(fd).is_none() {
+            self.epoll.lock().remove(&fd);
+            return None;
+        }
+
+        let file = self.get(fd)?;
+        let ret = self.dispatch_file(regs, sysno, fd, file)?;
+        // 準備状態が変わったかもしれないので待っているスレッドに確認させる
+        self.notify();
+        Some(ret)
+    }
+
+    fn dispatch_file(
+        &self,
+        regs: &mut SyscallRegs,
+        sysno: Sysno,
+        fd: c_int,
+        file: Arc<OpenFile>,
+    ) -> Option<i64> {
+        let ret = match sysno {
+            Sysno::read => read(&file, regs.rsi, regs.rdx as usize, None),
+            Sysno::write => write(&file, regs.rsi, regs.rdx as usize, None),
+            Sysno::pread64 => read(&file, regs.rsi, regs.rdx as usize, Some(regs.r10 as i64)),
+            Sysno::pwrite64 => write(&file, regs.rsi, regs.rdx as usize, Some(regs.r10 as i64)),
+            Sysno::readv => readv(&file, regs.rsi, regs.rdx as usize, None),
+            Sysno::writev => writev(&file, regs.rsi, regs.rdx as usize, None),
+            Sysno::preadv => readv(&file, regs.rsi, regs.rdx as usize, Some(regs.r10 as i64)),
+            Sysno::pwritev => writev(&file, regs.rsi, regs.rdx as usize, Some(regs.r10 as i64)),
+            // -1は現在位置（RWF_*フラグは無視する）
+            Sysno::preadv2 => {
+                let offset = Some(regs.r10 as i64).filter(|&offset| offset != -1);
+                readv(&file, regs.rsi, regs.rdx as usize, offset)
+            }
+            Sysno::pwritev2 => {
+                let offset = Some(regs.r10 as i64).filter(|&offset| offset != -1);
+                writev(&file, regs.rsi, regs.rdx as usize, offset)
+            }
+            Sysno::lseek => to_ret(file.with(|f| f.lseek(regs.rsi as i64, regs.rdx as c_int))),
+            Sysno::close => {
+                drop(file);
+                to_ret(self.close(fd).map(|()| 0))
+            }
+            Sysno::fstat => fstat(&file, regs.rsi),
+            Sysno::newfstatat => {
+                // fstatat(fd, "", buf, AT_EMPTY_PATH)のみ
+                if !empty_path(regs.rsi, regs.r10 as c_int) {
+                    return None;
+                }
+                fstat(&file, regs.rdx)
+            }
+            Sysno::statx => {
+                // statx(fd, "", AT_EMPTY_PATH, mask, buf)のみ
+                if !empty_path(regs.rsi, regs.rdx as c_int) {
+                    return None;
+                }
+                statx(&file, regs.r8)
+            }
+            // statfsはVirtualFileでは提供しない
+            Sysno::fstatfs => -(libc::ENOTSUP as i64),
+            Sysno::ftruncate => {
+                if (regs.rsi as i64) < 0 {
+                    return Some(-(libc::EINVAL as i64));
+                }
+                to_ret(file.with(|f| f.ftruncate(regs.rsi as i64)).map(|()| 0))
+            }
+            Sysno::fsync | Sysno::fdatasync => to_ret(file.with(|f| f.fsync()).map(|()| 0)),
+            Sysno::ioctl => to_ret(file.with(|f| f.ioctl(regs.rsi, regs.rdx))),
+            Sysno::fcntl => match regs.rsi as c_int {
+                libc::F_DUPFD | libc::F_DUPFD_CLOEXEC => self.dup(regs, Some(file)),
+                // フラグなどは予約したfdに対してカーネルが処理する
+                _ => return None,
+            },
+            Sysno::dup | Sysno::dup2 | Sysno::dup3 => self.dup(regs, Some(file)),
+            _ => return None,
+        };
+        Some(ret)
+    }
+
+    /// poll(2)/ppoll(2) - `fds`に仮想fdが含まれなければ`None`
+    pub(crate) fn poll(
+        &self,
+        fds: PollFds,
+        timeout: Option<Duration>,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> Option<i64> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut entries = fds.to_vec().ok()?;
+        if !entries.iter().any(|fd| self.contains(fd.fd)) {
+            return None;
+        }
+        let ret = self.poll_entries(&mut entries, timeout, sigmask, sigsetsize);
+        if ret >= 0 {
+            if let Err(e) = fds.write(&entries) {
+                return Some(-(e.errno() as i64));
+            }
+        }
+        Some(ret)
+    }
+
+    /// select(2)/pselect6(2) - 集合に仮想fdが含まれなければ`None`
+    ///
+    /// `pollfd`に変換して`poll`と同じ方法で待ちます。
+    pub(crate) fn select(
+        &self,
+        nfds: c_int,
+        sets: [FdSet; 3],
+        timeout: Option<Duration>,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> Option<i64> {
+        if self.is_empty() || !(0..=crate::poll::FD_SETSIZE as c_int).contains(&nfds) {
+            return None;
+        }
+        let requested = [libc::POLLIN, libc::POLLOUT, libc::POLLPRI];
+        let mut events = BTreeMap::<c_int, c_short>::new();
+        for (set, event) in sets.iter().zip(requested) {
+            for fd in set.fds().ok()? {
+                *events.entry(fd).or_default() |= event;
+            }
+        }
+        if !events.keys().any(|&fd| self.contains(fd)) {
+            return None;
+        }
+
+        let mut entries: Vec<pollfd> = events
+            .into_iter()
+            .map(|(fd, events)| pollfd {
+                fd,
+                events,
+                revents: 0,
+            })
+            .collect();
+        let ret = self.poll_entries(&mut entries, timeout, sigmask, sigsetsize);
+        if ret < 0 {
+            return Some(ret);
+        }
+        if entries.iter().any(|fd| fd.revents & libc::POLLNVAL != 0) {
+            return Some(-(libc::EBADF as i64));
+        }
+
+        // カーネルのPOLLIN_SET/POLLOUT_SET/POLLEX_SETと同じ対応
+        let ready = [
+            libc::POLLIN | libc::POLLRDNORM | libc::POLLHUP | libc::POLLERR,
+            libc::POLLOUT | libc::POLLWRNORM | libc::POLLERR,
+            libc::POLLPRI,
+        ];
+        let mut count = 0;
+        for ((set, event), mask) in sets.iter().zip(requested).zip(ready) {
+            let fds: Vec<c_int> = entries
+                .iter()
+                .filter(|fd| fd.events & event != 0 && fd.revents & mask != 0)
+                .map(|fd| fd.fd)
+                .collect();
+            count += fds.len() as i64;
+            if let Err(e) = set.set_fds(&fds) {
+                return Some(-(e.errno() as i64));
+            }
+        }
+        Some(count)
+    }
+
+    /// 実際のfdはカーネルの`ppoll`で、仮想fdは`VirtualFile::poll`で調べる
+    ///
+    /// 仮想fdの準備ができていなければ、実際のfdと`Waiter`のeventfdをカーネルで待ち、
+    /// 起こされたら確認し直します。戻り値は準備ができたfdの数か`-errno`です。
+    fn poll_entries(
+        &self,
+        fds: &mut [pollfd],
+        timeout: Option<Duration>,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> i64 {
+        // 準備状態を調べる前に登録し、その後の変化を取りこぼさない
+        let waiter = match self.waiter() {
+            Ok(waiter) => waiter,
+            Err(errno) => return errno,
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut kernel_fds = fds.to_vec();
+        kernel_fds.push(pollfd {
+            fd: waiter.efd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        loop {
+            let mut ready = false;
+            for (fd, kernel_fd) in fds.iter_mut().zip(kernel_fds.iter_mut()) {
+                // カーネルは負のfdを無視する
+                kernel_fd.fd = match self.poll_fd(fd.fd, fd.events) {
+                    Some(revents) => {
+                        fd.revents = revents;
+                        ready |= revents != 0;
+                        -1
+                    }
+                    None => fd.fd,
+                };
+            }
+
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let ts = to_timespec(if ready { Some(Duration::ZERO) } else { remaining });
+            let ret = unsafe {
+                raw_syscall_bypass(
+                    Sysno::ppoll.nr(),
+                    kernel_fds.as_mut_ptr() as u64,
+                    kernel_fds.len() as u64,
+                    ts.as_ref().map_or(0, |ts| ts as *const libc::timespec as u64),
+                    sigmask as u64,
+                    sigsetsize as u64,
+                    0,
+                )
+            };
+            if ret < 0 {
+                return ret;
+            }
+            waiter.drain();
+
+            for (fd, kernel_fd) in fds.iter_mut().zip(&kernel_fds) {
+                if kernel_fd.fd >= 0 {
+                    fd.revents = kernel_fd.revents;
+                }
+            }
+            let count = fds.iter().filter(|fd| fd.revents != 0).count();
+            if count > 0 || remaining == Some(Duration::ZERO) {
+                return count as i64;
+            }
+        }
+    }
+
+    /// epoll_ctl(2) - `fd`が仮想fdでなければ`None`
+    ///
+    /// 予約した`/dev/null`はepollに登録できないため、仮想fdの登録は表で管理します。
+    pub(crate) fn epoll_ctl(
+        &self,
+        epfd: c_int,
+        op: c_int,
+        fd: c_int,
+        event: UserPtr<epoll_event>,
+    ) -> Option<i64> {
+        if !self.contains(fd) {
+            return None;
+        }
+        // epfdが開かれていることだけを確認する
+        let ret = unsafe {
+            raw_syscall_bypass(Sysno::fcntl.nr(), epfd as u64, libc::F_GETFD as u64, 0, 0, 0, 0)
+        };
+        if ret < 0 {
+            return Some(ret);
+        }
+        if epfd == fd {
+            return Some(-(libc::EINVAL as i64));
+        }
+
+        let mut epoll = self.epoll.lock();
+        let registered = epoll.entry(epfd).or_default();
+        let exists = registered.contains_key(&fd);
+        let errno = match op {
+            libc::EPOLL_CTL_ADD | libc::EPOLL_CTL_MOD => match event.read() {
+                Err(e) => e.errno(),
+                Ok(_) if op == libc::EPOLL_CTL_ADD && exists => libc::EEXIST,
+                Ok(_) if op == libc::EPOLL_CTL_MOD && !exists => libc::ENOENT,
+                Ok(event) => {
+                    registered.insert(fd, event);
+                    0
+                }
+            },
+            libc::EPOLL_CTL_DEL if exists => {
+                registered.remove(&fd);
+                0
+            }
+            libc::EPOLL_CTL_DEL => libc::ENOENT,
+            _ => libc::EINVAL,
+        };
+        if registered.is_empty() {
+            epoll.remove(&epfd);
+        }
+        Some(-(errno as i64))
+    }
+
+    /// epoll_wait(2)/epoll_pwait(2)/epoll_pwait2(2) - `epfd`に仮想fdが登録されていなければ`None`
+    ///
+    /// 準備ができた仮想fdのイベントを先に書き込み、残りをカーネルで埋めます。
+    /// `EPOLLET`はレベルトリガとして扱います。
+    pub(crate) fn epoll_wait(
+        &self,
+        epfd: c_int,
+        events: EpollEvents,
+        timeout: Option<Duration>,
+        sigmask: *const sigset_t,
+        sigsetsize: size_t,
+    ) -> Option<i64> {
+        if self.is_empty() || !self.epoll.lock().contains_key(&epfd) {
+            return None;
+        }
+        if events.capacity() == 0 {
+            return Some(-(libc::EINVAL as i64));
+        }
+
+        let waiter = match self.waiter() {
+            Ok(waiter) => waiter,
+            Err(errno) => return Some(errno),
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let ready = self.epoll_ready(epfd, events.capacity());
+            let n = match events.write(&ready) {
+                Ok(n) => n,
+                Err(e) => return Some(-(e.errno() as i64)),
+            };
+
+            // 仮想fdの準備ができていなければ、epfdとeventfdをカーネルで待つ
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            if n == 0 && remaining != Some(Duration::ZERO) {
+                let mut fds = [epfd, waiter.efd].map(|fd| pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+                let ts = to_timespec(remaining);
+                let ret = unsafe {
+                    raw_syscall_bypass(
+                        Sysno::ppoll.nr(),
+                        fds.as_mut_ptr() as u64,
+                        fds.len() as u64,
+                        ts.as_ref().map_or(0, |ts| ts as *const libc::timespec as u64),
+                        sigmask as u64,
+                        sigsetsize as u64,
+                        0,
+                    )
+                };
+                if ret < 0 {
+                    return Some(ret);
+                }
+                waiter.drain();
+            }
+
+            let rest = events.skip(n);
+            let ret = if rest.capacity() == 0 {
+                0
+            } else {
+                unsafe {
+                    raw_syscall_bypass(
+                        Sysno::epoll_pwait.nr(),
+                        epfd as u64,
+                        rest.as_ptr() as u64,
+                        rest.capacity() as u64,
+                        0,
+                        0,
+                        0,
+                    )
+                }
+            };
+            if ret < 0 {
+                return Some(if n > 0 { n as i64 } else { ret });
+            }
+            let total = n as i64 + ret;
+            // 待った後に時間切れになっていれば、次の周回で確認してから返る
+            if total > 0 || remaining == Some(Duration::ZERO) {
+                return Some(total);
+            }
+        }
+    }
+
+    /// `epfd`に登録された仮想fdのうち準備ができたもの（`EPOLLONESHOT`は報告後に無効化する）
+    fn epoll_ready(&self, epfd: c_int, max: usize) -> Vec<epoll_event> {
+        let mut epoll = self.epoll.lock();
+        let Some(registered) = epoll.get_mut(&epfd) else {
+            return Vec::new();
+        };
+        let mut ready = Vec::new();
+        for (&fd, event) in registered.iter_mut() {
+            let requested = event.events;
+            if ready.len() >= max || requested == 0 {
+                continue;
+            }
+            let revents = self.poll_fd(fd, requested as u16 as c_short).unwrap_or(0);
+            if revents != 0 {
+                ready.push(epoll_event {
+                    events: revents as u16 as u32,
+                    u64: event.u64,
+                });
+                if requested & libc::EPOLLONESHOT as u32 != 0 {
+                    event.events = 0;
+                }
+            }
+        }
+        ready
+    }
+
+    /// 予約したfdをカーネルでdupし、新しいfdを`file`に対応付ける
+    fn dup(&self, regs: &SyscallRegs, file: Option<Arc<OpenFile>>) -> i64 {
+        let ret = unsafe { raw_syscall(regs) };
+        if ret >= 0 {
+            self.set(ret as c_int, file);
+        }
+        ret
+    }
+}
+
+/// `/dev/null`を開いてfd番号を予約する
+fn reserve_fd() -> Result<c_int, c_int> {
+    let ret = unsafe {
+        raw_syscall_bypass(
+            Sysno::openat.nr(),
+            libc::AT_FDCWD as u64,
+            c"/dev/null".as_ptr() as u64,
+            (libc::O_RDWR | libc::O_CLOEXEC) as u64,
+            0,
+            0,
+            0,
+        )
+    };
+    if ret < 0 {
+        return Err(-ret as c_int);
+    }
+    Ok(ret as c_int)
+}
+
+/// カーネルで待つ時間（`None`は無期限）
+fn to_timespec(duration: Option<Duration>) -> Option<libc::timespec> {
+    duration.map(|duration| libc::timespec {
+        tv_sec: duration.as_secs() as libc::time_t,
+        tv_nsec: duration.subsec_nanos() as libc::c_long,
+    })
+}
+
+/// `notify`で起こされるeventfd（dropで登録を外して閉じる）
+struct Waiter<'a> {
+    table: &'a VirtualFdTable,
+    efd: c_int,
+}
+
+impl Waiter<'_> {
+    /// 溜まった通知を読み捨てる
+    fn drain(&self) {
+        let mut count = 0u64;
+        unsafe {
+            raw_syscall_bypass(
+                Sysno::read.nr(),
+                self.efd as u64,
+                &mut count as *mut u64 as u64,
+                8,
+                0,
+                0,
+                0,
+            )
+        };
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.table.waiters.lock().retain(|&efd| efd != self.efd);
+        self.table.waiting.fetch_sub(1, Ordering::Release);
+        unsafe { raw_syscall_bypass(Sysno::close.nr(), self.efd as u64, 0, 0, 0, 0, 0) };
+    }
+}
+
+fn to_ret<T: Into<i64>>(result: Result<T, c_int>) -> i64 {
+    match result {
+        Ok(value) => value.into(),
+        Err(errno) => -(errno as i64),
+    }
+}
+
+fn read(file: &OpenFile, ptr: u64, count: usize, offset: Option<i64>) -> i64 {
+    let mut tmp = vec![0u8; count.min(IO_CHUNK)];
+    let n = match file.with(|f| match offset {
+        Some(offset) => f.pread(&mut tmp, offset),
+        None => f.read(&mut tmp),
+    }) {
+        Ok(n) => n.min(tmp.len()),
+        Err(errno) => return -(errno as i64),
+    };
+    match try_write_bytes(ptr as *mut c_void, &tmp[..n]) {
+        Ok(()) => n as i64,
+        Err(e) => -(e.errno() as i64),
+    }
+}
+
+fn write(file: &OpenFile, ptr: u64, count: usize, offset: Option<i64>) -> i64 {
+    let mut tmp = vec![0u8; count.min(IO_CHUNK)];
+    if let Err(e) = try_read_bytes(ptr as *const c_void, &mut tmp) {
+        return -(e.errno() as i64);
+    }
+    to_ret(
+        file.with(|f| match offset {
+            Some(offset) => f.pwrite(&tmp, offset),
+            None => f.write(&tmp),
+        })
+        .map(|n| n.min(tmp.len()) as i64),
+    )
+}
+
+/// iovecを順に読み書きし、短い読み書きかエラーで止める（途中のエラーは転送済みの量を返す）
+///
+/// `f`にはiovecとそれまでに転送した量を渡します。
+fn transfer_iovecs(ptr: u64, cnt: usize, mut f: impl FnMut(&iovec, i64) -> i64) -> i64 {
+    if cnt > IOV_MAX {
+        return -(libc::EINVAL as i64);
+    }
+    let mut total = 0;
+    for i in 0..cnt {
+        let iov = match try_read_val((ptr as *const iovec).wrapping_add(i)) {
+            Ok(iov) => iov,
+            Err(e) if total == 0 => return -(e.errno() as i64),
+            Err(_) => break,
+        };
+        let ret = f(&iov, total);
+        if ret < 0 {
+            return if total == 0 { ret } else { total };
+        }
+        total += ret;
+        if (ret as usize) < iov.iov_len {
+            break;
+        }
+    }
+    total
+}
+
+fn readv(file: &OpenFile, ptr: u64, cnt: usize, offset: Option<i64>) -> i64 {
+    transfer_iovecs(ptr, cnt, |iov, done| {
+        let offset = offset.map(|offset| offset + done);
+        read(file, iov.iov_base as u64, iov.iov_len, offset)
+    })
+}
+
+fn writev(file: &OpenFile, ptr: u64, cnt: usize, offset: Option<i64>) -> i64 {
+    transfer_iovecs(ptr, cnt, |iov, done| {
+        let offset = offset.map(|offset| offset + done);
+        write(file, iov.iov_base as u64, iov.iov_len, offset)
+    })
+}
+
+/// パス引数が空で`AT_EMPTY_PATH`が指定されているか（fd自体を対象にする）
+fn empty_path(path: u64, flags: c_int) -> bool {
+    let empty = path == 0 || try_read_val(path as *const u8) == Ok(0);
+    empty && flags & libc::AT_EMPTY_PATH != 0
+}
+
+fn fstat(file: &OpenFile, ptr: u64) -> i64 {
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if let Err(errno) = file.with(|f| f.stat(&mut st)) {
+        return -(errno as i64);
+    }
+    match try_write_val(ptr as *mut libc::stat, st) {
+        Ok(()) => 0,
+        Err(e) => -(e.errno() as i64),
+    }
+}
+
+/// `VirtualFile::stat`の結果を`statx`の形式で書き込む
+fn statx(file: &OpenFile, ptr: u64) -> i64 {
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if let Err(errno) = file.with(|f| f.stat(&mut st)) {
+        return -(errno as i64);
+    }
+    match try_write_val(ptr as *mut libc::statx, stat_to_statx(&st)) {
+        Ok(()) => 0,
+        Err(e) => -(e.errno() as i64),
+    }
+}
+
+fn stat_to_statx(st: &libc::stat) -> libc::statx {
+    fn timestamp(sec: i64, nsec: i64) -> libc::statx_timestamp {
+        let mut ts: libc::statx_timestamp = unsafe { std::mem::zeroed() };
+        ts.tv_sec = sec;
+        ts.tv_nsec = nsec as u32;
+        ts
+    }
+
+    let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+    stx.stx_mask = libc::STATX_BASIC_STATS;
+    stx.stx_blksize = st.st_blksize as u32;
+    stx.stx_nlink = st.st_nlink as u32;
+    stx.stx_uid = st.st_uid;
+    stx.stx_gid = st.st_gid;
+    stx.stx_mode = st.st_mode as u16;
+    stx.stx_ino = st.st_ino;
+    stx.stx_size = st.st_size as u64;
+    stx.stx_blocks = st.st_blocks as u64;
+    stx.stx_atime = timestamp(st.st_atime, st.st_atime_nsec);
+    stx.stx_mtime = timestamp(st.st_mtime, st.st_mtime_nsec);
+    stx.stx_ctime = timestamp(st.st_ctime, st.st_ctime_nsec);
+    stx.stx_rdev_major = libc::major(st.st_rdev);
+    stx.stx_rdev_minor = libc::minor(st.st_rdev);
+    stx.stx_dev_major = libc::major(st.st_dev);
+    stx.stx_dev_minor = libc::minor(st.st_dev);
+    stx
+}
+
+/// フック実装の関心集合に含まれず、ロックを取らずに処理するもの（`POLL_SYSNOS`の添字のビット）
+static UNLOCKED: AtomicU8 = AtomicU8::new(0);
+
+/// フック実装が処理する`POLL_SYSCALLS`を記録する（`register_syscall_hooks`などから呼ばれる）
+pub(crate) fn set_dispatcher_interests(interests: &SysnoSet) {
+    let bits = POLL_SYSNOS
+        .iter()
+        .enumerate()
+        .filter(|(_, sysno)| !interests.contains(**sysno))
+        .fold(0, |bits, (i, _)| bits | 1 << i);
+    UNLOCKED.store(bits, Ordering::Release);
+}
+
+/// 仮想fdのためのシステムコールを処理する（ディスパッチャのロックを取らない）
+///
+/// 仮想fdへの`FD_SYSCALLS`と、フック実装が関心を持たない`POLL_SYSCALLS`を処理します。
+/// それ以外は`None`です。
+pub(crate) fn dispatch_unlocked(regs: &mut SyscallRegs) -> Option<i64> {
+    if let Some(ret) = VIRTUAL_FDS.dispatch(regs) {
+        return Some(ret);
+    }
+    let index = POLL_SYSNOS
+        .iter()
+        .position(|sysno| sysno.nr() == regs.rax)?;
+    if VIRTUAL_FDS.is_empty() || UNLOCKED.load(Ordering::Acquire) & (1 << index) == 0 {
+        return None;
+    }
+
+    Some(dispatch_defaults(regs))
+}
+
+/// フックのディスパッチが参照するグローバルな表
+static VIRTUAL_FDS: VirtualFdTable = VirtualFdTable::new();
+
+/// グローバルな仮想fdの表
+pub fn virtual_fds() -> &'static VirtualFdTable {
+    &VIRTUAL_FDS
+}
+
+/// fork後の子プロセスで表と開かれたファイルのロックを解放する
+pub(crate) fn reinit_after_fork() {
+    // 表のロックはファイルの操作中には保持しないため、forkしたスレッドは持っていない
+    unsafe {
+        VIRTUAL_FDS.files.force_unlock();
+        VIRTUAL_FDS.epoll.force_unlock();
+        VIRTUAL_FDS.waiters.force_unlock();
+    }
+    // ファイルのロックは子に存在しないスレッドが操作中に保持していたもの
+    // （`VirtualFile`のメソッドからforkした場合は、メソッドから戻るときの解放が重なるだけ）
+    for file in VIRTUAL_FDS.files.lock().values() {
+        unsafe { file.0.force_unlock() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// テスト用のインメモリファイル
+    struct MemFile {
+        data: Arc<Mutex<Vec<u8>>>,
+        pos: usize,
+        closed: Arc<AtomicUsize>,
+    }
+
+    impl VirtualFile for MemFile {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, c_int> {
+            let data = self.data.lock().unwrap();
+            let n = buf.len().min(data.len().saturating_sub(self.pos));
+            buf[..n].copy_from_slice(&data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize, c_int> {
+            self.data.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn pread(&mut self, buf: &mut [u8], offset: i64) -> Result<usize, c_int> {
+            let data = self.data.lock().unwrap();
+            let start = (offset as usize).min(data.len());
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            Ok(n)
+        }
+
+        fn lseek(&mut self, offset: i64, whence: c_int) -> Result<i64, c_int> {
+            match whence {
+                libc::SEEK_SET => self.pos = offset as usize,
+                _ => return Err(libc::EINVAL),
+            }
+            Ok(self.pos as i64)
+        }
+
+        fn stat(&mut self, st: &mut libc::stat) -> Result<(), c_int> {
+            st.st_mode = libc::S_IFREG | 0o644;
+            st.st_size = self.data.lock().unwrap().len() as i64;
+            Ok(())
+        }
+
+        fn close(&mut self) {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn call(table: &VirtualFdTable, sysno: Sysno, args: [u64; 3]) -> Option<i64> {
+        let mut regs = SyscallRegs::new(sysno.nr(), args[0], args[1], args[2], 0, 0, 0);
+        table.dispatch(&mut regs)
+    }
+
+    #[test]
+    fn test_virtual_file_io() {
+        let table = VirtualFdTable::new();
+        let closed = Arc::new(AtomicUsize::new(0));
+        let fd = table
+            .insert(MemFile {
+                data: Arc::new(Mutex::new(Vec::new())),
+                pos: 0,
+                closed: closed.clone(),
+            })
+            .unwrap();
+        assert!(table.contains(fd));
+        // fd番号はカーネルで予約されている
+        assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0);
+        // 仮想fdでないfdには関与しない
+        assert_eq!(call(&table, Sysno::read, [0, 0, 0]), None);
+
+        let msg = b"hello";
+        assert_eq!(
+            call(&table, Sysno::write, [fd as u64, msg.as_ptr() as u64, 5]),
+            Some(5)
+        );
+        assert_eq!(
+            call(&table, Sysno::lseek, [fd as u64, 1, libc::SEEK_SET as u64]),
+            Some(1)
+        );
+
+        let mut a = [0u8; 2];
+        let mut b = [0u8; 8];
+        let iov = [
+            iovec {
+                iov_base: a.as_mut_ptr() as *mut c_void,
+                iov_len: a.len(),
+            },
+            iovec {
+                iov_base: b.as_mut_ptr() as *mut c_void,
+                iov_len: b.len(),
+            },
+        ];
+        assert_eq!(
+            call(&table, Sysno::readv, [fd as u64, iov.as_ptr() as u64, 2]),
+            Some(4)
+        );
+        assert_eq!((&a, &b[..2]), (b"el", &b"lo"[..]));
+        call(&table, Sysno::lseek, [fd as u64, 0, libc::SEEK_SET as u64]);
+        assert_eq!(
+            call(&table, Sysno::read, [fd as u64, 8, 1]),
+            Some(-(libc::EFAULT as i64))
+        );
+
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        assert_eq!(
+            call(
+                &table,
+                Sysno::fstat,
+                [fd as u64, &mut st as *mut _ as u64, 0]
+            ),
+            Some(0)
+        );
+        assert_eq!(st.st_size, 5);
+
+        // dupしたfdは同じオブジェクトを共有し、最後のcloseで閉じる
+        let dup = call(&table, Sysno::dup, [fd as u64, 0, 0]).unwrap();
+        assert!(dup >= 0 && table.contains(dup as c_int));
+        assert_eq!(call(&table, Sysno::close, [fd as u64, 0, 0]), Some(0));
+        assert!(!table.contains(fd));
+        assert_eq!(closed.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            call(&table, Sysno::lseek, [dup as u64, 0, libc::SEEK_SET as u64]),
+            Some(0)
+        );
+        assert_eq!(call(&table, Sysno::close, [dup as u64, 0, 0]), Some(0));
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_stat_and_preadv_virtual_fd() {
+        let table = VirtualFdTable::new();
+        let fd = table
+            .insert(MemFile {
+                data: Arc::new(Mutex::new(b"hello, world".to_vec())),
+                pos: 0,
+                closed: Arc::default(),
+            })
+            .unwrap();
+
+        // File::metadataと同じstatx(fd, "", AT_EMPTY_PATH)
+        let mut stx: libc::statx = unsafe { std::mem::zeroed() };
+        let flags = (libc::AT_EMPTY_PATH | libc::AT_STATX_SYNC_AS_STAT) as u64;
+        let mut regs = SyscallRegs::new(
+            Sysno::statx.nr(),
+            fd as u64,
+            c"".as_ptr() as u64,
+            flags,
+            libc::STATX_ALL as u64,
+            &mut stx as *mut _ as u64,
+            0,
+        );
+        assert_eq!(table.dispatch(&mut regs), Some(0));
+        assert_eq!(stx.stx_size, 12);
+        assert_eq!(stx.stx_mode as u32, libc::S_IFREG | 0o644);
+        // パスを指定したstatxは仮想fdを対象にしない
+        regs.rsi = c"x".as_ptr() as u64;
+        assert_eq!(table.dispatch(&mut regs), None);
+
+        // preadvは各iovecに続きのオフセットを渡す
+        let (mut a, mut b) = ([0u8; 3], [0u8; 16]);
+        let iov = [
+            iovec {
+                iov_base: a.as_mut_ptr() as *mut c_void,
+                iov_len: a.len(),
+            },
+            iovec {
+                iov_base: b.as_mut_ptr() as *mut c_void,
+                iov_len: b.len(),
+            },
+        ];
+        let iov_ptr = iov.as_ptr() as u64;
+        let mut regs = SyscallRegs::new(Sysno::preadv.nr(), fd as u64, iov_ptr, 2, 7, 0, 0);
+        assert_eq!(table.dispatch(&mut regs), Some(5));
+        assert_eq!((&a, &b[..2]), (b"wor", &b"ld"[..]));
+        // preadv2のオフセット-1は現在位置（read）
+        let mut regs = SyscallRegs::new(Sysno::preadv2.nr(), fd as u64, iov_ptr, 1, -1i64 as u64, 0, 0);
+        assert_eq!(table.dispatch(&mut regs), Some(3));
+        assert_eq!(&a, b"hel");
+
+        // 実装されていないものは/dev/nullに届かずエラーになる
+        assert_eq!(
+            call(&table, Sysno::ftruncate, [fd as u64, 0, 0]),
+            Some(-(libc::EINVAL as i64))
+        );
+        assert_eq!(
+            call(&table, Sysno::fsync, [fd as u64, 0, 0]),
+            Some(-(libc::EINVAL as i64))
+        );
+        let mut sfs: libc::statfs = unsafe { std::mem::zeroed() };
+        assert_eq!(
+            call(&table, Sysno::fstatfs, [fd as u64, &mut sfs as *mut _ as u64, 0]),
+            Some(-(libc::ENOTSUP as i64))
+        );
+
+        table.close(fd).unwrap();
+    }
+
+    #[test]
+    fn test_dup2_over_virtual_fd() {
+        let table = VirtualFdTable::new();
+        let closed = Arc::new(AtomicUsize::new(0));
+        let fd = table
+            .insert(MemFile {
+                data: Arc::new(Mutex::new(Vec::new())),
+                pos: 0,
+                closed: closed.clone(),
+            })
+            .unwrap();
+
+        // 実際のfdで上書きすると仮想fdではなくなる
+        let real = unsafe { libc::dup(2) };
+        assert_eq!(
+            call(&table, Sysno::dup2, [real as u64, fd as u64, 0]),
+            Some(fd as i64)
+        );
+        assert!(!table.contains(fd));
+        assert_eq!(closed.load(Ordering::SeqCst), 1);
+
+        unsafe {
+            libc::close(fd);
+            libc::close(real);
+        }
+    }
+
+    /// 準備状態を外から切り替えられるファイル
+    struct Pipe(Arc<std::sync::atomic::AtomicBool>);
+
+    impl VirtualFile for Pipe {
+        fn poll(&mut self, events: c_short) -> c_short {
+            if self.0.load(Ordering::SeqCst) {
+                events & libc::POLLIN
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn test_poll_virtual_fd() {
+        let table = VirtualFdTable::new();
+        let ready = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let vfd = table.insert(Pipe(ready.clone())).unwrap();
+        let mut pipe = [0; 2];
+        assert_eq!(unsafe { libc::pipe(pipe.as_mut_ptr()) }, 0);
+
+        let mut fds = [
+            pollfd {
+                fd: pipe[0],
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: vfd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let view = PollFds::new(fds.as_mut_ptr(), 2);
+        let no_mask = std::ptr::null();
+        assert_eq!(table.poll(view, Some(Duration::ZERO), no_mask, 0), Some(0));
+
+        // 待っている間に準備ができ、notifyで起こされれば返る
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(30));
+                ready.store(true, Ordering::SeqCst);
+                table.notify();
+            });
+            let start = Instant::now();
+            assert_eq!(table.poll(view, Some(Duration::from_secs(5)), no_mask, 0), Some(1));
+            assert!(start.elapsed() < Duration::from_secs(5));
+        });
+        assert_eq!((fds[0].revents, fds[1].revents), (0, libc::POLLIN));
+
+        // 仮想fdを含まなければカーネルに任せる
+        let real = PollFds::new(fds.as_mut_ptr(), 1);
+        assert_eq!(table.poll(real, None, no_mask, 0), None);
+
+        // selectはpollfdに変換して待つ
+        let nfds = pipe[1].max(vfd) + 1;
+        let mut readfds: libc::fd_set = unsafe { std::mem::zeroed() };
+        let mut writefds: libc::fd_set = unsafe { std::mem::zeroed() };
+        FdSet::new(&mut readfds, nfds).set_fds(&[pipe[0], vfd]).unwrap();
+        FdSet::new(&mut writefds, nfds).set_fds(&[pipe[1]]).unwrap();
+        let sets = [
+            FdSet::new(&mut readfds, nfds),
+            FdSet::new(&mut writefds, nfds),
+            FdSet::new(std::ptr::null_mut(), nfds),
+        ];
+        assert_eq!(table.select(nfds, sets, None, no_mask, 0), Some(2));
+        assert_eq!(sets[0].fds().unwrap(), vec![vfd]);
+        assert_eq!(sets[1].fds().unwrap(), vec![pipe[1]]);
+
+        // epollは仮想fdのイベントを先に返し、残りをカーネルで埋める
+        let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        let mut ev = epoll_event {
+            events: (libc::EPOLLIN | libc::EPOLLONESHOT) as u32,
+            u64: 42,
+        };
+        let ev_ptr = UserPtr::new(&mut ev);
+        assert_eq!(table.epoll_ctl(epfd, libc::EPOLL_CTL_ADD, vfd, ev_ptr), Some(0));
+        assert_eq!(
+            table.epoll_ctl(epfd, libc::EPOLL_CTL_ADD, vfd, ev_ptr),
+            Some(-(libc::EEXIST as i64))
+        );
+        assert_eq!(table.epoll_ctl(epfd, libc::EPOLL_CTL_ADD, pipe[1], ev_ptr), None);
+        let mut out_ev = epoll_event {
+            events: libc::EPOLLOUT as u32,
+            u64: 7,
+        };
+        assert_eq!(
+            unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, pipe[1], &mut out_ev) },
+            0
+        );
+
+        let mut buf = [epoll_event { events: 0, u64: 0 }; 4];
+        let events = EpollEvents::new(buf.as_mut_ptr(), 4);
+        let got = table.epoll_wait(epfd, events, None, no_mask, 0);
+        assert_eq!(got, Some(2));
+        assert_eq!(({ buf[0].u64 }, { buf[0].events }), (42, libc::EPOLLIN as u32));
+        assert_eq!({ buf[1].u64 }, 7);
+        // EPOLLONESHOTは一度報告すると無効になる
+        assert_eq!(table.epoll_wait(epfd, events, None, no_mask, 0), Some(1));
+        assert_eq!({ buf[0].u64 }, 7);
+
+        // 仮想fdを閉じると登録も外れる
+        table.close(vfd).unwrap();
+        assert_eq!(table.epoll_wait(epfd, events, None, no_mask, 0), None);
+
+        unsafe {
+            libc::close(epfd);
+            libc::close(pipe[0]);
+            libc::close(pipe[1]);
+        }
+    }
+
+    #[test]
+    fn test_epoll_wait_wakes_on_notify() {
+        let table = VirtualFdTable::new();
+        let ready = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let vfd = table.insert(Pipe(ready.clone())).unwrap();
+        let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        let mut ev = epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: 1,
+        };
+        let ev_ptr = UserPtr::new(&mut ev);
+        assert_eq!(table.epoll_ctl(epfd, libc::EPOLL_CTL_ADD, vfd, ev_ptr), Some(0));
+
+        let mut buf = [epoll_event { events: 0, u64: 0 }; 2];
+        let events = EpollEvents::new(buf.as_mut_ptr(), 2);
+        let no_mask = std::ptr::null();
+        assert_eq!(
+            table.epoll_wait(epfd, events, Some(Duration::ZERO), no_mask, 0),
+            Some(0)
+        );
+
+        // 無期限に待っていても、notifyで起こされて仮想fdのイベントを返す
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(30));
+                ready.store(true, Ordering::SeqCst);
+                table.notify();
+            });
+            assert_eq!(table.epoll_wait(epfd, events, None, no_mask, 0), Some(1));
+        });
+        assert_eq!({ buf[0].u64 }, 1);
+
+        table.close(vfd).unwrap();
+        unsafe { libc::close(epfd) };
+    }
+
+    #[test]
+    fn test_global_fd_extends_interests() {
+        use crate::interests::{is_hook_interested, set_hook_interests};
+        use crate::{register_syscall_hooks, SyscallHooks};
+
+        struct PpidHooks;
+        impl SyscallHooks for PpidHooks {
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::new(&[Sysno::getppid])
+            }
+        }
+
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+        set_hook_interests(SysnoSet::new(&[Sysno::getppid]));
+
+        // ローカルな表は関心集合を変えない
+        let table = VirtualFdTable::new();
+        let local = table.insert(Pipe(Arc::default())).unwrap();
+        assert!(!is_hook_interested(Sysno::read.nr()));
+        table.close(local).unwrap();
+
+        let fd = virtual_fds().insert(Pipe(Arc::default())).unwrap();
+        assert!(is_hook_interested(Sysno::read.nr()));
+        assert!(is_hook_interested(Sysno::epoll_wait.nr()));
+
+        // 仮想fdがある間に登録したフックの関心集合にも含まれる
+        register_syscall_hooks(PpidHooks);
+        assert!(is_hook_interested(Sysno::getppid.nr()));
+        assert!(is_hook_interested(Sysno::write.nr()));
+        assert!(!is_hook_interested(Sysno::getpid.nr()));
+
+        virtual_fds().close(fd).unwrap();
+        set_hook_interests(SysnoSet::all());
+    }
+
+    /// 書き込まれると読めるようになるファイル
+    struct Channel(Arc<Mutex<Vec<u8>>>);
+
+    impl VirtualFile for Channel {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, c_int> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn poll(&mut self, events: c_short) -> c_short {
+            if self.0.lock().unwrap().is_empty() {
+                0
+            } else {
+                events & libc::POLLIN
+            }
+        }
+    }
+
+    /// 別スレッドで仮想fdを`poll(-1)`で待ち、さらに別のスレッドから書き込む
+    ///
+    /// 書き込むスレッドは`before_write`を先にフック経由で呼びます。
+    /// 戻り値は(書き込みの結果, pollの結果)です。
+    fn poll_while_writing(before_write: Option<Sysno>) -> (Option<i64>, Option<i64>) {
+        use crate::hook_entry;
+        use std::sync::mpsc;
+
+        let fd = virtual_fds().insert(Channel(Arc::default())).unwrap();
+        let (poll_tx, poll_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut pfd = pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let fds = &mut pfd as *mut pollfd as u64;
+            let mut regs = SyscallRegs::new(Sysno::poll.nr(), fds, 1, -1i64 as u64, 0, 0, 0);
+            poll_tx.send(hook_entry(&mut regs)).unwrap();
+        });
+        std::thread::sleep(Duration::from_millis(50));
+
+        let (write_tx, write_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            if let Some(sysno) = before_write {
+                hook_entry(&mut SyscallRegs::new(sysno.nr(), 0, 0, 0, 0, 0, 0));
+            }
+            let msg = b"x";
+            let nr = Sysno::write.nr();
+            let mut regs = SyscallRegs::new(nr, fd as u64, msg.as_ptr() as u64, 1, 0, 0, 0);
+            write_tx.send(hook_entry(&mut regs)).unwrap();
+        });
+
+        let timeout = Duration::from_secs(5);
+        let written = write_rx.recv_timeout(timeout).ok();
+        let polled = poll_rx.recv_timeout(timeout).ok();
+        virtual_fds().close(fd).unwrap();
+        (written, polled)
+    }
+
+    #[test]
+    fn test_fork_while_file_in_use() {
+        use crate::hook_entry;
+        use std::sync::atomic::AtomicBool;
+
+        static RELEASE: AtomicBool = AtomicBool::new(false);
+
+        /// 読み込みが`RELEASE`までブロックするファイル
+        struct Blocking(Arc<AtomicBool>);
+
+        impl VirtualFile for Blocking {
+            fn read(&mut self, _buf: &mut [u8]) -> Result<usize, c_int> {
+                self.0.store(true, Ordering::SeqCst);
+                while !RELEASE.load(Ordering::SeqCst) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Ok(0)
+            }
+        }
+
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+        let started = Arc::new(AtomicBool::new(false));
+        let fd = virtual_fds().insert(Blocking(started.clone())).unwrap();
+
+        // 別のスレッドがファイルのロックを保持したまま読み込みで止まる
+        let reader = std::thread::spawn(move || {
+            let file = virtual_fds().get(fd).unwrap();
+            file.with(|file| file.read(&mut [0u8; 1]))
+        });
+        while !started.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+
+        let mut regs = SyscallRegs::new(Sysno::fork.nr(), 0, 0, 0, 0, 0, 0);
+        let pid = hook_entry(&mut regs);
+        if pid == 0 {
+            // 子プロセス: 読み込み中のスレッドはいないので、同じファイルを操作できる
+            let file = virtual_fds().get(fd).unwrap();
+            let ok = file.with(|file| file.poll(libc::POLLIN)) == libc::POLLIN;
+            unsafe { raw_syscall_bypass(Sysno::exit_group.nr(), !ok as u64, 0, 0, 0, 0, 0) };
+        }
+        assert!(pid > 0);
+        assert_eq!(crate::tests::wait_child(pid as i32), Some(0));
+
+        RELEASE.store(true, Ordering::SeqCst);
+        assert_eq!(reader.join().unwrap(), Ok(0));
+        virtual_fds().close(fd).unwrap();
+    }
+
+    #[test]
+    fn test_poll_does_not_block_writers() {
+        use crate::interests::set_hook_interests;
+        use crate::poll::PollFds;
+        use crate::{register_syscall_hooks, SyscallHooks};
+
+        struct PpidHooks;
+        impl SyscallHooks for PpidHooks {
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::new(&[Sysno::getppid])
+            }
+        }
+
+        struct PollHooks;
+        impl SyscallHooks for PollHooks {
+            fn hook_poll(&mut self, fds: PollFds, timeout: c_int) -> c_int {
+                crate::syscall_hooks::default_poll(fds, timeout)
+            }
+        }
+
+        let _global = crate::tests::GLOBAL_HOOK_STATE.lock().unwrap();
+
+        // 関心のないpollはロックを取らずに待つため、ロックを取るフックも進む
+        register_syscall_hooks(PpidHooks);
+        assert_eq!(poll_while_writing(Some(Sysno::getppid)), (Some(1), Some(1)));
+
+        // pollを処理するフックはロックを保持して待つが、仮想fdへの書き込みは進む
+        register_syscall_hooks(PollHooks);
+        assert_eq!(poll_while_writing(None), (Some(1), Some(1)));
+
+        set_hook_interests(SysnoSet::all());
+    }
+}
@@ -0,0 +1,60 @@
+//! `#[hook_library]`（オプションなし）を使ったフックライブラリ
+
+use zpoline_hook_api::abi::{Capabilities, HookLibraryDescriptor, LoaderCallbacks, LoaderInfo};
+use zpoline_hook_api::syscall_hooks::default_write;
+use zpoline_hook_api::{hook_library, HookFn, Sysno, SysnoSet, SyscallHooks, SyscallRegs};
+
+#[derive(Default)]
+struct MyHooks;
+
+#[hook_library]
+impl SyscallHooks for MyHooks {
+    fn hook_write(&mut self, fd: i32, buf: *const std::ffi::c_void, count: usize) -> isize {
+        default_write(fd, buf, count)
+    }
+
+    fn hook_getpid(&mut self) -> libc::pid_t {
+        4242
+    }
+
+    fn on_fork_child(&mut self) {}
+}
+
+/// ローダーが`dlsym`で探す名前でリンクされる
+mod exported {
+    extern "C" {
+        pub fn zpoline_hook_init() -> *const ();
+    }
+}
+
+/// ライブラリがハンドシェイクで要求する機能
+fn required_capabilities() -> Capabilities {
+    let callbacks = unsafe { std::mem::zeroed::<LoaderCallbacks>() };
+    let loader = LoaderInfo::new(Capabilities::empty(), callbacks);
+    let mut out = std::mem::MaybeUninit::<HookLibraryDescriptor>::zeroed();
+    unsafe {
+        zpoline_hook_api::abi::zpoline_hook_handshake(&loader, out.as_mut_ptr());
+        out.assume_init().required_capabilities
+    }
+}
+
+#[test]
+fn test_hook_library() {
+    let hook = unsafe { exported::zpoline_hook_init() };
+    assert_eq!(hook, zpoline_hook_api::get_trait_dispatch_hook());
+
+    // 関心集合はオーバーライドしたフックのシステムコールだけ
+    // （fork系はランタイムが常に監視する）
+    assert_eq!(MyHooks.interests(), SysnoSet::new(&[Sysno::write, Sysno::getpid]));
+    let interests = zpoline_hook_api::hook_interests();
+    assert!(interests.contains(Sysno::write) && interests.contains(Sysno::getpid));
+    assert!(!interests.contains(Sysno::read));
+
+    // 返された関数が登録したフックにディスパッチする
+    let hook: HookFn = unsafe { std::mem::transmute(hook) };
+    let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+    assert_eq!(hook(&mut regs), 4242);
+
+    // requiresがなければ何も要求しない
+    assert_eq!(required_capabilities(), Capabilities::empty());
+}
@@ -0,0 +1,45 @@
+//! `#[hook_library]`の`new`と、自分で定義した`interests`
+
+use zpoline_hook_api::{hook_library, HookFn, Sysno, SysnoSet, SyscallHooks, SyscallRegs};
+
+struct LevelHooks {
+    level: i32,
+}
+
+impl LevelHooks {
+    fn new() -> Self {
+        Self { level: 7 }
+    }
+}
+
+#[hook_library(new = LevelHooks::new)]
+impl SyscallHooks for LevelHooks {
+    fn interests(&self) -> SysnoSet {
+        SysnoSet::new(&[Sysno::getpid, Sysno::getppid])
+    }
+
+    fn hook_getpid(&mut self) -> libc::pid_t {
+        self.level
+    }
+}
+
+/// ローダーが`dlsym`で探す名前でリンクされる
+mod exported {
+    extern "C" {
+        pub fn zpoline_hook_init() -> *const ();
+    }
+}
+
+#[test]
+fn test_hook_library_new() {
+    let hook = unsafe { exported::zpoline_hook_init() };
+    let hook: HookFn = unsafe { std::mem::transmute(hook) };
+    let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+    assert_eq!(hook(&mut regs), 7);
+
+    // interestsを定義していればそのまま使う
+    assert_eq!(
+        LevelHooks::new().interests(),
+        SysnoSet::new(&[Sysno::getpid, Sysno::getppid])
+    );
+}
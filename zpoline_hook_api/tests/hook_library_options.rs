@@ -0,0 +1,74 @@
+//! `#[hook_library]`の`from_args`と`requires`
+
+use zpoline_hook_api::abi::{Capabilities, HookLibraryDescriptor, LoaderCallbacks, LoaderInfo};
+use zpoline_hook_api::args::{HookArgs, RawHookArgs};
+use zpoline_hook_api::{hook_library, HookFn, Sysno, SysnoSet, SyscallHooks, SyscallRegs};
+
+struct LevelHooks {
+    level: i32,
+}
+
+impl LevelHooks {
+    fn from_args(args: &HookArgs) -> Self {
+        Self {
+            level: args.get_parsed("level").unwrap_or(0),
+        }
+    }
+}
+
+#[hook_library(from_args = LevelHooks::from_args, requires(FORK_NOTIFY, HOOK_ARGS))]
+impl SyscallHooks for LevelHooks {
+    fn hook_getpid(&mut self) -> libc::pid_t {
+        self.level
+    }
+}
+
+/// ローダーが`dlsym`で探す名前でリンクされる
+mod exported {
+    use super::RawHookArgs;
+
+    extern "C" {
+        pub fn zpoline_hook_init() -> *const ();
+        pub fn zpoline_hook_init_with_args(args: *const RawHookArgs) -> *const ();
+    }
+}
+
+/// ライブラリがハンドシェイクで要求する機能
+fn required_capabilities() -> Capabilities {
+    let callbacks = unsafe { std::mem::zeroed::<LoaderCallbacks>() };
+    let loader = LoaderInfo::new(Capabilities::empty(), callbacks);
+    let mut out = std::mem::MaybeUninit::<HookLibraryDescriptor>::zeroed();
+    unsafe {
+        zpoline_hook_api::abi::zpoline_hook_handshake(&loader, out.as_mut_ptr());
+        out.assume_init().required_capabilities
+    }
+}
+
+/// 登録されたフックで`getpid`を実行する
+fn dispatch_getpid(hook: *const ()) -> i64 {
+    let hook: HookFn = unsafe { std::mem::transmute(hook) };
+    let mut regs = SyscallRegs::new(Sysno::getpid.nr(), 0, 0, 0, 0, 0, 0);
+    hook(&mut regs)
+}
+
+#[test]
+fn test_hook_library_options() {
+    // requiresは.init_arrayでmainより前（ハンドシェイクより前）に宣言される
+    assert_eq!(
+        required_capabilities(),
+        Capabilities::FORK_NOTIFY.union(Capabilities::HOOK_ARGS)
+    );
+
+    // zpoline_hook_initは空の引数で`from_args`を呼ぶ
+    let hook = unsafe { exported::zpoline_hook_init() };
+    assert_eq!(dispatch_getpid(hook), 0);
+
+    // zpoline_hook_init_with_argsは`from_args`で生成する
+    let args = HookArgs::parse("level=3");
+    let raw = args.to_raw();
+    let raw = raw.as_raw();
+    let hook = unsafe { exported::zpoline_hook_init_with_args(&raw) };
+    assert_eq!(hook, zpoline_hook_api::get_trait_dispatch_hook());
+    assert_eq!(dispatch_getpid(hook), 3);
+    assert_eq!(LevelHooks::from_args(&args).interests(), SysnoSet::new(&[Sysno::getpid]));
+}
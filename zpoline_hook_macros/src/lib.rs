@@ -0,0 +1,290 @@
+//! フックライブラリを宣言する`#[hook_library]`属性
+//!
+//! `impl SyscallHooks for T`に付けると、ローダーが呼ぶ初期化関数
+//! （`zpoline_hook_init`など）とフックの登録、オーバーライドしたメソッドから
+//! 求めた関心集合を生成します。通常は`zpoline_hook_api::hook_library`として使います。
+//!
+//! ```ignore
+//! use zpoline_hook_api::{hook_library, syscall_hooks::*, SyscallHooks};
+//!
+//! #[derive(Default)]
+//! struct MyHooks;
+//!
+//! #[hook_library]
+//! impl SyscallHooks for MyHooks {
+//!     fn hook_write(&mut self, fd: i32, buf: *const std::ffi::c_void, count: usize) -> isize {
+//!         default_write(fd, buf, count)
+//!     }
+//! }
+//! ```
+//!
+//! オプション:
+//!
+//! - `new = path` - フックを生成する関数（`fn() -> T`）。省略時は`T::default()`
+//! - `from_args = path` - 設定引数からフックを生成する関数（`fn(&HookArgs) -> T`）。
+//!   `zpoline_hook_init_with_args`も生成する
+//! - `requires(FORK_NOTIFY, ...)` - ローダーに要求する機能（`abi::Capabilities`の定数名）
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_quote, Ident, ImplItem, ImplItemFn, ItemImpl, Path};
+
+/// 生成するコードが前提とする`zpoline_hook_api`のABIバージョン
+const ABI_VERSION: u32 = 3;
+
+/// `abi::Capabilities`の定数
+const CAPABILITIES: &[&str] = &[
+    "INTEREST_FILTER",
+    "SHARED_THREAD_STATE",
+    "FORK_NOTIFY",
+    "HOOK_ARGS",
+    "HOT_RELOAD",
+    "CALL_CONTEXT",
+];
+
+/// `impl SyscallHooks for T`からフックライブラリのエクスポートを生成する
+#[proc_macro_attribute]
+pub fn hook_library(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// 属性のオプション
+#[derive(Default)]
+struct Options {
+    new: Option<Path>,
+    from_args: Option<Path>,
+    requires: Vec<Ident>,
+}
+
+impl Options {
+    fn parse(attr: TokenStream2) -> syn::Result<Self> {
+        let mut options = Self::default();
+        let parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("new") {
+                options.new = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("from_args") {
+                options.from_args = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("requires") {
+                meta.parse_nested_meta(|cap| {
+                    let ident = cap
+                        .path
+                        .get_ident()
+                        .ok_or_else(|| cap.error("expected a capability name"))?;
+                    if !CAPABILITIES.iter().any(|c| ident == c) {
+                        return Err(cap.error(format!(
+                            "unknown capability `{}`, expected one of: {}",
+                            ident,
+                            CAPABILITIES.join(", ")
+                        )));
+                    }
+                    options.requires.push(ident.clone());
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error(
+                    "unsupported hook_library option, expected `new`, `from_args` or `requires`",
+                ));
+            }
+            Ok(())
+        });
+        syn::parse::Parser::parse2(parser, attr)?;
+
+        if let (Some(new), Some(_)) = (&options.new, &options.from_args) {
+            return Err(syn::Error::new_spanned(
+                new,
+                "`new` and `from_args` cannot be used together",
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// フックメソッドならシステムコール名（`hook_`を除いた名前）を返す
+///
+/// メソッド名・引数・レシーバーが`SyscallHooks`と合っているかは、そのまま出力する
+/// `impl`をコンパイラが確認します。システムコール名は`interests`の生成で
+/// `Sysno`のバリアントとして参照されます。
+fn hook_sysno(method: &ImplItemFn) -> Option<Ident> {
+    let name = method.sig.ident.to_string();
+    name.strip_prefix("hook_")
+        .map(|sysno| Ident::new(sysno, method.sig.ident.span()))
+}
+
+fn expand(attr: TokenStream2, item: TokenStream2) -> syn::Result<TokenStream2> {
+    let options = Options::parse(attr)?;
+    let mut item: ItemImpl = syn::parse2(item)?;
+
+    match &item.trait_ {
+        Some((None, path, _))
+            if path
+                .segments
+                .last()
+                .is_some_and(|s| s.ident == "SyscallHooks") => {}
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "#[hook_library] must be placed on `impl SyscallHooks for T`",
+            ))
+        }
+    }
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "#[hook_library] does not support generic implementations",
+        ));
+    }
+
+    let mut sysnos = Vec::new();
+    let mut has_interests = false;
+    for impl_item in &item.items {
+        let ImplItem::Fn(method) = impl_item else {
+            return Err(syn::Error::new_spanned(
+                impl_item,
+                "only methods of SyscallHooks are allowed in #[hook_library]",
+            ));
+        };
+        match hook_sysno(method) {
+            Some(sysno) => sysnos.push(sysno),
+            None => has_interests |= method.sig.ident == "interests",
+        }
+    }
+
+    // interestsがなければオーバーライドしたフックのシステムコールだけを対象にする
+    if !has_interests {
+        item.items.push(parse_quote! {
+            fn interests(&self) -> ::zpoline_hook_api::SysnoSet {
+                ::zpoline_hook_api::SysnoSet::new(&[#(::zpoline_hook_api::Sysno::#sysnos),*])
+            }
+        });
+    }
+
+    let self_ty = &item.self_ty;
+    let construct = match (&options.new, &options.from_args) {
+        (Some(new), _) => quote! { #new() },
+        (None, Some(from_args)) => {
+            quote! { #from_args(&::zpoline_hook_api::args::HookArgs::default()) }
+        }
+        (None, None) => quote! { <#self_ty as ::core::default::Default>::default() },
+    };
+
+    let init_with_args = options.from_args.as_ref().map(|from_args| {
+        quote! {
+            /// 設定引数付きの初期化関数（`#[hook_library]`が生成）
+            ///
+            /// # Safety
+            ///
+            /// `args`はローダーから渡された`RawHookArgs`である必要がある
+            #[no_mangle]
+            pub unsafe extern "C" fn zpoline_hook_init_with_args(
+                args: *const ::zpoline_hook_api::args::RawHookArgs,
+            ) -> *const () {
+                let args = ::zpoline_hook_api::args::HookArgs::from_raw(args);
+                ::zpoline_hook_api::register_syscall_hooks(#from_args(&args));
+                ::zpoline_hook_api::get_trait_dispatch_hook()
+            }
+        }
+    });
+
+    // ハンドシェイクより前に宣言する必要があるため、ロード時に実行する
+    let requires = (!options.requires.is_empty()).then(|| {
+        let caps = &options.requires;
+        quote! {
+            const _: () = {
+                extern "C" fn require_capabilities() {
+                    ::zpoline_hook_api::abi::require_capabilities(
+                        ::zpoline_hook_api::abi::Capabilities::empty()
+                            #(.union(::zpoline_hook_api::abi::Capabilities::#caps))*
+                    );
+                }
+
+                #[used]
+                #[link_section = ".init_array"]
+                static REQUIRE_CAPABILITIES: extern "C" fn() = require_capabilities;
+            };
+        }
+    });
+
+    Ok(quote! {
+        #item
+
+        const _: () = ::core::assert!(
+            ::zpoline_hook_api::abi::ZPOLINE_ABI_VERSION == #ABI_VERSION,
+            "zpoline_hook_macros does not match the ABI version of zpoline_hook_api"
+        );
+
+        /// ローダーから呼ばれる初期化関数（`#[hook_library]`が生成）
+        #[no_mangle]
+        pub extern "C" fn zpoline_hook_init() -> *const () {
+            ::zpoline_hook_api::register_syscall_hooks(#construct);
+            ::zpoline_hook_api::get_trait_dispatch_hook()
+        }
+
+        #init_with_args
+
+        #requires
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_err(attr: TokenStream2, item: TokenStream2) -> String {
+        expand(attr, item).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_expand() {
+        let out = expand(
+            quote!(from_args = MyHooks::from_args, requires(FORK_NOTIFY, HOOK_ARGS)),
+            quote! {
+                impl SyscallHooks for MyHooks {
+                    fn hook_write(&mut self, fd: i32, buf: *const c_void, count: usize) -> isize { 0 }
+                    fn hook_openat(&mut self, d: i32, p: *const i8, f: i32, m: u32) -> i32 { 0 }
+                    fn on_fork_child(&mut self) {}
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+
+        assert!(out.contains("fn interests"));
+        assert!(out.contains(
+            "SysnoSet :: new (& [:: zpoline_hook_api :: Sysno :: write , :: zpoline_hook_api :: Sysno :: openat])"
+        ));
+        assert!(out.contains("fn zpoline_hook_init ()"));
+        assert!(out.contains("fn zpoline_hook_init_with_args"));
+        assert!(out.contains("Capabilities :: FORK_NOTIFY"));
+
+        // interestsを定義していればそのまま使う
+        let out = expand(
+            quote!(),
+            quote! {
+                impl zpoline_hook_api::SyscallHooks for MyHooks {
+                    fn interests(&self) -> SysnoSet { SysnoSet::all() }
+                    fn hook_getpid(&mut self) -> i32 { 0 }
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+        assert_eq!(out.matches("fn interests").count(), 1);
+        assert!(out.contains("Default"));
+        assert!(!out.contains("zpoline_hook_init_with_args"));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let item = quote! { impl Drop for MyHooks { fn drop(&mut self) {} } };
+        assert!(expand_err(quote!(), item).contains("impl SyscallHooks for T"));
+
+        let item = quote! { impl SyscallHooks for MyHooks {} };
+        assert!(expand_err(quote!(requires(TELEPORT)), item.clone()).contains("unknown capability"));
+        assert!(expand_err(quote!(new = a, from_args = b), item.clone())
+            .contains("cannot be used together"));
+        assert!(expand_err(quote!(verbose), item).contains("unsupported hook_library option"));
+    }
+}
@@ -1,5 +1,5 @@
-use zpoline_hook_api::{register_syscall_hooks, syscall_hooks::*, SyscallHooks, get_trait_dispatch_hook};
-use ctor::ctor;
+use zpoline_hook_api::args::HookArgs;
+use zpoline_hook_api::{context, hook_library, hook_log, syscall_hooks::*, SyscallHooks};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// システムコール統計を収集するフック
@@ -11,13 +11,34 @@ static READ_COUNT: AtomicUsize = AtomicUsize::new(0);
 static OPEN_COUNT: AtomicUsize = AtomicUsize::new(0);
 static GETPID_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+/// ログ出力する回数（`log_limit`引数で変更可能）
+static LOG_LIMIT: AtomicUsize = AtomicUsize::new(3);
+
+impl StatsHook {
+    /// 設定引数からフックを作る
+    ///
+    /// `ZPOLINE_HOOK=libzpoline_hook_trait_example.so?log_limit=10`のように指定する。
+    fn from_args(args: &HookArgs) -> Self {
+        if let Some(limit) = args.get_parsed::<usize>("log_limit") {
+            LOG_LIMIT.store(limit, Ordering::Relaxed);
+        }
+        eprintln!("[zpoline_hook_trait_example] Hooks registered successfully");
+        StatsHook
+    }
+}
+
+/// 初期化関数の生成と登録は`#[hook_library]`が行う。
+/// 関心集合はオーバーライドしたメソッド（write, read, open, getpid）から決まる
+#[hook_library(from_args = StatsHook::from_args)]
 impl SyscallHooks for StatsHook {
     /// write システムコールをフック
     fn hook_write(&mut self, fd: i32, buf: *const std::ffi::c_void, count: usize) -> isize {
         let count_val = WRITE_COUNT.fetch_add(1, Ordering::Relaxed);
-        // 最初の3回だけログ出力
-        if count_val < 3 {
-            eprintln!("[TRAIT HOOK] write(fd={}, count={}) - call #{}", fd, count, count_val + 1);
+        // 最初のLOG_LIMIT回だけログ出力
+        if count_val < LOG_LIMIT.load(Ordering::Relaxed) {
+            // 呼び出し元のアドレスもログに含める（フック内ではヒープ確保をしない）
+            let rip = context::current().map_or(0, |ctx| ctx.rip);
+            hook_log!("[TRAIT HOOK] write(fd={}, count={}) from {:#x} - call #{}", fd, count, rip, count_val + 1);
         }
         default_write(fd, buf, count)
     }
@@ -25,9 +46,9 @@ impl SyscallHooks for StatsHook {
     /// read システムコールをフック
     fn hook_read(&mut self, fd: i32, buf: *mut std::ffi::c_void, count: usize) -> isize {
         let count_val = READ_COUNT.fetch_add(1, Ordering::Relaxed);
-        // 最初の3回だけログ出力
-        if count_val < 3 {
-            eprintln!("[TRAIT HOOK] read(fd={}, count={}) - call #{}", fd, count, count_val + 1);
+        // 最初のLOG_LIMIT回だけログ出力
+        if count_val < LOG_LIMIT.load(Ordering::Relaxed) {
+            hook_log!("[TRAIT HOOK] read(fd={}, count={}) - call #{}", fd, count, count_val + 1);
         }
         default_read(fd, buf, count)
     }
@@ -45,22 +66,6 @@ impl SyscallHooks for StatsHook {
     }
 }
 
-/// ライブラリがロードされたときにフックを登録
-#[ctor]
-fn init_hooks() {
-    eprintln!("[zpoline_hook_trait_example] Initializing trait-based hooks...");
-    register_syscall_hooks(StatsHook);
-    eprintln!("[zpoline_hook_trait_example] Hooks registered successfully");
-}
-
-/// zpoline_loaderから呼ばれる初期化関数
-/// trait-based dispatcherへのポインタを返す
-#[no_mangle]
-pub extern "C" fn zpoline_hook_init() -> *const () {
-    eprintln!("[zpoline_hook_trait_example] zpoline_hook_init called");
-    get_trait_dispatch_hook()
-}
-
 /// 統計情報を取得するためのエクスポート関数（オプション）
 #[no_mangle]
 pub extern "C" fn get_stats() {
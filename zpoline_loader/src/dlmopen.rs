@@ -1,5 +1,7 @@
 use std::ffi::CString;
-use zpoline_hook_api::HookFn;
+use zpoline_hook_api::abi::{Capabilities, HookLibraryDescriptor, LoaderCallbacks, LoaderInfo};
+use zpoline_hook_api::args::{split_hook_spec, HookArgs, RawHookArgs};
+use zpoline_hook_api::{HookFn, SysnoSet};
 
 /// dlmopenのエラー
 #[derive(Debug)]
@@ -7,6 +9,8 @@ pub enum DlmopenError {
     LibraryNotSpecified,
     DlmopenFailed(String),
     SymbolNotFound(String),
+    IncompatibleAbi(String),
+    MissingHandshake,
 }
 
 impl std::fmt::Display for DlmopenError {
@@ -17,6 +21,13 @@ impl std::fmt::Display for DlmopenError {
             }
             DlmopenError::DlmopenFailed(e) => write!(f, "dlmopen failed: {}", e),
             DlmopenError::SymbolNotFound(s) => write!(f, "Symbol not found: {}", s),
+            DlmopenError::IncompatibleAbi(e) => {
+                write!(f, "Incompatible hook library ABI: {}", e)
+            }
+            DlmopenError::MissingHandshake => write!(
+                f,
+                "Hook library has no ABI handshake (set ZPOLINE_ALLOW_LEGACY_HOOKS=1 to load it anyway)"
+            ),
         }
     }
 }
@@ -37,16 +48,69 @@ extern "C" {
 // 新しいネームスペースIDを指定する定数
 const LM_ID_NEWLM: libc::c_long = -1;
 
+/// ロードされたフックライブラリ
+pub struct HookLibrary {
+    handle: *mut libc::c_void,
+    spec: String,
+    hook_fn: HookFn,
+    interests: SysnoSet,
+    fork_child: Option<extern "C" fn()>,
+}
+
+// ライブラリはdlcloseしないので、ハンドルはどのスレッドからでも使える
+unsafe impl Send for HookLibrary {}
+
+impl HookLibrary {
+    /// ロード時の指定（`ZPOLINE_HOOK`と同じ形式）
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn hook_fn(&self) -> HookFn {
+        self.hook_fn
+    }
+
+    /// ライブラリが宣言した関心集合（宣言がなければすべて）
+    pub fn interests(&self) -> SysnoSet {
+        self.interests
+    }
+
+    /// fork後の子プロセスでライブラリ側のランタイムも再初期化する
+    pub fn register_fork_child(&self) {
+        zpoline_hook_api::fork::__set_fork_child_callback(self.fork_child);
+    }
+
+    /// 入れ替えたライブラリを退役させる（`zpoline_hook_fini`があれば呼ぶ）
+    ///
+    /// ライブラリのコードは仮想化したシグナルのハンドラ、cloneの子の開始関数、
+    /// io_uringのマッピングなどから参照され続けることがあるため、dlcloseせずに
+    /// 常駐させます。フック関数が実行中でないことを確認してから呼ぶこと。
+    pub fn retire(self) {
+        let fini_symbol = CString::new("zpoline_hook_fini").unwrap();
+        let fini_fn_ptr = unsafe { dlsym(self.handle, fini_symbol.as_ptr()) };
+
+        if !fini_fn_ptr.is_null() {
+            let fini_fn: extern "C" fn() = unsafe { std::mem::transmute(fini_fn_ptr) };
+            fini_fn();
+        }
+        eprintln!("[zpoline] Hook library retired (kept resident): {}", self.spec);
+    }
+}
+
 /// フックライブラリを別ネームスペースにロードする
 ///
+/// フック関数と関心集合はまだ反映しない。
+///
 /// # 引数
-/// * `lib_path` - ロードするライブラリのパス
+/// * `lib_path` - ロードするライブラリのパス（`lib.so?key=value`で引数を付けられる）
 ///
 /// # 戻り値
-/// * `Ok(HookFn)` - ロードされたフック関数
+/// * `Ok(HookLibrary)` - ロードされたライブラリ
 /// * `Err(DlmopenError)` - エラー
-pub fn load_hook_library(lib_path: Option<&str>) -> Result<HookFn, DlmopenError> {
-    let lib_path = lib_path.ok_or(DlmopenError::LibraryNotSpecified)?;
+pub fn load_hook_library(lib_path: Option<&str>) -> Result<HookLibrary, DlmopenError> {
+    let lib_spec = lib_path.ok_or(DlmopenError::LibraryNotSpecified)?;
+    let (lib_path, spec_args) = split_hook_spec(lib_spec);
+    let args = hook_args(spec_args, std::env::var("ZPOLINE_HOOK_ARGS").ok().as_deref());
 
     eprintln!("[zpoline] Loading hook library: {}", lib_path);
 
@@ -82,20 +146,47 @@ pub fn load_hook_library(lib_path: Option<&str>) -> Result<HookFn, DlmopenError>
 
     eprintln!("[zpoline] Hook library loaded at handle: {:p}", handle);
 
+    // ABIハンドシェイク（古いライブラリにはないので任意）
+    handshake(handle)?;
+
+    // fork後の子プロセスでの再初期化関数（任意）
+    let fork_symbol = CString::new("zpoline_fork_child").unwrap();
+    let fork_fn_ptr = unsafe { dlsym(handle, fork_symbol.as_ptr()) };
+    let fork_child = (!fork_fn_ptr.is_null())
+        .then(|| unsafe { std::mem::transmute::<*mut libc::c_void, extern "C" fn()>(fork_fn_ptr) });
+
     // 初期化関数を呼び出してフック関数ポインタを取得
-    let init_symbol = CString::new("zpoline_hook_init").unwrap();
-    let init_fn_ptr = unsafe { dlsym(handle, init_symbol.as_ptr()) };
+    // 引数付きの初期化関数があればそちらを優先する
+    let init_args_symbol = CString::new("zpoline_hook_init_with_args").unwrap();
+    let init_args_fn_ptr = unsafe { dlsym(handle, init_args_symbol.as_ptr()) };
 
-    if init_fn_ptr.is_null() {
-        return Err(DlmopenError::SymbolNotFound(
-            "zpoline_hook_init".to_string(),
-        ));
-    }
+    let hook_fn_ptr = if !init_args_fn_ptr.is_null() {
+        let init_fn: unsafe extern "C" fn(*const RawHookArgs) -> *const () =
+            unsafe { std::mem::transmute(init_args_fn_ptr) };
+        eprintln!("[zpoline] Passing {} hook argument(s)", args.len());
+        let raw_args = args.to_raw();
+        unsafe { init_fn(&raw_args.as_raw()) }
+    } else {
+        let init_symbol = CString::new("zpoline_hook_init").unwrap();
+        let init_fn_ptr = unsafe { dlsym(handle, init_symbol.as_ptr()) };
+
+        if init_fn_ptr.is_null() {
+            return Err(DlmopenError::SymbolNotFound(
+                "zpoline_hook_init".to_string(),
+            ));
+        }
+        if !args.is_empty() {
+            eprintln!(
+                "[zpoline] Warning: hook library does not accept arguments, ignoring {} argument(s)",
+                args.len()
+            );
+        }
 
-    // 初期化関数を呼び出し
-    let init_fn: extern "C" fn() -> *const () =
-        unsafe { std::mem::transmute(init_fn_ptr) };
-    let hook_fn_ptr = init_fn();
+        // 初期化関数を呼び出し
+        let init_fn: extern "C" fn() -> *const () =
+            unsafe { std::mem::transmute(init_fn_ptr) };
+        init_fn()
+    };
 
     if hook_fn_ptr.is_null() {
         return Err(DlmopenError::SymbolNotFound(
@@ -108,7 +199,111 @@ pub fn load_hook_library(lib_path: Option<&str>) -> Result<HookFn, DlmopenError>
 
     eprintln!("[zpoline] Hook function initialized: {:p}", hook_fn_ptr);
 
-    Ok(hook_fn)
+    // ライブラリが宣言した関心集合を取得（任意）
+    let interests_symbol = CString::new("zpoline_get_hook_interests").unwrap();
+    let interests_fn_ptr = unsafe { dlsym(handle, interests_symbol.as_ptr()) };
+
+    let mut interests = SysnoSet::all();
+    if !interests_fn_ptr.is_null() {
+        let interests_fn: unsafe extern "C" fn(*mut SysnoSet) =
+            unsafe { std::mem::transmute(interests_fn_ptr) };
+        unsafe { interests_fn(&mut interests) };
+
+        eprintln!(
+            "[zpoline] Hook library handles {} syscalls",
+            interests.len()
+        );
+    }
+
+    Ok(HookLibrary {
+        handle,
+        spec: lib_spec.to_string(),
+        hook_fn,
+        interests,
+        fork_child,
+    })
+}
+
+/// ローダーが提供する機能
+const LOADER_CAPABILITIES: Capabilities = Capabilities::INTEREST_FILTER
+    .union(Capabilities::SHARED_THREAD_STATE)
+    .union(Capabilities::FORK_NOTIFY)
+    .union(Capabilities::HOOK_ARGS)
+    .union(Capabilities::HOT_RELOAD)
+    .union(Capabilities::CALL_CONTEXT);
+
+/// `lib.so?...`の引数と`ZPOLINE_HOOK_ARGS`をまとめる
+///
+/// 同じキーは`ZPOLINE_HOOK_ARGS`の指定が優先されます。
+fn hook_args(spec_args: Option<&str>, env_args: Option<&str>) -> HookArgs {
+    let mut args = HookArgs::default();
+    for s in [spec_args, env_args].into_iter().flatten() {
+        args.extend_from_str(s);
+    }
+    args
+}
+
+/// ライブラリが初期化の後に広げた関心集合を反映する（`LoaderCallbacks::extend_interests`）
+extern "C" fn extend_interests(set: *const SysnoSet) {
+    let Some(set) = (unsafe { set.as_ref() }) else {
+        return;
+    };
+    // フィルタを広げる前にチェーン先へ届くようにする
+    crate::exec::extend_next(set);
+    zpoline_hook_api::extend_hook_interests(set);
+}
+
+/// `zpoline_hook_handshake`でライブラリとABIの互換性を確認する
+///
+/// 互換性があればライブラリはローダーのスレッド状態とネストポリシーを共有します。
+/// ハンドシェイクを持たないライブラリは`ZPOLINE_ALLOW_LEGACY_HOOKS=1`の場合のみロードします。
+fn handshake(handle: *mut libc::c_void) -> Result<(), DlmopenError> {
+    let symbol = CString::new("zpoline_hook_handshake").unwrap();
+    let fn_ptr = unsafe { dlsym(handle, symbol.as_ptr()) };
+
+    if fn_ptr.is_null() {
+        if !allow_legacy_hooks(std::env::var("ZPOLINE_ALLOW_LEGACY_HOOKS").ok().as_deref()) {
+            return Err(DlmopenError::MissingHandshake);
+        }
+        eprintln!("[zpoline] Warning: loading hook library without ABI handshake (ZPOLINE_ALLOW_LEGACY_HOOKS)");
+        return Ok(());
+    }
+
+    let handshake_fn: unsafe extern "C" fn(*const LoaderInfo, *mut HookLibraryDescriptor) =
+        unsafe { std::mem::transmute(fn_ptr) };
+    let loader = LoaderInfo::new(
+        LOADER_CAPABILITIES,
+        LoaderCallbacks {
+            thread_hook_state: Some(zpoline_hook_api::nesting::__thread_hook_state),
+            max_depth: zpoline_hook_api::nesting::__max_depth(),
+            request_reload: Some(zpoline_hook_api::reload::request_reload),
+            current_frame: Some(zpoline_hook_api::context::__current_frame),
+            extend_interests: Some(extend_interests),
+        },
+    );
+    // ライブラリが書き込まなかった場合はサイズ0として拒否される
+    let mut descriptor: HookLibraryDescriptor = unsafe { std::mem::zeroed() };
+    unsafe { handshake_fn(&loader, &mut descriptor) };
+
+    descriptor
+        .check_compatible(&loader)
+        .map_err(|e| DlmopenError::IncompatibleAbi(e.to_string()))?;
+
+    let api_version = if descriptor.api_version.is_null() {
+        "unknown".into()
+    } else {
+        unsafe { std::ffi::CStr::from_ptr(descriptor.api_version) }.to_string_lossy()
+    };
+    eprintln!(
+        "[zpoline] Hook library ABI v{} (zpoline_hook_api {})",
+        descriptor.abi_version, api_version
+    );
+    Ok(())
+}
+
+/// `ZPOLINE_ALLOW_LEGACY_HOOKS`でハンドシェイクのないライブラリが許可されたか
+fn allow_legacy_hooks(value: Option<&str>) -> bool {
+    value == Some("1")
 }
 
 /// 環境変数からフックライブラリのパスを取得
@@ -146,6 +341,26 @@ pub fn get_hook_library_path() -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_hook_args() {
+        let args = hook_args(Some("level=1&verbose"), Some("level=2"));
+        assert_eq!(args.get("level"), Some("2"));
+        assert!(args.flag("verbose"));
+        assert!(hook_args(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_handshake_required() {
+        // ハンドシェイクのないライブラリは明示的に許可しない限り拒否する
+        let handle = unsafe { dlmopen(LM_ID_NEWLM, c"libm.so.6".as_ptr(), libc::RTLD_NOW) };
+        assert!(!handle.is_null());
+        assert!(matches!(handshake(handle), Err(DlmopenError::MissingHandshake)));
+
+        assert!(allow_legacy_hooks(Some("1")));
+        assert!(!allow_legacy_hooks(Some("0")));
+        assert!(!allow_legacy_hooks(None));
+    }
+
     #[test]
     fn test_get_hook_library_path() {
         // 環境変数がない場合はNoneまたはデフォルトパス
@@ -153,4 +368,29 @@ mod tests {
         // テスト環境では None の可能性が高い
         assert!(path.is_none() || path.is_some());
     }
+
+    #[test]
+    fn test_retire_keeps_library_resident() {
+        extern "C" fn hook(_regs: &mut zpoline_hook_api::SyscallRegs) -> i64 {
+            0
+        }
+
+        let handle = unsafe { dlmopen(LM_ID_NEWLM, c"libm.so.6".as_ptr(), libc::RTLD_NOW) };
+        assert!(!handle.is_null());
+        let cos = unsafe { dlsym(handle, c"cos".as_ptr()) };
+        assert!(!cos.is_null());
+
+        let library = HookLibrary {
+            handle,
+            spec: "libm.so.6".to_string(),
+            hook_fn: hook,
+            interests: SysnoSet::all(),
+            fork_child: None,
+        };
+        library.retire();
+
+        // 退役後もコードは引き続きマップされている
+        let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+        assert_ne!(unsafe { libc::dladdr(cos, &mut info) }, 0);
+    }
 }
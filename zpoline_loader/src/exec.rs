@@ -0,0 +1,842 @@
+//! execve/execveatをまたいでzpolineを有効に保つ
+//!
+//! `env -i`やsudo系のラッパーのように環境変数を消してexecveすると、
+//! `LD_PRELOAD`と`ZPOLINE_HOOK`が失われて子プログラムはフックされません。
+//! `ZPOLINE_EXEC`を設定すると、ローダーはexecve/execveatを横取りして`envp`を
+//! 書き換えてからフックライブラリの`hook_execve`に渡します。
+//!
+//! - `ZPOLINE_EXEC=reinject` - zpolineの環境変数を`envp`に再注入する
+//! - `ZPOLINE_EXEC=strip` - zpolineの環境変数を`envp`から取り除く
+//! - `ZPOLINE_EXEC_INCLUDE` / `ZPOLINE_EXEC_EXCLUDE` - 対象にする/しない実行ファイルの
+//!   パターン（`:`区切り）。`/`を含まないパターンはファイル名と比較し、末尾の`*`は
+//!   前方一致になります。
+//!
+//! execveはvforkやposix_spawnの子からも呼ばれるため、横取りした後の処理では
+//! mallocを使いません。`envp`はスレッドごとの領域（`raw_syscall_bypass`でmmap）に
+//! 組み立て、相対パスはスタック上のバッファで解決します。
+
+use std::cell::Cell;
+use std::ffi::{CStr, OsStr};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::OnceLock;
+use zpoline_hook_api::{raw_syscall_bypass, user_mem, HookFn, Sysno, SysnoSet, SyscallRegs};
+
+/// execveに渡す環境変数の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecMode {
+    /// zpolineの環境変数を再注入する
+    Reinject,
+    /// zpolineの環境変数を取り除く
+    Strip,
+}
+
+/// execve時の環境変数の書き換え方針
+#[derive(Debug, Clone)]
+pub struct ExecPolicy {
+    mode: ExecMode,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// ローダー自身のパス（LD_PRELOADに入れる値）
+    loader_path: String,
+    /// 再注入する`ZPOLINE_*`変数（`NAME=value`）
+    vars: Vec<Vec<u8>>,
+}
+
+/// 1つの環境変数の最大長（カーネルのMAX_ARG_STRLEN）
+const MAX_ARG_STRLEN: usize = 32 * 4096;
+
+impl ExecPolicy {
+    /// 環境変数から方針を読み取る（`ZPOLINE_EXEC`が未設定ならNone）
+    pub fn from_env(hook_path: Option<&str>) -> Option<Self> {
+        let mode = match std::env::var("ZPOLINE_EXEC").ok()?.as_str() {
+            "reinject" => ExecMode::Reinject,
+            "strip" => ExecMode::Strip,
+            "" | "inherit" => return None,
+            other => {
+                eprintln!("[zpoline] Warning: Unknown ZPOLINE_EXEC mode: {}", other);
+                return None;
+            }
+        };
+
+        let loader_path = loader_path()?;
+
+        // 起動時の`ZPOLINE_*`変数を保存しておく（アプリが書き換えても影響しない）
+        let mut vars: Vec<Vec<u8>> = std::env::vars_os()
+            .filter(|(name, _)| name.as_bytes().starts_with(b"ZPOLINE_"))
+            .filter(|(name, _)| name != "ZPOLINE_HOOK" || hook_path.is_none())
+            .map(|(name, value)| env_entry(name.as_bytes(), value.as_bytes()))
+            .collect();
+        if let Some(hook_path) = hook_path {
+            vars.push(env_entry(b"ZPOLINE_HOOK", hook_path.as_bytes()));
+        }
+
+        Some(Self::new(
+            mode,
+            split_patterns("ZPOLINE_EXEC_INCLUDE"),
+            split_patterns("ZPOLINE_EXEC_EXCLUDE"),
+            loader_path,
+            vars,
+        ))
+    }
+
+    pub fn new(
+        mode: ExecMode,
+        include: Vec<String>,
+        exclude: Vec<String>,
+        loader_path: String,
+        vars: Vec<Vec<u8>>,
+    ) -> Self {
+        Self {
+            mode,
+            include,
+            exclude,
+            loader_path,
+            vars,
+        }
+    }
+
+    pub fn mode(&self) -> ExecMode {
+        self.mode
+    }
+
+    /// 実行ファイルのパスが書き換えの対象か
+    pub fn applies_to(&self, path: &Path) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| path_matches(p, path));
+        included && !self.exclude.iter().any(|p| path_matches(p, path))
+    }
+
+    /// 書き換えた環境変数を`sink`に出力する（mallocを使わない）
+    fn write_env<'a, I>(&self, env: I, sink: &mut impl EnvSink)
+    where
+        I: Iterator<Item = &'a [u8]> + Clone,
+    {
+        match self.mode {
+            ExecMode::Reinject => self.reinject(env, sink),
+            ExecMode::Strip => self.strip(env, sink),
+        }
+    }
+
+    fn reinject<'a, I>(&self, env: I, sink: &mut impl EnvSink)
+    where
+        I: Iterator<Item = &'a [u8]> + Clone,
+    {
+        let loader = self.loader_path.as_bytes();
+        let mut has_preload = false;
+
+        for entry in env.clone() {
+            let (name, value) = split_entry(entry);
+            if name == b"LD_PRELOAD" {
+                has_preload = true;
+                if preload_entries(value).any(|lib| lib == loader) {
+                    sink.push(entry);
+                } else {
+                    sink.push(b"LD_PRELOAD=");
+                    sink.push(loader);
+                    if !value.is_empty() {
+                        sink.push(b":");
+                        sink.push(value);
+                    }
+                }
+            } else {
+                sink.push(entry);
+            }
+            sink.end();
+        }
+
+        if !has_preload {
+            sink.push(b"LD_PRELOAD=");
+            sink.push(loader);
+            sink.end();
+        }
+
+        // 子プロセス側で明示的に設定された値を優先する
+        for var in &self.vars {
+            let (name, _) = split_entry(var);
+            if !env.clone().any(|entry| split_entry(entry).0 == name) {
+                sink.push(var);
+                sink.end();
+            }
+        }
+    }
+
+    fn strip<'a, I>(&self, env: I, sink: &mut impl EnvSink)
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        let loader_name = Path::new(&self.loader_path).file_name();
+        let is_loader = |lib: &[u8]| {
+            lib == self.loader_path.as_bytes()
+                || Path::new(OsStr::from_bytes(lib)).file_name() == loader_name
+        };
+
+        for entry in env {
+            let (name, value) = split_entry(entry);
+            if name.starts_with(b"ZPOLINE_") {
+                continue;
+            }
+            if name == b"LD_PRELOAD" {
+                let mut rest = preload_entries(value).filter(|lib| !is_loader(lib));
+                if let Some(first) = rest.next() {
+                    sink.push(b"LD_PRELOAD=");
+                    sink.push(first);
+                    for lib in rest {
+                        sink.push(b":");
+                        sink.push(lib);
+                    }
+                    sink.end();
+                }
+                continue;
+            }
+            sink.push(entry);
+            sink.end();
+        }
+    }
+}
+
+/// 書き換えた環境変数の出力先
+///
+/// 1つのエントリは`push`した断片を連結したもので、`end`で区切る。
+trait EnvSink {
+    fn push(&mut self, bytes: &[u8]);
+    fn end(&mut self);
+}
+
+/// `envp`の組み立てに必要な大きさを数える
+#[derive(Default)]
+struct EnvSize {
+    entries: usize,
+    bytes: usize,
+}
+
+impl EnvSink for EnvSize {
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes += bytes.len();
+    }
+
+    fn end(&mut self) {
+        self.entries += 1;
+        self.bytes += 1;
+    }
+}
+
+impl EnvSize {
+    /// ポインタ配列（NULL終端）と文字列を合わせた大きさ
+    fn area_len(&self) -> usize {
+        (self.entries + 1) * std::mem::size_of::<usize>() + self.bytes
+    }
+}
+
+/// `EnvSize`で数えた領域に`envp`を組み立てる
+///
+/// 数えた後で環境変数が変わって収まらなくなった場合は`finish`がNoneを返す。
+struct EnvWriter {
+    ptrs: *mut *const libc::c_char,
+    strings: *mut u8,
+    size: EnvSize,
+    entries: usize,
+    start: usize,
+    offset: usize,
+    overflow: bool,
+}
+
+impl EnvWriter {
+    /// `area`は`size.area_len()`バイト書き込み可能である必要がある
+    fn new(area: *mut u8, size: EnvSize) -> Self {
+        let strings = area.wrapping_add((size.entries + 1) * std::mem::size_of::<usize>());
+        Self {
+            ptrs: area as *mut *const libc::c_char,
+            strings,
+            size,
+            entries: 0,
+            start: 0,
+            offset: 0,
+            overflow: false,
+        }
+    }
+
+    /// NULL終端した`envp`を返す
+    fn finish(self) -> Option<*const *const libc::c_char> {
+        if self.overflow {
+            return None;
+        }
+        unsafe { self.ptrs.add(self.entries).write(std::ptr::null()) };
+        Some(self.ptrs)
+    }
+}
+
+impl EnvSink for EnvWriter {
+    fn push(&mut self, bytes: &[u8]) {
+        if self.overflow || self.offset + bytes.len() > self.size.bytes {
+            self.overflow = true;
+            return;
+        }
+        let dst = self.strings.wrapping_add(self.offset);
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len()) };
+        self.offset += bytes.len();
+    }
+
+    fn end(&mut self) {
+        if self.overflow || self.offset >= self.size.bytes || self.entries >= self.size.entries {
+            self.overflow = true;
+            return;
+        }
+        unsafe {
+            self.strings.add(self.offset).write(0);
+            self.ptrs
+                .add(self.entries)
+                .write(self.strings.add(self.start) as *const libc::c_char);
+        }
+        self.entries += 1;
+        self.offset += 1;
+        self.start = self.offset;
+    }
+}
+
+thread_local! {
+    /// `envp`を組み立てる領域（アドレス, 大きさ）
+    ///
+    /// vforkの子は親のスレッドとTLSもアドレス空間も共有するため、子で確保した領域は
+    /// 親のスレッドに引き継がれて再利用される。解放はしない。
+    static EXEC_AREA: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+/// `len`バイト以上の領域を返す（mallocを使わない）
+fn exec_area(len: usize) -> Option<*mut u8> {
+    const PAGE_SIZE: usize = 4096;
+
+    EXEC_AREA.with(|area| {
+        let (addr, size) = area.get();
+        if len <= size {
+            return Some(addr as *mut u8);
+        }
+
+        let new_size = len.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        let new_addr = unsafe {
+            raw_syscall_bypass(
+                Sysno::mmap.nr(),
+                0,
+                new_size as u64,
+                (libc::PROT_READ | libc::PROT_WRITE) as u64,
+                (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64,
+                -1i64 as u64,
+                0,
+            )
+        };
+        if new_addr < 0 {
+            return None;
+        }
+        if size != 0 {
+            unsafe { raw_syscall_bypass(Sysno::munmap.nr(), addr as u64, size as u64, 0, 0, 0, 0) };
+        }
+        area.set((new_addr as usize, new_size));
+        Some(new_addr as *mut u8)
+    })
+}
+
+fn env_entry(name: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(name.len() + value.len() + 1);
+    entry.extend_from_slice(name);
+    entry.push(b'=');
+    entry.extend_from_slice(value);
+    entry
+}
+
+fn split_entry(entry: &[u8]) -> (&[u8], &[u8]) {
+    match entry.iter().position(|&b| b == b'=') {
+        Some(i) => (&entry[..i], &entry[i + 1..]),
+        None => (entry, &[]),
+    }
+}
+
+/// LD_PRELOADの要素（`:`と空白で区切られる）
+fn preload_entries(value: &[u8]) -> impl Iterator<Item = &[u8]> {
+    value
+        .split(|&b| b == b':' || b == b' ')
+        .filter(|lib| !lib.is_empty())
+}
+
+fn split_patterns(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .map(|v| {
+            v.split(':')
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// パターンとパスを比較する
+fn path_matches(pattern: &str, path: &Path) -> bool {
+    let target = if pattern.contains('/') {
+        path.as_os_str().as_bytes()
+    } else {
+        match path.file_name() {
+            Some(name) => name.as_bytes(),
+            None => return false,
+        }
+    };
+
+    match pattern.strip_suffix('*') {
+        Some(prefix) => target.starts_with(prefix.as_bytes()),
+        None => target == pattern.as_bytes(),
+    }
+}
+
+/// ローダー自身の絶対パス
+fn loader_path() -> Option<String> {
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    let found = unsafe { libc::dladdr(loader_path as *const libc::c_void, &mut info) };
+    if found == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+    let path = unsafe { CStr::from_ptr(info.dli_fname) };
+    let path = Path::new(std::ffi::OsStr::from_bytes(path.to_bytes()));
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    Some(path.to_string_lossy().into_owned())
+}
+
+static POLICY: OnceLock<ExecPolicy> = OnceLock::new();
+
+/// チェーン先のフック関数（フックライブラリまたはデフォルト）
+static NEXT_HOOK: AtomicPtr<()> = AtomicPtr::new(std::ptr::null_mut());
+
+/// チェーン先が処理するシステムコール
+///
+/// ホットリロードで差し替えられる。読み出し中のスレッドがいる可能性があるため、
+/// 古い集合は解放しない。
+static NEXT_INTERESTS: AtomicPtr<SysnoSet> = AtomicPtr::new(std::ptr::null_mut());
+
+/// 現在のフック関数の前段にexecveの書き換えを挟む
+pub fn install(policy: ExecPolicy) {
+    if POLICY.set(policy).is_err() {
+        return;
+    }
+    set_next(zpoline_hook_api::get_hook_fn(), zpoline_hook_api::hook_interests());
+    zpoline_hook_api::__hook_init(exec_hook);
+    zpoline_hook_api::clone::__set_vfork_child_hook(Some(vfork_child_hook));
+}
+
+/// チェーン先を差し替える（`install`していなければfalse）
+pub fn rechain(hook_fn: HookFn, interests: SysnoSet) -> bool {
+    if POLICY.get().is_none() {
+        return false;
+    }
+    set_next(hook_fn, interests);
+    true
+}
+
+/// チェーン先の関心集合に`set`を加える（`install`していなければ何もしない）
+pub fn extend_next(set: &SysnoSet) {
+    let mut current = NEXT_INTERESTS.load(Ordering::SeqCst);
+    while !current.is_null() {
+        let extended = Box::into_raw(Box::new(unsafe { *current }.union(set)));
+        match NEXT_INTERESTS.compare_exchange(current, extended, Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(_) => return,
+            Err(actual) => {
+                drop(unsafe { Box::from_raw(extended) });
+                current = actual;
+            }
+        }
+    }
+}
+
+fn set_next(hook_fn: HookFn, interests: SysnoSet) {
+    NEXT_INTERESTS.store(Box::into_raw(Box::new(interests)), Ordering::SeqCst);
+    NEXT_HOOK.store(hook_fn as *mut (), Ordering::SeqCst);
+    zpoline_hook_api::set_hook_interests(interests.with(Sysno::execve).with(Sysno::execveat));
+}
+
+fn forward(regs: &mut SyscallRegs) -> i64 {
+    let next_interests = NEXT_INTERESTS.load(Ordering::SeqCst);
+    let interested = !next_interests.is_null()
+        && unsafe { (*next_interests).contains_raw(regs.rax) };
+    if interested {
+        let next: HookFn = unsafe { std::mem::transmute(NEXT_HOOK.load(Ordering::SeqCst)) };
+        next(regs)
+    } else {
+        unsafe { zpoline_hook_api::raw_syscall(regs) }
+    }
+}
+
+extern "C" fn exec_hook(regs: &mut SyscallRegs) -> i64 {
+    rewrite_exec(regs, forward)
+}
+
+/// TLSを共有したまま親が待っているvforkの子（`posix_spawn`など）のシステムコール
+///
+/// フックライブラリには渡さず、execve/execveatの書き換えだけを行う。
+extern "C" fn vfork_child_hook(regs: &mut SyscallRegs) -> i64 {
+    rewrite_exec(regs, |regs| unsafe { zpoline_hook_api::raw_syscall(regs) })
+}
+
+/// execve/execveatの`envp`を書き換えて`run`で実行する（それ以外はそのまま`run`）
+fn rewrite_exec(regs: &mut SyscallRegs, run: fn(&mut SyscallRegs) -> i64) -> i64 {
+    let envp_arg = match Sysno::from_raw(regs.rax) {
+        Some(Sysno::execve) => 2,
+        Some(Sysno::execveat) => 3,
+        _ => return run(regs),
+    };
+
+    let Some(policy) = POLICY.get() else {
+        return run(regs);
+    };
+    let mut path_buf = [0u8; user_mem::PATH_MAX];
+    let Some(path) = target_path(regs, &mut path_buf) else {
+        return run(regs);
+    };
+    if !policy.applies_to(path) {
+        return run(regs);
+    }
+    // 読めないenvpはそのままカーネルに渡してEFAULTにする
+    let Some(env) = (unsafe { user_env(regs.arg(envp_arg)) }) else {
+        return run(regs);
+    };
+
+    let mut size = EnvSize::default();
+    policy.write_env(env.clone(), &mut size);
+    let Some(area) = exec_area(size.area_len()) else {
+        return run(regs);
+    };
+    let mut writer = EnvWriter::new(area, size);
+    policy.write_env(env, &mut writer);
+    let Some(envp) = writer.finish() else {
+        return run(regs);
+    };
+
+    // 成功すれば戻らない。領域は次のexecveで再利用する
+    let mut new_regs = regs.with_arg(envp_arg, envp as u64);
+    let ret = run(&mut new_regs);
+    *regs = new_regs.with_arg(envp_arg, regs.arg(envp_arg));
+    ret
+}
+
+/// 実行ファイルのパス（相対パスは`buf`の中で解決する）
+fn target_path<'a>(regs: &SyscallRegs, buf: &'a mut [u8; user_mem::PATH_MAX]) -> Option<&'a Path> {
+    let (dirfd, pathname, flags) = match Sysno::from_raw(regs.rax)? {
+        Sysno::execve => (libc::AT_FDCWD, regs.arg(0), 0),
+        _ => (regs.arg(0) as i32, regs.arg(1), regs.arg(4) as i32),
+    };
+
+    if in_trampoline(pathname) {
+        return None;
+    }
+    let mut path_buf = [0u8; user_mem::PATH_MAX];
+    let pathname = user_mem::try_path(pathname as *const libc::c_char, &mut path_buf)
+        .ok()?
+        .to_bytes();
+    if pathname.starts_with(b"/") {
+        let len = pathname.len();
+        buf[..len].copy_from_slice(pathname);
+        return Some(Path::new(OsStr::from_bytes(&buf[..len])));
+    }
+
+    let mut len = if dirfd == libc::AT_FDCWD {
+        // getcwdはNULを含む長さを返す
+        let ret = unsafe {
+            raw_syscall_bypass(
+                Sysno::getcwd.nr(),
+                buf.as_mut_ptr() as u64,
+                buf.len() as u64,
+                0,
+                0,
+                0,
+                0,
+            )
+        };
+        if ret <= 0 {
+            return None;
+        }
+        ret as usize - 1
+    } else {
+        let mut link = [0u8; 32];
+        write!(&mut link[..], "/proc/self/fd/{}\0", dirfd).ok()?;
+        let ret = unsafe {
+            raw_syscall_bypass(
+                Sysno::readlinkat.nr(),
+                libc::AT_FDCWD as u64,
+                link.as_ptr() as u64,
+                buf.as_mut_ptr() as u64,
+                buf.len() as u64,
+                0,
+                0,
+            )
+        };
+        if ret <= 0 || ret as usize >= buf.len() {
+            return None;
+        }
+        ret as usize
+    };
+
+    if !(pathname.is_empty() && flags & libc::AT_EMPTY_PATH != 0) {
+        let sep = usize::from(buf[len - 1] != b'/');
+        if len + sep + pathname.len() > buf.len() {
+            return None;
+        }
+        if sep == 1 {
+            buf[len] = b'/';
+        }
+        len += sep;
+        buf[len..len + pathname.len()].copy_from_slice(pathname);
+        len += pathname.len();
+    }
+    Some(Path::new(OsStr::from_bytes(&buf[..len])))
+}
+
+/// トランポリン（VA=0から）の中を指すアドレスか
+///
+/// プロセス内からは読めてしまうが、アプリケーションのデータではない。
+fn in_trampoline(addr: u64) -> bool {
+    (addr as usize) < crate::trampoline::TRAMPOLINE_SIZE
+}
+
+/// アプリケーションのenvpの各要素（NULLは空として扱う）
+///
+/// 先に全要素をフォールトセーフに読めることを確かめ、読めなければNoneを返す。
+///
+/// # Safety
+///
+/// 返すスライスはアプリケーションのメモリを参照するため、使い終わるまで
+/// envpと各要素が解放・変更されないこと
+unsafe fn user_env<'a>(envp: u64) -> Option<impl Iterator<Item = &'a [u8]> + Clone> {
+    let envp = envp as *const usize;
+    let entry = move |i: usize| -> Option<(usize, usize)> {
+        let ptr = user_mem::try_read_val(envp.wrapping_add(i)).ok()?;
+        if ptr == 0 || in_trampoline(ptr as u64) {
+            return None;
+        }
+        let len = user_mem::try_c_str_len(ptr as *const libc::c_char, MAX_ARG_STRLEN).ok()?;
+        Some((ptr, len))
+    };
+
+    let mut len = 0;
+    if !envp.is_null() {
+        if in_trampoline(envp as u64) {
+            return None;
+        }
+        while user_mem::try_read_val(envp.wrapping_add(len)).ok()? != 0 {
+            entry(len)?;
+            len += 1;
+        }
+    }
+    // 確かめた後は長さが分かっているので直接参照する
+    Some((0..len).map_while(move |i| {
+        let (ptr, len) = entry(i)?;
+        Some(std::slice::from_raw_parts(ptr as *const u8, len))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// エントリのリストとして集める
+    #[derive(Default)]
+    struct EnvList {
+        entries: Vec<Vec<u8>>,
+        current: Vec<u8>,
+    }
+
+    impl EnvSink for EnvList {
+        fn push(&mut self, bytes: &[u8]) {
+            self.current.extend_from_slice(bytes);
+        }
+
+        fn end(&mut self) {
+            self.entries.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    fn rewrite_env(policy: &ExecPolicy, env: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut list = EnvList::default();
+        policy.write_env(env.iter().copied(), &mut list);
+        list.entries
+    }
+
+    fn policy(mode: ExecMode) -> ExecPolicy {
+        ExecPolicy::new(
+            mode,
+            vec![],
+            vec!["sudo".to_string(), "/opt/untraced/*".to_string()],
+            "/usr/lib/libzpoline_loader.so".to_string(),
+            vec![b"ZPOLINE_HOOK=/usr/lib/libhooks.so".to_vec()],
+        )
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let policy = policy(ExecMode::Reinject);
+        assert!(policy.applies_to(Path::new("/usr/bin/env")));
+        assert!(!policy.applies_to(Path::new("/usr/bin/sudo")));
+        assert!(!policy.applies_to(Path::new("/opt/untraced/bin/tool")));
+
+        let only_make = ExecPolicy::new(
+            ExecMode::Strip,
+            vec!["make*".to_string()],
+            vec![],
+            String::new(),
+            vec![],
+        );
+        assert!(only_make.applies_to(Path::new("/usr/bin/make")));
+        assert!(only_make.applies_to(Path::new("/usr/bin/makeinfo")));
+        assert!(!only_make.applies_to(Path::new("/usr/bin/cmake")));
+    }
+
+    #[test]
+    fn test_reinject() {
+        let policy = policy(ExecMode::Reinject);
+
+        // env -iで空になった環境
+        let env = rewrite_env(&policy, &[]);
+        assert_eq!(
+            env,
+            vec![
+                b"LD_PRELOAD=/usr/lib/libzpoline_loader.so".to_vec(),
+                b"ZPOLINE_HOOK=/usr/lib/libhooks.so".to_vec(),
+            ]
+        );
+
+        // 既存のLD_PRELOADには追加し、明示的なZPOLINE_HOOKは尊重する
+        let env = rewrite_env(&policy, &[b"PATH=/bin", b"LD_PRELOAD=libfoo.so", b"ZPOLINE_HOOK=/tmp/h.so"]);
+        assert_eq!(
+            env,
+            vec![
+                b"PATH=/bin".to_vec(),
+                b"LD_PRELOAD=/usr/lib/libzpoline_loader.so:libfoo.so".to_vec(),
+                b"ZPOLINE_HOOK=/tmp/h.so".to_vec(),
+            ]
+        );
+
+        // すでに含まれていれば変更しない
+        let env = rewrite_env(&policy, &[b"LD_PRELOAD=libfoo.so /usr/lib/libzpoline_loader.so"]);
+        assert_eq!(env[0], b"LD_PRELOAD=libfoo.so /usr/lib/libzpoline_loader.so");
+    }
+
+    #[test]
+    fn test_strip() {
+        let policy = policy(ExecMode::Strip);
+        let env = rewrite_env(&policy, &[
+            b"PATH=/bin",
+            b"LD_PRELOAD=./target/release/libzpoline_loader.so:libfoo.so",
+            b"ZPOLINE_HOOK=/usr/lib/libhooks.so",
+            b"ZPOLINE_EXEC=strip",
+        ]);
+        assert_eq!(env, vec![b"PATH=/bin".to_vec(), b"LD_PRELOAD=libfoo.so".to_vec()]);
+
+        let env = rewrite_env(&policy, &[b"LD_PRELOAD=/usr/lib/libzpoline_loader.so"]);
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_target_path() {
+        let mut buf = [0u8; user_mem::PATH_MAX];
+        let execve =
+            |path: &CStr| SyscallRegs::new(Sysno::execve.nr(), path.as_ptr() as u64, 0, 0, 0, 0, 0);
+
+        let regs = execve(c"/usr/bin/env");
+        assert_eq!(target_path(&regs, &mut buf), Some(Path::new("/usr/bin/env")));
+
+        let regs = execve(c"bin/tool");
+        let expected = std::env::current_dir().unwrap().join("bin/tool");
+        assert_eq!(target_path(&regs, &mut buf), Some(expected.as_path()));
+
+        // execveatはdirfdからの相対パス
+        let dir = std::fs::File::open("/usr").unwrap();
+        let dir_path = std::fs::canonicalize("/usr").unwrap();
+        let execveat = |path: &CStr, flags: i32| {
+            use std::os::fd::AsRawFd;
+            SyscallRegs::new(
+                Sysno::execveat.nr(),
+                dir.as_raw_fd() as u64,
+                path.as_ptr() as u64,
+                0,
+                0,
+                flags as u64,
+                0,
+            )
+        };
+        let regs = execveat(c"bin/env", 0);
+        assert_eq!(target_path(&regs, &mut buf), Some(dir_path.join("bin/env").as_path()));
+        let regs = execveat(c"", libc::AT_EMPTY_PATH);
+        assert_eq!(target_path(&regs, &mut buf), Some(dir_path.as_path()));
+
+        // 読めないパスやトランポリンを指すパスはNone
+        let regs = SyscallRegs::new(Sysno::execve.nr(), 0xdead_0000, 0, 0, 0, 0, 0);
+        assert_eq!(target_path(&regs, &mut buf), None);
+        let regs = SyscallRegs::new(Sysno::execve.nr(), 0x10, 0, 0, 0, 0, 0);
+        assert_eq!(target_path(&regs, &mut buf), None);
+    }
+
+    #[test]
+    fn test_build_envp_in_area() {
+        let policy = policy(ExecMode::Reinject);
+        let entries = [c"PATH=/bin", c"LD_PRELOAD=libfoo.so"];
+        let mut envp: Vec<*const libc::c_char> = entries.iter().map(|e| e.as_ptr()).collect();
+        envp.push(std::ptr::null());
+
+        let env = unsafe { user_env(envp.as_ptr() as u64) }.unwrap();
+        let mut size = EnvSize::default();
+        policy.write_env(env.clone(), &mut size);
+        let area = exec_area(size.area_len()).unwrap();
+        let mut writer = EnvWriter::new(area, size);
+        policy.write_env(env, &mut writer);
+        let built = writer.finish().unwrap();
+
+        let built: Vec<&[u8]> = unsafe { user_env(built as u64) }.unwrap().collect();
+        assert_eq!(built, rewrite_env(&policy, &[b"PATH=/bin", b"LD_PRELOAD=libfoo.so"]));
+
+        // 小さい領域は確保し直さずに再利用する
+        assert_eq!(exec_area(1), Some(area));
+
+        // 数えた後で増えたエントリは書き込まない
+        let mut writer = EnvWriter::new(area, EnvSize::default());
+        writer.push(b"A=1");
+        writer.end();
+        assert!(writer.finish().is_none());
+
+        // NULLのenvpは空、読めないenvpはNone
+        assert_eq!(unsafe { user_env(0) }.unwrap().count(), 0);
+        assert!(unsafe { user_env(8) }.is_none());
+        assert!(unsafe { user_env(0xdead_0000) }.is_none());
+    }
+
+    #[test]
+    fn test_user_env_bad_pointers() {
+        let user_env_of = |entries: &[*const libc::c_char]| {
+            let envp: Vec<_> = entries.iter().copied().chain([std::ptr::null()]).collect();
+            unsafe { user_env(envp.as_ptr() as u64) }.map(|env| env.count())
+        };
+        assert_eq!(user_env_of(&[c"A=1".as_ptr()]), Some(1));
+
+        // 未マップの要素やトランポリンを指す要素があればNone（元のレジスタでカーネルに渡す）
+        assert_eq!(user_env_of(&[c"A=1".as_ptr(), 0xdead_0000 as *const _]), None);
+        assert_eq!(user_env_of(&[0x10 as *const _]), None);
+
+        // 読める範囲の直後が未マップでもNULまでで止まる
+        let page = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                2 * 4096,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        } as *mut u8;
+        assert_ne!(page as *mut libc::c_void, libc::MAP_FAILED);
+        unsafe { libc::munmap(page.add(4096) as *mut libc::c_void, 4096) };
+        let tail = unsafe { page.add(4096 - 4) };
+        unsafe { std::ptr::copy_nonoverlapping(c"B=2".as_ptr() as *const u8, tail, 4) };
+        assert_eq!(user_env_of(&[tail as *const _]), Some(1));
+
+        // NULが見つからないまま未マップのページに達するとNone
+        unsafe { std::ptr::copy_nonoverlapping(b"B=22".as_ptr(), tail, 4) };
+        assert_eq!(user_env_of(&[tail as *const _]), None);
+        unsafe { libc::munmap(page as *mut libc::c_void, 4096) };
+    }
+}
@@ -73,7 +73,7 @@ fn build_rewrite_config() -> RewriteConfig {
 
     // zpoline_hook_apiの関数が含まれる領域を除外
     // これらの関数にはraw_syscallが含まれているため、書き換えてはいけない
-    let hook_api_start = zpoline_hook_api::raw_syscall as usize;
+    let hook_api_start = zpoline_hook_api::raw_syscall as *const () as usize;
     let hook_api_end = hook_api_start + 4096; // 関数サイズの概算
     config = config.exclude_range(hook_api_start, hook_api_end);
 
@@ -99,10 +99,101 @@ fn build_rewrite_config() -> RewriteConfig {
 mod tests {
     use super::*;
 
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use zpoline_hook_api::{Sysno, SyscallHooks, SysnoSet};
+
+    /// 子プロセスでトランポリンを用意してlibcだけを書き換え、`register`でフックを
+    /// 登録してから`check`を実行する
+    ///
+    /// VA=0をマップできない環境（`vm.mmap_min_addr`）では`None`を返します。
+    /// 壊れた子が偶然0で終了しても成功と見なさないよう、成功には専用の終了コードを使います。
+    fn run_rewritten(register: fn(), check: fn() -> bool) -> Option<bool> {
+        const PASS: i32 = 42;
+        const SKIP: i32 = 77;
+
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let code = if crate::trampoline::setup_trampoline().is_err() {
+                SKIP
+            } else {
+                let mut rewriter = Rewriter::new(RewriteConfig::new());
+                for region in parse_proc_maps().unwrap() {
+                    let is_libc = region.pathname.as_ref().is_some_and(|path| {
+                        path.file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with("libc.so"))
+                    });
+                    if is_libc && region.is_executable() {
+                        rewriter.rewrite_region(&region).unwrap();
+                    }
+                }
+                register();
+                if check() {
+                    PASS
+                } else {
+                    1
+                }
+            };
+            unsafe {
+                zpoline_hook_api::raw_syscall_bypass(Sysno::exit_group.nr(), code as u64, 0, 0, 0, 0, 0)
+            };
+            unreachable!();
+        }
+
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        match (libc::WIFEXITED(status), libc::WEXITSTATUS(status)) {
+            (true, SKIP) => None,
+            (exited, code) => Some(exited && code == PASS),
+        }
+    }
+
+    /// glibcの`pthread_create`（clone3）で作ったスレッドが開始関数を実行するか
+    fn thread_runs() -> bool {
+        static RAN: AtomicBool = AtomicBool::new(false);
+        std::thread::spawn(|| RAN.store(true, Ordering::SeqCst))
+            .join()
+            .is_ok()
+            && RAN.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_pthread_create_through_trait_hooks() {
+        // スレッド生成だけを対象にする（pthread_joinのfutex待ちでディスパッチャの
+        // ロックを保持したままにならないように）
+        struct PassThrough;
+        impl SyscallHooks for PassThrough {
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::new(&[Sysno::clone, Sysno::clone3])
+            }
+        }
+
+        struct Observe;
+        impl zpoline_hook_api::EnterExitHooks for Observe {
+            type Scratch = ();
+
+            fn interests(&self) -> SysnoSet {
+                SysnoSet::new(&[Sysno::clone, Sysno::clone3])
+            }
+        }
+
+        // デフォルト実装（default_clone3）を経由しても子は呼び出し元のレジスタで戻る
+        let register = || zpoline_hook_api::register_syscall_hooks(PassThrough);
+        let Some(ok) = run_rewritten(register, thread_runs) else {
+            // VA=0をマップできない環境
+            return;
+        };
+        assert!(ok, "thread start routine did not run under SyscallHooks");
+
+        let register = || zpoline_hook_api::register_enter_exit_hooks(Observe);
+        let ok = run_rewritten(register, thread_runs).unwrap();
+        assert!(ok, "thread start routine did not run under EnterExitHooks");
+    }
+
     #[test]
     fn test_build_config() {
         let config = build_rewrite_config();
         // 少なくとも何らかの除外設定がされているはず
-        assert!(config.exclude_ranges.len() > 0);
+        assert!(!config.exclude_ranges.is_empty());
     }
 }
@@ -1,6 +1,9 @@
 mod init;
 mod trampoline;
 mod dlmopen;
+mod exec;
+mod reload;
+mod vdso;
 
 use ctor::ctor;
 use std::sync::Once;
@@ -10,6 +13,11 @@ static INIT_ONCE: Once = Once::new();
 /// LD_PRELOADによってロードされた際に自動的に呼ばれる初期化関数
 #[ctor]
 fn zpoline_init() {
+    // テストハーネス自体のsyscallは書き換えない
+    if cfg!(test) {
+        return;
+    }
+
     INIT_ONCE.call_once(|| {
         eprintln!("[zpoline] Initializing zpoline-rs...");
 
@@ -43,12 +51,14 @@ fn zpoline_init() {
         }
 
         // フックライブラリのロード（オプション）
+        let mut loaded_path = None;
         if let Some(lib_path) = dlmopen::get_hook_library_path() {
             match dlmopen::load_hook_library(Some(&lib_path)) {
-                Ok(hook_fn) => {
+                Ok(library) => {
                     eprintln!("[zpoline] Hook library loaded successfully");
-                    // フック関数を登録
-                    zpoline_hook_api::__hook_init(hook_fn);
+                    // フック関数と関心集合を登録
+                    reload::activate(library);
+                    loaded_path = Some(lib_path);
                 }
                 Err(e) => {
                     eprintln!("[zpoline] Warning: Failed to load hook library: {}", e);
@@ -59,6 +69,18 @@ fn zpoline_init() {
             eprintln!("[zpoline] Using built-in default hook (no separate namespace)");
         }
 
+        // vDSOの時刻関数をフック経路に流す（ZPOLINE_VDSO=hookで有効化）
+        vdso::init();
+
+        // execve時の環境変数の引き継ぎ（ZPOLINE_EXECで有効化）
+        if let Some(policy) = exec::ExecPolicy::from_env(loaded_path.as_deref()) {
+            eprintln!("[zpoline] Exec environment mode: {:?}", policy.mode());
+            exec::install(policy);
+        }
+
+        // フックライブラリのホットリロード
+        reload::init();
+
         eprintln!("[zpoline] Initialization complete!");
     });
 }
@@ -67,7 +89,7 @@ fn zpoline_init() {
 // これによりライブラリがロード時に初期化される
 #[no_mangle]
 pub extern "C" fn zpoline_get_version() -> *const u8 {
-    b"zpoline-rs 0.1.0\0".as_ptr()
+    c"zpoline-rs 0.1.0".as_ptr() as *const u8
 }
 
 #[cfg(test)]
@@ -0,0 +1,268 @@
+//! フックライブラリのホットリロード
+//!
+//! 新しいライブラリを別のネームスペースにロードしてフック関数を切り替え、
+//! 切り替え前に始まったフック呼び出しが終わってから古いライブラリを退役させます。
+//! 古いライブラリのコードはシグナルハンドラなどから参照され続けることがあるため、
+//! アンロードせずに常駐させます。
+//!
+//! - `ZPOLINE_RELOAD_SIGNAL` - リロードを要求するシグナル（`HUP`、`SIGUSR2`、`12`など）
+//! - `zpoline_request_reload()` - リロードを要求する（非同期シグナル安全）
+//! - `zpoline_reload(spec)` - 指定したライブラリに同期的に切り替える
+//!
+//! 要求されたリロードは専用のスレッド（`zpoline-reload`）で実行され、
+//! システムコールの経路でライブラリのロードやアンロードは行いません。
+
+use crate::dlmopen::{self, DlmopenError, HookLibrary};
+use crate::exec;
+use crate::vdso;
+use std::ffi::CStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use zpoline_hook_api::{raw_syscall_bypass, Sysno};
+
+/// 古いライブラリのフック呼び出しが終わるのを待つ時間
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 現在のフックライブラリ（リロードの排他も兼ねる）
+static CURRENT: Mutex<Option<HookLibrary>> = Mutex::new(None);
+
+/// 最初にロードしたライブラリを有効にする
+pub fn activate(library: HookLibrary) {
+    apply(&library);
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(library);
+}
+
+/// リロードの要求を受け付ける（`ZPOLINE_RELOAD_SIGNAL`があればシグナルも）
+pub fn init() {
+    spawn_reload_thread();
+    // fork後の子プロセスにはスレッドが引き継がれないので起動し直す
+    unsafe { libc::pthread_atfork(None, None, Some(respawn_after_fork)) };
+    install_signal_from_env();
+}
+
+/// 要求されたリロードを実行するスレッドを起動する
+fn spawn_reload_thread() {
+    let spawned = zpoline_hook_api::without_hooks(|| {
+        std::thread::Builder::new()
+            .name("zpoline-reload".to_string())
+            .spawn(reload_thread)
+    });
+    if let Err(e) = spawned {
+        eprintln!("[zpoline] Warning: Failed to start reload thread: {}", e);
+    }
+}
+
+fn reload_thread() {
+    // ローダー自身のシステムコールはフックしない
+    zpoline_hook_api::without_hooks(|| {
+        // アプリケーション宛てのシグナルを受け取らない
+        unsafe {
+            let mut set = std::mem::zeroed();
+            libc::sigfillset(&mut set);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+        }
+        loop {
+            zpoline_hook_api::reload::wait_reload_request();
+            run_requested();
+        }
+    })
+}
+
+extern "C" fn respawn_after_fork() {
+    spawn_reload_thread();
+}
+
+/// ライブラリのフック関数と関心集合を反映する
+fn apply(library: &HookLibrary) {
+    library.register_fork_child();
+    // execveの書き換えを挟んでいる場合はそのチェーン先を差し替える
+    if !exec::rechain(library.hook_fn(), library.interests()) {
+        zpoline_hook_api::__hook_init(library.hook_fn());
+        zpoline_hook_api::set_hook_interests(library.interests());
+    }
+    // 新しく関心集合に入ったvDSOの関数を書き換える
+    vdso::refresh();
+}
+
+/// フックライブラリを入れ替える
+///
+/// `spec`が`None`の場合は現在のライブラリを同じ指定で読み込み直します。
+/// 古いライブラリは常駐させたまま、フック呼び出しが`DRAIN_TIMEOUT`内に
+/// 終われば`zpoline_hook_fini`を呼びます。
+pub fn reload(spec: Option<&str>) -> Result<(), DlmopenError> {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    let spec = match spec {
+        Some(spec) => spec.to_string(),
+        None => current
+            .as_ref()
+            .map(|library| library.spec().to_string())
+            .ok_or(DlmopenError::LibraryNotSpecified)?,
+    };
+
+    eprintln!("[zpoline] Reloading hook library: {}", spec);
+    let library = dlmopen::load_hook_library(Some(&spec))?;
+    apply(&library);
+
+    if let Some(old) = current.replace(library) {
+        if zpoline_hook_api::reload::synchronize(DRAIN_TIMEOUT) {
+            old.retire();
+        } else {
+            eprintln!(
+                "[zpoline] Warning: hooks in {} are still running, skipping its fini",
+                old.spec()
+            );
+        }
+    }
+
+    eprintln!("[zpoline] Hook library reloaded");
+    Ok(())
+}
+
+/// `request_reload`で要求されたリロード
+fn run_requested() {
+    if let Err(e) = reload(None) {
+        eprintln!("[zpoline] Warning: Failed to reload hook library: {}", e);
+    }
+}
+
+/// シグナル名または番号を解析する
+fn parse_signal(name: &str) -> Option<i32> {
+    const NAMES: [(&str, i32); 6] = [
+        ("HUP", libc::SIGHUP),
+        ("INT", libc::SIGINT),
+        ("QUIT", libc::SIGQUIT),
+        ("USR1", libc::SIGUSR1),
+        ("USR2", libc::SIGUSR2),
+        ("TERM", libc::SIGTERM),
+    ];
+
+    let name = name.trim();
+    if let Ok(signo) = name.parse::<i32>() {
+        return (1..=libc::SIGRTMAX()).contains(&signo).then_some(signo);
+    }
+    let name = name.strip_prefix("SIG").unwrap_or(name);
+    if let Some(offset) = name.strip_prefix("RTMIN+") {
+        let signo = libc::SIGRTMIN() + offset.parse::<i32>().ok()?;
+        return (signo <= libc::SIGRTMAX()).then_some(signo);
+    }
+    NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, signo)| signo)
+}
+
+/// カーネルの`struct sigaction`
+#[repr(C)]
+struct KernelSigaction {
+    handler: usize,
+    flags: u64,
+    restorer: usize,
+    mask: u64,
+}
+
+const SA_RESTORER: u64 = 0x0400_0000;
+
+// ハンドラからの復帰用。glibcの`__restore_rt`は書き換え済みでトランポリンを
+// 経由してしまうため、書き換え対象外のローダー内に置く
+std::arch::global_asm!(
+    ".pushsection .text.zpoline_reload_restorer,\"ax\",@progbits",
+    ".globl zpoline_reload_restorer",
+    ".hidden zpoline_reload_restorer",
+    ".p2align 4",
+    "zpoline_reload_restorer:",
+    "    mov eax, 15",
+    "    syscall",
+    ".popsection",
+);
+
+extern "C" {
+    fn zpoline_reload_restorer();
+}
+
+extern "C" fn on_reload_signal(_signo: libc::c_int) {
+    zpoline_hook_api::reload::request_reload();
+}
+
+/// `ZPOLINE_RELOAD_SIGNAL`が設定されていればシグナルハンドラを登録する
+fn install_signal_from_env() {
+    let Ok(name) = std::env::var("ZPOLINE_RELOAD_SIGNAL") else {
+        return;
+    };
+    let Some(signo) = parse_signal(&name) else {
+        eprintln!("[zpoline] Warning: Unknown ZPOLINE_RELOAD_SIGNAL: {}", name);
+        return;
+    };
+
+    let action = KernelSigaction {
+        handler: on_reload_signal as *const () as usize,
+        flags: (libc::SA_RESTART as u64) | SA_RESTORER,
+        restorer: zpoline_reload_restorer as *const () as usize,
+        mask: 0,
+    };
+    let ret = unsafe {
+        raw_syscall_bypass(
+            Sysno::rt_sigaction.nr(),
+            signo as u64,
+            &action as *const KernelSigaction as u64,
+            0,
+            std::mem::size_of::<u64>() as u64,
+            0,
+            0,
+        )
+    };
+    if ret < 0 {
+        eprintln!("[zpoline] Warning: Failed to install reload signal handler: {}", ret);
+    } else {
+        eprintln!("[zpoline] Hook library reload on signal {}", signo);
+    }
+}
+
+/// アプリケーションからリロードを要求する（非同期シグナル安全）
+#[no_mangle]
+pub extern "C" fn zpoline_request_reload() {
+    zpoline_hook_api::reload::request_reload();
+}
+
+/// フックライブラリを同期的に切り替える
+///
+/// `spec`がnullの場合は現在のライブラリを読み込み直す。成功すれば0、失敗すれば-1。
+///
+/// # Safety
+///
+/// `spec`はnullか、NUL終端された文字列を指している必要がある
+#[no_mangle]
+pub unsafe extern "C" fn zpoline_reload(spec: *const libc::c_char) -> libc::c_int {
+    let spec = if spec.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(spec).to_str() {
+            Ok(spec) => Some(spec),
+            Err(_) => return -1,
+        }
+    };
+
+    // ローダー自身のシステムコールはフックしない
+    match zpoline_hook_api::without_hooks(|| reload(spec)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("[zpoline] Warning: Failed to reload hook library: {}", e);
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("HUP"), Some(libc::SIGHUP));
+        assert_eq!(parse_signal("SIGUSR2"), Some(libc::SIGUSR2));
+        assert_eq!(parse_signal("usr1"), Some(libc::SIGUSR1));
+        assert_eq!(parse_signal("12"), Some(12));
+        assert_eq!(parse_signal("SIGRTMIN+1"), Some(libc::SIGRTMIN() + 1));
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("SIGFOO"), None);
+    }
+}
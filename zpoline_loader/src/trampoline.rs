@@ -15,19 +15,19 @@ impl std::fmt::Display for TrampolineError {
 impl std::error::Error for TrampolineError {}
 
 /// 最大システムコール番号（Linux x86-64では約450程度）
-/// 安全のため、もう少し大きめに確保
-const MAX_SYSCALL_NR: usize = 512;
+/// 安全のため、もう少し大きめに確保（SysnoSetの上限と共通）
+const MAX_SYSCALL_NR: usize = zpoline_hook_api::sysno::MAX_SYSNO;
 
 /// トランポリン全体のサイズ
 /// callq *%raxはraxの値をそのままアドレスとして使うため、
 /// syscall番号0-511の各バイトアドレスをカバーする必要がある
-const TRAMPOLINE_SIZE: usize = MAX_SYSCALL_NR + 4096; // syscall番号分 + スタブ用
+pub(crate) const TRAMPOLINE_SIZE: usize = MAX_SYSCALL_NR + 4096; // syscall番号分 + スタブ用
 
 /// VA=0にトランポリンを生成
 ///
 /// 構造:
 /// - 0x0000 - 0xNNNN: NOP sled (各syscall番号に対応)
-/// - 末尾: jmp命令でhook_entryへ
+/// - 末尾: スタブ（hook_entry_frameを呼ぶ）
 pub fn setup_trampoline() -> Result<(), TrampolineError> {
     // VA=0にメモリをマップ
     // MAP_FIXEDを使用して強制的に0番地に配置
@@ -35,7 +35,7 @@ pub fn setup_trampoline() -> Result<(), TrampolineError> {
     // 0番地を指定するため、NonZeroUsizeは使えないので、
     // 代わりにlibc::mmapを直接呼び出す
 
-    let addr = 0 as *mut std::ffi::c_void;
+    let addr = std::ptr::null_mut::<std::ffi::c_void>();
     let result = unsafe {
         libc::mmap(
             addr,
@@ -53,11 +53,15 @@ pub fn setup_trampoline() -> Result<(), TrampolineError> {
 
     let mapped_ptr = result as *mut u8;
 
-    // マップされたメモリをバイトスライスとして取得
-    let trampoline_mem = unsafe { std::slice::from_raw_parts_mut(mapped_ptr, TRAMPOLINE_SIZE) };
+    // トランポリンコードをローカルバッファに生成
+    // VA=0はRustの参照としては扱えない（nullスライスはUB）ため、
+    // 生成後にvolatile書き込みでコピーする
+    let mut code = vec![0u8; TRAMPOLINE_SIZE];
+    generate_trampoline_code(&mut code)?;
 
-    // トランポリンコードを生成
-    generate_trampoline_code(trampoline_mem)?;
+    for (i, byte) in code.iter().enumerate() {
+        unsafe { mapped_ptr.wrapping_add(i).write_volatile(*byte) };
+    }
 
     Ok(())
 }
@@ -76,9 +80,7 @@ fn generate_trampoline_code(mem: &mut [u8]) -> Result<(), TrampolineError> {
     // 0からstub_offset-1までをNOPで埋める
     // これにより、任意のsyscall番号（=アドレス）からスタートしても
     // NOP命令を実行し続けてスタブに到達できる
-    for i in 0..stub_offset {
-        mem[i] = 0x90; // NOP (1バイト命令)
-    }
+    mem[..stub_offset].fill(0x90); // NOP (1バイト命令)
 
     // スタブコードを生成
     generate_hook_stub(&mut mem[stub_offset..])?;
@@ -88,24 +90,87 @@ fn generate_trampoline_code(mem: &mut [u8]) -> Result<(), TrampolineError> {
 
 /// フックスタブを生成
 ///
-/// このスタブはhook_entryを呼び出し、結果を返す。
+/// このスタブは関心集合に含まれるsyscallについて`hook_entry_frame`を呼び出し、結果を返す。
 /// 簡易実装のため、完全なレジスタ保存/復元は省略。
 fn generate_hook_stub(mem: &mut [u8]) -> Result<(), TrampolineError> {
-    // hook_entry関数のアドレスを取得
-    let hook_entry_addr = zpoline_hook_api::hook_entry as usize;
+    // エントリ関数のアドレスを取得
+    let hook_entry_addr = zpoline_hook_api::context::hook_entry_frame as *const () as usize;
 
     // 以下のコードを生成:
-    // レジスタをSyscallRegs構造体のメモリレイアウトに合わせてスタックに積む
-    // struct SyscallRegs { rax, rdi, rsi, rdx, r10, r8, r9 }
-    // スタックは高位→低位に成長するため、逆順にpush: r9, r8, r10, rdx, rsi, rdi, rax
+    // レジスタをSyscallFrame構造体のメモリレイアウトに合わせてスタックに積む
+    // struct SyscallFrame { regs: { rax, rdi, rsi, rdx, r10, r8, r9 }, rbx, rbp, r12-r15,
+    //                       red_zone, return_address }
+    // スタックは高位→低位に成長するため、逆順にpush: r15, ..., rbx, r9, ..., rax
     //
     // スタックアライメント:
-    // callq *%raxでリターンアドレス(8B)がpushされている
-    // 7個のレジスタ(56B)をpushする
-    // 合計64B → 16バイトアラインのため8Bのパディングが必要
+    // syscall命令の位置でのrspは16バイトアラインとは限らないため、
+    // rbxに退避したうえで`and rsp, -16`で明示的にアラインする
 
     let mut offset = 0;
 
+    // rt_sigreturnの特別扱い
+    // カーネルはrspがシグナルフレームを指していることを前提にするため、
+    // callqで積まれた戻りアドレスを捨ててから直接実行する（戻ってこない）
+    // フックやhook_entryを経由させると別のフレームを復元してしまう
+
+    // cmp rax, SYS_rt_sigreturn
+    mem[offset..offset + 4].copy_from_slice(&[0x48, 0x83, 0xf8, libc::SYS_rt_sigreturn as u8]);
+    offset += 4;
+
+    // jne +6
+    mem[offset..offset + 2].copy_from_slice(&[0x75, 0x06]);
+    offset += 2;
+
+    // add rsp, 8
+    mem[offset..offset + 4].copy_from_slice(&[0x48, 0x83, 0xc4, 0x08]);
+    offset += 4;
+
+    // syscall
+    mem[offset..offset + 2].copy_from_slice(&[0x0f, 0x05]);
+    offset += 2;
+
+    // 関心集合の判定
+    // フックライブラリが処理しないsyscallはhook_entryを経由せずここで実行する
+    // raxはNOP sledに着地した時点でMAX_SYSCALL_NR未満であることが保証されている
+    // r11はsyscall命令で破壊されるレジスタなので自由に使える
+
+    // movabs r11, bitmap_addr
+    let bitmap_addr = zpoline_hook_api::interests::__hook_interests_bitmap() as usize;
+    mem[offset] = 0x49;
+    mem[offset + 1] = 0xbb;
+    mem[offset + 2..offset + 10].copy_from_slice(&bitmap_addr.to_le_bytes());
+    offset += 10;
+
+    // bt [r11], rax
+    mem[offset..offset + 4].copy_from_slice(&[0x49, 0x0f, 0xa3, 0x03]);
+    offset += 4;
+
+    // jc +3 (ビットが立っていればフック経路へ)
+    mem[offset] = 0x72;
+    mem[offset + 1] = 0x03;
+    offset += 2;
+
+    // syscall
+    mem[offset] = 0x0f;
+    mem[offset + 1] = 0x05;
+    offset += 2;
+
+    // ret
+    mem[offset] = 0xc3;
+    offset += 1;
+
+    // レッドゾーンを保護
+    // syscallを発行した関数がレッドゾーンを使っている可能性がある
+    // sub rsp, 0x80
+    mem[offset..offset + 7].copy_from_slice(&[0x48, 0x81, 0xec, 0x80, 0x00, 0x00, 0x00]);
+    offset += 7;
+
+    // 残りの汎用レジスタを逆順にpush（SyscallFrameのregsの直後に並ぶ）
+    // push r15; push r14; push r13; push r12; push rbp; push rbx
+    mem[offset..offset + 10]
+        .copy_from_slice(&[0x41, 0x57, 0x41, 0x56, 0x41, 0x55, 0x41, 0x54, 0x55, 0x53]);
+    offset += 10;
+
     // レジスタを逆順にpush（r9から始めてraxで終わる）
     // これによりスタック上で rsp+0:rax, rsp+8:rdi, ... となる
 
@@ -140,23 +205,27 @@ fn generate_hook_stub(mem: &mut [u8]) -> Result<(), TrampolineError> {
     mem[offset] = 0x50;
     offset += 1;
 
-    // スタックアライメント調整: 8バイト減算（パディング）
-    // sub rsp, 8
+    // アライメント前のrsp（フレームの先頭）をrbxに保持する
+    // rbxの元の値はフレームに保存済み
+    // mov rbx, rsp
+    mem[offset] = 0x48;
+    mem[offset + 1] = 0x89;
+    mem[offset + 2] = 0xe3;
+    offset += 3;
+
+    // and rsp, -16
     mem[offset] = 0x48;
     mem[offset + 1] = 0x83;
-    mem[offset + 2] = 0xec;
-    mem[offset + 3] = 0x08;
+    mem[offset + 2] = 0xe4;
+    mem[offset + 3] = 0xf0;
     offset += 4;
 
-    // mov rdi, rsp (第一引数としてスタックポインタ = &mut SyscallRegsを渡す)
-    // ただし、パディングの8バイト分を加算
-    // lea rdi, [rsp + 8]
+    // 第一引数として *mut SyscallFrame を渡す
+    // mov rdi, rbx
     mem[offset] = 0x48;
-    mem[offset + 1] = 0x8d;
-    mem[offset + 2] = 0x7c;
-    mem[offset + 3] = 0x24;
-    mem[offset + 4] = 0x08;
-    offset += 5;
+    mem[offset + 1] = 0x89;
+    mem[offset + 2] = 0xdf;
+    offset += 3;
 
     // movabs r11, hook_entry_addr (r11を使用してraxを保護)
     mem[offset] = 0x49;
@@ -171,13 +240,12 @@ fn generate_hook_stub(mem: &mut [u8]) -> Result<(), TrampolineError> {
     mem[offset + 2] = 0xd3;
     offset += 3;
 
-    // パディングを解除
-    // add rsp, 8
+    // アライメント前のrspを復元
+    // mov rsp, rbx
     mem[offset] = 0x48;
-    mem[offset + 1] = 0x83;
-    mem[offset + 2] = 0xc4;
-    mem[offset + 3] = 0x08;
-    offset += 4;
+    mem[offset + 1] = 0x89;
+    mem[offset + 2] = 0xdc;
+    offset += 3;
 
     // hook_entryの戻り値（rax）はシステムコールの戻り値
     // スタックからレジスタを復元（raxは復元しない - 戻り値として使う）
@@ -216,6 +284,16 @@ fn generate_hook_stub(mem: &mut [u8]) -> Result<(), TrampolineError> {
     mem[offset + 1] = 0x59;
     offset += 2;
 
+    // pop rbx; pop rbp; pop r12; pop r13; pop r14; pop r15
+    mem[offset..offset + 10]
+        .copy_from_slice(&[0x5b, 0x5d, 0x41, 0x5c, 0x41, 0x5d, 0x41, 0x5e, 0x41, 0x5f]);
+    offset += 10;
+
+    // レッドゾーン分を戻す
+    // add rsp, 0x80
+    mem[offset..offset + 7].copy_from_slice(&[0x48, 0x81, 0xc4, 0x80, 0x00, 0x00, 0x00]);
+    offset += 7;
+
     // ret (callq *%raxで積まれたリターンアドレスに戻る)
     mem[offset] = 0xc3;
 
@@ -228,7 +306,7 @@ mod tests {
 
     #[test]
     fn test_trampoline_constants() {
-        assert!(MAX_SYSCALL_NR > 400);
-        assert!(TRAMPOLINE_SIZE > MAX_SYSCALL_NR);
+        const { assert!(MAX_SYSCALL_NR > 400) };
+        const { assert!(TRAMPOLINE_SIZE > MAX_SYSCALL_NR) };
     }
 }
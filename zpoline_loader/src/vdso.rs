@@ -0,0 +1,639 @@
+//! vDSOの時刻関数をフック経路に流す
+//!
+//! `clock_gettime`などはvDSOの関数としてユーザー空間で完結するため、
+//! `syscall`命令の書き換えではフックに届きません。`ZPOLINE_VDSO=hook`を設定すると、
+//! ローダーはglibcが保持しているvDSO関数へのポインタ（ld.soの`GLRO(dl_vdso_*)`）と、
+//! IFUNCでvDSO関数そのものに解決されたGOTのエントリ（`gettimeofday`や`time`）を
+//! `mov eax, nr; call rax; ret`だけのスタブに差し替え、書き換えたsyscallと同じく
+//! トランポリンに入るようにします。
+//!
+//! vDSO自体は書き換えません。新しいカーネルはvDSOをシールしており`mprotect`できないためです。
+//! そのためglibcを経由しない呼び出し（静的リンクのバイナリやGoのランタイムなど）は
+//! 対象外です。
+//!
+//! vDSOの関数は引数の並びと戻り値（失敗時は負のerrno）がシステムコールと同じなので、
+//! フックからは通常のシステムコールに見えます。関心集合に含まれる関数だけを差し替え、
+//! それ以外はvDSOの高速な実装のまま残します。
+
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use zpoline_hook_api::Sysno;
+
+// vDSO関数の代わりに呼ばれるスタブ
+// raxにシステムコール番号を入れて書き換え済みのsyscallと同じ経路に入る
+std::arch::global_asm!(
+    ".pushsection .text.zpoline_vdso_stubs,\"ax\",@progbits",
+    ".macro zpoline_vdso_stub name, nr",
+    ".globl \\name",
+    ".hidden \\name",
+    ".p2align 4",
+    "\\name:",
+    "    mov eax, \\nr",
+    "    call rax",
+    "    ret",
+    ".endm",
+    "zpoline_vdso_stub zpoline_vdso_clock_gettime, 228",
+    "zpoline_vdso_stub zpoline_vdso_clock_getres, 229",
+    "zpoline_vdso_stub zpoline_vdso_gettimeofday, 96",
+    "zpoline_vdso_stub zpoline_vdso_time, 201",
+    "zpoline_vdso_stub zpoline_vdso_getcpu, 309",
+    ".popsection",
+);
+
+extern "C" {
+    fn zpoline_vdso_clock_gettime();
+    fn zpoline_vdso_clock_getres();
+    fn zpoline_vdso_gettimeofday();
+    fn zpoline_vdso_time();
+    fn zpoline_vdso_getcpu();
+}
+
+/// 差し替える関数と対応するシステムコール、スタブ
+const VDSO_FUNCTIONS: [(&str, Sysno, unsafe extern "C" fn()); 5] = [
+    (
+        "__vdso_clock_gettime",
+        Sysno::clock_gettime,
+        zpoline_vdso_clock_gettime,
+    ),
+    (
+        "__vdso_clock_getres",
+        Sysno::clock_getres,
+        zpoline_vdso_clock_getres,
+    ),
+    (
+        "__vdso_gettimeofday",
+        Sysno::gettimeofday,
+        zpoline_vdso_gettimeofday,
+    ),
+    ("__vdso_time", Sysno::time, zpoline_vdso_time),
+    ("__vdso_getcpu", Sysno::getcpu, zpoline_vdso_getcpu),
+];
+
+/// `SHT_DYNSYM`（`libc`クレートには定義がない）
+const SHT_DYNSYM: u32 = 11;
+
+// 動的セクションのタグと再配置の種類（`libc`クレートには定義がない）
+const DT_NULL: i64 = 0;
+const DT_PLTRELSZ: i64 = 2;
+const DT_STRTAB: i64 = 5;
+const DT_SYMTAB: i64 = 6;
+const DT_RELA: i64 = 7;
+const DT_RELASZ: i64 = 8;
+const DT_JMPREL: i64 = 23;
+const R_X86_64_JUMP_SLOT: u32 = 7;
+
+/// `Elf64_Dyn`
+#[repr(C)]
+struct Dyn {
+    d_tag: i64,
+    d_val: u64,
+}
+
+/// `Elf64_Rela`
+#[repr(C)]
+struct Rela {
+    r_offset: u64,
+    r_info: u64,
+    r_addend: i64,
+}
+
+/// vDSOのエラー
+#[derive(Debug)]
+pub enum VdsoError {
+    /// vDSOがマップされていない
+    NotMapped,
+    /// ld.soがマップされていない（静的リンクなど）
+    NoDynamicLoader,
+    /// ELFとして解釈できない
+    InvalidElf(&'static str),
+    MprotectFailed(std::io::Error),
+}
+
+impl std::fmt::Display for VdsoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VdsoError::NotMapped => write!(f, "vDSO is not mapped"),
+            VdsoError::NoDynamicLoader => write!(f, "dynamic loader is not mapped"),
+            VdsoError::InvalidElf(reason) => write!(f, "invalid ELF image: {}", reason),
+            VdsoError::MprotectFailed(e) => write!(f, "mprotect failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VdsoError {}
+
+/// vDSOの関数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VdsoFunction {
+    pub sysno: Sysno,
+    pub addr: usize,
+    /// 代わりに呼ばれるスタブのアドレス
+    pub stub: usize,
+}
+
+/// 差し替え済みのシステムコール
+static REDIRECTED: Mutex<Vec<Sysno>> = Mutex::new(Vec::new());
+
+/// `ZPOLINE_VDSO=hook`が指定されたか
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// `ZPOLINE_VDSO`を読み、有効なら関心集合に含まれる関数を差し替える
+pub fn init() {
+    match std::env::var("ZPOLINE_VDSO").as_deref() {
+        Ok("hook") => ENABLED.store(true, Ordering::Relaxed),
+        Ok("") | Ok("skip") | Err(_) => return,
+        Ok(other) => {
+            eprintln!("[zpoline] Warning: Unknown ZPOLINE_VDSO mode: {}", other);
+            return;
+        }
+    }
+    refresh();
+}
+
+/// 関心集合の変更後に呼ぶ（`ZPOLINE_VDSO=hook`でなければ何もしない）
+pub fn refresh() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    match redirect_interested() {
+        Ok(0) => {}
+        Ok(count) => eprintln!("[zpoline] vDSO functions redirected: {}", count),
+        Err(e) => eprintln!("[zpoline] Warning: Failed to redirect vDSO: {}", e),
+    }
+}
+
+/// 関心集合に含まれる関数を差し替え、新たに差し替えた数を返す
+///
+/// 差し替え済みの関数はそのまま残すため、リロードで関心集合が変わった後に
+/// もう一度呼んでも安全です。関心集合から外れた関数はトランポリンの
+/// ビットマップ判定で直接実行されます。
+fn redirect_interested() -> Result<usize, VdsoError> {
+    let mut redirected = REDIRECTED.lock().unwrap_or_else(|e| e.into_inner());
+    let functions: Vec<_> = find_functions()?
+        .into_iter()
+        .filter(|function| {
+            !redirected.contains(&function.sysno)
+                && zpoline_hook_api::interests::is_hook_interested(function.sysno.nr())
+        })
+        .collect();
+    if functions.is_empty() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for (function, slots) in find_glibc_slots(&functions)? {
+        if slots.is_empty() {
+            eprintln!(
+                "[zpoline]   Warning: glibc does not use vDSO {}",
+                function.sysno
+            );
+            continue;
+        }
+        for slot in slots {
+            unsafe { write_slot(slot, function.stub)? };
+        }
+        redirected.push(function.sysno);
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// vDSOの動的シンボルから差し替え対象の関数を探す
+pub fn find_functions() -> Result<Vec<VdsoFunction>, VdsoError> {
+    let base = unsafe { libc::getauxval(libc::AT_SYSINFO_EHDR) } as usize;
+    if base == 0 {
+        return Err(VdsoError::NotMapped);
+    }
+    unsafe { find_functions_in(base) }
+}
+
+/// # Safety
+///
+/// `base`はマップされたELFイメージの先頭であること
+unsafe fn find_functions_in(base: usize) -> Result<Vec<VdsoFunction>, VdsoError> {
+    let ehdr = elf_header(base)?;
+
+    // ロードバイアス（vDSOは通常0にリンクされている）
+    let load = program_headers(base, ehdr)
+        .iter()
+        .find(|phdr| phdr.p_type == libc::PT_LOAD)
+        .ok_or(VdsoError::InvalidElf("no PT_LOAD"))?;
+    let bias = (base + load.p_offset as usize).wrapping_sub(load.p_vaddr as usize);
+
+    // vDSOはセクションヘッダも含めてマップされる
+    let shdrs = std::slice::from_raw_parts(
+        (base + ehdr.e_shoff as usize) as *const libc::Elf64_Shdr,
+        ehdr.e_shnum as usize,
+    );
+    let dynsym = shdrs
+        .iter()
+        .find(|shdr| shdr.sh_type == SHT_DYNSYM)
+        .ok_or(VdsoError::InvalidElf("no .dynsym"))?;
+    let strtab = shdrs
+        .get(dynsym.sh_link as usize)
+        .ok_or(VdsoError::InvalidElf("bad .dynstr link"))?;
+    if dynsym.sh_entsize as usize != std::mem::size_of::<libc::Elf64_Sym>() {
+        return Err(VdsoError::InvalidElf("bad .dynsym entry size"));
+    }
+    let syms = std::slice::from_raw_parts(
+        (base + dynsym.sh_offset as usize) as *const libc::Elf64_Sym,
+        (dynsym.sh_size / dynsym.sh_entsize) as usize,
+    );
+    let strings = base + strtab.sh_offset as usize;
+
+    let mut functions = Vec::new();
+    for sym in syms.iter().filter(|sym| sym.st_value != 0) {
+        if sym.st_name as u64 >= strtab.sh_size {
+            continue;
+        }
+        let name = CStr::from_ptr((strings + sym.st_name as usize) as *const libc::c_char);
+        let Some(&(_, sysno, stub)) = VDSO_FUNCTIONS
+            .iter()
+            .find(|(function, _, _)| function.as_bytes() == name.to_bytes())
+        else {
+            continue;
+        };
+        functions.push(VdsoFunction {
+            sysno,
+            addr: bias.wrapping_add(sym.st_value as usize),
+            stub: stub as usize,
+        });
+    }
+    Ok(functions)
+}
+
+/// glibcとロード済みのオブジェクトがvDSO関数のアドレスを保持している場所を探す
+///
+/// `GLRO(dl_vdso_*)`はld.soの書き込み可能なセグメント（RELRO）にあるため、
+/// その中から関数のアドレスと一致するワードを探します。glibc 2.31以降の
+/// `gettimeofday`と`time`はIFUNCでvDSO関数そのものに解決されるため、
+/// 各オブジェクトのGOTも調べます。
+fn find_glibc_slots(
+    functions: &[VdsoFunction],
+) -> Result<Vec<(VdsoFunction, Vec<Slot>)>, VdsoError> {
+    let base = unsafe { libc::getauxval(libc::AT_BASE) } as usize;
+    if base == 0 {
+        return Err(VdsoError::NoDynamicLoader);
+    }
+
+    let mut result: Vec<_> = functions.iter().map(|&f| (f, Vec::new())).collect();
+    // 遅延束縛のPLTスロットは、シンボルがvDSO関数に解決される場合だけ差し替える
+    let lazy: Vec<_> = functions
+        .iter()
+        .map(|f| libc_name(f.sysno).filter(|name| resolves_to(name, f.addr)))
+        .collect();
+    unsafe {
+        let loader = LoadedObject {
+            bias: base,
+            phdrs: program_headers(base, elf_header(base)?),
+        };
+        scan_writable(&loader, &mut result);
+        let vdso = libc::getauxval(libc::AT_SYSINFO_EHDR) as usize;
+        for object in loaded_objects().iter().filter(|object| object.bias != vdso) {
+            scan_got(object, &lazy, &mut result);
+        }
+    }
+    for (_, slots) in &mut result {
+        slots.sort_by_key(|slot| slot.addr);
+        slots.dedup_by_key(|slot| slot.addr);
+    }
+    Ok(result)
+}
+
+/// ロード済みのオブジェクト
+struct LoadedObject {
+    bias: usize,
+    phdrs: &'static [libc::Elf64_Phdr],
+}
+
+impl LoadedObject {
+    fn relro(&self) -> Option<std::ops::Range<usize>> {
+        self.phdrs
+            .iter()
+            .find(|phdr| phdr.p_type == libc::PT_GNU_RELRO)
+            .map(|phdr| {
+                let start = self.bias + phdr.p_vaddr as usize;
+                start..start + phdr.p_memsz as usize
+            })
+    }
+
+    fn slot(&self, addr: usize) -> Slot {
+        Slot {
+            addr,
+            read_only: self.relro().is_some_and(|r| r.contains(&addr)),
+        }
+    }
+
+    /// 動的セクションのアドレス（ld.soが加算済みでなければロードバイアスを足す）
+    fn address(&self, value: u64) -> usize {
+        let value = value as usize;
+        if value < self.bias {
+            self.bias + value
+        } else {
+            value
+        }
+    }
+}
+
+/// 呼び出し元のネームスペースにロードされているオブジェクト
+fn loaded_objects() -> Vec<LoadedObject> {
+    unsafe extern "C" fn callback(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        data: *mut libc::c_void,
+    ) -> libc::c_int {
+        let objects = &mut *(data as *mut Vec<LoadedObject>);
+        let info = &*info;
+        if !info.dlpi_phdr.is_null() {
+            objects.push(LoadedObject {
+                bias: info.dlpi_addr as usize,
+                phdrs: std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize),
+            });
+        }
+        0
+    }
+
+    let mut objects = Vec::new();
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(callback),
+            &mut objects as *mut Vec<LoadedObject> as *mut libc::c_void,
+        )
+    };
+    objects
+}
+
+/// 書き込み可能なセグメントから関数のアドレスと一致するワードを探す
+///
+/// # Safety
+///
+/// `object`はマップされたELFオブジェクトであること
+unsafe fn scan_writable(object: &LoadedObject, result: &mut [(VdsoFunction, Vec<Slot>)]) {
+    for phdr in object.phdrs {
+        if phdr.p_type != libc::PT_LOAD || phdr.p_flags & libc::PF_W == 0 {
+            continue;
+        }
+        let words = std::slice::from_raw_parts(
+            (object.bias + phdr.p_vaddr as usize) as *const usize,
+            phdr.p_memsz as usize / std::mem::size_of::<usize>(),
+        );
+        for word in words {
+            if let Some((_, slots)) = result.iter_mut().find(|(f, _)| f.addr == *word) {
+                slots.push(object.slot(word as *const usize as usize));
+            }
+        }
+    }
+}
+
+/// 再配置されたGOTのエントリのうちvDSO関数を指すもの（またはこれから解決されるもの）を探す
+///
+/// `lazy`は`result`と同じ並びで、遅延束縛のPLTスロットを差し替える関数のシンボル名です。
+///
+/// # Safety
+///
+/// `object`はld.soが再配置を済ませたELFオブジェクトであること
+unsafe fn scan_got(
+    object: &LoadedObject,
+    lazy: &[Option<&str>],
+    result: &mut [(VdsoFunction, Vec<Slot>)],
+) {
+    let Some(dynamic) = object
+        .phdrs
+        .iter()
+        .find(|phdr| phdr.p_type == libc::PT_DYNAMIC)
+    else {
+        return;
+    };
+
+    let mut tables = [(0, 0); 2];
+    let (mut symtab, mut strtab) = (0, 0);
+    let mut entry = (object.bias + dynamic.p_vaddr as usize) as *const Dyn;
+    while (*entry).d_tag != DT_NULL {
+        let value = (*entry).d_val;
+        match (*entry).d_tag {
+            DT_RELA => tables[0].0 = object.address(value),
+            DT_RELASZ => tables[0].1 = value as usize,
+            DT_JMPREL => tables[1].0 = object.address(value),
+            DT_PLTRELSZ => tables[1].1 = value as usize,
+            DT_SYMTAB => symtab = object.address(value),
+            DT_STRTAB => strtab = object.address(value),
+            _ => {}
+        }
+        entry = entry.add(1);
+    }
+
+    let symbol = |rela: &Rela| {
+        if symtab == 0 || strtab == 0 {
+            return None;
+        }
+        let sym = &*(symtab as *const libc::Elf64_Sym).add((rela.r_info >> 32) as usize);
+        Some(CStr::from_ptr((strtab + sym.st_name as usize) as *const libc::c_char).to_bytes())
+    };
+
+    for (table, size) in tables {
+        if table == 0 {
+            continue;
+        }
+        let relas =
+            std::slice::from_raw_parts(table as *const Rela, size / std::mem::size_of::<Rela>());
+        for rela in relas {
+            let addr = object.bias + rela.r_offset as usize;
+            let value = *(addr as *const usize);
+            let jump_slot = rela.r_info as u32 == R_X86_64_JUMP_SLOT;
+            let found = result.iter_mut().zip(lazy).find(|((f, _), name)| {
+                f.addr == value
+                    || (jump_slot && name.is_some_and(|name| symbol(rela) == Some(name.as_bytes())))
+            });
+            if let Some(((_, slots), _)) = found {
+                slots.push(object.slot(addr));
+            }
+        }
+    }
+}
+
+/// vDSO関数に対応するglibcの関数名
+fn libc_name(sysno: Sysno) -> Option<&'static str> {
+    VDSO_FUNCTIONS
+        .iter()
+        .find(|(_, s, _)| *s == sysno)
+        .and_then(|(name, _, _)| name.strip_prefix("__vdso_"))
+}
+
+/// シンボルがグローバルに`addr`（vDSO関数）へ解決されるか
+fn resolves_to(name: &str, addr: usize) -> bool {
+    let Ok(name) = std::ffi::CString::new(name) else {
+        return false;
+    };
+    unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) as usize == addr }
+}
+
+/// glibcが保持するvDSO関数のアドレス
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    addr: usize,
+    /// RELROで読み取り専用になっているか
+    read_only: bool,
+}
+
+/// # Safety
+///
+/// `slot`は`find_glibc_slots`が返したものであること
+unsafe fn write_slot(slot: Slot, value: usize) -> Result<(), VdsoError> {
+    if !slot.read_only {
+        (slot.addr as *mut usize).write_volatile(value);
+        return Ok(());
+    }
+
+    let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+    let page = (slot.addr & !(page_size - 1)) as *mut libc::c_void;
+    let protect = |prot| {
+        if libc::mprotect(page, page_size, prot) != 0 {
+            Err(VdsoError::MprotectFailed(std::io::Error::last_os_error()))
+        } else {
+            Ok(())
+        }
+    };
+
+    protect(libc::PROT_READ | libc::PROT_WRITE)?;
+    (slot.addr as *mut usize).write_volatile(value);
+    protect(libc::PROT_READ)
+}
+
+/// # Safety
+///
+/// `base`はマップされたELFイメージの先頭であること
+unsafe fn elf_header<'a>(base: usize) -> Result<&'a libc::Elf64_Ehdr, VdsoError> {
+    let ehdr = &*(base as *const libc::Elf64_Ehdr);
+    if ehdr.e_ident[..4] != *b"\x7fELF" {
+        return Err(VdsoError::InvalidElf("bad magic"));
+    }
+    Ok(ehdr)
+}
+
+/// # Safety
+///
+/// `ehdr`は`base`にマップされたELFイメージのヘッダであること
+unsafe fn program_headers<'a>(base: usize, ehdr: &libc::Elf64_Ehdr) -> &'a [libc::Elf64_Phdr] {
+    std::slice::from_raw_parts(
+        (base + ehdr.e_phoff as usize) as *const libc::Elf64_Phdr,
+        ehdr.e_phnum as usize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock_gettime() -> VdsoFunction {
+        find_functions()
+            .unwrap()
+            .into_iter()
+            .find(|function| function.sysno == Sysno::clock_gettime)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_find_functions() {
+        let function = clock_gettime();
+
+        // シンボルのアドレスはdlsymで引いたものと一致する
+        let handle = unsafe {
+            libc::dlopen(
+                c"linux-vdso.so.1".as_ptr(),
+                libc::RTLD_LAZY | libc::RTLD_NOLOAD,
+            )
+        };
+        assert!(!handle.is_null());
+        let sym = unsafe { libc::dlsym(handle, c"__vdso_clock_gettime".as_ptr()) };
+        assert_eq!(sym as usize, function.addr);
+    }
+
+    #[test]
+    fn test_find_glibc_slots() {
+        let function = clock_gettime();
+        let slots = find_glibc_slots(&[function]).unwrap();
+        let (_, slots) = &slots[0];
+        assert!(!slots.is_empty());
+        for slot in slots {
+            assert!(slot.read_only);
+            assert_eq!(unsafe { *(slot.addr as *const usize) }, function.addr);
+        }
+    }
+
+    #[test]
+    fn test_find_got_slots() {
+        let function = find_functions()
+            .unwrap()
+            .into_iter()
+            .find(|function| function.sysno == Sysno::gettimeofday)
+            .unwrap();
+        // glibcのバージョンによってはIFUNCがvDSOに解決されない
+        if !resolves_to("gettimeofday", function.addr) {
+            return;
+        }
+
+        // このテストバイナリのGOTにvDSO関数へ解決されたエントリがある
+        let mut tv = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        };
+        unsafe { libc::gettimeofday(&mut tv, std::ptr::null_mut()) };
+        let slots = find_glibc_slots(&[function]).unwrap();
+        let (_, slots) = &slots[0];
+        let exe = unsafe { libc::getauxval(libc::AT_PHDR) } as usize;
+        assert!(slots.iter().any(|slot| slot.addr.abs_diff(exe) < 1 << 30));
+        for slot in slots {
+            assert_eq!(unsafe { *(slot.addr as *const usize) }, function.addr);
+        }
+    }
+
+    /// テストバイナリ自身をローダーとトレース用のフックライブラリ付きで実行し直す
+    #[test]
+    fn test_libc_time_functions_reach_hooks() {
+        if std::env::var_os("ZPOLINE_VDSO_TEST_CHILD").is_some() {
+            let mut tv = libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            };
+            unsafe {
+                libc::gettimeofday(&mut tv, std::ptr::null_mut());
+                libc::time(std::ptr::null_mut());
+            }
+            return;
+        }
+
+        let exe = std::env::current_exe().unwrap();
+        let dir = exe.parent().unwrap().parent().unwrap();
+        let loader = dir.join("libzpoline_loader.so");
+        let hook = dir.join("libzpoline_hook_impl.so");
+        assert!(
+            loader.exists() && hook.exists(),
+            "build the workspace before running this test"
+        );
+
+        let output = std::process::Command::new(&exe)
+            .args([
+                "--exact",
+                "vdso::tests::test_libc_time_functions_reach_hooks",
+                "--test-threads=1",
+            ])
+            .env("ZPOLINE_VDSO_TEST_CHILD", "1")
+            .env("ZPOLINE_VDSO", "hook")
+            .env("ZPOLINE_HOOK", &hook)
+            .env("LD_PRELOAD", &loader)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("[HOOK] gettimeofday (nr=96"), "{}", stderr);
+        assert!(stderr.contains("[HOOK] time (nr=201"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_stub_code() {
+        // mov eax, 228 / call rax / ret
+        let code = unsafe { std::slice::from_raw_parts(clock_gettime().stub as *const u8, 8) };
+        assert_eq!(code, [0xb8, 228, 0, 0, 0, 0xff, 0xd0, 0xc3]);
+    }
+}
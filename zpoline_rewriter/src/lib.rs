@@ -6,8 +6,6 @@ pub use rewriter::{RewriteConfig, Rewriter, RewriteError, RewriteStats};
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_parse_maps() {
         // Test will be implemented
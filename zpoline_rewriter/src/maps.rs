@@ -93,7 +93,7 @@ fn parse_maps_line(line: &str) -> Option<MemoryRegion> {
         return None;
     }
 
-    let readable = perms.chars().nth(0)? == 'r';
+    let readable = perms.chars().next()? == 'r';
     let writable = perms.chars().nth(1)? == 'w';
     let executable = perms.chars().nth(2)? == 'x';
     let private = perms.chars().nth(3)? == 'p';
@@ -139,10 +139,10 @@ mod tests {
 
         assert_eq!(region.start, 0x7f8b4c000000);
         assert_eq!(region.end, 0x7f8b4c021000);
-        assert_eq!(region.readable, true);
-        assert_eq!(region.writable, false);
-        assert_eq!(region.executable, true);
-        assert_eq!(region.private, true);
+        assert!(region.readable);
+        assert!(!region.writable);
+        assert!(region.executable);
+        assert!(region.private);
         assert_eq!(region.offset, 0);
         assert_eq!(region.device, "08:01");
         assert_eq!(region.inode, 1234);
@@ -159,9 +159,9 @@ mod tests {
 
         assert_eq!(region.start, 0x7ffd1234000);
         assert_eq!(region.end, 0x7ffd1235000);
-        assert_eq!(region.readable, true);
-        assert_eq!(region.writable, true);
-        assert_eq!(region.executable, false);
+        assert!(region.readable);
+        assert!(region.writable);
+        assert!(!region.executable);
         assert_eq!(region.pathname, None);
     }
 }
@@ -30,7 +30,7 @@ impl std::fmt::Display for RewriteError {
 impl std::error::Error for RewriteError {}
 
 /// 書き換え設定
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct RewriteConfig {
     /// 除外するパスのリスト（このパスを含む領域は書き換えない）
     pub exclude_paths: HashSet<PathBuf>,
@@ -40,16 +40,6 @@ pub struct RewriteConfig {
     pub dry_run: bool,
 }
 
-impl Default for RewriteConfig {
-    fn default() -> Self {
-        Self {
-            exclude_paths: HashSet::new(),
-            exclude_ranges: Vec::new(),
-            dry_run: false,
-        }
-    }
-}
-
 impl RewriteConfig {
     /// 新しい設定を作成
     pub fn new() -> Self {
@@ -170,7 +160,7 @@ impl Rewriter {
 
         let page_size = page_size::get();
         let aligned_start = (region.start / page_size) * page_size;
-        let aligned_size = ((region.end - aligned_start + page_size - 1) / page_size) * page_size;
+        let aligned_size = (region.end - aligned_start).div_ceil(page_size) * page_size;
 
         // 書き込み可能にする
         unsafe {
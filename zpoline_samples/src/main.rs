@@ -1,25 +1,9 @@
-use zpoline_hook_api::{SyscallRegs, __hook_init};
+use zpoline_hook_api::{Sysno, SyscallRegs, __hook_init};
 
 /// システムコールをトレースするカスタムフック
 extern "C" fn trace_hook(regs: &mut SyscallRegs) -> i64 {
     // システムコール番号に応じて名前を表示
-    let syscall_name = match regs.rax {
-        0 => "read",
-        1 => "write",
-        2 => "open",
-        3 => "close",
-        4 => "stat",
-        5 => "fstat",
-        9 => "mmap",
-        10 => "mprotect",
-        11 => "munmap",
-        39 => "getpid",
-        60 => "exit",
-        158 => "arch_prctl",
-        186 => "gettid",
-        231 => "exit_group",
-        _ => "<unknown>",
-    };
+    let syscall_name = Sysno::name_of(regs.rax).unwrap_or("<unknown>");
 
     eprintln!(
         "[TRACE] syscall: {} (nr={}, args=[{:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x}])",